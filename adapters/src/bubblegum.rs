@@ -0,0 +1,117 @@
+use crate::solana_parser::instructions;
+use solana_transaction_status::{UiParsedMessage, UiTransactionStatusMeta};
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub const BUBBLEGUM_PROGRAM_ID: &str = "BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY";
+
+// Log wrappers Bubblegum emits leaf events through (SPL Noop and Metaplex Noop)
+const NOOP_PROGRAM_IDS: &[&str] = &["noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV", "mnoopTCrg4p8ry25e4bcWA9XZjbNjMTfgYVGGEdRsf3"];
+
+/// A compressed NFT entering or leaving the wallet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CnftMovement {
+    pub asset_id: String,
+    pub amount: i64, // +1 in, -1 out
+}
+
+#[derive(Debug, PartialEq)]
+enum Instruction {
+    Mint,
+    Transfer,
+    Burn,
+}
+
+/// Compressed NFTs minted to, transferred to or from, or burned by `wallet` in the transaction.
+/// Asset ids are the Bubblegum PDA of the merkle tree and leaf nonce.
+pub fn cnft_movements(message: &UiParsedMessage, meta: &UiTransactionStatusMeta, wallet: &str) -> Vec<CnftMovement> {
+    let ixs = instructions(message, meta);
+    let mut movements = Vec::new();
+
+    for (i, ix) in ixs.iter().enumerate() {
+        if ix.program != BUBBLEGUM_PROGRAM_ID {
+            continue;
+        }
+        let Some(kind) = decode_instruction(&ix.data) else { continue };
+        let account = |n: usize| ix.accounts.get(n).map(String::as_str).unwrap_or_default();
+
+        match kind {
+            Instruction::Mint => {
+                // The new leaf's nonce isn't an argument; its asset id comes from the leaf event logged next
+                if account(1) != wallet {
+                    continue;
+                }
+                let event = ixs[i + 1..]
+                    .iter()
+                    .take_while(|next| next.program != BUBBLEGUM_PROGRAM_ID)
+                    .filter(|next| NOOP_PROGRAM_IDS.contains(&next.program.as_str()))
+                    .find_map(|next| leaf_event(&next.data));
+                if let Some((asset_id, owner)) = event {
+                    if owner == wallet {
+                        movements.push(CnftMovement { asset_id, amount: 1 });
+                    }
+                }
+            }
+            Instruction::Transfer => {
+                // tree_authority, leaf_owner, leaf_delegate, new_leaf_owner, merkle_tree, ...
+                let (from, to) = (account(1), account(3));
+                if from == to || (from != wallet && to != wallet) {
+                    continue;
+                }
+                if let Some(asset_id) = asset_id(account(4), &ix.data) {
+                    movements.push(CnftMovement { asset_id, amount: if to == wallet { 1 } else { -1 } });
+                }
+            }
+            Instruction::Burn => {
+                // tree_authority, leaf_owner, leaf_delegate, merkle_tree, ...
+                if account(1) != wallet {
+                    continue;
+                }
+                if let Some(asset_id) = asset_id(account(3), &ix.data) {
+                    movements.push(CnftMovement { asset_id, amount: -1 });
+                }
+            }
+        }
+    }
+    movements
+}
+
+// Anchor discriminators: first 8 bytes of sha256("global:<instruction>")
+fn decode_instruction(data: &[u8]) -> Option<Instruction> {
+    let discriminator = data.get(..8)?;
+    let matches = |name: &str| hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8] == *discriminator;
+    if matches("mint_v1") || matches("mint_to_collection_v1") {
+        Some(Instruction::Mint)
+    } else if matches("transfer") {
+        Some(Instruction::Transfer)
+    } else if matches("burn") {
+        Some(Instruction::Burn)
+    } else {
+        None
+    }
+}
+
+// Transfer and burn take (root, data_hash, creator_hash, nonce: u64, index: u32)
+fn asset_id(tree: &str, data: &[u8]) -> Option<String> {
+    let nonce = data.get(8 + 96..8 + 104)?;
+    let tree = Pubkey::from_str(tree).ok()?;
+    let program = Pubkey::from_str(BUBBLEGUM_PROGRAM_ID).ok()?;
+    Some(Pubkey::find_program_address(&[b"asset", tree.as_ref(), nonce], &program).0.to_string())
+}
+
+/// (asset id, owner) from a Bubblegum `LeafSchemaEvent` logged through the noop program.
+/// The event is usually wrapped as account-compression application data: [1, 0, len: u32, event...].
+fn leaf_event(data: &[u8]) -> Option<(String, String)> {
+    let event = match data {
+        [1, 0, len @ ..] if len.len() >= 4 && u32::from_le_bytes(len[..4].try_into().ok()?) as usize == data.len() - 6 => &data[6..],
+        _ => data,
+    };
+    // event type LeafSchemaEvent (1), version V1 (0), LeafSchema::V1 (0), then id and owner
+    if event.get(..3)? != [1, 0, 0] {
+        return None;
+    }
+    let id: [u8; 32] = event.get(3..35)?.try_into().ok()?;
+    let owner: [u8; 32] = event.get(35..67)?.try_into().ok()?;
+    Some((Pubkey::new_from_array(id).to_string(), Pubkey::new_from_array(owner).to_string()))
+}
@@ -0,0 +1,313 @@
+use spectraplex_core::fx::normalize_currency;
+use spectraplex_core::models::{Chain, ChainIngestor, EntryType, LedgerEntry, Transaction};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, NaiveDateTime};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// Fiat balances are valued 1:1 in their own currency so cost basis works without a price feed
+const FIAT_CURRENCIES: &[&str] = &["USD", "EUR", "GBP", "CAD", "AUD", "JPY", "CHF"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exchange {
+    Coinbase,
+    Kraken,
+    Binance,
+}
+
+impl FromStr for Exchange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "coinbase" => Ok(Exchange::Coinbase),
+            "kraken" => Ok(Exchange::Kraken),
+            "binance" => Ok(Exchange::Binance),
+            _ => Err(anyhow::anyhow!("Unknown exchange: {}", s)),
+        }
+    }
+}
+
+impl Exchange {
+    pub fn chain(&self) -> Chain {
+        match self {
+            Exchange::Coinbase => Chain::Coinbase,
+            Exchange::Kraken => Chain::Kraken,
+            Exchange::Binance => Chain::Binance,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Exchange::Coinbase => "coinbase",
+            Exchange::Kraken => "kraken",
+            Exchange::Binance => "binance",
+        }
+    }
+}
+
+/// A downloaded exchange statement. The "wallet" is whatever label the account is tracked under.
+pub struct CexStatement {
+    path: PathBuf,
+    exchange: Exchange,
+    user_id: Uuid,
+}
+
+impl CexStatement {
+    pub fn new(path: PathBuf, exchange: Exchange, user_id: Uuid) -> Self {
+        Self { path, exchange, user_id }
+    }
+}
+
+#[async_trait::async_trait]
+impl ChainIngestor for CexStatement {
+    async fn fetch_history(&self, wallet: &str, limit: usize) -> anyhow::Result<Vec<Transaction>> {
+        let file = std::fs::File::open(&self.path)?;
+        let mut txs = parse_statement(file, self.exchange, self.user_id, wallet)?;
+        txs.truncate(limit);
+        Ok(txs)
+    }
+}
+
+/// Turns a statement into bronze transactions. Rows that belong to one event (Kraken refid,
+/// Binance rows sharing a timestamp) become one transaction; the original rows are kept in `raw_metadata`.
+pub fn parse_statement<R: Read>(mut reader: R, exchange: Exchange, user_id: Uuid, account: &str) -> anyhow::Result<Vec<Transaction>> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+
+    // Coinbase puts a few lines of account info above the real header
+    let text = match exchange {
+        Exchange::Coinbase => {
+            let start = text
+                .lines()
+                .position(|l| l.contains("Timestamp") && l.contains("Transaction Type"))
+                .ok_or_else(|| anyhow::anyhow!("Coinbase statement has no Timestamp/Transaction Type header"))?;
+            text.lines().skip(start).collect::<Vec<_>>().join("\n")
+        }
+        _ => text,
+    };
+
+    let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_reader(text.as_bytes());
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.to_string()).collect();
+
+    let mut groups: Vec<(String, Vec<Map<String, Value>>)> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (i, record) in rdr.records().enumerate() {
+        let record = record?;
+        let row: Map<String, Value> = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(h, v)| (h.clone(), Value::String(v.to_string())))
+            .collect();
+
+        let key = match exchange {
+            Exchange::Coinbase => field(&row, &["ID"]).map(|id| id.to_string()).unwrap_or_else(|| format!("row-{}", i)),
+            Exchange::Kraken => field(&row, &["refid"]).unwrap_or_default().to_string(),
+            Exchange::Binance => format!(
+                "{}:{}",
+                field(&row, &["UTC_Time"]).unwrap_or_default(),
+                field(&row, &["Account"]).unwrap_or_default()
+            ),
+        };
+        match index.get(&key) {
+            Some(&pos) => groups[pos].1.push(row),
+            None => {
+                index.insert(key.clone(), groups.len());
+                groups.push((key, vec![row]));
+            }
+        }
+    }
+
+    let mut txs = Vec::new();
+    for (key, rows) in groups {
+        let time = match exchange {
+            Exchange::Coinbase => field(&rows[0], &["Timestamp"]),
+            Exchange::Kraken => field(&rows[0], &["time"]),
+            Exchange::Binance => field(&rows[0], &["UTC_Time"]),
+        }
+        .ok_or_else(|| anyhow::anyhow!("{} row is missing its time column", exchange.name()))?;
+
+        txs.push(Transaction {
+            id: Uuid::new_v4(),
+            user_id,
+            wallet_address: account.to_string(),
+            timestamp: parse_time(time)?,
+            tx_hash: format!("{}:{}", exchange.name(), key),
+            chain: exchange.chain(),
+            raw_metadata: json!({ "exchange": exchange.name(), "rows": rows }),
+        });
+    }
+    Ok(txs)
+}
+
+/// Normalizes a bronze transaction produced by `parse_statement`.
+pub fn parse_cex_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEntry>> {
+    let exchange: Exchange = tx.raw_metadata["exchange"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Transaction {} has no exchange in raw_metadata", tx.id))?
+        .parse()?;
+    let rows: Vec<Map<String, Value>> = serde_json::from_value(tx.raw_metadata["rows"].clone())?;
+
+    let mut entries = Vec::new();
+    for row in &rows {
+        match exchange {
+            Exchange::Coinbase => coinbase_entries(tx, row, &mut entries)?,
+            Exchange::Kraken => kraken_entries(tx, row, &mut entries)?,
+            Exchange::Binance => binance_entries(tx, row, &mut entries)?,
+        }
+    }
+
+    for entry in entries.iter_mut() {
+        if entry.fiat_value.is_none() && FIAT_CURRENCIES.contains(&entry.asset_symbol.as_str()) {
+            entry.fiat_value = Some(entry.amount.abs());
+            entry.fiat_currency = Some(entry.asset_symbol.clone());
+            entry.price_source = Some("exchange".to_string());
+        }
+    }
+    Ok(entries)
+}
+
+fn coinbase_entries(tx: &Transaction, row: &Map<String, Value>, entries: &mut Vec<LedgerEntry>) -> anyhow::Result<()> {
+    let kind = field(row, &["Transaction Type"]).unwrap_or_default();
+    let asset = field(row, &["Asset"]).unwrap_or_default().to_string();
+    let quantity = number(field(row, &["Quantity Transacted"]))?.unwrap_or_default().abs();
+    let currency = field(row, &["Price Currency", "Spot Price Currency"]).unwrap_or("USD");
+    let currency = normalize_currency(currency)?;
+    let subtotal = number(field(row, &["Subtotal"]))?.map(|v| v.abs());
+    let fees = number(field(row, &["Fees and/or Spread", "Fees"]))?.map(|v| v.abs()).filter(|v| !v.is_zero());
+
+    // Crypto legs are valued at the subtotal Coinbase charged or paid out
+    let valued = |asset: String, amount: BigDecimal, entry_type: EntryType| {
+        let mut entry = LedgerEntry::new(tx, asset, amount, entry_type);
+        if let Some(value) = &subtotal {
+            entry.fiat_value = Some(value.clone());
+            entry.fiat_currency = Some(currency.clone());
+            entry.price_source = Some("coinbase".to_string());
+        }
+        entry
+    };
+
+    match kind.to_lowercase().as_str() {
+        "buy" | "advanced trade buy" => {
+            entries.push(valued(asset, quantity, EntryType::Trade));
+            entries.push(LedgerEntry::new(tx, currency.clone(), -subtotal.clone().unwrap_or_default(), EntryType::Trade));
+        }
+        "sell" | "advanced trade sell" => {
+            entries.push(valued(asset, -quantity, EntryType::Trade));
+            entries.push(LedgerEntry::new(tx, currency.clone(), subtotal.clone().unwrap_or_default(), EntryType::Trade));
+        }
+        "convert" => {
+            // Notes read "Converted 0.5 ETH to 1,000.12 USDC"
+            let notes = field(row, &["Notes"]).unwrap_or_default();
+            let words: Vec<&str> = notes.split_whitespace().collect();
+            let [_, sent_qty, sent_asset, "to", received_qty, received_asset] = words.as_slice() else {
+                anyhow::bail!("Can't read Coinbase convert notes: {}", notes);
+            };
+            entries.push(valued(sent_asset.to_string(), -number(Some(sent_qty))?.unwrap_or_default(), EntryType::Trade));
+            entries.push(valued(received_asset.to_string(), number(Some(received_qty))?.unwrap_or_default(), EntryType::Trade));
+        }
+        "send" | "withdrawal" => entries.push(valued(asset, -quantity, EntryType::Transfer)),
+        "receive" | "deposit" => entries.push(valued(asset, quantity, EntryType::Transfer)),
+        "staking income" => entries.push(valued(asset, quantity, EntryType::Staking)),
+        k if k.contains("reward") || k.contains("income") => entries.push(valued(asset, quantity, EntryType::Income)),
+        _ => anyhow::bail!("Unsupported Coinbase transaction type: {}", kind),
+    }
+
+    if let Some(fee) = fees {
+        entries.push(LedgerEntry::new(tx, currency, -fee, EntryType::Fee));
+    }
+    Ok(())
+}
+
+fn kraken_entries(tx: &Transaction, row: &Map<String, Value>, entries: &mut Vec<LedgerEntry>) -> anyhow::Result<()> {
+    let kind = field(row, &["type"]).unwrap_or_default().to_lowercase();
+    let asset = kraken_asset(field(row, &["asset"]).unwrap_or_default());
+    let amount = number(field(row, &["amount"]))?.unwrap_or_default();
+    let fee = number(field(row, &["fee"]))?.unwrap_or_default();
+
+    let entry_type = match kind.as_str() {
+        "trade" | "spend" | "receive" => EntryType::Trade,
+        "staking" | "earn" | "dividend" => EntryType::Staking,
+        _ => EntryType::Transfer, // deposit, withdrawal, transfer
+    };
+    if !amount.is_zero() {
+        entries.push(LedgerEntry::new(tx, asset.clone(), amount, entry_type));
+    }
+    if !fee.is_zero() {
+        entries.push(LedgerEntry::new(tx, asset, -fee.abs(), EntryType::Fee));
+    }
+    Ok(())
+}
+
+fn binance_entries(tx: &Transaction, row: &Map<String, Value>, entries: &mut Vec<LedgerEntry>) -> anyhow::Result<()> {
+    let operation = field(row, &["Operation"]).unwrap_or_default().to_lowercase();
+    let coin = field(row, &["Coin"]).unwrap_or_default().to_string();
+    let change = number(field(row, &["Change"]))?.unwrap_or_default();
+    if change.is_zero() {
+        return Ok(());
+    }
+
+    let entry_type = if operation.contains("fee") {
+        EntryType::Fee
+    } else if operation == "deposit" || operation == "withdraw" || operation.contains("transfer") {
+        EntryType::Transfer
+    } else if operation.contains("staking") {
+        EntryType::Staking
+    } else if operation.contains("interest") || operation.contains("distribution") || operation.contains("airdrop") || operation.contains("reward") || operation.contains("commission") {
+        EntryType::Income
+    } else if operation.contains("buy") || operation.contains("sell") || operation.contains("sold") || operation.contains("spend")
+        || operation.contains("revenue") || operation.contains("convert") || operation.contains("exchange")
+    {
+        EntryType::Trade
+    } else {
+        EntryType::Transfer
+    };
+    entries.push(LedgerEntry::new(tx, coin, change, entry_type));
+    Ok(())
+}
+
+// Kraken prefixes legacy codes with X (crypto) or Z (fiat) and calls bitcoin XBT
+fn kraken_asset(code: &str) -> String {
+    let code = code.split('.').next().unwrap_or(code); // Staked variants like ETH2.S or DOT.S
+    let code = match code {
+        c if c.len() == 4 && (c.starts_with('X') || c.starts_with('Z')) => &c[1..],
+        c => c,
+    };
+    match code {
+        "XBT" => "BTC".to_string(),
+        "XDG" => "DOGE".to_string(),
+        "ETH2" => "ETH".to_string(),
+        c => c.to_string(),
+    }
+}
+
+fn field<'a>(row: &'a Map<String, Value>, names: &[&str]) -> Option<&'a str> {
+    names.iter().find_map(|n| row.get(*n)).and_then(|v| v.as_str()).filter(|s| !s.is_empty())
+}
+
+// Amounts may carry currency symbols and thousands separators, e.g. "-$1,234.56"
+fn number(value: Option<&str>) -> anyhow::Result<Option<BigDecimal>> {
+    let Some(value) = value else { return Ok(None) };
+    let cleaned: String = value.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-' || *c == 'e' || *c == 'E').collect();
+    if cleaned.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(BigDecimal::from_str(&cleaned)?))
+}
+
+fn parse_time(value: &str) -> anyhow::Result<i64> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp());
+    }
+    let trimmed = value.trim_end_matches(" UTC");
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%d %H:%M:%S", "%y-%m-%d %H:%M:%S"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(trimmed, format) {
+            return Ok(dt.and_utc().timestamp());
+        }
+    }
+    Err(anyhow::anyhow!("Unrecognized statement time: {}", value))
+}
@@ -0,0 +1,248 @@
+use crate::evm::{builtin_networks, EvmNetwork};
+use spectraplex_core::models::{ChainIngestor, Transaction};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+// keccak256("Transfer(address,address,uint256)")
+pub const ERC20_TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+// Blocks searched per ingest unless with_scan_blocks says otherwise
+pub const DEFAULT_SCAN_BLOCKS: u64 = 1_000;
+
+// Nodes without trace_filter are searched block by block, so keep any one window small
+pub const MAX_SCAN_BLOCKS: u64 = 10_000;
+
+// decimals()
+const DECIMALS_SELECTOR: &str = "0x313ce567";
+
+pub struct EthereumAdapter {
+    client: reqwest::Client,
+    rpc_url: String,
+    from_block: Option<u64>,
+    scan_blocks: u64,
+    network: EvmNetwork,
+}
+
+impl EthereumAdapter {
+    /// Adapter for Ethereum mainnet.
+    pub fn new(rpc_url: &str, from_block: Option<u64>) -> Self {
+        let mainnet = builtin_networks().into_iter().next().expect("ethereum is built in");
+        Self::for_network(mainnet, rpc_url, from_block)
+    }
+
+    /// Adapter for any EVM network; the RPC must serve that network's chain id.
+    pub fn for_network(network: EvmNetwork, rpc_url: &str, from_block: Option<u64>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            rpc_url: rpc_url.to_string(),
+            from_block,
+            scan_blocks: DEFAULT_SCAN_BLOCKS,
+            network,
+        }
+    }
+
+    /// Number of blocks one fetch searches: forward from the start block if one was
+    /// given, else back from the latest block. At most MAX_SCAN_BLOCKS.
+    pub fn with_scan_blocks(mut self, blocks: u64) -> Self {
+        self.scan_blocks = blocks;
+        self
+    }
+
+    async fn call(&self, method: &str, params: Value) -> anyhow::Result<Value> {
+        let body: Value = self
+            .client
+            .post(&self.rpc_url)
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        if let Some(error) = body.get("error") {
+            anyhow::bail!("{} failed: {}", method, error);
+        }
+        Ok(body["result"].clone())
+    }
+
+    // Hashes of transactions the wallet sent, received ETH in, or moved ERC-20 tokens in.
+    //
+    // ETH movements come from trace_filter (Erigon, Nethermind, Reth), which also sees ETH
+    // sent to the wallet by internal calls in other accounts' transactions. Without it we fall
+    // back to reading every block in the window, which only finds transactions the wallet sent
+    // or was the direct recipient of.
+    async fn find_transactions(&self, wallet: &str, from: u64, to: u64) -> anyhow::Result<BTreeSet<(u64, String)>> {
+        let mut found = match self.traced_transactions(wallet, from, to).await {
+            Ok(found) => found,
+            Err(e) => {
+                eprintln!("No trace_filter ({}), scanning blocks {}..={}; ETH from internal calls in other transactions will be missed", e, from, to);
+                let mut found = BTreeSet::new();
+                for number in from..=to {
+                    let block = self.call("eth_getBlockByNumber", json!([hex(number), true])).await?;
+                    for tx in block["transactions"].as_array().into_iter().flatten() {
+                        if same_address(&tx["from"], wallet) || same_address(&tx["to"], wallet) {
+                            found.insert((number, tx["hash"].as_str().unwrap_or_default().to_string()));
+                        }
+                    }
+                }
+                found
+            }
+        };
+
+        let topic = address_topic(wallet);
+        for topics in [json!([ERC20_TRANSFER_TOPIC, topic]), json!([ERC20_TRANSFER_TOPIC, null, topic])] {
+            let logs = self.call("eth_getLogs", json!([{ "fromBlock": hex(from), "toBlock": hex(to), "topics": topics }])).await?;
+            for log in logs.as_array().into_iter().flatten() {
+                let number = parse_quantity(&log["blockNumber"]).unwrap_or(0);
+                found.insert((number, log["transactionHash"].as_str().unwrap_or_default().to_string()));
+            }
+        }
+        Ok(found)
+    }
+
+    // Every call frame, top-level or internal, sent from or to the wallet
+    async fn traced_transactions(&self, wallet: &str, from: u64, to: u64) -> anyhow::Result<BTreeSet<(u64, String)>> {
+        let mut found = BTreeSet::new();
+        for side in ["fromAddress", "toAddress"] {
+            let filter = json!([{ "fromBlock": hex(from), "toBlock": hex(to), side: [wallet] }]);
+            for trace in self.call("trace_filter", filter).await?.as_array().into_iter().flatten() {
+                // Block rewards have no transaction
+                let Some(hash) = trace["transactionHash"].as_str() else {
+                    continue;
+                };
+                // Some clients give the block number as a JSON number, others as hex
+                let number = trace["blockNumber"].as_u64().or_else(|| parse_quantity(&trace["blockNumber"])).unwrap_or(0);
+                found.insert((number, hash.to_string()));
+            }
+        }
+        Ok(found)
+    }
+
+    async fn token_decimals(&self, token: &str) -> anyhow::Result<u64> {
+        let result = self.call("eth_call", json!([{ "to": token, "data": DECIMALS_SELECTOR }, "latest"])).await?;
+        parse_quantity(&result).ok_or_else(|| anyhow::anyhow!("{} returned no decimals()", token))
+    }
+}
+
+#[async_trait::async_trait]
+impl ChainIngestor for EthereumAdapter {
+    async fn fetch_history(&self, wallet: &str, limit: usize) -> anyhow::Result<Vec<Transaction>> {
+        // Catch an RPC pointed at the wrong network before storing its data under this one
+        let chain_id = parse_quantity(&self.call("eth_chainId", json!([])).await?);
+        if chain_id != Some(self.network.chain_id) {
+            anyhow::bail!("RPC serves chain id {:?}, but {} is chain id {}", chain_id, self.network.name, self.network.chain_id);
+        }
+
+        let latest = parse_quantity(&self.call("eth_blockNumber", json!([])).await?)
+            .ok_or_else(|| anyhow::anyhow!("eth_blockNumber returned no block"))?;
+        if self.scan_blocks == 0 || self.scan_blocks > MAX_SCAN_BLOCKS {
+            anyhow::bail!("Scan window must be 1 to {} blocks, got {}", MAX_SCAN_BLOCKS, self.scan_blocks);
+        }
+        // Longer histories are ingested a window at a time by moving the start block
+        let (from, to) = match self.from_block {
+            Some(from) => (from, latest.min(from.saturating_add(self.scan_blocks - 1))),
+            None => (latest.saturating_sub(self.scan_blocks - 1), latest),
+        };
+        if from > to {
+            anyhow::bail!("Start block {} is past the latest block {}", from, latest);
+        }
+
+        let found = self.find_transactions(wallet, from, to).await?;
+        let skip = found.len().saturating_sub(limit);
+
+        let mut blocks: HashMap<u64, Value> = HashMap::new();
+        let mut decimals: HashMap<String, u64> = HashMap::new();
+        let mut transactions = Vec::new();
+
+        for (number, hash) in found.into_iter().skip(skip) {
+            let tx = self.call("eth_getTransactionByHash", json!([hash])).await?;
+            let receipt = self.call("eth_getTransactionReceipt", json!([hash])).await?;
+
+            let block = match blocks.get(&number) {
+                Some(block) => block.clone(),
+                None => {
+                    let block = self.call("eth_getBlockByNumber", json!([hex(number), false])).await?;
+                    let header = json!({
+                        "number": block["number"],
+                        "timestamp": block["timestamp"],
+                        "baseFeePerGas": block["baseFeePerGas"],
+                    });
+                    blocks.insert(number, header.clone());
+                    header
+                }
+            };
+
+            // Internal calls need the debug API; nodes without it just don't get them parsed
+            let trace = match self.call("debug_traceTransaction", json!([hash, { "tracer": "callTracer" }])).await {
+                Ok(trace) => trace,
+                Err(e) => {
+                    eprintln!("No call trace for {}: {}", hash, e);
+                    Value::Null
+                }
+            };
+
+            let mut token_decimals = Map::new();
+            for log in receipt["logs"].as_array().into_iter().flatten() {
+                if log["topics"][0].as_str() != Some(ERC20_TRANSFER_TOPIC) {
+                    continue;
+                }
+                let token = log["address"].as_str().unwrap_or_default().to_lowercase();
+                if !decimals.contains_key(&token) {
+                    match self.token_decimals(&token).await {
+                        Ok(d) => {
+                            decimals.insert(token.clone(), d);
+                        }
+                        Err(e) => {
+                            // NFTs share the Transfer topic and have no decimals()
+                            eprintln!("Skipping token {}: {}", token, e);
+                            continue;
+                        }
+                    }
+                }
+                token_decimals.insert(token.clone(), json!(decimals[&token]));
+            }
+
+            transactions.push(Transaction {
+                id: Uuid::new_v4(),
+                user_id: Uuid::nil(), // Placeholder
+                wallet_address: wallet.to_string(),
+                timestamp: parse_quantity(&block["timestamp"]).unwrap_or(0) as i64,
+                tx_hash: hash,
+                chain: self.network.chain(),
+                raw_metadata: json!({
+                    "network": {
+                        "name": self.network.name,
+                        "chain_id": self.network.chain_id,
+                        "native_asset": self.network.native_asset,
+                        "native_decimals": self.network.native_decimals,
+                    },
+                    "transaction": tx,
+                    "receipt": receipt,
+                    "block": block,
+                    "trace": trace,
+                    "token_decimals": token_decimals,
+                }),
+            });
+        }
+
+        Ok(transactions)
+    }
+}
+
+fn hex(n: u64) -> String {
+    format!("0x{:x}", n)
+}
+
+/// Parses a JSON-RPC hex quantity such as "0x1b4".
+pub fn parse_quantity(value: &Value) -> Option<u64> {
+    u64::from_str_radix(value.as_str()?.trim_start_matches("0x"), 16).ok()
+}
+
+// Addresses appear in indexed topics left-padded to 32 bytes
+fn address_topic(address: &str) -> String {
+    format!("0x{:0>64}", address.trim_start_matches("0x").to_lowercase())
+}
+
+fn same_address(value: &Value, address: &str) -> bool {
+    value.as_str().map(|v| v.eq_ignore_ascii_case(address)).unwrap_or(false)
+}
@@ -0,0 +1,167 @@
+use crate::ethereum::ERC20_TRANSFER_TOPIC;
+use crate::solana_parser::classify_swap;
+use spectraplex_core::models::{EntryType, LedgerEntry, Transaction};
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, Zero};
+use serde_json::Value;
+
+// Transactions ingested before networks were configurable are all mainnet
+const DEFAULT_NATIVE_ASSET: &str = "ETH";
+const DEFAULT_NATIVE_DECIMALS: i64 = 18;
+
+/// Normalizes a bronze transaction produced by `EthereumAdapter`, on any EVM network.
+/// ERC-20 tokens are keyed by their contract address, the way SPL tokens are keyed by mint.
+pub fn parse_ethereum_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEntry>> {
+    let raw = &tx.raw_metadata;
+    let (evm_tx, receipt) = (&raw["transaction"], &raw["receipt"]);
+    let wallet = tx.wallet_address.to_lowercase();
+    let is_wallet = |value: &Value| value.as_str().map(|a| a.to_lowercase() == wallet).unwrap_or(false);
+    let succeeded = receipt["status"].as_str() != Some("0x0");
+    let native = Native {
+        asset: raw["network"]["native_asset"].as_str().unwrap_or(DEFAULT_NATIVE_ASSET).to_string(),
+        decimals: raw["network"]["native_decimals"].as_i64().unwrap_or(DEFAULT_NATIVE_DECIMALS),
+    };
+
+    let mut entries = Vec::new();
+
+    // 1. Top-level ETH value. A reverted transaction moves nothing but still pays gas.
+    let value = quantity(&evm_tx["value"])?;
+    if succeeded && !value.is_zero() {
+        if is_wallet(&evm_tx["from"]) {
+            entries.push(native.entry(tx, -value.clone(), EntryType::Transfer));
+        }
+        if is_wallet(&evm_tx["to"]) {
+            entries.push(native.entry(tx, value, EntryType::Transfer));
+        }
+    }
+
+    // 2. Gas, paid by the sender. EIP-1559 blocks split it into the burned base fee and the priority tip.
+    if is_wallet(&evm_tx["from"]) {
+        let gas_used = quantity(&receipt["gasUsed"])?;
+        let gas_price = quantity(receipt.get("effectiveGasPrice").unwrap_or(&evm_tx["gasPrice"]))?;
+        match raw["block"].get("baseFeePerGas").filter(|v| !v.is_null()) {
+            Some(base_fee) => {
+                let base_fee = quantity(base_fee)?;
+                let tip = &gas_price - &base_fee;
+                entries.push(native.entry(tx, -(&gas_used * &base_fee), EntryType::Fee));
+                if !tip.is_zero() {
+                    entries.push(native.entry(tx, -(&gas_used * tip), EntryType::Fee));
+                }
+            }
+            None => entries.push(native.entry(tx, -(gas_used * gas_price), EntryType::Fee)),
+        }
+        // OP-stack rollups (Optimism, Base) also charge for posting the transaction to L1
+        if let Some(l1_fee) = receipt.get("l1Fee").filter(|v| !v.is_null()) {
+            let l1_fee = quantity(l1_fee)?;
+            if !l1_fee.is_zero() {
+                entries.push(native.entry(tx, -l1_fee, EntryType::Fee));
+            }
+        }
+    }
+
+    if succeeded {
+        // 3. ERC-20 Transfer logs touching the wallet
+        for log in receipt["logs"].as_array().into_iter().flatten() {
+            let topics = log["topics"].as_array().map(|t| t.as_slice()).unwrap_or_default();
+            // ERC-721 transfers index the token id as a fourth topic
+            if topics.len() != 3 || topics[0].as_str() != Some(ERC20_TRANSFER_TOPIC) {
+                continue;
+            }
+            let token = log["address"].as_str().unwrap_or_default().to_lowercase();
+            let Some(decimals) = raw["token_decimals"][&token].as_i64() else {
+                continue;
+            };
+            let amount = BigDecimal::new(uint256(&log["data"])?, decimals);
+            if topic_is(&topics[1], &wallet) {
+                entries.push(LedgerEntry::new(tx, token.clone(), -amount.clone(), EntryType::Transfer));
+            }
+            if topic_is(&topics[2], &wallet) {
+                entries.push(LedgerEntry::new(tx, token, amount, EntryType::Transfer));
+            }
+        }
+
+        // 4. ETH moved by internal calls (callTracer frames below the top-level call)
+        for call in raw["trace"]["calls"].as_array().into_iter().flatten() {
+            internal_transfers(tx, call, &wallet, &native, &mut entries)?;
+        }
+    }
+
+    // Net several movements of the same asset (e.g. ETH sent and refunded) into one entry
+    let mut merged: Vec<LedgerEntry> = Vec::new();
+    for entry in entries {
+        let existing = merged
+            .iter_mut()
+            .find(|e| e.asset_symbol == entry.asset_symbol && matches!(e.entry_type, EntryType::Transfer) && matches!(entry.entry_type, EntryType::Transfer));
+        match existing {
+            Some(e) => e.amount += entry.amount,
+            None => merged.push(entry),
+        }
+    }
+    merged.retain(|e| !e.amount.is_zero());
+
+    classify_swap(&mut merged);
+    Ok(merged)
+}
+
+fn internal_transfers(tx: &Transaction, call: &Value, wallet: &str, native: &Native, entries: &mut Vec<LedgerEntry>) -> anyhow::Result<()> {
+    // A reverted frame and everything below it didn't happen
+    if call.get("error").is_some() {
+        return Ok(());
+    }
+    let value = match call.get("value") {
+        Some(v) if !v.is_null() => quantity(v)?,
+        _ => BigDecimal::zero(),
+    };
+    let is_call = call["type"].as_str().map(|t| t != "DELEGATECALL" && t != "STATICCALL").unwrap_or(true);
+    if is_call && !value.is_zero() {
+        if call["from"].as_str().map(|a| a.to_lowercase() == wallet).unwrap_or(false) {
+            entries.push(native.entry(tx, -value.clone(), EntryType::Transfer));
+        }
+        if call["to"].as_str().map(|a| a.to_lowercase() == wallet).unwrap_or(false) {
+            entries.push(native.entry(tx, value, EntryType::Transfer));
+        }
+    }
+    for child in call["calls"].as_array().into_iter().flatten() {
+        internal_transfers(tx, child, wallet, native, entries)?;
+    }
+    Ok(())
+}
+
+// The network's gas token (ETH, POL, ...)
+struct Native {
+    asset: String,
+    decimals: i64,
+}
+
+impl Native {
+    fn entry(&self, tx: &Transaction, wei: BigDecimal, entry_type: EntryType) -> LedgerEntry {
+        let (digits, scale) = wei.as_bigint_and_exponent();
+        LedgerEntry::new(tx, self.asset.clone(), BigDecimal::new(digits, scale + self.decimals).normalized(), entry_type)
+    }
+}
+
+// Hex quantity ("0x...") as an integer decimal
+fn quantity(value: &Value) -> anyhow::Result<BigDecimal> {
+    let s = value.as_str().ok_or_else(|| anyhow::anyhow!("Expected a hex quantity, got {}", value))?;
+    Ok(BigDecimal::from(parse_hex(s)?))
+}
+
+fn uint256(value: &Value) -> anyhow::Result<BigInt> {
+    parse_hex(value.as_str().unwrap_or("0x0"))
+}
+
+fn parse_hex(s: &str) -> anyhow::Result<BigInt> {
+    let digits = s.trim_start_matches("0x");
+    if digits.is_empty() {
+        return Ok(BigInt::zero());
+    }
+    BigInt::parse_bytes(digits.as_bytes(), 16).ok_or_else(|| anyhow::anyhow!("Invalid hex value {}", s))
+}
+
+fn topic_is(topic: &Value, wallet: &str) -> bool {
+    // get() rather than slicing: RPC input is untrusted and may be short or not ASCII
+    topic
+        .as_str()
+        .and_then(|t| t.len().checked_sub(40).and_then(|start| t.get(start..)))
+        .is_some_and(|tail| tail.eq_ignore_ascii_case(wallet.trim_start_matches("0x")))
+}
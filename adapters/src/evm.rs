@@ -0,0 +1,79 @@
+use spectraplex_core::models::Chain;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// An EVM network the Ethereum adapter and parser can run against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvmNetwork {
+    pub name: String,
+    pub chain_id: u64,
+    pub native_asset: String,
+    #[serde(default = "default_native_decimals")]
+    pub native_decimals: i64,
+    // Used when no RPC is given on the command line / in the request
+    #[serde(default)]
+    pub rpc_url: Option<String>,
+    // Base URL of a block explorer; transactions live under /tx/<hash> on all the common ones
+    #[serde(default)]
+    pub explorer_url: Option<String>,
+}
+
+fn default_native_decimals() -> i64 {
+    18
+}
+
+impl EvmNetwork {
+    pub fn new(name: &str, chain_id: u64, native_asset: &str, explorer_url: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            chain_id,
+            native_asset: native_asset.to_string(),
+            native_decimals: default_native_decimals(),
+            rpc_url: None,
+            explorer_url: Some(explorer_url.to_string()),
+        }
+    }
+
+    pub fn chain(&self) -> Chain {
+        Chain::from_name(&self.name)
+    }
+
+    pub fn tx_url(&self, tx_hash: &str) -> Option<String> {
+        self.explorer_url.as_ref().map(|url| format!("{}/tx/{}", url.trim_end_matches('/'), tx_hash))
+    }
+}
+
+/// Networks supported out of the box.
+pub fn builtin_networks() -> Vec<EvmNetwork> {
+    vec![
+        EvmNetwork::new("ethereum", 1, "ETH", "https://etherscan.io"),
+        EvmNetwork::new("arbitrum", 42161, "ETH", "https://arbiscan.io"),
+        EvmNetwork::new("base", 8453, "ETH", "https://basescan.org"),
+        EvmNetwork::new("optimism", 10, "ETH", "https://optimistic.etherscan.io"),
+        EvmNetwork::new("polygon", 137, "POL", "https://polygonscan.com"),
+    ]
+}
+
+/// Built-in networks, plus the ones in `path` (a JSON array of networks) if given.
+/// A configured network replaces the built-in one with the same name.
+pub fn load_networks(path: Option<&Path>) -> anyhow::Result<Vec<EvmNetwork>> {
+    let mut networks = builtin_networks();
+    let Some(path) = path else {
+        return Ok(networks);
+    };
+    let configured: Vec<EvmNetwork> = serde_json::from_reader(std::fs::File::open(path)?)
+        .map_err(|e| anyhow::anyhow!("Invalid EVM network config {}: {}", path.display(), e))?;
+    for network in configured {
+        if Chain::from_name(&network.name).is_evm() {
+            networks.retain(|n| n.name != network.name);
+            networks.push(network);
+        } else {
+            anyhow::bail!("{} is not available as an EVM network name", network.name);
+        }
+    }
+    Ok(networks)
+}
+
+pub fn find_network<'a>(networks: &'a [EvmNetwork], name: &str) -> Option<&'a EvmNetwork> {
+    networks.iter().find(|n| n.name == name)
+}
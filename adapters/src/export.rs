@@ -0,0 +1,334 @@
+use spectraplex_core::models::{EntryType, LedgerEntry};
+use bigdecimal::{num_bigint::Sign, BigDecimal, Zero};
+use chrono::{DateTime, NaiveDateTime};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::str::FromStr;
+use uuid::Uuid;
+
+// Universal CSV layouts accepted by third-party tax tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Koinly,
+    CoinTracker,
+    CoinLedger,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "koinly" => Ok(ExportFormat::Koinly),
+            "cointracker" => Ok(ExportFormat::CoinTracker),
+            "coinledger" => Ok(ExportFormat::CoinLedger),
+            _ => Err(anyhow::anyhow!("Unknown export format: {} (expected koinly, cointracker or coinledger)", s)),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFormat::Koinly => "koinly_universal.csv",
+            ExportFormat::CoinTracker => "cointracker.csv",
+            ExportFormat::CoinLedger => "coinledger_universal.csv",
+        }
+    }
+
+    fn header(&self) -> &'static [&'static str] {
+        match self {
+            ExportFormat::Koinly => &[
+                "Date", "Sent Amount", "Sent Currency", "Received Amount", "Received Currency", "Fee Amount", "Fee Currency",
+                "Net Worth Amount", "Net Worth Currency", "Label", "Description", "TxHash",
+            ],
+            ExportFormat::CoinTracker => &[
+                "Date", "Received Quantity", "Received Currency", "Sent Quantity", "Sent Currency", "Fee Amount", "Fee Currency", "Tag",
+            ],
+            ExportFormat::CoinLedger => &[
+                "Date (UTC)", "Platform (Optional)", "Asset Sent", "Amount Sent", "Asset Received", "Amount Received",
+                "Fee Currency (Optional)", "Fee Amount (Optional)", "Type", "Description (Optional)", "TxHash (Optional)",
+            ],
+        }
+    }
+
+    fn date_format(&self) -> &'static str {
+        match self {
+            ExportFormat::Koinly => "%Y-%m-%d %H:%M:%S UTC",
+            ExportFormat::CoinTracker | ExportFormat::CoinLedger => "%m/%d/%Y %H:%M:%S",
+        }
+    }
+
+    // Label/Tag/Type column for each kind of row
+    fn label(&self, kind: RowKind) -> &'static str {
+        match (self, kind) {
+            (ExportFormat::Koinly, RowKind::Fee) => "cost",
+            (ExportFormat::Koinly, RowKind::Staking) => "staking",
+            (ExportFormat::Koinly, RowKind::Income) => "income",
+            (ExportFormat::Koinly, _) => "",
+            (ExportFormat::CoinTracker, RowKind::Fee) => "fee",
+            (ExportFormat::CoinTracker, RowKind::Staking) => "staked",
+            (ExportFormat::CoinTracker, RowKind::Income) => "income",
+            (ExportFormat::CoinTracker, _) => "",
+            (ExportFormat::CoinLedger, RowKind::Trade) => "Trade",
+            (ExportFormat::CoinLedger, RowKind::Deposit) => "Deposit",
+            (ExportFormat::CoinLedger, RowKind::Withdrawal) => "Withdrawal",
+            (ExportFormat::CoinLedger, RowKind::Fee) => "Fee",
+            (ExportFormat::CoinLedger, RowKind::Staking) => "Staking",
+            (ExportFormat::CoinLedger, RowKind::Income) => "Income",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKind {
+    Trade,
+    Deposit,
+    Withdrawal,
+    Fee, // A transaction that only cost its network fee
+    Staking,
+    Income,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Amount {
+    pub quantity: BigDecimal, // Always positive; the column says which direction
+    pub asset: String,
+}
+
+// One line of a universal CSV: what left the wallet, what arrived, and the fee paid for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportRow {
+    pub timestamp: i64,
+    pub kind: RowKind,
+    pub sent: Option<Amount>,
+    pub received: Option<Amount>,
+    pub fee: Option<Amount>,
+    pub net_worth: Option<Amount>, // Fiat value of the row, only Koinly carries it
+    pub tx_hash: Option<String>,
+}
+
+/// Collapses ledger entries into export rows per transaction: the two legs of a trade become one row,
+/// and the transaction's fee is attached to its first row.
+pub fn build_rows(entries: &[LedgerEntry], tx_hashes: &HashMap<Uuid, String>) -> Vec<ExportRow> {
+    let mut order: Vec<Uuid> = Vec::new();
+    let mut by_tx: HashMap<Uuid, Vec<&LedgerEntry>> = HashMap::new();
+    for entry in entries {
+        by_tx.entry(entry.transaction_id).or_insert_with(|| {
+            order.push(entry.transaction_id);
+            Vec::new()
+        }).push(entry);
+    }
+
+    let mut rows = Vec::new();
+    for tx_id in order {
+        let group = &by_tx[&tx_id];
+        let timestamp = group[0].timestamp;
+        let tx_hash = tx_hashes.get(&tx_id).cloned();
+        let row = |kind, sent, received, net_worth| ExportRow {
+            timestamp,
+            kind,
+            sent,
+            received,
+            fee: None,
+            net_worth,
+            tx_hash: tx_hash.clone(),
+        };
+
+        // Fees are summed per asset; only the first can ride along on a row
+        let mut fees: Vec<Amount> = Vec::new();
+        for entry in group.iter().filter(|e| matches!(e.entry_type, EntryType::Fee)) {
+            match fees.iter_mut().find(|f| f.asset == entry.asset_symbol) {
+                Some(fee) => fee.quantity += entry.amount.abs(),
+                None => fees.push(amount(entry)),
+            }
+        }
+
+        let mut tx_rows = Vec::new();
+        let trades: Vec<&LedgerEntry> = group.iter().copied().filter(|e| matches!(e.entry_type, EntryType::Trade)).collect();
+        let paired = match trades.as_slice() {
+            [a, b] if is_outflow(a) != is_outflow(b) => {
+                let (sent, received) = if is_outflow(a) { (a, b) } else { (b, a) };
+                tx_rows.push(row(RowKind::Trade, Some(amount(sent)), Some(amount(received)), net_worth(received).or_else(|| net_worth(sent))));
+                true
+            }
+            _ => false,
+        };
+
+        for entry in group.iter().filter(|e| !matches!(e.entry_type, EntryType::Fee)) {
+            if paired && matches!(entry.entry_type, EntryType::Trade) {
+                continue;
+            }
+            let kind = match (&entry.entry_type, is_outflow(entry)) {
+                (_, true) => RowKind::Withdrawal,
+                (EntryType::Staking, false) => RowKind::Staking,
+                (EntryType::Income, false) => RowKind::Income,
+                _ => RowKind::Deposit,
+            };
+            let (sent, received) = if is_outflow(entry) { (Some(amount(entry)), None) } else { (None, Some(amount(entry))) };
+            tx_rows.push(row(kind, sent, received, net_worth(entry)));
+        }
+
+        let mut fees = fees.into_iter();
+        if let Some(first) = tx_rows.first_mut() {
+            first.fee = fees.next();
+        }
+        rows.extend(tx_rows);
+        for fee in fees {
+            rows.push(row(RowKind::Fee, Some(fee), None, None));
+        }
+    }
+    rows
+}
+
+pub fn write_csv<W: Write>(format: ExportFormat, rows: &[ExportRow], writer: W) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(format.header())?;
+
+    for row in rows {
+        let date = DateTime::from_timestamp(row.timestamp, 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {}", row.timestamp))?
+            .format(format.date_format())
+            .to_string();
+        let (sent_qty, sent_asset) = columns(&row.sent);
+        let (received_qty, received_asset) = columns(&row.received);
+        let (fee_qty, fee_asset) = columns(&row.fee);
+        let label = format.label(row.kind).to_string();
+        let tx_hash = row.tx_hash.clone().unwrap_or_default();
+
+        let record = match format {
+            ExportFormat::Koinly => {
+                let (worth_qty, worth_currency) = columns(&row.net_worth);
+                vec![
+                    date, sent_qty, sent_asset, received_qty, received_asset, fee_qty, fee_asset,
+                    worth_qty, worth_currency, label, String::new(), tx_hash,
+                ]
+            }
+            ExportFormat::CoinTracker => vec![date, received_qty, received_asset, sent_qty, sent_asset, fee_qty, fee_asset, label],
+            ExportFormat::CoinLedger => vec![
+                date, String::new(), sent_asset, sent_qty, received_asset, received_qty, fee_asset, fee_qty, label, String::new(), tx_hash,
+            ],
+        };
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads a file in one of the export layouts back into rows. Columns a format doesn't have come back as None.
+pub fn read_csv<R: Read>(format: ExportFormat, reader: R) -> anyhow::Result<Vec<ExportRow>> {
+    let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let headers: HashMap<String, usize> = rdr.headers()?.iter().enumerate().map(|(i, h)| (h.to_string(), i)).collect();
+    if let Some(missing) = format.header().iter().find(|h| !headers.contains_key(**h)) {
+        anyhow::bail!("{:?} CSV is missing a {} column", format, missing);
+    }
+
+    let mut rows = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let field = |name: &str| record.get(headers[name]).unwrap_or_default();
+
+        let (date, sent, received, fee, label, net_worth, tx_hash) = match format {
+            ExportFormat::Koinly => (
+                field("Date"),
+                parse_amount(field("Sent Amount"), field("Sent Currency"))?,
+                parse_amount(field("Received Amount"), field("Received Currency"))?,
+                parse_amount(field("Fee Amount"), field("Fee Currency"))?,
+                field("Label"),
+                parse_amount(field("Net Worth Amount"), field("Net Worth Currency"))?,
+                field("TxHash"),
+            ),
+            ExportFormat::CoinTracker => (
+                field("Date"),
+                parse_amount(field("Sent Quantity"), field("Sent Currency"))?,
+                parse_amount(field("Received Quantity"), field("Received Currency"))?,
+                parse_amount(field("Fee Amount"), field("Fee Currency"))?,
+                field("Tag"),
+                None,
+                "",
+            ),
+            ExportFormat::CoinLedger => (
+                field("Date (UTC)"),
+                parse_amount(field("Amount Sent"), field("Asset Sent"))?,
+                parse_amount(field("Amount Received"), field("Asset Received"))?,
+                parse_amount(field("Fee Amount (Optional)"), field("Fee Currency (Optional)"))?,
+                field("Type"),
+                None,
+                field("TxHash (Optional)"),
+            ),
+        };
+
+        let timestamp = NaiveDateTime::parse_from_str(date, format.date_format())?.and_utc().timestamp();
+
+        // Labels only name the special kinds; the rest follow from which side is filled in
+        let labelled = [RowKind::Fee, RowKind::Staking, RowKind::Income, RowKind::Trade, RowKind::Deposit, RowKind::Withdrawal]
+            .into_iter()
+            .find(|k| !format.label(*k).is_empty() && format.label(*k).eq_ignore_ascii_case(label));
+        let kind = match (labelled, &sent, &received) {
+            (Some(kind), _, _) => kind,
+            (None, Some(_), Some(_)) => RowKind::Trade,
+            (None, Some(_), None) => RowKind::Withdrawal,
+            (None, None, _) => RowKind::Deposit,
+        };
+
+        rows.push(ExportRow {
+            timestamp,
+            kind,
+            sent,
+            received,
+            fee,
+            net_worth,
+            tx_hash: Some(tx_hash.to_string()).filter(|h| !h.is_empty()),
+        });
+    }
+    Ok(rows)
+}
+
+fn is_outflow(entry: &LedgerEntry) -> bool {
+    entry.amount.sign() == Sign::Minus
+}
+
+fn amount(entry: &LedgerEntry) -> Amount {
+    Amount {
+        quantity: entry.amount.abs(),
+        asset: entry.asset_symbol.clone(),
+    }
+}
+
+fn net_worth(entry: &LedgerEntry) -> Option<Amount> {
+    Some(Amount {
+        quantity: entry.fiat_value.as_ref()?.abs(),
+        asset: entry.fiat_currency.clone()?,
+    })
+}
+
+fn columns(amount: &Option<Amount>) -> (String, String) {
+    match amount {
+        Some(a) => (plain(&a.quantity), a.asset.clone()),
+        None => (String::new(), String::new()),
+    }
+}
+
+// Tax tools don't accept exponent notation
+fn plain(value: &BigDecimal) -> String {
+    let value = value.normalized();
+    if value.as_bigint_and_exponent().1 < 0 {
+        value.with_scale(0).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn parse_amount(quantity: &str, asset: &str) -> anyhow::Result<Option<Amount>> {
+    if quantity.is_empty() || asset.is_empty() {
+        return Ok(None);
+    }
+    let quantity = BigDecimal::from_str(quantity)?;
+    if quantity.is_zero() {
+        return Ok(None);
+    }
+    Ok(Some(Amount {
+        quantity,
+        asset: asset.to_string(),
+    }))
+}
@@ -0,0 +1,77 @@
+use spectraplex_core::models::{Chain, ChainIngestor, Transaction};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+pub const HYPERLIQUID_API_URL: &str = "https://api.hyperliquid.xyz";
+
+pub struct HyperliquidAdapter {
+    client: reqwest::Client,
+    api_url: String,
+}
+
+impl HyperliquidAdapter {
+    pub fn new(api_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn info(&self, request: Value) -> anyhow::Result<Vec<Value>> {
+        let response = self
+            .client
+            .post(format!("{}/info", self.api_url))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?;
+        let body: Value = response.json().await?;
+        match body {
+            Value::Array(items) => Ok(items),
+            other => Err(anyhow::anyhow!("Unexpected Hyperliquid info response for {}: {}", request["type"], other)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ChainIngestor for HyperliquidAdapter {
+    async fn fetch_history(&self, wallet: &str, limit: usize) -> anyhow::Result<Vec<Transaction>> {
+        let fills = self.info(json!({ "type": "userFills", "user": wallet })).await?;
+        let funding = self.info(json!({ "type": "userFunding", "user": wallet, "startTime": 0 })).await?;
+        // Deposits, withdrawals, transfers and liquidations
+        let ledger = self.info(json!({ "type": "userNonFundingLedgerUpdates", "user": wallet, "startTime": 0 })).await?;
+
+        let mut events: Vec<(&str, Value)> = Vec::new();
+        events.extend(fills.into_iter().map(|f| ("fill", f)));
+        events.extend(funding.into_iter().map(|f| ("funding", f)));
+        events.extend(ledger.into_iter().map(|l| ("ledger", l)));
+
+        let mut transactions: Vec<Transaction> = events
+            .into_iter()
+            .map(|(kind, data)| {
+                let time_ms = data["time"].as_i64().unwrap_or(0);
+                let hash = data["hash"].as_str().unwrap_or_default();
+                // Several fills of one order share a hash, so the trade id keeps them apart
+                let tx_hash = match (kind, data["tid"].as_i64()) {
+                    ("fill", Some(tid)) => format!("{}:{}", hash, tid),
+                    ("funding", _) => format!("funding:{}:{}", data["delta"]["coin"].as_str().unwrap_or_default(), time_ms),
+                    _ => hash.to_string(),
+                };
+                Transaction {
+                    id: Uuid::new_v4(),
+                    user_id: Uuid::nil(), // Placeholder
+                    wallet_address: wallet.to_string(),
+                    timestamp: time_ms / 1000,
+                    tx_hash,
+                    chain: Chain::Hyperliquid,
+                    raw_metadata: json!({ "kind": kind, "data": data }),
+                }
+            })
+            .collect();
+
+        // Most recent `limit` events, oldest first
+        transactions.sort_by_key(|t| t.timestamp);
+        let skip = transactions.len().saturating_sub(limit);
+        Ok(transactions.split_off(skip))
+    }
+}
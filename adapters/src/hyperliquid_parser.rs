@@ -0,0 +1,116 @@
+use spectraplex_core::models::{EntryType, LedgerEntry, Transaction};
+use bigdecimal::{BigDecimal, Zero};
+use serde_json::Value;
+use std::str::FromStr;
+
+// Perps margin and PnL settle in USDC
+const SETTLEMENT_ASSET: &str = "USDC";
+
+/// Normalizes a bronze transaction produced by `HyperliquidAdapter`.
+pub fn parse_hyperliquid_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEntry>> {
+    let data = &tx.raw_metadata["data"];
+    match tx.raw_metadata["kind"].as_str() {
+        Some("fill") => parse_fill(tx, data),
+        Some("funding") => parse_funding(tx, data),
+        Some("ledger") => parse_ledger_update(tx, data),
+        other => Err(anyhow::anyhow!("Unknown Hyperliquid event kind {:?} in {}", other, tx.tx_hash)),
+    }
+}
+
+fn parse_fill(tx: &Transaction, fill: &Value) -> anyhow::Result<Vec<LedgerEntry>> {
+    let mut entries = Vec::new();
+    let coin = str_field(fill, "coin")?;
+    let dir = fill["dir"].as_str().unwrap_or_default();
+
+    if dir == "Buy" || dir == "Sell" {
+        // Spot fills swap the token against USDC
+        let size = decimal(fill, "sz")?;
+        let notional = &size * decimal(fill, "px")?;
+        let (token, quote) = if dir == "Buy" { (size, -notional) } else { (-size, notional) };
+        entries.push(LedgerEntry::new(tx, coin.to_string(), token, EntryType::Trade));
+        entries.push(LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), quote, EntryType::Trade));
+    } else {
+        // Perp positions aren't assets we hold; only the realized PnL moves the margin balance.
+        // Liquidation fills carry their loss here too.
+        let pnl = decimal(fill, "closedPnl")?;
+        if !pnl.is_zero() {
+            let mut entry = LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), pnl, EntryType::Trade);
+            entry.source_program = Some(format!("perp:{}", coin));
+            entries.push(entry);
+        }
+    }
+
+    let fee = decimal(fill, "fee")?;
+    if !fee.is_zero() {
+        // Negative fees are maker rebates
+        let fee_token = fill["feeToken"].as_str().unwrap_or(SETTLEMENT_ASSET);
+        entries.push(LedgerEntry::new(tx, fee_token.to_string(), -fee, EntryType::Fee));
+    }
+    Ok(entries)
+}
+
+fn parse_funding(tx: &Transaction, funding: &Value) -> anyhow::Result<Vec<LedgerEntry>> {
+    let delta = &funding["delta"];
+    let usdc = decimal(delta, "usdc")?;
+    if usdc.is_zero() {
+        return Ok(vec![]);
+    }
+    // Positive when the position was paid funding, negative when it paid
+    let mut entry = LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), usdc, EntryType::Income);
+    entry.source_program = Some(format!("funding:{}", str_field(delta, "coin")?));
+    Ok(vec![entry])
+}
+
+fn parse_ledger_update(tx: &Transaction, update: &Value) -> anyhow::Result<Vec<LedgerEntry>> {
+    let delta = &update["delta"];
+    let wallet = tx.wallet_address.to_lowercase();
+    let outgoing = |delta: &Value| delta["user"].as_str().map(|u| u.to_lowercase() == wallet).unwrap_or(false);
+
+    let mut entries = Vec::new();
+    match str_field(delta, "type")? {
+        "deposit" => entries.push(LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), decimal(delta, "usdc")?, EntryType::Transfer)),
+        "withdraw" => {
+            // The bridge fee comes out of the amount the user asked to withdraw
+            let fee = optional_decimal(delta, "fee")?;
+            entries.push(LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), -(decimal(delta, "usdc")? - &fee), EntryType::Transfer));
+            if !fee.is_zero() {
+                entries.push(LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), -fee, EntryType::Fee));
+            }
+        }
+        "internalTransfer" | "subAccountTransfer" => {
+            let usdc = decimal(delta, "usdc")?;
+            let amount = if outgoing(delta) { -usdc } else { usdc };
+            entries.push(LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), amount, EntryType::Transfer));
+            let fee = optional_decimal(delta, "fee")?;
+            if outgoing(delta) && !fee.is_zero() {
+                entries.push(LedgerEntry::new(tx, SETTLEMENT_ASSET.to_string(), -fee, EntryType::Fee));
+            }
+        }
+        "spotTransfer" => {
+            let token = str_field(delta, "token")?;
+            let amount = decimal(delta, "amount")?;
+            let amount = if outgoing(delta) { -amount } else { amount };
+            entries.push(LedgerEntry::new(tx, token.to_string(), amount, EntryType::Transfer));
+        }
+        // Spot <-> perp moves stay inside the account, and a liquidation's loss is booked on its fills
+        "accountClassTransfer" | "liquidation" => {}
+        other => eprintln!("Skipping unsupported Hyperliquid ledger update {} in {}", other, tx.tx_hash),
+    }
+    Ok(entries)
+}
+
+fn str_field<'a>(value: &'a Value, name: &str) -> anyhow::Result<&'a str> {
+    value[name].as_str().ok_or_else(|| anyhow::anyhow!("Hyperliquid event is missing {}", name))
+}
+
+// Hyperliquid sends every number as a string
+fn decimal(value: &Value, name: &str) -> anyhow::Result<BigDecimal> {
+    Ok(BigDecimal::from_str(str_field(value, name)?)?)
+}
+
+fn optional_decimal(value: &Value, name: &str) -> anyhow::Result<BigDecimal> {
+    match value[name].as_str() {
+        Some(s) => Ok(BigDecimal::from_str(s)?),
+        None => Ok(BigDecimal::zero()),
+    }
+}
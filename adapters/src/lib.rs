@@ -1,4 +1,19 @@
 pub mod solana;
 pub mod solana_grpc;
 pub mod solana_parser;
-pub mod repo;
\ No newline at end of file
+pub mod solana_nft;
+pub mod bubblegum;
+pub mod solana_lending;
+pub mod solana_liquidity;
+pub mod solana_bridge;
+pub mod repo;
+pub mod sqlite;
+pub mod price_import;
+pub mod export;
+pub mod cex_import;
+pub mod hyperliquid;
+pub mod hyperliquid_parser;
+pub mod evm;
+pub mod ethereum;
+pub mod ethereum_parser;
+pub mod normalize;
\ No newline at end of file
@@ -0,0 +1,44 @@
+use crate::{cex_import, ethereum_parser, hyperliquid_parser, solana_parser};
+use spectraplex_core::adjustments::{self, Adjustment, AuditRecord, Change, ManualEntry};
+use spectraplex_core::models::{Chain, LedgerEntry, ParserVersion, Transaction};
+use spectraplex_core::storage::Storage;
+use uuid::Uuid;
+
+/// Parser that handles transactions from `chain`, at its current version. Bump a version whenever a change to
+/// that parser (or a decoder it calls) changes the entries it produces, so re-normalizing rebuilds them.
+pub fn parser_for(chain: &Chain) -> ParserVersion {
+    let (name, version) = match chain {
+        Chain::Solana => ("solana", 1),
+        Chain::Hyperliquid => ("hyperliquid", 1),
+        Chain::Ethereum | Chain::Evm(_) => ("ethereum", 1),
+        Chain::Coinbase | Chain::Kraken | Chain::Binance => ("cex", 1),
+        Chain::Manual => ("manual", 1),
+    };
+    ParserVersion { name: name.to_string(), version }
+}
+
+/// Parses a raw transaction into ledger entries with the parser for its chain, stamped with that parser's version.
+pub fn parse_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEntry>> {
+    let mut entries = match tx.chain {
+        Chain::Solana => solana_parser::parse_solana_transaction(tx)?,
+        Chain::Hyperliquid => hyperliquid_parser::parse_hyperliquid_transaction(tx)?,
+        Chain::Ethereum | Chain::Evm(_) => ethereum_parser::parse_ethereum_transaction(tx)?,
+        Chain::Coinbase | Chain::Kraken | Chain::Binance => cex_import::parse_cex_transaction(tx)?,
+        Chain::Manual => adjustments::parse_manual_transaction(tx)?,
+    };
+    let parser = parser_for(&tx.chain);
+    for entry in &mut entries {
+        entry.parser = Some(parser.clone());
+    }
+    Ok(entries)
+}
+
+/// Saves a hand-entered movement as a manual transaction with its entry, and records it in the audit trail.
+pub async fn create_manual_entry<S: Storage + ?Sized>(store: &S, user_id: Uuid, manual: &ManualEntry) -> anyhow::Result<LedgerEntry> {
+    let tx = manual.to_transaction(user_id)?;
+    let entries = parse_transaction(&tx)?;
+    let entry = entries.first().cloned().ok_or_else(|| anyhow::anyhow!("Manual transaction {} has no entry", tx.tx_hash))?;
+    let record = AuditRecord::new(&entry, &Adjustment::new(&entry), &Change::CreateEntry(manual.clone()), chrono::Utc::now().timestamp());
+    store.save_manual_entry(&tx, &entries, &record).await?;
+    Ok(entry)
+}
@@ -0,0 +1,175 @@
+use spectraplex_core::fx::{normalize_currency, FxRate};
+use spectraplex_core::pricing::PriceCandle;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate};
+use serde_json::Value;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+// What an OHLC file is a history of. Files only carry time and prices.
+#[derive(Debug, Clone)]
+pub struct OhlcImport {
+    pub asset_symbol: String,
+    pub fiat_currency: String,
+    pub resolution_secs: i64,
+    pub source: String,
+}
+
+impl OhlcImport {
+    fn candle(&self, open_time: i64, open: BigDecimal, high: BigDecimal, low: BigDecimal, close: BigDecimal) -> PriceCandle {
+        PriceCandle {
+            asset_symbol: self.asset_symbol.clone(),
+            fiat_currency: self.fiat_currency.to_uppercase(),
+            open_time,
+            resolution_secs: self.resolution_secs,
+            open,
+            high,
+            low,
+            close,
+            source: self.source.clone(),
+        }
+    }
+}
+
+/// Loads a `.csv` or `.json` OHLC file, picking the parser from the extension.
+pub fn load_ohlc_file(path: &Path, import: &OhlcImport) -> anyhow::Result<Vec<PriceCandle>> {
+    let file = std::fs::File::open(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => parse_ohlc_csv(file, import),
+        Some("json") => {
+            let value: Value = serde_json::from_reader(file)?;
+            parse_ohlc_json(&value, import)
+        }
+        _ => Err(anyhow::anyhow!("Unsupported price file {:?}, expected .csv or .json", path)),
+    }
+}
+
+/// CSV with a header row naming a time column (timestamp/time/open_time/date) and open, high, low, close.
+/// Extra columns such as volume are ignored.
+pub fn parse_ohlc_csv<R: Read>(reader: R, import: &OhlcImport) -> anyhow::Result<Vec<PriceCandle>> {
+    let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.to_lowercase()).collect();
+
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|h| names.contains(&h.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("Price CSV is missing a {} column", names[0]))
+    };
+    let time_idx = column(&["timestamp", "time", "open_time", "date"])?;
+    let open_idx = column(&["open"])?;
+    let high_idx = column(&["high"])?;
+    let low_idx = column(&["low"])?;
+    let close_idx = column(&["close"])?;
+
+    let mut candles = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let field = |idx: usize| record.get(idx).unwrap_or_default();
+        candles.push(import.candle(
+            parse_time(field(time_idx))?,
+            BigDecimal::from_str(field(open_idx))?,
+            BigDecimal::from_str(field(high_idx))?,
+            BigDecimal::from_str(field(low_idx))?,
+            BigDecimal::from_str(field(close_idx))?,
+        ));
+    }
+    Ok(candles)
+}
+
+/// JSON array of either objects (`{"timestamp", "open", "high", "low", "close"}`)
+/// or exchange-style rows (`[time, open, high, low, close, ...]`).
+pub fn parse_ohlc_json(value: &Value, import: &OhlcImport) -> anyhow::Result<Vec<PriceCandle>> {
+    let rows = value
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("Price JSON must be an array of candles"))?;
+
+    let mut candles = Vec::new();
+    for row in rows {
+        let fields: Vec<&Value> = match row {
+            Value::Array(items) if items.len() >= 5 => items.iter().take(5).collect(),
+            Value::Object(map) => {
+                let time = ["timestamp", "time", "open_time", "date"]
+                    .iter()
+                    .find_map(|k| map.get(*k))
+                    .ok_or_else(|| anyhow::anyhow!("Candle is missing a timestamp: {}", row))?;
+                let mut fields = vec![time];
+                for key in ["open", "high", "low", "close"] {
+                    fields.push(map.get(key).ok_or_else(|| anyhow::anyhow!("Candle is missing {}: {}", key, row))?);
+                }
+                fields
+            }
+            _ => return Err(anyhow::anyhow!("Unrecognised candle: {}", row)),
+        };
+
+        candles.push(import.candle(
+            parse_time(&json_text(fields[0]))?,
+            json_decimal(fields[1])?,
+            json_decimal(fields[2])?,
+            json_decimal(fields[3])?,
+            json_decimal(fields[4])?,
+        ));
+    }
+    Ok(candles)
+}
+
+/// FX rate CSV with a header naming a date column (date/time/timestamp) and a rate column (rate/close).
+/// Each row is the price of one `base` in `quote` for that day.
+pub fn parse_fx_csv<R: Read>(reader: R, base: &str, quote: &str, source: &str) -> anyhow::Result<Vec<FxRate>> {
+    let base = normalize_currency(base)?;
+    let quote = normalize_currency(quote)?;
+    let mut rdr = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let headers: Vec<String> = rdr.headers()?.iter().map(|h| h.to_lowercase()).collect();
+
+    let date_idx = headers
+        .iter()
+        .position(|h| ["date", "time", "timestamp"].contains(&h.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("FX CSV is missing a date column"))?;
+    let rate_idx = headers
+        .iter()
+        .position(|h| ["rate", "close"].contains(&h.as_str()))
+        .ok_or_else(|| anyhow::anyhow!("FX CSV is missing a rate column"))?;
+
+    let mut rates = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let timestamp = parse_time(record.get(date_idx).unwrap_or_default())?;
+        let rate_date = DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid FX date in {:?}", record))?
+            .date_naive();
+        rates.push(FxRate {
+            base_currency: base.clone(),
+            quote_currency: quote.clone(),
+            rate_date,
+            rate: BigDecimal::from_str(record.get(rate_idx).unwrap_or_default())?,
+            source: source.to_string(),
+        });
+    }
+    Ok(rates)
+}
+
+// Accepts unix seconds, unix milliseconds, RFC 3339 or a bare YYYY-MM-DD date (UTC)
+fn parse_time(value: &str) -> anyhow::Result<i64> {
+    if let Ok(n) = value.parse::<i64>() {
+        return Ok(if n > 100_000_000_000 { n / 1000 } else { n });
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp());
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).expect("midnight").and_utc().timestamp());
+    }
+    Err(anyhow::anyhow!("Unrecognised timestamp: {}", value))
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn json_decimal(value: &Value) -> anyhow::Result<BigDecimal> {
+    Ok(BigDecimal::from_str(&json_text(value))?)
+}
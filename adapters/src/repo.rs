@@ -1,5 +1,15 @@
-use spectraplex_core::models::{Transaction, LedgerEntry};
-use sqlx::{postgres::PgPool, Row};
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, Transaction, LedgerEntry, ParserVersion, UserSettings};
+use spectraplex_core::adjustments::{self, Adjustment, AuditRecord, Change};
+use spectraplex_core::balances::DailyChange;
+use spectraplex_core::fx::{FxRate, FxSource};
+use spectraplex_core::spam::SpamOverride;
+use spectraplex_core::pricing::{format_resolution, PriceCandle, PricePoint, PriceSource, OBSERVATION_RESOLUTION_SECS};
+use spectraplex_core::storage::{Cursor, Page, QueryFilter, Storage, SyncState};
+use sqlx::{postgres::{PgConnection, PgPool, PgRow, Postgres}, QueryBuilder, Row};
+use uuid::Uuid;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::HashMap;
 
 pub struct Repository {
     pool: PgPool,
@@ -9,71 +19,105 @@ impl Repository {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+}
 
-    pub async fn save_transactions(&self, txs: &[Transaction]) -> anyhow::Result<()> {
-        for tx in txs {
-            let chain_str = match tx.chain {
-                spectraplex_core::models::Chain::Solana => "solana",
-                spectraplex_core::models::Chain::Hyperliquid => "hyperliquid",
-                spectraplex_core::models::Chain::Ethereum => "ethereum",
-            };
+#[async_trait::async_trait]
+impl Storage for Repository {
+    async fn save_transactions(&self, txs: &[Transaction]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, txs).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    // Rolls them into the daily balances as well
+    async fn save_ledger_entries(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_ledger_entries(&mut db, entries).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_transactions_with_entries(&self, txs: &[Transaction], entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, txs).await?;
+        insert_ledger_entries(&mut db, entries).await?;
+        db.commit().await?;
+        Ok(())
+    }
 
-            // Using unchecked query to avoid needing a running DB during compilation
+    // Takes the deleted entries back out of the daily balances before the replacements go in
+    async fn replace_ledger_entries(&self, transaction_ids: &[Uuid], entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for batch in transaction_ids.chunks(BATCH_SIZE) {
             sqlx::query(
                 r#"
-                INSERT INTO transactions (id, user_id, wallet_address, timestamp, tx_hash, chain, raw_metadata)
-                VALUES ($1, $2, $3, $4, $5, $6::chain_enum, $7)
-                ON CONFLICT (id) DO NOTHING
+                WITH removed AS (
+                    DELETE FROM ledger_entries
+                    WHERE transaction_id = ANY($1)
+                    RETURNING wallet_address, asset_symbol, timestamp, amount, is_spam
+                )
+                INSERT INTO daily_balances (wallet_address, asset_symbol, day, net_change)
+                SELECT wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date, -SUM(amount)
+                FROM removed
+                WHERE NOT is_spam
+                GROUP BY wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date
+                ON CONFLICT (wallet_address, asset_symbol, day)
+                DO UPDATE SET net_change = daily_balances.net_change + EXCLUDED.net_change
                 "#
             )
-            .bind(tx.id)
-            .bind(tx.user_id)
-            .bind(&tx.wallet_address)
-            .bind(tx.timestamp)
-            .bind(&tx.tx_hash)
-            .bind(chain_str)
-            .bind(&tx.raw_metadata)
-            .execute(&self.pool)
+            .bind(batch)
+            .execute(&mut *db)
             .await?;
         }
+        insert_ledger_entries(&mut db, entries).await?;
+        db.commit().await?;
         Ok(())
     }
 
-    pub async fn save_ledger_entries(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
-        for entry in entries {
-            let entry_type_str = match entry.entry_type {
-                spectraplex_core::models::EntryType::Trade => "trade",
-                spectraplex_core::models::EntryType::Fee => "fee",
-                spectraplex_core::models::EntryType::Transfer => "transfer",
-                spectraplex_core::models::EntryType::Staking => "staking",
-                spectraplex_core::models::EntryType::Income => "income",
-            };
-            
-            sqlx::query(
-                r#"
-                INSERT INTO ledger_entries (id, transaction_id, user_id, wallet_address, asset_symbol, amount, entry_type, fiat_value)
-                VALUES ($1, $2, $3, $4, $5, $6, $7::entry_type_enum, $8)
-                ON CONFLICT (id) DO NOTHING
-                "#
-            )
-            .bind(entry.id)
-            .bind(entry.transaction_id)
-            .bind(entry.user_id)
-            .bind(&entry.wallet_address)
-            .bind(&entry.asset_symbol)
-            .bind(&entry.amount)
-            .bind(entry_type_str)
-            .bind(&entry.fiat_value)
-            .execute(&self.pool)
+    async fn save_ingested(&self, txs: &[Transaction], state: &SyncState) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, txs).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO sync_state (wallet_address, chain, last_timestamp, last_tx_hashes)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (wallet_address, chain)
+            DO UPDATE SET last_timestamp = EXCLUDED.last_timestamp, last_tx_hashes = EXCLUDED.last_tx_hashes, updated_at = NOW()
+            "#
+        )
+        .bind(&state.wallet_address)
+        .bind(state.chain.name())
+        .bind(state.last_timestamp)
+        .bind(&state.last_tx_hashes)
+        .execute(&mut *db)
+        .await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn get_sync_state(&self, wallet: &str, chain: &Chain) -> anyhow::Result<Option<SyncState>> {
+        let row = sqlx::query("SELECT last_timestamp, last_tx_hashes FROM sync_state WHERE wallet_address = $1 AND chain = $2")
+            .bind(wallet)
+            .bind(chain.name())
+            .fetch_optional(&self.pool)
             .await?;
+
+        match row {
+            Some(row) => Ok(Some(SyncState {
+                wallet_address: wallet.to_string(),
+                chain: chain.clone(),
+                last_timestamp: row.try_get("last_timestamp")?,
+                last_tx_hashes: row.try_get("last_tx_hashes")?,
+            })),
+            None => Ok(None),
         }
-        Ok(())
     }
-    
-    pub async fn get_transactions_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<Transaction>> {
+
+    async fn get_transactions_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<Transaction>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, user_id, wallet_address, timestamp, tx_hash, chain::text, raw_metadata
+            SELECT id, user_id, wallet_address, timestamp, tx_hash, chain, raw_metadata
             FROM transactions
             WHERE wallet_address = $1
             ORDER BY timestamp ASC
@@ -83,68 +127,773 @@ impl Repository {
         .fetch_all(&self.pool)
         .await?;
 
-        let mut txs = Vec::new();
-        for row in rows {
-            let chain_str: String = row.try_get("chain")?;
-            let chain = match chain_str.as_str() {
-                "solana" => spectraplex_core::models::Chain::Solana,
-                "hyperliquid" => spectraplex_core::models::Chain::Hyperliquid,
-                "ethereum" => spectraplex_core::models::Chain::Ethereum,
-                _ => return Err(anyhow::anyhow!("Unknown chain: {}", chain_str)),
-            };
-            
-            txs.push(Transaction {
-                id: row.try_get("id")?,
-                user_id: row.try_get("user_id")?,
-                wallet_address: row.try_get("wallet_address")?,
-                timestamp: row.try_get("timestamp")?,
-                tx_hash: row.try_get("tx_hash")?,
-                chain,
-                raw_metadata: row.try_get("raw_metadata")?,
-            });
-        }
-        Ok(txs)
+        rows.iter().map(transaction_from_row).collect()
     }
 
-    pub async fn get_ledger_entries_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<LedgerEntry>> {
+    async fn get_ledger_entries_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<LedgerEntry>> {
         // Optimized: Query directly on indexed wallet_address column
         let rows = sqlx::query(
             r#"
             SELECT 
-                id, transaction_id, user_id, wallet_address, asset_symbol, amount, 
-                entry_type::text, fiat_value
+                id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount, 
+                entry_type::text, fiat_value, fiat_currency, price_source, price_resolution, source_program, is_spam, position_account,
+                bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version
             FROM ledger_entries
             WHERE wallet_address = $1
-            ORDER BY created_at ASC
+            ORDER BY timestamp ASC, created_at ASC
+            "#
+        )
+        .bind(wallet)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(ledger_entry_from_row).collect()
+    }
+
+    async fn get_ledger_entries_by_user(&self, user_id: Uuid) -> anyhow::Result<Vec<LedgerEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT 
+                id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount, 
+                entry_type::text, fiat_value, fiat_currency, price_source, price_resolution, source_program, is_spam, position_account,
+                bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version
+            FROM ledger_entries
+            WHERE user_id = $1
+            ORDER BY timestamp ASC, created_at ASC
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(ledger_entry_from_row).collect()
+    }
+
+    async fn get_wallet_owner(&self, wallet: &str) -> anyhow::Result<Option<Uuid>> {
+        let row = sqlx::query("SELECT user_id FROM transactions WHERE wallet_address = $1 LIMIT 1")
+            .bind(wallet)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(Some(row.try_get("user_id")?)),
+            None => Ok(None),
+        }
+    }
+
+    // Read from the daily_balances table the entry writes keep up to date, rather than summing every entry
+    async fn get_daily_balance_changes(&self, wallet: &str, to: NaiveDate) -> anyhow::Result<Vec<DailyChange>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT asset_symbol, day, net_change
+            FROM daily_balances
+            WHERE wallet_address = $1 AND day <= $2
+            ORDER BY day ASC
+            "#
+        )
+        .bind(wallet)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut changes = Vec::new();
+        for row in rows {
+            changes.push(DailyChange {
+                asset_symbol: row.try_get("asset_symbol")?,
+                day: row.try_get("day")?,
+                net_change: row.try_get("net_change")?,
+            });
+        }
+        Ok(changes)
+    }
+
+    async fn page_transactions(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<Transaction>> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT t.id, t.user_id, t.wallet_address, t.timestamp, t.tx_hash, t.chain, t.raw_metadata FROM transactions t WHERE t.wallet_address = "
+        );
+        query.push_bind(wallet);
+        push_filter(&mut query, filter, after, "t");
+        if filter.asset.is_some() || filter.entry_type.is_some() {
+            query.push(" AND EXISTS (SELECT 1 FROM ledger_entries l WHERE l.transaction_id = t.id");
+            push_entry_fields(&mut query, filter);
+            query.push(")");
+        }
+        push_tx_fields(&mut query, filter);
+        query.push(" ORDER BY t.timestamp ASC, t.id ASC LIMIT ").push_bind(limit as i64 + 1);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let txs = rows.iter().map(transaction_from_row).collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Page::from_rows(txs, limit, |t| Cursor { timestamp: t.timestamp, id: t.id }))
+    }
+
+    async fn page_ledger_entries(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<LedgerEntry>> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT
+                l.id, l.transaction_id, l.user_id, l.wallet_address, l.timestamp, l.asset_symbol, l.amount,
+                l.entry_type::text, l.fiat_value, l.fiat_currency, l.price_source, l.price_resolution, l.source_program, l.is_spam, l.position_account,
+                l.bridge_protocol, l.bridge_destination_chain, l.bridge_destination_address, l.bridge_transfer_id, l.linked_entry_id, l.parser_name, l.parser_version
+            FROM ledger_entries l
+            "#
+        );
+        if filter.chain.is_some() || filter.tx_hash.is_some() {
+            query.push(" JOIN transactions t ON t.id = l.transaction_id");
+        }
+        query.push(" WHERE l.wallet_address = ").push_bind(wallet);
+        push_filter(&mut query, filter, after, "l");
+        push_entry_fields(&mut query, filter);
+        push_tx_fields(&mut query, filter);
+        query.push(" ORDER BY l.timestamp ASC, l.id ASC LIMIT ").push_bind(limit as i64 + 1);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let entries = rows.iter().map(ledger_entry_from_row).collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Page::from_rows(entries, limit, |e| Cursor { timestamp: e.timestamp, id: e.id }))
+    }
+
+    async fn get_tx_hashes(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, String>> {
+        let rows = sqlx::query("SELECT id, tx_hash FROM transactions WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut hashes = HashMap::new();
+        for row in rows {
+            hashes.insert(row.try_get("id")?, row.try_get("tx_hash")?);
+        }
+        Ok(hashes)
+    }
+
+    async fn get_transaction_chains(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, Chain>> {
+        let rows = sqlx::query("SELECT id, chain FROM transactions WHERE id = ANY($1)")
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut chains = HashMap::new();
+        for row in rows {
+            let chain: String = row.try_get("chain")?;
+            chains.insert(row.try_get("id")?, Chain::from_name(&chain));
+        }
+        Ok(chains)
+    }
+
+    async fn update_ledger_valuations(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for batch in entries.chunks(BATCH_SIZE) {
+            sqlx::query(
+                r#"
+                UPDATE ledger_entries AS l
+                SET fiat_value = t.fiat_value, fiat_currency = t.fiat_currency, price_source = t.price_source, price_resolution = t.price_resolution
+                FROM UNNEST($1::uuid[], $2::numeric[], $3::text[], $4::text[], $5::text[])
+                    AS t(id, fiat_value, fiat_currency, price_source, price_resolution)
+                WHERE l.id = t.id
+                "#
+            )
+            .bind(batch.iter().map(|e| e.id).collect::<Vec<_>>())
+            .bind(batch.iter().map(|e| e.fiat_value.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|e| e.fiat_currency.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|e| e.price_source.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|e| e.price_resolution.clone()).collect::<Vec<_>>())
+            .execute(&mut *db)
+            .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    // Entries whose flag flips move in or out of the daily balances
+    async fn update_spam_flags(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for batch in entries.chunks(BATCH_SIZE) {
+            sqlx::query(
+                r#"
+                WITH changed AS (
+                    UPDATE ledger_entries AS l
+                    SET is_spam = t.is_spam
+                    FROM UNNEST($1::uuid[], $2::bool[]) AS t(id, is_spam)
+                    WHERE l.id = t.id AND l.is_spam <> t.is_spam
+                    RETURNING l.wallet_address, l.asset_symbol, l.timestamp, l.amount, l.is_spam
+                )
+                INSERT INTO daily_balances (wallet_address, asset_symbol, day, net_change)
+                SELECT wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date, SUM(CASE WHEN is_spam THEN -amount ELSE amount END)
+                FROM changed
+                GROUP BY wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date
+                ON CONFLICT (wallet_address, asset_symbol, day)
+                DO UPDATE SET net_change = daily_balances.net_change + EXCLUDED.net_change
+                "#
+            )
+            .bind(batch.iter().map(|e| e.id).collect::<Vec<_>>())
+            .bind(batch.iter().map(|e| e.is_spam).collect::<Vec<_>>())
+            .execute(&mut *db)
+            .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn update_bridge_links(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let bridged: Vec<(Uuid, &BridgeTransfer)> = entries.iter().filter_map(|e| Some((e.id, e.bridge.as_ref()?))).collect();
+        let mut db = self.pool.begin().await?;
+        for batch in bridged.chunks(BATCH_SIZE) {
+            sqlx::query(
+                r#"
+                UPDATE ledger_entries AS l
+                SET entry_type = 'bridge', bridge_protocol = t.protocol, bridge_destination_chain = t.destination_chain,
+                    bridge_destination_address = t.destination_address, bridge_transfer_id = t.transfer_id, linked_entry_id = t.linked_entry_id
+                FROM UNNEST($1::uuid[], $2::text[], $3::text[], $4::text[], $5::text[], $6::uuid[])
+                    AS t(id, protocol, destination_chain, destination_address, transfer_id, linked_entry_id)
+                WHERE l.id = t.id
+                "#
+            )
+            .bind(batch.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+            .bind(batch.iter().map(|(_, b)| b.protocol.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|(_, b)| b.destination_chain.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|(_, b)| b.destination_address.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|(_, b)| b.transfer_id.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|(_, b)| b.linked_entry_id).collect::<Vec<_>>())
+            .execute(&mut *db)
+            .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_price_candles(&self, candles: &[PriceCandle]) -> anyhow::Result<()> {
+        // A batch can't upsert the same bar twice, so the last copy of a bar wins like it would row by row
+        let candles = last_by_key(candles, |c| (c.asset_symbol.clone(), c.fiat_currency.clone(), c.resolution_secs, c.open_time));
+        let mut tx = self.pool.begin().await?;
+        for batch in candles.chunks(BATCH_SIZE) {
+            // Re-importing a file replaces the bars it covers
+            sqlx::query(
+                r#"
+                INSERT INTO price_history (asset_symbol, fiat_currency, resolution_secs, open_time, open, high, low, close, source)
+                SELECT * FROM UNNEST($1::text[], $2::text[], $3::int8[], $4::int8[], $5::numeric[], $6::numeric[], $7::numeric[], $8::numeric[], $9::text[])
+                ON CONFLICT (asset_symbol, fiat_currency, resolution_secs, open_time)
+                DO UPDATE SET open = EXCLUDED.open, high = EXCLUDED.high, low = EXCLUDED.low, close = EXCLUDED.close, source = EXCLUDED.source
+                "#
+            )
+            .bind(batch.iter().map(|c| c.asset_symbol.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.fiat_currency.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.resolution_secs).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.open_time).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.open.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.high.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.low.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.close.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|c| c.source.as_str()).collect::<Vec<_>>())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn save_fx_rates(&self, rates: &[FxRate]) -> anyhow::Result<()> {
+        let rates = last_by_key(rates, |r| (r.base_currency.clone(), r.quote_currency.clone(), r.rate_date));
+        let mut tx = self.pool.begin().await?;
+        for batch in rates.chunks(BATCH_SIZE) {
+            sqlx::query(
+                r#"
+                INSERT INTO fx_rates (base_currency, quote_currency, rate_date, rate, source)
+                SELECT * FROM UNNEST($1::text[], $2::text[], $3::date[], $4::numeric[], $5::text[])
+                ON CONFLICT (base_currency, quote_currency, rate_date)
+                DO UPDATE SET rate = EXCLUDED.rate, source = EXCLUDED.source
+                "#
+            )
+            .bind(batch.iter().map(|r| r.base_currency.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.quote_currency.as_str()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.rate_date).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.rate.clone()).collect::<Vec<_>>())
+            .bind(batch.iter().map(|r| r.source.as_str()).collect::<Vec<_>>())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_spam_overrides(&self, user_id: Uuid) -> anyhow::Result<HashMap<String, bool>> {
+        let rows = sqlx::query("SELECT asset_symbol, is_spam FROM spam_overrides WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut overrides = HashMap::new();
+        for row in rows {
+            overrides.insert(row.try_get("asset_symbol")?, row.try_get("is_spam")?);
+        }
+        Ok(overrides)
+    }
+
+    async fn save_spam_override(&self, o: &SpamOverride) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO spam_overrides (user_id, asset_symbol, is_spam)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, asset_symbol) DO UPDATE SET is_spam = EXCLUDED.is_spam, updated_at = NOW()
+            "#
+        )
+        .bind(o.user_id)
+        .bind(&o.asset_symbol)
+        .bind(o.is_spam)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_spam_override(&self, user_id: Uuid, asset: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM spam_overrides WHERE user_id = $1 AND asset_symbol = $2")
+            .bind(user_id)
+            .bind(asset)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_user_settings(&self, user_id: Uuid) -> anyhow::Result<Option<UserSettings>> {
+        let row = sqlx::query("SELECT user_id, reporting_currency FROM user_settings WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(UserSettings {
+                user_id: row.try_get("user_id")?,
+                reporting_currency: row.try_get("reporting_currency")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn save_user_settings(&self, settings: &UserSettings) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_settings (user_id, reporting_currency)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET reporting_currency = EXCLUDED.reporting_currency, updated_at = NOW()
             "#
         )
+        .bind(settings.user_id)
+        .bind(&settings.reporting_currency)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_adjustments_by_wallet(&self, wallet: &str) -> anyhow::Result<HashMap<Uuid, Adjustment>> {
+        let rows = sqlx::query(
+            "SELECT entry_id, user_id, wallet_address, entry_type, fiat_value, fiat_currency, note, tags FROM ledger_adjustments WHERE wallet_address = $1"
+        )
         .bind(wallet)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut entries = Vec::new();
+        let mut adjustments = HashMap::new();
+        for row in rows {
+            let a = adjustment_from_row(&row)?;
+            adjustments.insert(a.entry_id, a);
+        }
+        Ok(adjustments)
+    }
+
+    async fn adjust_entry(&self, user_id: Uuid, entry_id: Uuid, change: &Change) -> anyhow::Result<Option<Adjustment>> {
+        let mut db = self.pool.begin().await?;
+        let row = sqlx::query(
+            r#"
+            SELECT
+                id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount,
+                entry_type::text, fiat_value, fiat_currency, price_source, price_resolution, source_program, is_spam, position_account,
+                bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version
+            FROM ledger_entries
+            WHERE id = $1 AND user_id = $2
+            FOR UPDATE
+            "#
+        )
+        .bind(entry_id)
+        .bind(user_id)
+        .fetch_optional(&mut *db)
+        .await?;
+        let Some(row) = row else { return Ok(None) };
+        let mut entry = ledger_entry_from_row(&row)?;
+
+        let row = sqlx::query(
+            "SELECT entry_id, user_id, wallet_address, entry_type, fiat_value, fiat_currency, note, tags FROM ledger_adjustments WHERE entry_id = $1"
+        )
+        .bind(entry_id)
+        .fetch_optional(&mut *db)
+        .await?;
+        let before = match row {
+            Some(row) => adjustment_from_row(&row)?,
+            None => Adjustment::new(&entry),
+        };
+        let (adjustment, record) = adjustments::adjust(&mut entry, &before, change, chrono::Utc::now().timestamp())?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ledger_adjustments (entry_id, user_id, wallet_address, entry_type, fiat_value, fiat_currency, note, tags)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (entry_id) DO UPDATE SET entry_type = EXCLUDED.entry_type, fiat_value = EXCLUDED.fiat_value,
+                fiat_currency = EXCLUDED.fiat_currency, note = EXCLUDED.note, tags = EXCLUDED.tags, updated_at = NOW()
+            "#
+        )
+        .bind(adjustment.entry_id)
+        .bind(adjustment.user_id)
+        .bind(&adjustment.wallet_address)
+        .bind(adjustment.entry_type.as_ref().map(|t| t.name()))
+        .bind(&adjustment.fiat_value)
+        .bind(&adjustment.fiat_currency)
+        .bind(&adjustment.note)
+        .bind(&adjustment.tags)
+        .execute(&mut *db)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE ledger_entries
+            SET entry_type = $2::entry_type_enum, fiat_value = $3, fiat_currency = $4, price_source = $5, price_resolution = $6
+            WHERE id = $1
+            "#
+        )
+        .bind(entry.id)
+        .bind(entry.entry_type.name())
+        .bind(&entry.fiat_value)
+        .bind(&entry.fiat_currency)
+        .bind(&entry.price_source)
+        .bind(&entry.price_resolution)
+        .execute(&mut *db)
+        .await?;
+
+        insert_audit_record(&mut db, &record).await?;
+        db.commit().await?;
+        Ok(Some(adjustment))
+    }
+
+    async fn save_manual_entry(&self, tx: &Transaction, entries: &[LedgerEntry], record: &AuditRecord) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, std::slice::from_ref(tx)).await?;
+        insert_ledger_entries(&mut db, entries).await?;
+        insert_audit_record(&mut db, record).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn get_audit_trail(&self, user_id: Uuid, entry_id: Option<Uuid>) -> anyhow::Result<Vec<AuditRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, entry_id, change, previous, recorded_at
+            FROM adjustment_audit
+            WHERE user_id = $1 AND ($2::uuid IS NULL OR entry_id = $2)
+            ORDER BY recorded_at ASC, id ASC
+            "#
+        )
+        .bind(user_id)
+        .bind(entry_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut records = Vec::new();
         for row in rows {
-            let entry_type_str: String = row.try_get("entry_type")?;
-            let entry_type = match entry_type_str.as_str() {
-                "trade" => spectraplex_core::models::EntryType::Trade,
-                "fee" => spectraplex_core::models::EntryType::Fee,
-                "transfer" => spectraplex_core::models::EntryType::Transfer,
-                "staking" => spectraplex_core::models::EntryType::Staking,
-                "income" => spectraplex_core::models::EntryType::Income,
-                _ => spectraplex_core::models::EntryType::Transfer,
-            };
-
-            entries.push(LedgerEntry {
+            records.push(AuditRecord {
                 id: row.try_get("id")?,
-                transaction_id: row.try_get("transaction_id")?,
                 user_id: row.try_get("user_id")?,
-                wallet_address: row.try_get("wallet_address")?,
-                asset_symbol: row.try_get("asset_symbol")?,
-                amount: row.try_get("amount")?,
-                entry_type,
-                fiat_value: row.try_get("fiat_value")?,
+                entry_id: row.try_get("entry_id")?,
+                change: serde_json::from_value(row.try_get("change")?)?,
+                previous: row.try_get("previous")?,
+                recorded_at: row.try_get("recorded_at")?,
             });
         }
-        Ok(entries)
+        Ok(records)
+    }
+}
+
+#[async_trait::async_trait]
+impl FxSource for Repository {
+    async fn rate_on(&self, base: &str, quote: &str, date: NaiveDate) -> anyhow::Result<Option<BigDecimal>> {
+        let row = sqlx::query(
+            r#"
+            SELECT rate
+            FROM fx_rates
+            WHERE base_currency = $1 AND quote_currency = $2 AND rate_date <= $3
+            ORDER BY rate_date DESC
+            LIMIT 1
+            "#
+        )
+        .bind(base)
+        .bind(quote)
+        .bind(date)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("rate")?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for Repository {
+    async fn price_at(&self, asset: &str, timestamp: i64, fiat: &str) -> anyhow::Result<Option<PricePoint>> {
+        // Finest candle that contains the timestamp wins
+        let row = sqlx::query(
+            r#"
+            SELECT open_time, resolution_secs, close, source
+            FROM price_history
+            WHERE asset_symbol = $1 AND fiat_currency = $2
+              AND open_time <= $3 AND open_time + resolution_secs > $3
+            ORDER BY resolution_secs ASC
+            LIMIT 1
+            "#
+        )
+        .bind(asset)
+        .bind(fiat.to_uppercase())
+        .bind(timestamp)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(PricePoint {
+                price: row.try_get("close")?,
+                source: row.try_get("source")?,
+                resolution: format_resolution(row.try_get("resolution_secs")?),
+                timestamp: row.try_get("open_time")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn nearest_observation(
+        &self,
+        asset: &str,
+        timestamp: i64,
+        fiat: &str,
+        window_secs: i64,
+    ) -> anyhow::Result<Option<PricePoint>> {
+        let row = sqlx::query(
+            r#"
+            SELECT open_time, close, source
+            FROM price_history
+            WHERE asset_symbol = $1 AND fiat_currency = $2 AND resolution_secs = $5
+              AND open_time BETWEEN $3 - $4 AND $3 + $4
+            ORDER BY ABS(open_time - $3) ASC
+            LIMIT 1
+            "#
+        )
+        .bind(asset)
+        .bind(fiat.to_uppercase())
+        .bind(timestamp)
+        .bind(window_secs)
+        .bind(OBSERVATION_RESOLUTION_SECS)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(PricePoint {
+                price: row.try_get("close")?,
+                source: row.try_get("source")?,
+                resolution: format_resolution(OBSERVATION_RESOLUTION_SECS),
+                timestamp: row.try_get("open_time")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+}
+
+// Rows per multi-row statement. Each column goes over as one array parameter, so this only bounds statement size.
+const BATCH_SIZE: usize = 1000;
+
+async fn insert_transactions(conn: &mut PgConnection, txs: &[Transaction]) -> anyhow::Result<()> {
+    for batch in txs.chunks(BATCH_SIZE) {
+        // Using unchecked query to avoid needing a running DB during compilation
+        sqlx::query(
+            r#"
+            INSERT INTO transactions (id, user_id, wallet_address, timestamp, tx_hash, chain, raw_metadata)
+            SELECT * FROM UNNEST($1::uuid[], $2::uuid[], $3::text[], $4::int8[], $5::text[], $6::text[], $7::jsonb[])
+            ON CONFLICT (id) DO NOTHING
+            "#
+        )
+        .bind(batch.iter().map(|tx| tx.id).collect::<Vec<_>>())
+        .bind(batch.iter().map(|tx| tx.user_id).collect::<Vec<_>>())
+        .bind(batch.iter().map(|tx| tx.wallet_address.as_str()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|tx| tx.timestamp).collect::<Vec<_>>())
+        .bind(batch.iter().map(|tx| tx.tx_hash.as_str()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|tx| tx.chain.name()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|tx| &tx.raw_metadata).collect::<Vec<_>>())
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// Keeps the last row for each key, in input order
+fn last_by_key<T, K: std::hash::Hash + Eq>(rows: &[T], key: impl Fn(&T) -> K) -> Vec<&T> {
+    let last: HashMap<K, usize> = rows.iter().enumerate().map(|(i, row)| (key(row), i)).collect();
+    rows.iter().enumerate().filter(|(i, row)| last[&key(row)] == *i).map(|(_, row)| row).collect()
+}
+
+async fn insert_audit_record(conn: &mut PgConnection, record: &AuditRecord) -> anyhow::Result<()> {
+    sqlx::query("INSERT INTO adjustment_audit (id, user_id, entry_id, change, previous, recorded_at) VALUES ($1, $2, $3, $4, $5, $6)")
+        .bind(record.id)
+        .bind(record.user_id)
+        .bind(record.entry_id)
+        .bind(serde_json::to_value(&record.change)?)
+        .bind(&record.previous)
+        .bind(record.recorded_at)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+async fn insert_ledger_entries(conn: &mut PgConnection, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+    for batch in entries.chunks(BATCH_SIZE) {
+        let bridge = |f: fn(&BridgeTransfer) -> Option<String>| batch.iter().map(|e| e.bridge.as_ref().and_then(f)).collect::<Vec<_>>();
+
+        // Rolls newly inserted entries into the daily balance table in the same statement,
+        // so re-saving an existing entry doesn't count it twice
+        sqlx::query(
+            r#"
+            WITH inserted AS (
+                INSERT INTO ledger_entries (id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount, entry_type, fiat_value, fiat_currency, price_source, price_resolution, source_program, is_spam, position_account,
+                    bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version)
+                SELECT id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount, entry_type::entry_type_enum, fiat_value, fiat_currency, price_source, price_resolution, source_program, is_spam, position_account,
+                    bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version
+                FROM UNNEST($1::uuid[], $2::uuid[], $3::uuid[], $4::text[], $5::int8[], $6::text[], $7::numeric[], $8::text[], $9::numeric[], $10::text[],
+                    $11::text[], $12::text[], $13::text[], $14::bool[], $15::text[], $16::text[], $17::text[], $18::text[], $19::text[], $20::uuid[], $21::text[], $22::int4[])
+                    AS t(id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount, entry_type, fiat_value, fiat_currency, price_source, price_resolution, source_program, is_spam, position_account,
+                        bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version)
+                ON CONFLICT (id) DO NOTHING
+                RETURNING wallet_address, asset_symbol, timestamp, amount, is_spam
+            )
+            INSERT INTO daily_balances (wallet_address, asset_symbol, day, net_change)
+            SELECT wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date, SUM(amount)
+            FROM inserted
+            WHERE NOT is_spam
+            GROUP BY wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date
+            ON CONFLICT (wallet_address, asset_symbol, day)
+            DO UPDATE SET net_change = daily_balances.net_change + EXCLUDED.net_change
+            "#
+        )
+        .bind(batch.iter().map(|e| e.id).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.transaction_id).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.user_id).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.wallet_address.as_str()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.timestamp).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.asset_symbol.as_str()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.amount.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.entry_type.name()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.fiat_value.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.fiat_currency.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.price_source.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.price_resolution.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.source_program.clone()).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.is_spam).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.position_account.clone()).collect::<Vec<_>>())
+        .bind(bridge(|b| Some(b.protocol.clone())))
+        .bind(bridge(|b| b.destination_chain.clone()))
+        .bind(bridge(|b| b.destination_address.clone()))
+        .bind(bridge(|b| b.transfer_id.clone()))
+        .bind(batch.iter().map(|e| e.bridge.as_ref().and_then(|b| b.linked_entry_id)).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.parser.as_ref().map(|p| p.name.clone())).collect::<Vec<_>>())
+        .bind(batch.iter().map(|e| e.parser.as_ref().map(|p| p.version as i32)).collect::<Vec<_>>())
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// Time range and keyset position, on the table being paged (`alias`)
+fn push_filter(query: &mut QueryBuilder<Postgres>, filter: &QueryFilter, after: Option<Cursor>, alias: &str) {
+    if let Some(from) = filter.from {
+        query.push(format!(" AND {}.timestamp >= ", alias)).push_bind(from);
     }
+    if let Some(to) = filter.to {
+        query.push(format!(" AND {}.timestamp < ", alias)).push_bind(to);
+    }
+    if let Some(after) = after {
+        query.push(format!(" AND ({0}.timestamp, {0}.id) > (", alias)).push_bind(after.timestamp).push(", ").push_bind(after.id).push(")");
+    }
+}
+
+// Filters on ledger entry columns, aliased `l`
+fn push_entry_fields(query: &mut QueryBuilder<Postgres>, filter: &QueryFilter) {
+    if let Some(asset) = &filter.asset {
+        query.push(" AND l.asset_symbol = ").push_bind(asset.clone());
+    }
+    if let Some(entry_type) = &filter.entry_type {
+        query.push(" AND l.entry_type = ").push_bind(entry_type.name()).push("::entry_type_enum");
+    }
+}
+
+// Filters on transaction columns, aliased `t`
+fn push_tx_fields(query: &mut QueryBuilder<Postgres>, filter: &QueryFilter) {
+    if let Some(chain) = &filter.chain {
+        query.push(" AND t.chain = ").push_bind(chain.name().to_string());
+    }
+    if let Some(hash) = &filter.tx_hash {
+        query.push(" AND t.tx_hash = ").push_bind(hash.clone());
+    }
+}
+
+fn transaction_from_row(row: &PgRow) -> anyhow::Result<Transaction> {
+    let chain: String = row.try_get("chain")?;
+    Ok(Transaction {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        wallet_address: row.try_get("wallet_address")?,
+        timestamp: row.try_get("timestamp")?,
+        tx_hash: row.try_get("tx_hash")?,
+        chain: Chain::from_name(&chain),
+        raw_metadata: row.try_get("raw_metadata")?,
+    })
+}
+
+fn ledger_entry_from_row(row: &PgRow) -> anyhow::Result<LedgerEntry> {
+    let entry_type = EntryType::from_name(&row.try_get::<String, _>("entry_type")?);
+    let bridge = match row.try_get::<Option<String>, _>("bridge_protocol")? {
+        Some(protocol) => Some(BridgeTransfer {
+            protocol,
+            destination_chain: row.try_get("bridge_destination_chain")?,
+            destination_address: row.try_get("bridge_destination_address")?,
+            transfer_id: row.try_get("bridge_transfer_id")?,
+            linked_entry_id: row.try_get("linked_entry_id")?,
+        }),
+        None => None,
+    };
+    let parser = match row.try_get::<Option<String>, _>("parser_name")? {
+        Some(name) => Some(ParserVersion { name, version: row.try_get::<i32, _>("parser_version")? as u32 }),
+        None => None,
+    };
+
+    Ok(LedgerEntry {
+        id: row.try_get("id")?,
+        transaction_id: row.try_get("transaction_id")?,
+        user_id: row.try_get("user_id")?,
+        wallet_address: row.try_get("wallet_address")?,
+        timestamp: row.try_get("timestamp")?,
+        asset_symbol: row.try_get("asset_symbol")?,
+        amount: row.try_get("amount")?,
+        entry_type,
+        fiat_value: row.try_get("fiat_value")?,
+        fiat_currency: row.try_get("fiat_currency")?,
+        price_source: row.try_get("price_source")?,
+        price_resolution: row.try_get("price_resolution")?,
+        source_program: row.try_get("source_program")?,
+        is_spam: row.try_get("is_spam")?,
+        position_account: row.try_get("position_account")?,
+        bridge,
+        parser,
+    })
+}
+
+fn adjustment_from_row(row: &PgRow) -> anyhow::Result<Adjustment> {
+    Ok(Adjustment {
+        entry_id: row.try_get("entry_id")?,
+        user_id: row.try_get("user_id")?,
+        wallet_address: row.try_get("wallet_address")?,
+        entry_type: row.try_get::<Option<String>, _>("entry_type")?.map(|t| EntryType::from_name(&t)),
+        fiat_value: row.try_get("fiat_value")?,
+        fiat_currency: row.try_get("fiat_currency")?,
+        note: row.try_get("note")?,
+        tags: row.try_get("tags")?,
+    })
 }
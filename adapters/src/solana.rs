@@ -1,10 +1,19 @@
 use spectraplex_core::models::{Chain, Transaction, ChainIngestor};
+use spectraplex_core::reconcile::{BalanceSource, OnchainBalance};
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::rpc_request::TokenAccountsFilter;
+use bigdecimal::BigDecimal;
 use solana_sdk::{pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::UiTransactionEncoding;
 use std::str::FromStr;
 use uuid::Uuid;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use crate::solana_nft::{self, NftMetadata};
+
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
 
 pub struct SolanaAdapter {
     client: RpcClient,
@@ -16,6 +25,15 @@ impl SolanaAdapter {
             client: RpcClient::new(rpc_url.to_string()),
         }
     }
+
+    // Supply and Metaplex metadata of a 0-decimal mint, so the parser can tell NFTs apart
+    fn nft_metadata(&self, mint: &str) -> anyhow::Result<NftMetadata> {
+        let mint = Pubkey::from_str(mint)?;
+        let data = self.client.get_account_data(&solana_nft::metadata_address(&mint)?)?;
+        let mut metadata = solana_nft::decode_metadata(&data)?;
+        metadata.supply = self.client.get_token_supply(&mint)?.amount.parse().ok();
+        Ok(metadata)
+    }
 }
 
 #[async_trait::async_trait]
@@ -26,17 +44,39 @@ impl ChainIngestor for SolanaAdapter {
         let signatures = self.client.get_signatures_for_address(&pubkey)?;
         
         let mut transactions = Vec::new();
+        let mut nfts: HashMap<String, Option<NftMetadata>> = HashMap::new();
 
         for sig_info in signatures.iter().take(limit) {
             let sig = Signature::from_str(&sig_info.signature)?;
             
-            // Fetch full transaction details in JSON format
-            // We use UiTransactionEncoding::JsonParsed to get as much detail as possible, 
-            // or Json for raw structure. "Json" is often safer for raw storage.
-            match self.client.get_transaction(&sig, UiTransactionEncoding::Json) {
+            // JsonParsed, as the parser only reads parsed messages and instructions. Without a
+            // max version the node refuses every versioned (v0) transaction.
+            let config = RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::JsonParsed),
+                commitment: None,
+                max_supported_transaction_version: Some(0),
+            };
+            match self.client.get_transaction_with_config(&sig, config) {
                 Ok(tx) => {
                     // Serialize the entire response to a JSON Value
-                    let raw_metadata = serde_json::to_value(&tx).unwrap_or(json!({}));
+                    let mut raw_metadata = serde_json::to_value(&tx).unwrap_or(json!({}));
+
+                    let mut nft_metadata = serde_json::Map::new();
+                    for mint in zero_decimal_mints(&raw_metadata) {
+                        let metadata = nfts.entry(mint.clone()).or_insert_with(|| match self.nft_metadata(&mint) {
+                            Ok(m) => Some(m),
+                            Err(e) => {
+                                eprintln!("No NFT metadata for {}: {}", mint, e);
+                                None
+                            }
+                        });
+                        if let Some(m) = metadata {
+                            nft_metadata.insert(mint, serde_json::to_value(m)?);
+                        }
+                    }
+                    if !nft_metadata.is_empty() {
+                        raw_metadata["nft_metadata"] = Value::Object(nft_metadata);
+                    }
 
                     transactions.push(Transaction {
                         id: Uuid::new_v4(),
@@ -57,3 +97,50 @@ impl ChainIngestor for SolanaAdapter {
         Ok(transactions)
     }
 }
+
+#[async_trait::async_trait]
+impl BalanceSource for SolanaAdapter {
+    async fn current_balances(&self, wallet: &str) -> anyhow::Result<Vec<OnchainBalance>> {
+        let pubkey = Pubkey::from_str(wallet)?;
+
+        // Native SOL (getBalance)
+        let lamports = self.client.get_balance(&pubkey)?;
+        let mut balances = vec![OnchainBalance {
+            asset_symbol: "SOL".to_string(),
+            balance: BigDecimal::from(lamports) / BigDecimal::from(1_000_000_000u64),
+        }];
+
+        // SPL tokens from both token programs (getTokenAccountsByOwner, jsonParsed)
+        for program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let program_id = Pubkey::from_str(program)?;
+            let accounts = self.client.get_token_accounts_by_owner(&pubkey, TokenAccountsFilter::ProgramId(program_id))?;
+
+            for keyed in accounts {
+                let data = serde_json::to_value(&keyed.account.data)?;
+                let info = &data["parsed"]["info"];
+                let (Some(mint), Some(amount)) = (info["mint"].as_str(), info["tokenAmount"]["uiAmountString"].as_str()) else {
+                    eprintln!("Skipping unparsed token account {}", keyed.pubkey);
+                    continue;
+                };
+                balances.push(OnchainBalance {
+                    asset_symbol: mint.to_string(),
+                    balance: BigDecimal::from_str(amount)?,
+                });
+            }
+        }
+
+        Ok(balances)
+    }
+}
+
+fn zero_decimal_mints(raw: &Value) -> Vec<String> {
+    let mut mints: Vec<String> = ["preTokenBalances", "postTokenBalances"]
+        .iter()
+        .flat_map(|key| raw["meta"][key].as_array().cloned().unwrap_or_default())
+        .filter(|b| b["uiTokenAmount"]["decimals"].as_u64() == Some(0))
+        .filter_map(|b| b["mint"].as_str().map(String::from))
+        .collect();
+    mints.sort();
+    mints.dedup();
+    mints
+}
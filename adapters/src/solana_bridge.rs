@@ -0,0 +1,214 @@
+use crate::evm::builtin_networks;
+use crate::solana_parser::{instructions, split_network_fee, Ix};
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, LedgerEntry, Transaction};
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_transaction_status::{UiParsedMessage, UiTransactionStatusMeta};
+use solana_sdk::hash::hashv;
+use bigdecimal::{BigDecimal, Zero};
+
+pub const WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID: &str = "wormDTUJ6AWPNvk59vGQbDvGJmqbDTdgWgAqcLBCgUb";
+pub const DLN_SOURCE_PROGRAM_ID: &str = "src5qyZHqTqecJV4aY6Cb6zDZLMDzrDKKezs22MPHr4";
+pub const DLN_DESTINATION_PROGRAM_ID: &str = "dst5MGcFPoBeREFAA5E3tU5ij8m5uVYwkzkSAbsLbNo";
+pub const ALLBRIDGE_CORE_PROGRAM_ID: &str = "BrdgN2RPzEMWF96ZbnnJaUtQDQx7VRXYaHHbYCBvceWB";
+
+// Wormhole's own chain ids
+const WORMHOLE_CHAINS: &[(u16, &str)] = &[
+    (1, "solana"),
+    (2, "ethereum"),
+    (4, "bsc"),
+    (5, "polygon"),
+    (23, "arbitrum"),
+    (24, "optimism"),
+    (30, "base"),
+];
+
+// deBridge uses EVM chain ids, and this one for Solana
+const DLN_SOLANA_CHAIN_ID: u64 = 7565164;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeDirection {
+    Send,
+    Receive,
+}
+
+enum Discriminator {
+    Tag(u8),              // Wormhole's native programs: first byte of the data
+    Anchor(&'static str), // First 8 bytes of sha256("global:<instruction>")
+}
+
+// Argument layouts the destination is read from
+enum Args {
+    Undecoded,
+    WormholeTransfer,            // nonce u32, amount u64, fee u64, target address [u8; 32], target chain u16
+    WormholeTransferWithPayload, // nonce u32, amount u64, target address [u8; 32], target chain u16, payload
+    DlnOrder,                    // give amount u64, take (chain id u256, token Vec<u8>, amount u256), receiver Vec<u8>, ...
+}
+
+// Allbridge's arguments aren't decoded; its transfers are paired on amount and time alone
+const INSTRUCTIONS: &[(&str, Discriminator, &str, BridgeDirection, Args)] = &[
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(4), "wormhole", BridgeDirection::Send, Args::WormholeTransfer), // transfer_wrapped
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(5), "wormhole", BridgeDirection::Send, Args::WormholeTransfer), // transfer_native
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(11), "wormhole", BridgeDirection::Send, Args::WormholeTransferWithPayload),
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(12), "wormhole", BridgeDirection::Send, Args::WormholeTransferWithPayload),
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(2), "wormhole", BridgeDirection::Receive, Args::Undecoded), // complete_native
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(3), "wormhole", BridgeDirection::Receive, Args::Undecoded), // complete_wrapped
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(9), "wormhole", BridgeDirection::Receive, Args::Undecoded),
+    (WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, Discriminator::Tag(10), "wormhole", BridgeDirection::Receive, Args::Undecoded),
+    (DLN_SOURCE_PROGRAM_ID, Discriminator::Anchor("create_order"), "debridge", BridgeDirection::Send, Args::DlnOrder),
+    (DLN_SOURCE_PROGRAM_ID, Discriminator::Anchor("create_order_with_nonce"), "debridge", BridgeDirection::Send, Args::DlnOrder),
+    (DLN_DESTINATION_PROGRAM_ID, Discriminator::Anchor("fulfill_order"), "debridge", BridgeDirection::Receive, Args::Undecoded),
+    (ALLBRIDGE_CORE_PROGRAM_ID, Discriminator::Anchor("swap_and_bridge"), "allbridge", BridgeDirection::Send, Args::Undecoded),
+    (ALLBRIDGE_CORE_PROGRAM_ID, Discriminator::Anchor("receive_tokens"), "allbridge", BridgeDirection::Receive, Args::Undecoded),
+];
+
+/// A bridge instruction in the transaction and what it says about the other side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeInstruction {
+    pub program: String,
+    pub direction: BridgeDirection,
+    pub transfer: BridgeTransfer,
+}
+
+/// Bridge instructions in the transaction, top-level or invoked by another program.
+pub fn bridge_instructions(message: &UiParsedMessage, meta: &UiTransactionStatusMeta) -> Vec<BridgeInstruction> {
+    let logs = match &meta.log_messages {
+        OptionSerializer::Some(logs) => logs.as_slice(),
+        _ => &[],
+    };
+    instructions(message, meta).iter().filter_map(|ix| decode(ix, logs)).collect()
+}
+
+fn decode(ix: &Ix, logs: &[String]) -> Option<BridgeInstruction> {
+    let (_, discriminator, protocol, direction, args) = INSTRUCTIONS.iter().find(|(program, discriminator, _, _, _)| {
+        *program == ix.program
+            && match discriminator {
+                Discriminator::Tag(tag) => ix.data.first() == Some(tag),
+                Discriminator::Anchor(name) => {
+                    ix.data.get(..8) == Some(&hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8])
+                }
+            }
+    })?;
+    let data = &ix.data[match discriminator {
+        Discriminator::Tag(_) => 1,
+        Discriminator::Anchor(_) => 8,
+    }..];
+
+    // Data too short for the layout still counts as a bridge transfer, just without a destination
+    let (destination_chain, destination_address) = match args {
+        Args::Undecoded => None,
+        Args::WormholeTransfer => wormhole_target(data, true),
+        Args::WormholeTransferWithPayload => wormhole_target(data, false),
+        Args::DlnOrder => dln_receiver(data),
+    }
+    .map_or((None, None), |(chain, address)| (chain, Some(address)));
+
+    // The core bridge logs the sequence of the message it posts, which identifies the transfer on the other side
+    let transfer_id = if *protocol == "wormhole" && *direction == BridgeDirection::Send {
+        logs.iter().find_map(|l| l.strip_prefix("Program log: Sequence: ")).map(str::to_string)
+    } else {
+        None
+    };
+
+    Some(BridgeInstruction {
+        program: ix.program.clone(),
+        direction: *direction,
+        transfer: BridgeTransfer {
+            protocol: protocol.to_string(),
+            destination_chain,
+            destination_address,
+            transfer_id,
+            linked_entry_id: None,
+        },
+    })
+}
+
+fn wormhole_target(data: &[u8], with_fee: bool) -> Option<(Option<String>, String)> {
+    let mut reader = Reader(data);
+    reader.take(4)?; // nonce
+    reader.take(8)?; // amount
+    if with_fee {
+        reader.take(8)?;
+    }
+    let address = reader.take(32)?;
+    let chain_id = u16::from_le_bytes(reader.take(2)?.try_into().ok()?);
+    let chain = WORMHOLE_CHAINS.iter().find(|(id, _)| *id == chain_id).map(|(_, name)| name.to_string());
+    Some((chain.clone(), format_address(address, chain.as_deref())))
+}
+
+fn dln_receiver(data: &[u8]) -> Option<(Option<String>, String)> {
+    let mut reader = Reader(data);
+    reader.take(8)?; // give amount
+    let chain_id = reader.take(32)?;
+    reader.vec()?; // take token
+    reader.take(32)?; // take amount
+    let receiver = reader.vec()?;
+
+    // Big-endian u256; anything past u64 isn't a chain we know
+    let (high, low) = chain_id.split_at(24);
+    let chain = match u64::from_be_bytes(low.try_into().ok()?) {
+        _ if high.iter().any(|b| *b != 0) => None,
+        DLN_SOLANA_CHAIN_ID => Some("solana".to_string()),
+        id => builtin_networks().into_iter().find(|n| n.chain_id == id).map(|n| n.name),
+    };
+    Some((chain.clone(), format_address(receiver, chain.as_deref())))
+}
+
+// EVM addresses as 0x hex (Wormhole pads them to 32 bytes), anything else as base58
+fn format_address(bytes: &[u8], chain: Option<&str>) -> String {
+    let evm = chain.is_some_and(|c| Chain::from_name(c).is_evm()) || bytes.len() == 20;
+    if evm && bytes.len() >= 20 {
+        let hex: String = bytes[bytes.len() - 20..].iter().map(|b| format!("{:02x}", b)).collect();
+        format!("0x{}", hex)
+    } else {
+        bs58::encode(bytes).into_string()
+    }
+}
+
+// Borsh-style reader over instruction arguments
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    // u32 length prefix, then the bytes
+    fn vec(&mut self) -> Option<&'a [u8]> {
+        let len = u32::from_le_bytes(self.take(4)?.try_into().ok()?);
+        self.take(len as usize)
+    }
+}
+
+/// Retypes the wallet's transfers in a bridge transaction as `Bridge` entries, carrying what was decoded
+/// about the other side: outflows take the sending instruction's, inflows the receiving one's.
+/// Pairing them with the other chain is left to `bridge::link_transfers`, once both are ingested.
+/// Returns whether the transaction was a bridge one.
+pub(crate) fn classify_bridge(
+    tx: &Transaction,
+    message: &UiParsedMessage,
+    meta: &UiTransactionStatusMeta,
+    entries: &mut Vec<LedgerEntry>,
+) -> bool {
+    let found = bridge_instructions(message, meta);
+    if found.is_empty() {
+        return false;
+    }
+
+    // Wormhole's message fee stays in the SOL leg; only the network fee is split off
+    split_network_fee(tx, message, meta, entries);
+
+    for entry in entries.iter_mut().filter(|e| matches!(e.entry_type, EntryType::Transfer)) {
+        let direction = if entry.amount < BigDecimal::zero() { BridgeDirection::Send } else { BridgeDirection::Receive };
+        if let Some(ix) = found.iter().find(|i| i.direction == direction) {
+            entry.entry_type = EntryType::Bridge;
+            entry.source_program = Some(ix.program.clone());
+            entry.bridge = Some(ix.transfer.clone());
+        }
+    }
+    true
+}
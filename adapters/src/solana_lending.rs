@@ -0,0 +1,122 @@
+use crate::solana_parser::{instructions, split_network_fee, Ix};
+use spectraplex_core::models::{EntryType, LedgerEntry, Transaction};
+use solana_transaction_status::{UiParsedMessage, UiTransactionStatusMeta};
+use solana_sdk::hash::hashv;
+use bigdecimal::{BigDecimal, Zero};
+
+pub const SOLEND_PROGRAM_ID: &str = "So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo";
+pub const MARGINFI_PROGRAM_ID: &str = "MFv2hWf31Z9kbCa1snEPYctwafyhdvnV7FK9z6Kh6vj";
+pub const KAMINO_LEND_PROGRAM_ID: &str = "KLend2g3cP87fffoy8q1mQqGKjrxjC8boSyAYavgmjD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LendingAction {
+    Deposit,
+    Withdraw,
+    Borrow,
+    Repay,
+}
+
+impl LendingAction {
+    // Deposits and repayments leave the wallet; withdrawals and borrows arrive in it
+    fn is_outflow(&self) -> bool {
+        matches!(self, LendingAction::Deposit | LendingAction::Repay)
+    }
+
+    fn entry_type(&self) -> EntryType {
+        match self {
+            LendingAction::Deposit | LendingAction::Withdraw => EntryType::Collateral,
+            LendingAction::Borrow | LendingAction::Repay => EntryType::Loan,
+        }
+    }
+}
+
+/// A lending instruction and the protocol account (obligation or margin account) it acts on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LendingInstruction {
+    pub program: String,
+    pub action: LendingAction,
+    pub account: String,
+}
+
+enum Discriminator {
+    Tag(u8),             // SPL token-lending forks: first byte of the data
+    Anchor(&'static str), // First 8 bytes of sha256("global:<instruction>")
+}
+
+// Instructions that move liquidity, and the index of the position account among their accounts
+const INSTRUCTIONS: &[(&str, Discriminator, LendingAction, usize)] = &[
+    (SOLEND_PROGRAM_ID, Discriminator::Tag(14), LendingAction::Deposit, 8), // deposit_reserve_liquidity_and_obligation_collateral
+    (SOLEND_PROGRAM_ID, Discriminator::Tag(15), LendingAction::Withdraw, 3), // withdraw_obligation_collateral_and_redeem_reserve_collateral
+    (SOLEND_PROGRAM_ID, Discriminator::Tag(10), LendingAction::Borrow, 4),
+    (SOLEND_PROGRAM_ID, Discriminator::Tag(11), LendingAction::Repay, 3),
+    (MARGINFI_PROGRAM_ID, Discriminator::Anchor("lending_account_deposit"), LendingAction::Deposit, 1),
+    (MARGINFI_PROGRAM_ID, Discriminator::Anchor("lending_account_withdraw"), LendingAction::Withdraw, 1),
+    (MARGINFI_PROGRAM_ID, Discriminator::Anchor("lending_account_borrow"), LendingAction::Borrow, 1),
+    (MARGINFI_PROGRAM_ID, Discriminator::Anchor("lending_account_repay"), LendingAction::Repay, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("deposit_reserve_liquidity_and_obligation_collateral"), LendingAction::Deposit, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("deposit_reserve_liquidity_and_obligation_collateral_v2"), LendingAction::Deposit, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("withdraw_obligation_collateral_and_redeem_reserve_collateral"), LendingAction::Withdraw, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("withdraw_obligation_collateral_and_redeem_reserve_collateral_v2"), LendingAction::Withdraw, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("borrow_obligation_liquidity"), LendingAction::Borrow, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("borrow_obligation_liquidity_v2"), LendingAction::Borrow, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("repay_obligation_liquidity"), LendingAction::Repay, 1),
+    (KAMINO_LEND_PROGRAM_ID, Discriminator::Anchor("repay_obligation_liquidity_v2"), LendingAction::Repay, 1),
+];
+
+/// Lending instructions in the transaction, top-level or invoked by another program, that `wallet` took part in.
+pub fn lending_instructions(message: &UiParsedMessage, meta: &UiTransactionStatusMeta, wallet: &str) -> Vec<LendingInstruction> {
+    instructions(message, meta)
+        .iter()
+        .filter(|ix| ix.accounts.iter().any(|a| a == wallet))
+        .filter_map(decode)
+        .collect()
+}
+
+fn decode(ix: &Ix) -> Option<LendingInstruction> {
+    let (_, _, action, account) = INSTRUCTIONS.iter().find(|(program, discriminator, _, _)| {
+        *program == ix.program
+            && match discriminator {
+                Discriminator::Tag(tag) => ix.data.first() == Some(tag),
+                Discriminator::Anchor(name) => {
+                    ix.data.get(..8) == Some(&hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8])
+                }
+            }
+    })?;
+    Some(LendingInstruction {
+        program: ix.program.clone(),
+        action: *action,
+        account: ix.accounts.get(*account)?.clone(),
+    })
+}
+
+/// Retypes the wallet's transfers in a lending transaction: outflows as the deposit or repayment,
+/// inflows as the withdrawal or borrow, each tagged with its protocol account. Principal and interest
+/// are separated later, across the wallet's history (`lending::track_positions`).
+/// Returns whether the transaction was a lending one.
+pub(crate) fn classify_lending(
+    tx: &Transaction,
+    message: &UiParsedMessage,
+    meta: &UiTransactionStatusMeta,
+    entries: &mut Vec<LedgerEntry>,
+) -> bool {
+    let found = lending_instructions(message, meta, &tx.wallet_address);
+    if found.is_empty() {
+        return false;
+    }
+    let outflow = found.iter().find(|i| i.action.is_outflow());
+    let inflow = found.iter().find(|i| !i.action.is_outflow());
+
+    // Split the fee off before the SOL leg becomes principal. Rent for a new obligation account
+    // is refundable, so it stays in the leg.
+    split_network_fee(tx, message, meta, entries);
+
+    for entry in entries.iter_mut().filter(|e| matches!(e.entry_type, EntryType::Transfer)) {
+        let ix = if entry.amount < BigDecimal::zero() { outflow } else { inflow };
+        if let Some(ix) = ix {
+            entry.entry_type = ix.action.entry_type();
+            entry.source_program = Some(ix.program.clone());
+            entry.position_account = Some(ix.account.clone());
+        }
+    }
+    true
+}
@@ -0,0 +1,103 @@
+use crate::solana_parser::{instructions, split_network_fee, Ix};
+use spectraplex_core::models::{EntryType, LedgerEntry, Transaction};
+use solana_transaction_status::{UiParsedMessage, UiTransactionStatusMeta};
+use solana_sdk::hash::hashv;
+use bigdecimal::{BigDecimal, Zero};
+
+pub const RAYDIUM_AMM_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+pub const RAYDIUM_CPMM_PROGRAM_ID: &str = "CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C";
+pub const RAYDIUM_CLMM_PROGRAM_ID: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+pub const ORCA_WHIRLPOOL_PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+pub const METEORA_POOLS_PROGRAM_ID: &str = "Eo7WjKq67rjJQSZxS6z3YkapzY3eMj6Xy8X5EQVn5UaB";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiquidityAction {
+    Add,    // Pool assets out, pool share (LP token or position NFT) in
+    Remove, // Pool share out, pool assets in
+}
+
+enum Discriminator {
+    Tag(u8),              // Raydium's native AMM: first byte of the data
+    Anchor(&'static str), // First 8 bytes of sha256("global:<instruction>")
+}
+
+// Opening a CLMM position mints its NFT and closing one burns it, so they count with the liquidity they carry
+const INSTRUCTIONS: &[(&str, Discriminator, LiquidityAction)] = &[
+    (RAYDIUM_AMM_PROGRAM_ID, Discriminator::Tag(3), LiquidityAction::Add), // deposit
+    (RAYDIUM_AMM_PROGRAM_ID, Discriminator::Tag(4), LiquidityAction::Remove), // withdraw
+    (RAYDIUM_CPMM_PROGRAM_ID, Discriminator::Anchor("deposit"), LiquidityAction::Add),
+    (RAYDIUM_CPMM_PROGRAM_ID, Discriminator::Anchor("withdraw"), LiquidityAction::Remove),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("open_position"), LiquidityAction::Add),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("open_position_v2"), LiquidityAction::Add),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("open_position_with_token22_nft"), LiquidityAction::Add),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("increase_liquidity"), LiquidityAction::Add),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("increase_liquidity_v2"), LiquidityAction::Add),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("decrease_liquidity"), LiquidityAction::Remove),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("decrease_liquidity_v2"), LiquidityAction::Remove),
+    (RAYDIUM_CLMM_PROGRAM_ID, Discriminator::Anchor("close_position"), LiquidityAction::Remove),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("open_position"), LiquidityAction::Add),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("open_position_with_metadata"), LiquidityAction::Add),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("increase_liquidity"), LiquidityAction::Add),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("increase_liquidity_v2"), LiquidityAction::Add),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("decrease_liquidity"), LiquidityAction::Remove),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("decrease_liquidity_v2"), LiquidityAction::Remove),
+    (ORCA_WHIRLPOOL_PROGRAM_ID, Discriminator::Anchor("close_position"), LiquidityAction::Remove),
+    (METEORA_POOLS_PROGRAM_ID, Discriminator::Anchor("add_balance_liquidity"), LiquidityAction::Add),
+    (METEORA_POOLS_PROGRAM_ID, Discriminator::Anchor("add_imbalance_liquidity"), LiquidityAction::Add),
+    (METEORA_POOLS_PROGRAM_ID, Discriminator::Anchor("remove_balance_liquidity"), LiquidityAction::Remove),
+    (METEORA_POOLS_PROGRAM_ID, Discriminator::Anchor("remove_liquidity_single_side"), LiquidityAction::Remove),
+];
+
+/// Liquidity instructions in the transaction that `wallet` took part in, with the pool program each went to.
+pub fn liquidity_instructions(message: &UiParsedMessage, meta: &UiTransactionStatusMeta, wallet: &str) -> Vec<(String, LiquidityAction)> {
+    instructions(message, meta)
+        .iter()
+        .filter(|ix| ix.accounts.iter().any(|a| a == wallet))
+        .filter_map(decode)
+        .collect()
+}
+
+fn decode(ix: &Ix) -> Option<(String, LiquidityAction)> {
+    let (_, _, action) = INSTRUCTIONS.iter().find(|(program, discriminator, _)| {
+        *program == ix.program
+            && match discriminator {
+                Discriminator::Tag(tag) => ix.data.first() == Some(tag),
+                Discriminator::Anchor(name) => {
+                    ix.data.get(..8) == Some(&hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8])
+                }
+            }
+    })?;
+    Some((ix.program.clone(), *action))
+}
+
+/// Retypes the wallet's transfers in a liquidity add or removal: the pool assets as `Liquidity` and
+/// the LP token or position NFT as `LpToken`. Transactions that both add and remove (rebalancing a
+/// position) are left alone, as there's no telling which outflow is a pool asset and which a share.
+/// Returns whether the transaction was classified.
+pub(crate) fn classify_liquidity(
+    tx: &Transaction,
+    message: &UiParsedMessage,
+    meta: &UiTransactionStatusMeta,
+    entries: &mut Vec<LedgerEntry>,
+) -> bool {
+    let found = liquidity_instructions(message, meta, &tx.wallet_address);
+    let Some((program, action)) = found.first().cloned() else {
+        return false;
+    };
+    if found.iter().any(|(_, a)| *a != action) {
+        return false;
+    }
+
+    // SOL pools are paid in and out of the wallet's lamports; the fee isn't liquidity
+    split_network_fee(tx, message, meta, entries);
+
+    for entry in entries.iter_mut().filter(|e| matches!(e.entry_type, EntryType::Transfer)) {
+        let outflow = entry.amount < BigDecimal::zero();
+        entry.entry_type = match (action, outflow) {
+            (LiquidityAction::Add, true) | (LiquidityAction::Remove, false) => EntryType::Liquidity,
+            (LiquidityAction::Add, false) | (LiquidityAction::Remove, true) => EntryType::LpToken,
+        };
+        entry.source_program = Some(program.clone());
+    }
+    true
+}
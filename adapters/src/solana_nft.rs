@@ -0,0 +1,212 @@
+use spectraplex_core::models::{EntryType, LedgerEntry, Transaction};
+use solana_transaction_status::{UiInstruction, UiParsedInstruction, UiParsedMessage, UiTransactionStatusMeta};
+use solana_transaction_status::option_serializer::OptionSerializer;
+use solana_sdk::pubkey::Pubkey;
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+pub const TOKEN_METADATA_PROGRAM_ID: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+
+// Marketplaces whose instructions settle NFT sales
+pub const NFT_MARKETPLACE_PROGRAMS: &[&str] = &[
+    "M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K", // Magic Eden v2
+    "TSWAPaqyCSx2KABk68Shruf4rp7CxcNi8hAsbdwmHbN", // Tensor Swap
+    "TCMPhJdwrcuKPpPh3MeMqhT8N1NZsnTx6TLyZN3bAzD", // Tensor Marketplace
+];
+
+/// Metaplex metadata for an NFT mint, stored with the transaction at ingest time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NftMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub seller_fee_basis_points: u16,
+    pub creators: Vec<String>,
+    pub supply: Option<u64>, // From the mint, not the metadata account
+}
+
+/// The Metaplex metadata PDA for `mint`.
+pub fn metadata_address(mint: &Pubkey) -> anyhow::Result<Pubkey> {
+    let program = Pubkey::from_str(TOKEN_METADATA_PROGRAM_ID)?;
+    Ok(Pubkey::find_program_address(&[b"metadata", program.as_ref(), mint.as_ref()], &program).0)
+}
+
+/// Decodes the leading fields of a Metaplex metadata account (Borsh layout).
+pub fn decode_metadata(data: &[u8]) -> anyhow::Result<NftMetadata> {
+    let mut reader = Borsh { data, pos: 0 };
+    reader.take(1 + 32 + 32)?; // key, update authority, mint
+    let name = reader.string()?;
+    let symbol = reader.string()?;
+    reader.string()?; // uri
+    let seller_fee_basis_points = u16::from_le_bytes(reader.take(2)?.try_into()?);
+
+    let mut creators = Vec::new();
+    if reader.take(1)?[0] == 1 {
+        let count = reader.u32()?;
+        for _ in 0..count {
+            let address: [u8; 32] = reader.take(32)?.try_into()?;
+            reader.take(2)?; // verified, share
+            creators.push(Pubkey::new_from_array(address).to_string());
+        }
+    }
+
+    Ok(NftMetadata { name, symbol, seller_fee_basis_points, creators, supply: None })
+}
+
+struct Borsh<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Borsh<'a> {
+    fn take(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| anyhow::anyhow!("Metadata account ends at byte {}", self.data.len()))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    // Metaplex pads names and symbols with NULs to a fixed length
+    fn string(&mut self) -> anyhow::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).trim_end_matches('\0').to_string())
+    }
+}
+
+/// Whether a token movement is an NFT: a whole unit of a 0-decimal mint, with a supply of one when we know it.
+pub fn is_nft(decimals: u8, delta: f64, metadata: Option<&NftMetadata>) -> bool {
+    decimals == 0 && delta.abs() == 1.0 && metadata.and_then(|m| m.supply).unwrap_or(1) == 1
+}
+
+pub fn marketplace(message: &UiParsedMessage) -> Option<&'static str> {
+    message.instructions.iter().find_map(|ix| {
+        let program = match ix {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => parsed.program_id.as_str(),
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => decoded.program_id.as_str(),
+            UiInstruction::Compiled(compiled) => message.account_keys.get(compiled.program_id_index as usize)?.pubkey.as_str(),
+        };
+        NFT_MARKETPLACE_PROGRAMS.iter().find(|p| **p == program).copied()
+    })
+}
+
+/// Rewrites the SOL side of an NFT purchase, sale or mint as a trade leg, with the network fee,
+/// royalties and marketplace fees split out as `Fee` entries.
+pub(crate) fn split_nft_trade(
+    tx: &Transaction,
+    message: &UiParsedMessage,
+    meta: &UiTransactionStatusMeta,
+    nft_mint: &str,
+    metadata: Option<&NftMetadata>,
+    entries: &mut Vec<LedgerEntry>,
+) {
+    let Some(idx) = message.account_keys.iter().position(|k| k.pubkey == tx.wallet_address) else {
+        return;
+    };
+    let Some(nft_in) = entries.iter().find(|e| e.asset_symbol == nft_mint).map(|e| e.amount > BigDecimal::zero()) else {
+        return;
+    };
+
+    let change = |i: usize| {
+        let pre = meta.pre_balances.get(i).copied().unwrap_or(0) as i128;
+        let post = meta.post_balances.get(i).copied().unwrap_or(0) as i128;
+        post - pre
+    };
+    let fee_payer = idx == 0;
+    let network_fee = if fee_payer { meta.fee as i128 } else { 0 };
+
+    // Royalties and marketplace fees are charged to whoever took the listing or bid, i.e. signed
+    let program = marketplace(message);
+    let (royalties, marketplace_fee) = match program {
+        Some(_) if fee_payer => third_party_payments(message, meta, idx, nft_mint, nft_in, metadata),
+        _ => (0, 0),
+    };
+
+    // Adding the fees back leaves what the NFT itself cost or fetched
+    let leg = change(idx) + network_fee + royalties + marketplace_fee;
+    if leg == 0 || (leg < 0) != nft_in {
+        return; // Gifts, airdrops and plain transfers
+    }
+
+    entries.retain(|e| e.asset_symbol != "SOL");
+    for entry in entries.iter_mut().filter(|e| e.asset_symbol == nft_mint) {
+        entry.entry_type = EntryType::Trade;
+    }
+    entries.push(LedgerEntry::new(tx, "SOL".to_string(), lamports(leg), EntryType::Trade));
+    for fee in [network_fee, royalties, marketplace_fee] {
+        if fee != 0 {
+            entries.push(LedgerEntry::new(tx, "SOL".to_string(), lamports(-fee), EntryType::Fee));
+        }
+    }
+    if let Some(program) = program {
+        for entry in entries.iter_mut() {
+            entry.source_program = Some(program.to_string());
+        }
+    }
+}
+
+// Lamports other parties gained in the transaction, split into (royalties, marketplace fees).
+// Creators named in the metadata get royalties; everyone else except the wallet and the other
+// side of the trade took a marketplace fee. Newly created accounts only received rent.
+fn third_party_payments(
+    message: &UiParsedMessage,
+    meta: &UiTransactionStatusMeta,
+    wallet_idx: usize,
+    nft_mint: &str,
+    nft_in: bool,
+    metadata: Option<&NftMetadata>,
+) -> (i128, i128) {
+    let mut token_accounts = HashSet::new();
+    let mut counterparties = HashSet::new();
+    for balances in [&meta.pre_token_balances, &meta.post_token_balances] {
+        if let OptionSerializer::Some(balances) = balances {
+            for b in balances {
+                token_accounts.insert(b.account_index as usize);
+                if let OptionSerializer::Some(owner) = &b.owner {
+                    if b.mint == nft_mint {
+                        counterparties.insert(owner.clone());
+                    }
+                }
+            }
+        }
+    }
+    let creators: Vec<&String> = metadata.map(|m| m.creators.iter().collect()).unwrap_or_default();
+
+    let mut royalties = 0;
+    let mut others = Vec::new();
+    let mut counterparty_received = false;
+    for (i, key) in message.account_keys.iter().enumerate() {
+        let pre = meta.pre_balances.get(i).copied().unwrap_or(0) as i128;
+        let gained = meta.post_balances.get(i).copied().unwrap_or(0) as i128 - pre;
+        if i == wallet_idx || token_accounts.contains(&i) || pre == 0 || gained <= 0 {
+            continue;
+        }
+        if counterparties.contains(&key.pubkey) {
+            counterparty_received = true;
+        } else if creators.contains(&&key.pubkey) {
+            royalties += gained;
+        } else {
+            others.push(gained);
+        }
+    }
+
+    // Pool-based marketplaces pay the seller through an escrow account rather than its wallet;
+    // the sale price is by far the largest payment, so that's the escrow
+    if nft_in && !counterparty_received {
+        if let Some(max) = others.iter().copied().max() {
+            let pos = others.iter().position(|g| *g == max).unwrap_or(0);
+            others.remove(pos);
+        }
+    }
+    (royalties, others.iter().sum())
+}
+
+fn lamports(amount: i128) -> BigDecimal {
+    BigDecimal::new(amount.into(), 9).normalized()
+}
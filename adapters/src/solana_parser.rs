@@ -1,8 +1,19 @@
+use crate::bubblegum;
+use crate::solana_bridge;
+use crate::solana_lending;
+use crate::solana_liquidity;
+use crate::solana_nft::{self, NftMetadata};
 use spectraplex_core::models::{Transaction, LedgerEntry, EntryType};
 use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionStatusMeta};
+use solana_transaction_status::{UiInstruction, UiParsedInstruction, UiParsedMessage};
 use solana_transaction_status::option_serializer::OptionSerializer;
-use uuid::Uuid;
-use bigdecimal::{BigDecimal, FromPrimitive};
+use bigdecimal::{BigDecimal, FromPrimitive, Zero, num_bigint::Sign};
+
+// Quote assets with reliable price history that swaps can be priced against
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+// Instructions every wallet prepends; they say nothing about what the transaction did
+const COMPUTE_BUDGET_PROGRAM_ID: &str = "ComputeBudget111111111111111111111111111111";
 
 pub fn parse_solana_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEntry>> {
     let mut entries = Vec::new();
@@ -27,21 +38,28 @@ pub fn parse_solana_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEn
                     let sol_change = extract_sol_change(meta, idx);
                     
                     if sol_change.abs() > 0.000001 {
-                        entries.push(LedgerEntry {
-                            id: Uuid::new_v4(),
-                            transaction_id: tx.id,
-                            user_id: tx.user_id,
-                            wallet_address: tx.wallet_address.clone(),
-                            asset_symbol: "SOL".to_string(),
-                            amount: BigDecimal::from_f64(sol_change).unwrap_or_default(),
-                            entry_type: if sol_change > 0.0 { EntryType::Transfer } else { EntryType::Transfer }, // Simplified for now
-                            fiat_value: None,
-                        });
+                        // A debit of exactly the network fee is the fee payer's cost, not a transfer
+                        let entry_type = if is_fee_only(meta, idx) { EntryType::Fee } else { EntryType::Transfer };
+                        entries.push(LedgerEntry::new(
+                            tx,
+                            "SOL".to_string(),
+                            BigDecimal::from_f64(sol_change).unwrap_or_default(),
+                            entry_type,
+                        ));
                     }
                 }
             }
     }
 
+    // Metaplex metadata the adapter looked up for 0-decimal mints, if any
+    let nft_metadata: std::collections::HashMap<String, NftMetadata> = tx
+        .raw_metadata
+        .get("nft_metadata")
+        .map(|m| serde_json::from_value(m.clone()))
+        .transpose()?
+        .unwrap_or_default();
+    let mut nft_mints = Vec::new();
+
     // 3. Extract SPL Token Changes
     if let OptionSerializer::Some(pre_token_balances) = &meta.pre_token_balances {
         if let OptionSerializer::Some(post_token_balances) = &meta.post_token_balances {
@@ -66,28 +84,225 @@ pub fn parse_solana_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEn
                     let post_amount = post.ui_token_amount.ui_amount.unwrap_or(0.0);
                     let delta = post_amount - pre_amount;
 
+                    if solana_nft::is_nft(post.ui_token_amount.decimals, delta, nft_metadata.get(&mint)) {
+                        nft_mints.push(mint.clone());
+                    }
+
                     if delta.abs() > 0.000001 {
-                         entries.push(LedgerEntry {
-                             id: Uuid::new_v4(),
-                             transaction_id: tx.id,
-                             user_id: tx.user_id,
-                             wallet_address: tx.wallet_address.clone(),
-                             asset_symbol: mint,
-                             amount: BigDecimal::from_f64(delta).unwrap_or_default(),
-                             entry_type: EntryType::Transfer,
-                             fiat_value: None,
-                         });
+                         entries.push(LedgerEntry::new(
+                             tx,
+                             mint,
+                             BigDecimal::from_f64(delta).unwrap_or_default(),
+                             EntryType::Transfer,
+                         ));
                     }
                 }
             }
+
+            // Token accounts closed by the transaction (e.g. after selling an NFT) have no post balance
+            for pre in pre_token_balances {
+                let owner_match = matches!(&pre.owner, OptionSerializer::Some(owner) if owner == &tx.wallet_address);
+                if !owner_match || post_token_balances.iter().any(|p| p.account_index == pre.account_index) {
+                    continue;
+                }
+                let delta = -pre.ui_token_amount.ui_amount.unwrap_or(0.0);
+                if solana_nft::is_nft(pre.ui_token_amount.decimals, delta, nft_metadata.get(&pre.mint)) {
+                    nft_mints.push(pre.mint.clone());
+                }
+                if delta.abs() > 0.000001 {
+                    entries.push(LedgerEntry::new(
+                        tx,
+                        pre.mint.clone(),
+                        BigDecimal::from_f64(delta).unwrap_or_default(),
+                        EntryType::Transfer,
+                    ));
+                }
+            }
+        }
+    }
+
+    // Compressed NFTs (Bubblegum) live in merkle trees and never touch token accounts
+    if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+        if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+            for movement in bubblegum::cnft_movements(message, meta, &tx.wallet_address) {
+                nft_mints.push(movement.asset_id.clone());
+                entries.push(LedgerEntry::new(tx, movement.asset_id, BigDecimal::from(movement.amount), EntryType::Transfer));
+            }
+        }
+    }
+
+    // 4. Tag entries with the program the transaction invoked
+    if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+        if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+            let program = source_program(message);
+            for entry in entries.iter_mut() {
+                entry.source_program = program.clone();
+            }
+        }
+    }
+
+    // 5. Liquidity pool adds and removals. A CLMM position NFT is the pool share, not a purchase.
+    let mut liquidity = false;
+    if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+        if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+            liquidity = solana_liquidity::classify_liquidity(tx, message, meta, &mut entries);
+        }
+    }
+
+    // 6. Cross-chain bridge transfers
+    let mut bridge = false;
+    if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+        if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+            bridge = !liquidity && solana_bridge::classify_bridge(tx, message, meta, &mut entries);
+        }
+    }
+
+    if liquidity || bridge {
+        return Ok(entries);
+    }
+
+    // 7. NFT purchases, sales and mints. Bundles of several NFTs are left as transfers.
+    if let [nft_mint] = nft_mints.as_slice() {
+        if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+            if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+                solana_nft::split_nft_trade(tx, message, meta, nft_mint, nft_metadata.get(nft_mint), &mut entries);
+            }
         }
     }
 
+    // 8. Lending deposits, withdrawals, borrows and repayments
+    let mut lending = false;
+    if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+        if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+            lending = solana_lending::classify_lending(tx, message, meta, &mut entries);
+        }
+    }
+
+    // 9. Classify swaps. The network fee comes off the SOL leg first, or it would skew the amount traded
+    // and the price the swap implies.
+    if !lending {
+        if swap_legs(&entries).is_some() {
+            if let solana_transaction_status::EncodedTransaction::Json(ui_tx) = transaction {
+                if let solana_transaction_status::UiMessage::Parsed(message) = &ui_tx.message {
+                    split_network_fee(tx, message, meta, &mut entries);
+                }
+            }
+        }
+        classify_swap(&mut entries);
+    }
+
     Ok(entries)
 }
 
+/// Marks a transaction as a swap when, fees aside, the wallet sent exactly one asset and received exactly one other.
+pub fn classify_swap(entries: &mut [LedgerEntry]) {
+    if let Some(legs) = swap_legs(entries) {
+        for i in legs {
+            entries[i].entry_type = EntryType::Trade;
+        }
+    }
+}
+
+// The two legs of a swap, if the entries make one
+fn swap_legs(entries: &[LedgerEntry]) -> Option<[usize; 2]> {
+    let legs: Vec<usize> = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !matches!(e.entry_type, EntryType::Fee))
+        .map(|(i, _)| i)
+        .collect();
+
+    let [a, b] = legs.as_slice() else { return None };
+    let (ea, eb) = (&entries[*a], &entries[*b]);
+    let opposite = (ea.amount.sign() == Sign::Minus) != (eb.amount.sign() == Sign::Minus);
+    (opposite && ea.asset_symbol != eb.asset_symbol).then_some([*a, *b])
+}
+
+/// First top-level program the transaction called, skipping compute budget setup.
+fn source_program(message: &UiParsedMessage) -> Option<String> {
+    message
+        .instructions
+        .iter()
+        .filter_map(|ix| match ix {
+            UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => Some(parsed.program_id.clone()),
+            UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => Some(decoded.program_id.clone()),
+            UiInstruction::Compiled(compiled) => message
+                .account_keys
+                .get(compiled.program_id_index as usize)
+                .map(|k| k.pubkey.clone()),
+        })
+        .find(|program| program != COMPUTE_BUDGET_PROGRAM_ID)
+}
+
+fn is_fee_only(meta: &UiTransactionStatusMeta, wallet_index: usize) -> bool {
+    let pre = meta.pre_balances.get(wallet_index).copied().unwrap_or(0);
+    let post = meta.post_balances.get(wallet_index).copied().unwrap_or(0);
+    wallet_index == 0 && pre.checked_sub(post) == Some(meta.fee)
+}
+
 fn extract_sol_change(meta: &UiTransactionStatusMeta, wallet_index: usize) -> f64 {
     let pre = meta.pre_balances.get(wallet_index).copied().unwrap_or(0) as f64;
     let post = meta.post_balances.get(wallet_index).copied().unwrap_or(0) as f64;
     (post - pre) / 1_000_000_000.0 // Lamports to SOL
 }
+
+// One instruction, top-level or inner, in execution order
+pub(crate) struct Ix {
+    pub program: String,
+    pub accounts: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+// Top-level instructions, each followed by the inner instructions it invoked
+pub(crate) fn instructions(message: &UiParsedMessage, meta: &UiTransactionStatusMeta) -> Vec<Ix> {
+    let inner = match &meta.inner_instructions {
+        OptionSerializer::Some(inner) => inner.as_slice(),
+        _ => &[],
+    };
+    let mut ixs = Vec::new();
+    for (i, ix) in message.instructions.iter().enumerate() {
+        ixs.extend(to_ix(message, ix));
+        for set in inner.iter().filter(|set| set.index as usize == i) {
+            ixs.extend(set.instructions.iter().filter_map(|ix| to_ix(message, ix)));
+        }
+    }
+    ixs
+}
+
+fn to_ix(message: &UiParsedMessage, ix: &UiInstruction) -> Option<Ix> {
+    let key = |index: u8| message.account_keys.get(index as usize).map(|k| k.pubkey.clone());
+    match ix {
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(decoded)) => Some(Ix {
+            program: decoded.program_id.clone(),
+            accounts: decoded.accounts.clone(),
+            data: bs58::decode(&decoded.data).into_vec().ok()?,
+        }),
+        UiInstruction::Compiled(compiled) => Some(Ix {
+            program: key(compiled.program_id_index)?,
+            accounts: compiled.accounts.iter().filter_map(|a| key(*a)).collect(),
+            data: bs58::decode(&compiled.data).into_vec().ok()?,
+        }),
+        // Programs the RPC fully parses (system, token, ...) have no raw data to decode
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(_)) => None,
+    }
+}
+
+/// When the wallet paid the network fee, splits it off the wallet's SOL transfer as a `Fee` entry,
+/// recomputing the rest from lamports (the parsed change went through a float). For transactions
+/// whose SOL leg is about to be reclassified as something other than a transfer.
+pub(crate) fn split_network_fee(tx: &Transaction, message: &UiParsedMessage, meta: &UiTransactionStatusMeta, entries: &mut Vec<LedgerEntry>) {
+    if message.account_keys.first().is_none_or(|k| k.pubkey != tx.wallet_address) {
+        return;
+    }
+    let Some(sol) = entries.iter_mut().find(|e| e.asset_symbol == "SOL" && matches!(e.entry_type, EntryType::Transfer)) else {
+        return;
+    };
+    let change = meta.post_balances.first().copied().unwrap_or(0) as i128 - meta.pre_balances.first().copied().unwrap_or(0) as i128;
+    sol.amount = lamports(change + meta.fee as i128);
+    entries.push(LedgerEntry::new(tx, "SOL".to_string(), lamports(-(meta.fee as i128)), EntryType::Fee));
+    entries.retain(|e| !e.amount.is_zero());
+}
+
+fn lamports(amount: i128) -> BigDecimal {
+    BigDecimal::new(amount.into(), 9).normalized()
+}
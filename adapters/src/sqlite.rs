@@ -0,0 +1,901 @@
+use spectraplex_core::adjustments::{self, Adjustment, AuditRecord, Change};
+use spectraplex_core::fx::{FxRate, FxSource};
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, LedgerEntry, ParserVersion, Transaction, UserSettings};
+use spectraplex_core::pricing::{format_resolution, PriceCandle, PricePoint, PriceSource, OBSERVATION_RESOLUTION_SECS};
+use spectraplex_core::spam::SpamOverride;
+use spectraplex_core::storage::{Cursor, Page, QueryFilter, Storage, SyncState};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use uuid::Uuid;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Created on connect. Amounts are kept as decimal text, since SQLite has no exact numeric type.
+const SCHEMA: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS transactions (
+        id BLOB PRIMARY KEY,
+        user_id BLOB NOT NULL,
+        wallet_address TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        tx_hash TEXT NOT NULL,
+        chain TEXT NOT NULL,
+        raw_metadata TEXT NOT NULL
+    )
+    "#,
+    "CREATE INDEX IF NOT EXISTS idx_transactions_wallet_keyset ON transactions(wallet_address, timestamp, id)",
+    r#"
+    CREATE TABLE IF NOT EXISTS ledger_entries (
+        id BLOB PRIMARY KEY,
+        transaction_id BLOB REFERENCES transactions(id),
+        user_id BLOB NOT NULL,
+        wallet_address TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        asset_symbol TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        entry_type TEXT NOT NULL,
+        fiat_value TEXT,
+        fiat_currency TEXT,
+        price_source TEXT,
+        price_resolution TEXT,
+        source_program TEXT,
+        is_spam BOOLEAN NOT NULL DEFAULT FALSE,
+        position_account TEXT,
+        bridge_protocol TEXT,
+        bridge_destination_chain TEXT,
+        bridge_destination_address TEXT,
+        bridge_transfer_id TEXT,
+        linked_entry_id BLOB,
+        parser_name TEXT,
+        parser_version INTEGER
+    )
+    "#,
+    "CREATE INDEX IF NOT EXISTS idx_ledger_wallet_keyset ON ledger_entries(wallet_address, timestamp, id)",
+    "CREATE INDEX IF NOT EXISTS idx_ledger_user_time ON ledger_entries(user_id, timestamp)",
+    r#"
+    CREATE TABLE IF NOT EXISTS sync_state (
+        wallet_address TEXT NOT NULL,
+        chain TEXT NOT NULL,
+        last_timestamp INTEGER,
+        last_tx_hashes TEXT NOT NULL,
+        PRIMARY KEY (wallet_address, chain)
+    )
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS price_history (
+        asset_symbol TEXT NOT NULL,
+        fiat_currency TEXT NOT NULL,
+        resolution_secs INTEGER NOT NULL,
+        open_time INTEGER NOT NULL,
+        open TEXT NOT NULL,
+        high TEXT NOT NULL,
+        low TEXT NOT NULL,
+        close TEXT NOT NULL,
+        source TEXT NOT NULL,
+        PRIMARY KEY (asset_symbol, fiat_currency, resolution_secs, open_time)
+    )
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS fx_rates (
+        base_currency TEXT NOT NULL,
+        quote_currency TEXT NOT NULL,
+        rate_date TEXT NOT NULL,
+        rate TEXT NOT NULL,
+        source TEXT NOT NULL,
+        PRIMARY KEY (base_currency, quote_currency, rate_date)
+    )
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS spam_overrides (
+        user_id BLOB NOT NULL,
+        asset_symbol TEXT NOT NULL,
+        is_spam BOOLEAN NOT NULL,
+        PRIMARY KEY (user_id, asset_symbol)
+    )
+    "#,
+    r#"
+    CREATE TABLE IF NOT EXISTS user_settings (
+        user_id BLOB PRIMARY KEY,
+        reporting_currency TEXT NOT NULL
+    )
+    "#,
+    // Tags, changes and previous values are JSON text
+    r#"
+    CREATE TABLE IF NOT EXISTS ledger_adjustments (
+        entry_id BLOB PRIMARY KEY,
+        user_id BLOB NOT NULL,
+        wallet_address TEXT NOT NULL,
+        entry_type TEXT,
+        fiat_value TEXT,
+        fiat_currency TEXT,
+        note TEXT,
+        tags TEXT NOT NULL
+    )
+    "#,
+    "CREATE INDEX IF NOT EXISTS idx_adjustments_wallet ON ledger_adjustments(wallet_address)",
+    r#"
+    CREATE TABLE IF NOT EXISTS adjustment_audit (
+        id BLOB PRIMARY KEY,
+        user_id BLOB NOT NULL,
+        entry_id BLOB NOT NULL,
+        change TEXT NOT NULL,
+        previous TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL
+    )
+    "#,
+    "CREATE INDEX IF NOT EXISTS idx_adjustment_audit_user ON adjustment_audit(user_id, recorded_at)",
+];
+
+// Bound parameters per `IN (...)` lookup, well under SQLite's limit
+const LOOKUP_BATCH_SIZE: usize = 500;
+
+const LEDGER_COLUMNS: &str = "id, transaction_id, user_id, wallet_address, timestamp, asset_symbol, amount, entry_type, fiat_value, fiat_currency, \
+    price_source, price_resolution, source_program, is_spam, position_account, \
+    bridge_protocol, bridge_destination_chain, bridge_destination_address, bridge_transfer_id, linked_entry_id, parser_name, parser_version";
+
+/// Storage in an embedded SQLite file, for running the pipeline without a database server.
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Opens (creating if needed) the database at a URL such as `sqlite://spectraplex.db` or `sqlite::memory:`.
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)?.create_if_missing(true).foreign_keys(true);
+        // One connection: SQLite serializes writes anyway, and an in-memory database lives only as long as its connection
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+        for statement in SCHEMA {
+            sqlx::query(statement).execute(&pool).await?;
+        }
+        Ok(Self { pool })
+    }
+
+    async fn transactions_by_id(&self, column: &str, ids: &[Uuid]) -> anyhow::Result<Vec<SqliteRow>> {
+        let mut rows = Vec::new();
+        for batch in ids.chunks(LOOKUP_BATCH_SIZE) {
+            let mut query = QueryBuilder::<Sqlite>::new(format!("SELECT id, {} FROM transactions WHERE id IN (", column));
+            let mut list = query.separated(", ");
+            for id in batch {
+                list.push_bind(*id);
+            }
+            list.push_unseparated(")");
+            rows.extend(query.build().fetch_all(&self.pool).await?);
+        }
+        Ok(rows)
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for SqliteStore {
+    async fn save_transactions(&self, txs: &[Transaction]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, txs).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_ledger_entries(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_ledger_entries(&mut db, entries).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_transactions_with_entries(&self, txs: &[Transaction], entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, txs).await?;
+        insert_ledger_entries(&mut db, entries).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn replace_ledger_entries(&self, transaction_ids: &[Uuid], entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for batch in transaction_ids.chunks(LOOKUP_BATCH_SIZE) {
+            let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM ledger_entries WHERE transaction_id IN (");
+            let mut list = query.separated(", ");
+            for id in batch {
+                list.push_bind(*id);
+            }
+            list.push_unseparated(")");
+            query.build().execute(&mut *db).await?;
+        }
+        insert_ledger_entries(&mut db, entries).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_ingested(&self, txs: &[Transaction], state: &SyncState) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, txs).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO sync_state (wallet_address, chain, last_timestamp, last_tx_hashes)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (wallet_address, chain)
+            DO UPDATE SET last_timestamp = excluded.last_timestamp, last_tx_hashes = excluded.last_tx_hashes
+            "#
+        )
+        .bind(&state.wallet_address)
+        .bind(state.chain.name())
+        .bind(state.last_timestamp)
+        .bind(serde_json::to_string(&state.last_tx_hashes)?)
+        .execute(&mut *db)
+        .await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn get_sync_state(&self, wallet: &str, chain: &Chain) -> anyhow::Result<Option<SyncState>> {
+        let row = sqlx::query("SELECT last_timestamp, last_tx_hashes FROM sync_state WHERE wallet_address = $1 AND chain = $2")
+            .bind(wallet)
+            .bind(chain.name())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(SyncState {
+                wallet_address: wallet.to_string(),
+                chain: chain.clone(),
+                last_timestamp: row.try_get("last_timestamp")?,
+                last_tx_hashes: serde_json::from_str(row.try_get("last_tx_hashes")?)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_transactions_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<Transaction>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, wallet_address, timestamp, tx_hash, chain, raw_metadata
+            FROM transactions
+            WHERE wallet_address = $1
+            ORDER BY timestamp ASC, rowid ASC
+            "#
+        )
+        .bind(wallet)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(transaction_from_row).collect()
+    }
+
+    async fn get_ledger_entries_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<LedgerEntry>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM ledger_entries WHERE wallet_address = $1 ORDER BY timestamp ASC, rowid ASC", LEDGER_COLUMNS))
+            .bind(wallet)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(ledger_entry_from_row).collect()
+    }
+
+    async fn get_ledger_entries_by_user(&self, user_id: Uuid) -> anyhow::Result<Vec<LedgerEntry>> {
+        let rows = sqlx::query(&format!("SELECT {} FROM ledger_entries WHERE user_id = $1 ORDER BY timestamp ASC, rowid ASC", LEDGER_COLUMNS))
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(ledger_entry_from_row).collect()
+    }
+
+    async fn get_wallet_owner(&self, wallet: &str) -> anyhow::Result<Option<Uuid>> {
+        let row = sqlx::query("SELECT user_id FROM transactions WHERE wallet_address = $1 LIMIT 1")
+            .bind(wallet)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(Some(row.try_get("user_id")?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn page_transactions(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<Transaction>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT t.id, t.user_id, t.wallet_address, t.timestamp, t.tx_hash, t.chain, t.raw_metadata FROM transactions t WHERE t.wallet_address = "
+        );
+        query.push_bind(wallet.to_string());
+        push_filter(&mut query, filter, after, "t");
+        if filter.asset.is_some() || filter.entry_type.is_some() {
+            query.push(" AND EXISTS (SELECT 1 FROM ledger_entries l WHERE l.transaction_id = t.id");
+            push_entry_fields(&mut query, filter);
+            query.push(")");
+        }
+        push_tx_fields(&mut query, filter);
+        query.push(" ORDER BY t.timestamp ASC, t.id ASC LIMIT ").push_bind(limit as i64 + 1);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let txs = rows.iter().map(transaction_from_row).collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Page::from_rows(txs, limit, |t| Cursor { timestamp: t.timestamp, id: t.id }))
+    }
+
+    async fn page_ledger_entries(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<LedgerEntry>> {
+        let columns = LEDGER_COLUMNS.split(", ").map(|c| format!("l.{}", c.trim())).collect::<Vec<_>>().join(", ");
+        let mut query = QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM ledger_entries l", columns));
+        if filter.chain.is_some() || filter.tx_hash.is_some() {
+            query.push(" JOIN transactions t ON t.id = l.transaction_id");
+        }
+        query.push(" WHERE l.wallet_address = ").push_bind(wallet.to_string());
+        push_filter(&mut query, filter, after, "l");
+        push_entry_fields(&mut query, filter);
+        push_tx_fields(&mut query, filter);
+        query.push(" ORDER BY l.timestamp ASC, l.id ASC LIMIT ").push_bind(limit as i64 + 1);
+
+        let rows = query.build().fetch_all(&self.pool).await?;
+        let entries = rows.iter().map(ledger_entry_from_row).collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Page::from_rows(entries, limit, |e| Cursor { timestamp: e.timestamp, id: e.id }))
+    }
+
+    async fn get_tx_hashes(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, String>> {
+        let mut hashes = HashMap::new();
+        for row in self.transactions_by_id("tx_hash", ids).await? {
+            hashes.insert(row.try_get("id")?, row.try_get("tx_hash")?);
+        }
+        Ok(hashes)
+    }
+
+    async fn get_transaction_chains(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, Chain>> {
+        let mut chains = HashMap::new();
+        for row in self.transactions_by_id("chain", ids).await? {
+            let chain: String = row.try_get("chain")?;
+            chains.insert(row.try_get("id")?, Chain::from_name(&chain));
+        }
+        Ok(chains)
+    }
+
+    async fn update_ledger_valuations(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for e in entries {
+            sqlx::query("UPDATE ledger_entries SET fiat_value = $2, fiat_currency = $3, price_source = $4, price_resolution = $5 WHERE id = $1")
+                .bind(e.id)
+                .bind(e.fiat_value.as_ref().map(|v| v.to_string()))
+                .bind(&e.fiat_currency)
+                .bind(&e.price_source)
+                .bind(&e.price_resolution)
+                .execute(&mut *db)
+                .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn update_spam_flags(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for e in entries {
+            sqlx::query("UPDATE ledger_entries SET is_spam = $2 WHERE id = $1")
+                .bind(e.id)
+                .bind(e.is_spam)
+                .execute(&mut *db)
+                .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn update_bridge_links(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for (id, b) in entries.iter().filter_map(|e| Some((e.id, e.bridge.as_ref()?))) {
+            sqlx::query(
+                r#"
+                UPDATE ledger_entries
+                SET entry_type = 'bridge', bridge_protocol = $2, bridge_destination_chain = $3,
+                    bridge_destination_address = $4, bridge_transfer_id = $5, linked_entry_id = $6
+                WHERE id = $1
+                "#
+            )
+            .bind(id)
+            .bind(&b.protocol)
+            .bind(&b.destination_chain)
+            .bind(&b.destination_address)
+            .bind(&b.transfer_id)
+            .bind(b.linked_entry_id)
+            .execute(&mut *db)
+            .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_price_candles(&self, candles: &[PriceCandle]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for candle in candles {
+            sqlx::query(
+                r#"
+                INSERT INTO price_history (asset_symbol, fiat_currency, resolution_secs, open_time, open, high, low, close, source)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (asset_symbol, fiat_currency, resolution_secs, open_time)
+                DO UPDATE SET open = excluded.open, high = excluded.high, low = excluded.low, close = excluded.close, source = excluded.source
+                "#
+            )
+            .bind(&candle.asset_symbol)
+            .bind(&candle.fiat_currency)
+            .bind(candle.resolution_secs)
+            .bind(candle.open_time)
+            .bind(candle.open.to_string())
+            .bind(candle.high.to_string())
+            .bind(candle.low.to_string())
+            .bind(candle.close.to_string())
+            .bind(&candle.source)
+            .execute(&mut *db)
+            .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn save_fx_rates(&self, rates: &[FxRate]) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        for rate in rates {
+            sqlx::query(
+                r#"
+                INSERT INTO fx_rates (base_currency, quote_currency, rate_date, rate, source)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (base_currency, quote_currency, rate_date)
+                DO UPDATE SET rate = excluded.rate, source = excluded.source
+                "#
+            )
+            .bind(&rate.base_currency)
+            .bind(&rate.quote_currency)
+            .bind(rate.rate_date)
+            .bind(rate.rate.to_string())
+            .bind(&rate.source)
+            .execute(&mut *db)
+            .await?;
+        }
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn get_spam_overrides(&self, user_id: Uuid) -> anyhow::Result<HashMap<String, bool>> {
+        let rows = sqlx::query("SELECT asset_symbol, is_spam FROM spam_overrides WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut overrides = HashMap::new();
+        for row in rows {
+            overrides.insert(row.try_get("asset_symbol")?, row.try_get("is_spam")?);
+        }
+        Ok(overrides)
+    }
+
+    async fn save_spam_override(&self, o: &SpamOverride) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO spam_overrides (user_id, asset_symbol, is_spam)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, asset_symbol) DO UPDATE SET is_spam = excluded.is_spam
+            "#
+        )
+        .bind(o.user_id)
+        .bind(&o.asset_symbol)
+        .bind(o.is_spam)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_spam_override(&self, user_id: Uuid, asset: &str) -> anyhow::Result<bool> {
+        let result = sqlx::query("DELETE FROM spam_overrides WHERE user_id = $1 AND asset_symbol = $2")
+            .bind(user_id)
+            .bind(asset)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn get_user_settings(&self, user_id: Uuid) -> anyhow::Result<Option<UserSettings>> {
+        let row = sqlx::query("SELECT user_id, reporting_currency FROM user_settings WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(UserSettings {
+                user_id: row.try_get("user_id")?,
+                reporting_currency: row.try_get("reporting_currency")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn save_user_settings(&self, settings: &UserSettings) -> anyhow::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_settings (user_id, reporting_currency)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET reporting_currency = excluded.reporting_currency
+            "#
+        )
+        .bind(settings.user_id)
+        .bind(&settings.reporting_currency)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_adjustments_by_wallet(&self, wallet: &str) -> anyhow::Result<HashMap<Uuid, Adjustment>> {
+        let rows = sqlx::query(
+            "SELECT entry_id, user_id, wallet_address, entry_type, fiat_value, fiat_currency, note, tags FROM ledger_adjustments WHERE wallet_address = $1"
+        )
+        .bind(wallet)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut adjustments = HashMap::new();
+        for row in rows {
+            let a = adjustment_from_row(&row)?;
+            adjustments.insert(a.entry_id, a);
+        }
+        Ok(adjustments)
+    }
+
+    async fn adjust_entry(&self, user_id: Uuid, entry_id: Uuid, change: &Change) -> anyhow::Result<Option<Adjustment>> {
+        let mut db = self.pool.begin().await?;
+        let row = sqlx::query(&format!("SELECT {} FROM ledger_entries WHERE id = $1 AND user_id = $2", LEDGER_COLUMNS))
+            .bind(entry_id)
+            .bind(user_id)
+            .fetch_optional(&mut *db)
+            .await?;
+        let Some(row) = row else { return Ok(None) };
+        let mut entry = ledger_entry_from_row(&row)?;
+
+        let row = sqlx::query(
+            "SELECT entry_id, user_id, wallet_address, entry_type, fiat_value, fiat_currency, note, tags FROM ledger_adjustments WHERE entry_id = $1"
+        )
+        .bind(entry_id)
+        .fetch_optional(&mut *db)
+        .await?;
+        let before = match row {
+            Some(row) => adjustment_from_row(&row)?,
+            None => Adjustment::new(&entry),
+        };
+        let (adjustment, record) = adjustments::adjust(&mut entry, &before, change, chrono::Utc::now().timestamp())?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ledger_adjustments (entry_id, user_id, wallet_address, entry_type, fiat_value, fiat_currency, note, tags)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (entry_id) DO UPDATE SET entry_type = excluded.entry_type, fiat_value = excluded.fiat_value,
+                fiat_currency = excluded.fiat_currency, note = excluded.note, tags = excluded.tags
+            "#
+        )
+        .bind(adjustment.entry_id)
+        .bind(adjustment.user_id)
+        .bind(&adjustment.wallet_address)
+        .bind(adjustment.entry_type.as_ref().map(|t| t.name()))
+        .bind(adjustment.fiat_value.as_ref().map(|v| v.to_string()))
+        .bind(&adjustment.fiat_currency)
+        .bind(&adjustment.note)
+        .bind(serde_json::to_string(&adjustment.tags)?)
+        .execute(&mut *db)
+        .await?;
+
+        sqlx::query("UPDATE ledger_entries SET entry_type = $2, fiat_value = $3, fiat_currency = $4, price_source = $5, price_resolution = $6 WHERE id = $1")
+            .bind(entry.id)
+            .bind(entry.entry_type.name())
+            .bind(entry.fiat_value.as_ref().map(|v| v.to_string()))
+            .bind(&entry.fiat_currency)
+            .bind(&entry.price_source)
+            .bind(&entry.price_resolution)
+            .execute(&mut *db)
+            .await?;
+
+        insert_audit_record(&mut db, &record).await?;
+        db.commit().await?;
+        Ok(Some(adjustment))
+    }
+
+    async fn save_manual_entry(&self, tx: &Transaction, entries: &[LedgerEntry], record: &AuditRecord) -> anyhow::Result<()> {
+        let mut db = self.pool.begin().await?;
+        insert_transactions(&mut db, std::slice::from_ref(tx)).await?;
+        insert_ledger_entries(&mut db, entries).await?;
+        insert_audit_record(&mut db, record).await?;
+        db.commit().await?;
+        Ok(())
+    }
+
+    async fn get_audit_trail(&self, user_id: Uuid, entry_id: Option<Uuid>) -> anyhow::Result<Vec<AuditRecord>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, user_id, entry_id, change, previous, recorded_at
+            FROM adjustment_audit
+            WHERE user_id = $1 AND ($2 IS NULL OR entry_id = $2)
+            ORDER BY recorded_at ASC, id ASC
+            "#
+        )
+        .bind(user_id)
+        .bind(entry_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(AuditRecord {
+                id: row.try_get("id")?,
+                user_id: row.try_get("user_id")?,
+                entry_id: row.try_get("entry_id")?,
+                change: serde_json::from_str(row.try_get("change")?)?,
+                previous: serde_json::from_str(row.try_get("previous")?)?,
+                recorded_at: row.try_get("recorded_at")?,
+            });
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait::async_trait]
+impl FxSource for SqliteStore {
+    async fn rate_on(&self, base: &str, quote: &str, date: NaiveDate) -> anyhow::Result<Option<BigDecimal>> {
+        // Dates are stored as ISO text, which sorts chronologically
+        let row = sqlx::query(
+            r#"
+            SELECT rate
+            FROM fx_rates
+            WHERE base_currency = $1 AND quote_currency = $2 AND rate_date <= $3
+            ORDER BY rate_date DESC
+            LIMIT 1
+            "#
+        )
+        .bind(base)
+        .bind(quote)
+        .bind(date)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|r| decimal(&r, "rate")).transpose()
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for SqliteStore {
+    async fn price_at(&self, asset: &str, timestamp: i64, fiat: &str) -> anyhow::Result<Option<PricePoint>> {
+        // Finest candle that contains the timestamp wins
+        let row = sqlx::query(
+            r#"
+            SELECT open_time, resolution_secs, close, source
+            FROM price_history
+            WHERE asset_symbol = $1 AND fiat_currency = $2
+              AND open_time <= $3 AND open_time + resolution_secs > $3
+            ORDER BY resolution_secs ASC
+            LIMIT 1
+            "#
+        )
+        .bind(asset)
+        .bind(fiat.to_uppercase())
+        .bind(timestamp)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(PricePoint {
+                price: decimal(&row, "close")?,
+                source: row.try_get("source")?,
+                resolution: format_resolution(row.try_get("resolution_secs")?),
+                timestamp: row.try_get("open_time")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    async fn nearest_observation(
+        &self,
+        asset: &str,
+        timestamp: i64,
+        fiat: &str,
+        window_secs: i64,
+    ) -> anyhow::Result<Option<PricePoint>> {
+        let row = sqlx::query(
+            r#"
+            SELECT open_time, close, source
+            FROM price_history
+            WHERE asset_symbol = $1 AND fiat_currency = $2 AND resolution_secs = $5
+              AND open_time BETWEEN $3 - $4 AND $3 + $4
+            ORDER BY ABS(open_time - $3) ASC
+            LIMIT 1
+            "#
+        )
+        .bind(asset)
+        .bind(fiat.to_uppercase())
+        .bind(timestamp)
+        .bind(window_secs)
+        .bind(OBSERVATION_RESOLUTION_SECS)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some(PricePoint {
+                price: decimal(&row, "close")?,
+                source: row.try_get("source")?,
+                resolution: format_resolution(OBSERVATION_RESOLUTION_SECS),
+                timestamp: row.try_get("open_time")?,
+            })),
+            None => Ok(None),
+        }
+    }
+}
+
+async fn insert_transactions(conn: &mut SqliteConnection, txs: &[Transaction]) -> anyhow::Result<()> {
+    for tx in txs {
+        sqlx::query(
+            r#"
+            INSERT INTO transactions (id, user_id, wallet_address, timestamp, tx_hash, chain, raw_metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO NOTHING
+            "#
+        )
+        .bind(tx.id)
+        .bind(tx.user_id)
+        .bind(&tx.wallet_address)
+        .bind(tx.timestamp)
+        .bind(&tx.tx_hash)
+        .bind(tx.chain.name())
+        .bind(serde_json::to_string(&tx.raw_metadata)?)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn insert_audit_record(conn: &mut SqliteConnection, record: &AuditRecord) -> anyhow::Result<()> {
+    sqlx::query("INSERT INTO adjustment_audit (id, user_id, entry_id, change, previous, recorded_at) VALUES ($1, $2, $3, $4, $5, $6)")
+        .bind(record.id)
+        .bind(record.user_id)
+        .bind(record.entry_id)
+        .bind(serde_json::to_string(&record.change)?)
+        .bind(record.previous.to_string())
+        .bind(record.recorded_at)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+async fn insert_ledger_entries(conn: &mut SqliteConnection, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+    for e in entries {
+        let bridge = e.bridge.as_ref();
+        sqlx::query(&format!(
+            "INSERT INTO ledger_entries ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22) ON CONFLICT (id) DO NOTHING",
+            LEDGER_COLUMNS
+        ))
+        .bind(e.id)
+        .bind(e.transaction_id)
+        .bind(e.user_id)
+        .bind(&e.wallet_address)
+        .bind(e.timestamp)
+        .bind(&e.asset_symbol)
+        .bind(e.amount.to_string())
+        .bind(e.entry_type.name())
+        .bind(e.fiat_value.as_ref().map(|v| v.to_string()))
+        .bind(&e.fiat_currency)
+        .bind(&e.price_source)
+        .bind(&e.price_resolution)
+        .bind(&e.source_program)
+        .bind(e.is_spam)
+        .bind(&e.position_account)
+        .bind(bridge.map(|b| b.protocol.clone()))
+        .bind(bridge.and_then(|b| b.destination_chain.clone()))
+        .bind(bridge.and_then(|b| b.destination_address.clone()))
+        .bind(bridge.and_then(|b| b.transfer_id.clone()))
+        .bind(bridge.and_then(|b| b.linked_entry_id))
+        .bind(e.parser.as_ref().map(|p| p.name.clone()))
+        .bind(e.parser.as_ref().map(|p| p.version))
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+// Time range and keyset position, on the table being paged (`alias`)
+fn push_filter(query: &mut QueryBuilder<Sqlite>, filter: &QueryFilter, after: Option<Cursor>, alias: &str) {
+    if let Some(from) = filter.from {
+        query.push(format!(" AND {}.timestamp >= ", alias)).push_bind(from);
+    }
+    if let Some(to) = filter.to {
+        query.push(format!(" AND {}.timestamp < ", alias)).push_bind(to);
+    }
+    if let Some(after) = after {
+        query.push(format!(" AND ({0}.timestamp, {0}.id) > (", alias)).push_bind(after.timestamp).push(", ").push_bind(after.id).push(")");
+    }
+}
+
+// Filters on ledger entry columns, aliased `l`
+fn push_entry_fields(query: &mut QueryBuilder<Sqlite>, filter: &QueryFilter) {
+    if let Some(asset) = &filter.asset {
+        query.push(" AND l.asset_symbol = ").push_bind(asset.clone());
+    }
+    if let Some(entry_type) = &filter.entry_type {
+        query.push(" AND l.entry_type = ").push_bind(entry_type.name());
+    }
+}
+
+// Filters on transaction columns, aliased `t`
+fn push_tx_fields(query: &mut QueryBuilder<Sqlite>, filter: &QueryFilter) {
+    if let Some(chain) = &filter.chain {
+        query.push(" AND t.chain = ").push_bind(chain.name().to_string());
+    }
+    if let Some(hash) = &filter.tx_hash {
+        query.push(" AND t.tx_hash = ").push_bind(hash.clone());
+    }
+}
+
+fn decimal(row: &SqliteRow, column: &str) -> anyhow::Result<BigDecimal> {
+    let text: String = row.try_get(column)?;
+    Ok(BigDecimal::from_str(&text)?)
+}
+
+fn transaction_from_row(row: &SqliteRow) -> anyhow::Result<Transaction> {
+    let chain: String = row.try_get("chain")?;
+    Ok(Transaction {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        wallet_address: row.try_get("wallet_address")?,
+        timestamp: row.try_get("timestamp")?,
+        tx_hash: row.try_get("tx_hash")?,
+        chain: Chain::from_name(&chain),
+        raw_metadata: serde_json::from_str(row.try_get("raw_metadata")?)?,
+    })
+}
+
+fn ledger_entry_from_row(row: &SqliteRow) -> anyhow::Result<LedgerEntry> {
+    let bridge = match row.try_get::<Option<String>, _>("bridge_protocol")? {
+        Some(protocol) => Some(BridgeTransfer {
+            protocol,
+            destination_chain: row.try_get("bridge_destination_chain")?,
+            destination_address: row.try_get("bridge_destination_address")?,
+            transfer_id: row.try_get("bridge_transfer_id")?,
+            linked_entry_id: row.try_get("linked_entry_id")?,
+        }),
+        None => None,
+    };
+    let parser = match row.try_get::<Option<String>, _>("parser_name")? {
+        Some(name) => Some(ParserVersion { name, version: row.try_get("parser_version")? }),
+        None => None,
+    };
+    let fiat_value = match row.try_get::<Option<String>, _>("fiat_value")? {
+        Some(v) => Some(BigDecimal::from_str(&v)?),
+        None => None,
+    };
+
+    Ok(LedgerEntry {
+        id: row.try_get("id")?,
+        transaction_id: row.try_get("transaction_id")?,
+        user_id: row.try_get("user_id")?,
+        wallet_address: row.try_get("wallet_address")?,
+        timestamp: row.try_get("timestamp")?,
+        asset_symbol: row.try_get("asset_symbol")?,
+        amount: decimal(row, "amount")?,
+        entry_type: EntryType::from_name(&row.try_get::<String, _>("entry_type")?),
+        fiat_value,
+        fiat_currency: row.try_get("fiat_currency")?,
+        price_source: row.try_get("price_source")?,
+        price_resolution: row.try_get("price_resolution")?,
+        source_program: row.try_get("source_program")?,
+        is_spam: row.try_get("is_spam")?,
+        position_account: row.try_get("position_account")?,
+        bridge,
+        parser,
+    })
+}
+
+fn adjustment_from_row(row: &SqliteRow) -> anyhow::Result<Adjustment> {
+    let fiat_value = match row.try_get::<Option<String>, _>("fiat_value")? {
+        Some(v) => Some(BigDecimal::from_str(&v)?),
+        None => None,
+    };
+    Ok(Adjustment {
+        entry_id: row.try_get("entry_id")?,
+        user_id: row.try_get("user_id")?,
+        wallet_address: row.try_get("wallet_address")?,
+        entry_type: row.try_get::<Option<String>, _>("entry_type")?.map(|t| EntryType::from_name(&t)),
+        fiat_value,
+        fiat_currency: row.try_get("fiat_currency")?,
+        note: row.try_get("note")?,
+        tags: serde_json::from_str(row.try_get("tags")?)?,
+    })
+}
@@ -0,0 +1,136 @@
+use spectraplex_adapters::bubblegum::BUBBLEGUM_PROGRAM_ID;
+use spectraplex_adapters::solana_parser;
+use spectraplex_core::models::{Chain, EntryType, Transaction};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use solana_sdk::hash::hashv;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const NOOP: &str = "noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV";
+
+fn discriminator(name: &str) -> Vec<u8> {
+    hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8].to_vec()
+}
+
+fn bubblegum_ix(name: &str, accounts: &[String], args: Vec<u8>) -> Value {
+    let mut data = discriminator(name);
+    data.extend(args);
+    json!({ "programId": BUBBLEGUM_PROGRAM_ID, "accounts": accounts, "data": bs58::encode(data).into_string(), "stackHeight": null })
+}
+
+fn solana_tx(wallet: &str, payer: &str, instructions: Value, inner: Value) -> Transaction {
+    let raw = json!({
+        "slot": 123459,
+        "transaction": {
+            "signatures": ["cnftsig"],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": payer, "signer": true, "writable": true },
+                    { "pubkey": BUBBLEGUM_PROGRAM_ID, "signer": false, "writable": false }
+                ],
+                "instructions": instructions,
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [1_000_000_000u64, 1],
+            "postBalances": [999_995_000u64, 1],
+            "innerInstructions": inner,
+            "logMessages": [],
+            "preTokenBalances": [],
+            "postTokenBalances": [],
+            "rewards": []
+        },
+        "blockTime": 1672531500
+    });
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        wallet_address: wallet.to_string(),
+        timestamp: 1672531500,
+        tx_hash: "cnftsig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: raw,
+    }
+}
+
+fn key(byte: u8) -> String {
+    Pubkey::new_from_array([byte; 32]).to_string()
+}
+
+// Transfer and burn arguments: root, data_hash, creator_hash, nonce, index
+fn leaf_args(nonce: u64) -> Vec<u8> {
+    let mut args = vec![0; 96];
+    args.extend(nonce.to_le_bytes());
+    args.extend((nonce as u32).to_le_bytes());
+    args
+}
+
+fn expected_asset_id(tree: &str, nonce: u64) -> String {
+    let program = Pubkey::from_str(BUBBLEGUM_PROGRAM_ID).unwrap();
+    let tree = Pubkey::from_str(tree).unwrap();
+    Pubkey::find_program_address(&[b"asset", tree.as_ref(), &nonce.to_le_bytes()], &program).0.to_string()
+}
+
+#[test]
+fn test_cnft_minted_to_wallet_is_keyed_by_leaf_event_asset_id() {
+    let (wallet, minter, tree, asset) = (key(1), key(2), key(3), Pubkey::new_from_array([4; 32]));
+
+    // LeafSchemaEvent wrapped as account-compression application data
+    let mut event = vec![1, 0, 0];
+    event.extend(asset.to_bytes());
+    event.extend(Pubkey::from_str(&wallet).unwrap().to_bytes());
+    event.extend([0; 32 + 8 + 64 + 32]); // delegate, nonce, hashes, leaf hash
+    let mut wrapped = vec![1, 0];
+    wrapped.extend((event.len() as u32).to_le_bytes());
+    wrapped.extend(event);
+
+    // An unsolicited drop: someone else signs and pays
+    let accounts = [key(9), wallet.clone(), wallet.clone(), tree, minter.clone()];
+    let tx = solana_tx(
+        &wallet,
+        &minter,
+        json!([bubblegum_ix("mint_to_collection_v1", &accounts, vec![0; 16])]),
+        json!([{ "index": 0, "instructions": [
+            { "programId": NOOP, "accounts": [], "data": bs58::encode(wrapped).into_string(), "stackHeight": 2 }
+        ] }]),
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].asset_symbol, asset.to_string());
+    assert_eq!(entries[0].amount, BigDecimal::from(1));
+    assert!(matches!(entries[0].entry_type, EntryType::Transfer));
+    assert_eq!(entries[0].source_program.as_deref(), Some(BUBBLEGUM_PROGRAM_ID));
+}
+
+#[test]
+fn test_cnft_transfer_out_and_burn() {
+    let (wallet, other, tree) = (key(1), key(2), key(3));
+    let transfer = [key(9), wallet.clone(), wallet.clone(), other, tree.clone()];
+    let burn = [key(9), wallet.clone(), wallet.clone(), tree.clone()];
+    let tx = solana_tx(
+        &wallet,
+        &wallet,
+        json!([bubblegum_ix("transfer", &transfer, leaf_args(5)), bubblegum_ix("burn", &burn, leaf_args(6))]),
+        json!([]),
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+    let moved: Vec<(String, BigDecimal)> = entries
+        .iter()
+        .filter(|e| matches!(e.entry_type, EntryType::Transfer))
+        .map(|e| (e.asset_symbol.clone(), e.amount.clone()))
+        .collect();
+    assert_eq!(
+        moved,
+        vec![(expected_asset_id(&tree, 5), BigDecimal::from(-1)), (expected_asset_id(&tree, 6), BigDecimal::from(-1))]
+    );
+    // The wallet signed, so it paid the network fee
+    assert!(entries.iter().any(|e| e.asset_symbol == "SOL" && matches!(e.entry_type, EntryType::Fee)));
+}
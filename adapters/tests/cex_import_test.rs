@@ -0,0 +1,101 @@
+use spectraplex_adapters::cex_import::{self, Exchange};
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry};
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+fn normalize(statement: &str, exchange: Exchange) -> Vec<Vec<LedgerEntry>> {
+    cex_import::parse_statement(statement.as_bytes(), exchange, Uuid::nil(), "main")
+        .unwrap()
+        .iter()
+        .map(|tx| cex_import::parse_cex_transaction(tx).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_coinbase_statement_skips_preamble_and_values_trades() {
+    let statement = "\
+Transactions
+User,someone@example.com,abc123
+ID,Timestamp,Transaction Type,Asset,Quantity Transacted,Price Currency,Price at Transaction,Subtotal,Total (inclusive of fees and/or spread),Fees and/or Spread,Notes
+a1,2024-01-01 10:00:00 UTC,Buy,SOL,2,USD,$100.00,$200.00,$202.99,$2.99,Bought 2 SOL for 202.99 USD
+a2,2024-01-05 12:00:00 UTC,Convert,SOL,1,USD,$110.00,$110.00,$110.00,$0.00,\"Converted 1 SOL to 1,100 BONK\"
+a3,2024-02-01 00:00:00 UTC,Staking Income,SOL,0.01,USD,$100.00,$1.00,$1.00,,
+";
+    let txs = cex_import::parse_statement(statement.as_bytes(), Exchange::Coinbase, Uuid::nil(), "main").unwrap();
+    assert_eq!(txs.len(), 3);
+    assert!(matches!(txs[0].chain, Chain::Coinbase));
+    assert_eq!(txs[0].tx_hash, "coinbase:a1");
+    assert_eq!(txs[0].raw_metadata["rows"][0]["Transaction Type"], "Buy");
+
+    let entries = normalize(statement, Exchange::Coinbase);
+    let buy = &entries[0];
+    assert_eq!(buy.len(), 3);
+    assert!(matches!(buy[0].entry_type, EntryType::Trade));
+    assert_eq!(buy[0].amount, dec("2"));
+    assert_eq!(buy[0].fiat_value, Some(dec("200")));
+    assert_eq!(buy[1].asset_symbol, "USD");
+    assert_eq!(buy[1].amount, dec("-200"));
+    assert!(matches!(buy[2].entry_type, EntryType::Fee));
+    assert_eq!(buy[2].fiat_value, Some(dec("2.99")));
+
+    let convert = &entries[1];
+    assert_eq!(convert[1].asset_symbol, "BONK");
+    assert_eq!(convert[1].amount, dec("1100"));
+
+    assert!(matches!(entries[2][0].entry_type, EntryType::Staking));
+}
+
+#[test]
+fn test_kraken_ledger_groups_trade_rows_by_refid() {
+    let statement = "\
+\"txid\",\"refid\",\"time\",\"type\",\"subtype\",\"aclass\",\"asset\",\"amount\",\"fee\",\"balance\"
+\"L1\",\"D1\",\"2024-01-01 09:00:00\",\"deposit\",\"\",\"currency\",\"ZUSD\",\"1000.0000\",\"0.0000\",\"1000.0000\"
+\"L2\",\"T1\",\"2024-01-02 09:00:00\",\"trade\",\"\",\"currency\",\"ZUSD\",\"-500.0000\",\"1.3000\",\"498.7000\"
+\"L3\",\"T1\",\"2024-01-02 09:00:00\",\"trade\",\"\",\"currency\",\"XXBT\",\"0.0120000000\",\"0.0000000000\",\"0.0120000000\"
+\"L4\",\"W1\",\"2024-01-03 09:00:00\",\"withdrawal\",\"\",\"currency\",\"XXBT\",\"-0.0100000000\",\"0.0002000000\",\"0.0018000000\"
+";
+    let entries = normalize(statement, Exchange::Kraken);
+    assert_eq!(entries.len(), 3);
+
+    assert!(matches!(entries[0][0].entry_type, EntryType::Transfer));
+    assert_eq!(entries[0][0].asset_symbol, "USD");
+    assert_eq!(entries[0][0].fiat_value, Some(dec("1000")));
+
+    let trade = &entries[1];
+    assert_eq!(trade.len(), 3);
+    assert_eq!(trade[0].amount, dec("-500"));
+    assert!(matches!(trade[1].entry_type, EntryType::Fee));
+    assert_eq!(trade[1].amount, dec("-1.3"));
+    assert_eq!(trade[2].asset_symbol, "BTC");
+    assert!(matches!(trade[2].entry_type, EntryType::Trade));
+
+    let withdrawal = &entries[2];
+    assert_eq!(withdrawal[0].amount, dec("-0.01"));
+    assert_eq!(withdrawal[1].amount, dec("-0.0002"));
+}
+
+#[test]
+fn test_binance_rows_sharing_a_timestamp_form_one_trade() {
+    let statement = "\
+User_ID,UTC_Time,Account,Operation,Coin,Change,Remark
+1,2024-01-01 10:00:00,Spot,Deposit,USDT,500,
+1,2024-01-02 11:00:00,Spot,Transaction Buy,SOL,5,
+1,2024-01-02 11:00:00,Spot,Transaction Spend,USDT,-495,
+1,2024-01-02 11:00:00,Spot,Transaction Fee,SOL,-0.005,
+1,2024-01-03 00:00:00,Earn,Simple Earn Flexible Interest,USDT,0.12,
+";
+    let txs = cex_import::parse_statement(statement.as_bytes(), Exchange::Binance, Uuid::nil(), "main").unwrap();
+    assert_eq!(txs.len(), 3);
+    assert_eq!(txs[1].raw_metadata["rows"].as_array().unwrap().len(), 3);
+
+    let entries = normalize(statement, Exchange::Binance);
+    assert!(matches!(entries[0][0].entry_type, EntryType::Transfer));
+    let kinds: Vec<&EntryType> = entries[1].iter().map(|e| &e.entry_type).collect();
+    assert!(matches!(kinds.as_slice(), [EntryType::Trade, EntryType::Trade, EntryType::Fee]));
+    assert!(matches!(entries[2][0].entry_type, EntryType::Income));
+}
@@ -0,0 +1,303 @@
+use spectraplex_adapters::ethereum::{EthereumAdapter, ERC20_TRANSFER_TOPIC, MAX_SCAN_BLOCKS};
+use spectraplex_adapters::{ethereum_parser, evm};
+use spectraplex_core::models::{Chain, ChainIngestor, EntryType, LedgerEntry, Transaction};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "0x1111111111111111111111111111111111111111";
+const ROUTER: &str = "0x2222222222222222222222222222222222222222";
+const TOKEN: &str = "0x3333333333333333333333333333333333333333";
+const OTHER: &str = "0x4444444444444444444444444444444444444444";
+
+const SWAP: &str = "0xaaaa";
+const AIRDROP: &str = "0xbbbb";
+const REVERTED: &str = "0xcccc";
+// Someone else's call that pays the wallet from inside a contract
+const INTERNAL: &str = "0xeeee";
+
+fn topic(address: &str) -> String {
+    format!("0x{:0>64}", address.trim_start_matches("0x"))
+}
+
+fn transfer_log(hash: &str, block: &str, from: &str, to: &str, amount: u64) -> Value {
+    json!({
+        "address": TOKEN, "blockNumber": block, "transactionHash": hash,
+        "topics": [ERC20_TRANSFER_TOPIC, topic(from), topic(to)],
+        "data": format!("0x{:064x}", amount)
+    })
+}
+
+fn transaction(hash: &str) -> Value {
+    match hash {
+        SWAP => json!({ "hash": SWAP, "from": WALLET, "to": ROUTER, "value": "0x6f05b59d3b20000", "gasPrice": "0x6fc23ac00" }), // 0.5 ETH
+        AIRDROP => json!({ "hash": AIRDROP, "from": OTHER, "to": TOKEN, "value": "0x0", "gasPrice": "0x6fc23ac00" }),
+        INTERNAL => json!({ "hash": INTERNAL, "from": OTHER, "to": ROUTER, "value": "0x0", "gasPrice": "0x6fc23ac00" }),
+        _ => json!({ "hash": REVERTED, "from": WALLET, "to": ROUTER, "value": "0xde0b6b3a7640000", "gasPrice": "0x5d21dba00" }), // 1 ETH
+    }
+}
+
+fn receipt(hash: &str) -> Value {
+    match hash {
+        // 21000 gas at 30 gwei, 25 of it base fee
+        SWAP => json!({ "status": "0x1", "gasUsed": "0x5208", "effectiveGasPrice": "0x6fc23ac00",
+            "logs": [transfer_log(SWAP, "0x64", ROUTER, WALLET, 1_000_000_000)] }),
+        AIRDROP => json!({ "status": "0x1", "gasUsed": "0x5208", "effectiveGasPrice": "0x6fc23ac00",
+            "logs": [transfer_log(AIRDROP, "0x65", OTHER, WALLET, 5_000_000)] }),
+        INTERNAL => json!({ "status": "0x1", "gasUsed": "0x5208", "effectiveGasPrice": "0x6fc23ac00", "logs": [] }),
+        _ => json!({ "status": "0x0", "gasUsed": "0x5208", "effectiveGasPrice": "0x5d21dba00", "logs": [] }),
+    }
+}
+
+fn in_range(filter: &Value, block: u64) -> bool {
+    let bound = |key: &str| u64::from_str_radix(filter[key].as_str().unwrap_or_default().trim_start_matches("0x"), 16).unwrap_or(0);
+    (bound("fromBlock")..=bound("toBlock")).contains(&block)
+}
+
+// A traced node answers trace_filter and refuses to hand out whole blocks, so a test
+// against it fails if the adapter falls back to scanning block by block
+fn rpc_result(request: &Value, traced: bool) -> Result<Value, Value> {
+    let params = &request["params"];
+    match request["method"].as_str().unwrap_or_default() {
+        "eth_chainId" => Ok(json!("0x1")),
+        "eth_blockNumber" => Ok(json!("0x65")),
+        "eth_getBlockByNumber" if traced && params[1] == json!(true) => Err(json!({ "code": -32000, "message": "full blocks are disabled" })),
+        "eth_getBlockByNumber" => {
+            let (number, txs) = match params[0].as_str() {
+                Some("0x64") => ("0x64", vec![transaction(SWAP)]),
+                _ => ("0x65", vec![transaction(REVERTED), json!({ "hash": "0xdddd", "from": OTHER, "to": ROUTER })]),
+            };
+            let txs = if params[1] == json!(true) { json!(txs) } else { json!([]) };
+            Ok(json!({ "number": number, "timestamp": "0x65920080", "baseFeePerGas": "0x5d21dba00", "transactions": txs }))
+        }
+        "eth_getLogs" => {
+            // Only transfers into the wallet exist
+            let incoming = params[0]["topics"][1].is_null();
+            let logs = if incoming {
+                vec![(100, transfer_log(SWAP, "0x64", ROUTER, WALLET, 1_000_000_000)), (101, transfer_log(AIRDROP, "0x65", OTHER, WALLET, 5_000_000))]
+            } else {
+                vec![]
+            };
+            Ok(json!(logs.into_iter().filter(|(block, _)| in_range(&params[0], *block)).map(|(_, log)| log).collect::<Vec<_>>()))
+        }
+        "trace_filter" if traced => {
+            let traces = if params[0]["fromAddress"].is_array() {
+                vec![(100, json!(SWAP)), (101, json!(REVERTED))]
+            } else {
+                // The swap's refund, the internal payment and a block reward
+                vec![(100, json!(SWAP)), (101, json!(INTERNAL)), (101, Value::Null)]
+            };
+            Ok(json!(traces
+                .into_iter()
+                .filter(|(block, _)| in_range(&params[0], *block))
+                .map(|(block, hash)| json!({ "blockNumber": block, "transactionHash": hash }))
+                .collect::<Vec<_>>()))
+        }
+        "eth_getTransactionByHash" => Ok(transaction(params[0].as_str().unwrap_or_default())),
+        "eth_getTransactionReceipt" => Ok(receipt(params[0].as_str().unwrap_or_default())),
+        "eth_call" => Ok(json!(format!("0x{:064x}", 6))),
+        "debug_traceTransaction" if params[0] == json!(SWAP) => Ok(json!({
+            "type": "CALL", "from": WALLET, "to": ROUTER, "value": "0x6f05b59d3b20000",
+            "calls": [
+                { "type": "CALL", "from": ROUTER, "to": WALLET, "value": "0x16345785d8a0000" }, // 0.1 ETH refund
+                { "type": "CALL", "from": ROUTER, "to": WALLET, "value": "0x16345785d8a0000", "error": "execution reverted" }
+            ]
+        })),
+        "debug_traceTransaction" if params[0] == json!(INTERNAL) => Ok(json!({
+            "type": "CALL", "from": OTHER, "to": ROUTER, "value": "0x0",
+            "calls": [{ "type": "CALL", "from": ROUTER, "to": WALLET, "value": "0x2c68af0bb140000" }] // 0.2 ETH
+        })),
+        method => Err(json!({ "code": -32601, "message": format!("the method {} does not exist", method) })),
+    }
+}
+
+// Local stand-in for a node such as anvil
+fn spawn_node() -> String {
+    spawn_node_with(false)
+}
+
+// Local stand-in for an archive node with the trace API, such as Erigon
+fn spawn_traced_node() -> String {
+    spawn_node_with(true)
+}
+
+fn spawn_node_with(traced: bool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut content_length = 0;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim_end().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.trim_end().split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            let _ = reader.read_exact(&mut body);
+            let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            let response = match rpc_result(&request, traced) {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                Err(error) => json!({ "jsonrpc": "2.0", "id": request["id"], "error": error }),
+            }
+            .to_string();
+            let _ = write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            );
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+fn amounts(entries: &[LedgerEntry], kind: fn(&EntryType) -> bool) -> Vec<(String, BigDecimal)> {
+    entries.iter().filter(|e| kind(&e.entry_type)).map(|e| (e.asset_symbol.clone(), e.amount.clone())).collect()
+}
+
+#[tokio::test]
+async fn test_ingest_and_parse_evm_history() {
+    let adapter = EthereumAdapter::new(&spawn_node(), Some(100));
+    let txs = adapter.fetch_history(WALLET, 10).await.unwrap();
+    let hashes: Vec<&str> = txs.iter().map(|t| t.tx_hash.as_str()).collect();
+    assert_eq!(hashes, vec![SWAP, AIRDROP, REVERTED]);
+    assert_eq!(txs[0].timestamp, 1_704_067_200);
+    assert!(txs[1].raw_metadata["trace"].is_null());
+
+    // Swap: 0.5 ETH out, 0.1 refunded by an internal call, 1000 tokens in, gas split into base and tip
+    let swap = ethereum_parser::parse_ethereum_transaction(&txs[0]).unwrap();
+    assert_eq!(
+        amounts(&swap, |t| matches!(t, EntryType::Trade)),
+        vec![("ETH".to_string(), dec("-0.4")), (TOKEN.to_string(), dec("1000"))]
+    );
+    assert_eq!(
+        amounts(&swap, |t| matches!(t, EntryType::Fee)),
+        vec![("ETH".to_string(), dec("-0.000525")), ("ETH".to_string(), dec("-0.000105"))]
+    );
+
+    // Someone else paid gas to send the wallet tokens
+    let airdrop = ethereum_parser::parse_ethereum_transaction(&txs[1]).unwrap();
+    assert_eq!(amounts(&airdrop, |t| matches!(t, EntryType::Transfer)), vec![(TOKEN.to_string(), dec("5"))]);
+    assert!(amounts(&airdrop, |t| matches!(t, EntryType::Fee)).is_empty());
+
+    // A reverted transaction only costs its gas (all base fee here)
+    let reverted = ethereum_parser::parse_ethereum_transaction(&txs[2]).unwrap();
+    assert_eq!(reverted.len(), 1);
+    assert_eq!(amounts(&reverted, |t| matches!(t, EntryType::Fee)), vec![("ETH".to_string(), dec("-0.000525"))]);
+}
+
+#[tokio::test]
+async fn test_trace_filter_finds_eth_paid_by_internal_calls() {
+    let adapter = EthereumAdapter::new(&spawn_traced_node(), Some(100));
+    let txs = adapter.fetch_history(WALLET, 10).await.unwrap();
+    let hashes: Vec<&str> = txs.iter().map(|t| t.tx_hash.as_str()).collect();
+    assert_eq!(hashes, vec![SWAP, AIRDROP, REVERTED, INTERNAL]);
+
+    let internal = ethereum_parser::parse_ethereum_transaction(&txs[3]).unwrap();
+    assert_eq!(amounts(&internal, |t| matches!(t, EntryType::Transfer)), vec![("ETH".to_string(), dec("0.2"))]);
+    assert!(amounts(&internal, |t| matches!(t, EntryType::Fee)).is_empty());
+}
+
+#[tokio::test]
+async fn test_scan_window_is_bounded() {
+    let node = spawn_traced_node();
+    let first = EthereumAdapter::new(&node, Some(100)).with_scan_blocks(1).fetch_history(WALLET, 10).await.unwrap();
+    assert_eq!(first.iter().map(|t| t.tx_hash.as_str()).collect::<Vec<_>>(), vec![SWAP]);
+
+    // Without a start block the window ends at the latest block
+    let latest = EthereumAdapter::new(&node, None).with_scan_blocks(1).fetch_history(WALLET, 10).await.unwrap();
+    assert_eq!(latest.iter().map(|t| t.tx_hash.as_str()).collect::<Vec<_>>(), vec![AIRDROP, REVERTED, INTERNAL]);
+
+    let err = EthereumAdapter::new(&node, None).with_scan_blocks(MAX_SCAN_BLOCKS + 1).fetch_history(WALLET, 10).await.unwrap_err();
+    assert!(err.to_string().contains("Scan window"), "{}", err);
+    let err = EthereumAdapter::new(&node, Some(200)).fetch_history(WALLET, 10).await.unwrap_err();
+    assert!(err.to_string().contains("past the latest block"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_rpc_for_another_network_is_rejected() {
+    let networks = evm::load_networks(None).unwrap();
+    let base = evm::find_network(&networks, "base").unwrap().clone();
+    let err = EthereumAdapter::for_network(base, &spawn_node(), Some(100)).fetch_history(WALLET, 10).await.unwrap_err();
+    assert!(err.to_string().contains("chain id"), "{}", err);
+}
+
+#[test]
+fn test_configured_network_pays_l1_data_fee_in_its_native_asset() {
+    let path = std::env::temp_dir().join(format!("evm_networks_{}.json", Uuid::new_v4()));
+    std::fs::write(&path, r#"[{ "name": "opchain", "chain_id": 999, "native_asset": "OPC", "explorer_url": "https://explorer.opchain.example/" }]"#).unwrap();
+    let networks = evm::load_networks(Some(&path)).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let network = evm::find_network(&networks, "opchain").unwrap();
+    assert!(matches!(network.chain(), Chain::Evm(ref name) if name == "opchain"));
+    assert_eq!(network.tx_url("0xabc").unwrap(), "https://explorer.opchain.example/tx/0xabc");
+
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp: 1_704_067_200,
+        tx_hash: SWAP.to_string(),
+        chain: network.chain(),
+        raw_metadata: json!({
+            "network": { "name": "opchain", "chain_id": 999, "native_asset": "OPC", "native_decimals": 18 },
+            "transaction": { "from": WALLET, "to": OTHER, "value": "0xde0b6b3a7640000" },
+            // 21000 gas at 2 gwei (all base fee) plus 0.0001 OPC for posting to L1
+            "receipt": { "status": "0x1", "gasUsed": "0x5208", "effectiveGasPrice": "0x77359400", "l1Fee": "0x5af3107a4000", "logs": [] },
+            "block": { "baseFeePerGas": "0x77359400" },
+            "trace": null,
+            "token_decimals": {},
+        }),
+    };
+    let entries = ethereum_parser::parse_ethereum_transaction(&tx).unwrap();
+    assert_eq!(amounts(&entries, |t| matches!(t, EntryType::Transfer)), vec![("OPC".to_string(), dec("-1"))]);
+    assert_eq!(
+        amounts(&entries, |t| matches!(t, EntryType::Fee)),
+        vec![("OPC".to_string(), dec("-0.000042")), ("OPC".to_string(), dec("-0.0001"))]
+    );
+}
+
+#[test]
+fn test_malformed_log_topics_are_skipped() {
+    let log = |from: &str| json!({
+        "address": TOKEN, "blockNumber": "0x64", "transactionHash": SWAP,
+        "topics": [ERC20_TRANSFER_TOPIC, from, topic(WALLET)], "data": format!("0x{:064x}", 1_000_000)
+    });
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp: 1_704_067_200,
+        tx_hash: SWAP.to_string(),
+        chain: Chain::Ethereum,
+        raw_metadata: json!({
+            "transaction": { "from": OTHER, "to": TOKEN, "value": "0x0" },
+            // A short topic and one whose last 40 bytes start inside a multi-byte character
+            "receipt": { "status": "0x1", "gasUsed": "0x5208", "effectiveGasPrice": "0x77359400",
+                "logs": [log("0x12"), log(&format!("0x{}é{}", "0".repeat(23), &WALLET[3..]))] },
+            "block": { "baseFeePerGas": "0x77359400" },
+            "trace": null,
+            "token_decimals": { TOKEN: 6 },
+        }),
+    };
+    let entries = ethereum_parser::parse_ethereum_transaction(&tx).unwrap();
+    assert_eq!(amounts(&entries, |t| matches!(t, EntryType::Transfer)), vec![(TOKEN.to_string(), dec("2"))]);
+}
@@ -0,0 +1,60 @@
+use spectraplex_adapters::price_import;
+use spectraplex_core::fx::{self, FxTable};
+use spectraplex_core::lots;
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use bigdecimal::BigDecimal;
+use serde_json::json;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn valued_entry(timestamp: i64, amount: i64, value: &str, currency: &str) -> LedgerEntry {
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp,
+        tx_hash: "sig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    };
+    let mut entry = LedgerEntry::new(&tx, "SOL".to_string(), BigDecimal::from(amount), EntryType::Trade);
+    entry.fiat_value = Some(BigDecimal::from_str(value).unwrap());
+    entry.fiat_currency = Some(currency.to_string());
+    entry
+}
+
+#[tokio::test]
+async fn test_convert_uses_latest_rate_and_inverse_pairs() {
+    let csv = "Date,Rate\n2024-01-01,0.90\n2024-01-03,0.92\n";
+    let table = FxTable::new(price_import::parse_fx_csv(csv.as_bytes(), "usd", "eur", "ecb").unwrap());
+
+    let mut entries = vec![
+        valued_entry(1_704_153_600, 1, "100", "USD"), // 2024-01-02, uses the 01-01 rate
+        valued_entry(1_704_326_400, 1, "92", "EUR"),  // Already in the target currency
+    ];
+    fx::convert_entries(&table, &mut entries, "eur").await.unwrap();
+    assert_eq!(entries[0].fiat_value, Some(BigDecimal::from(90)));
+    assert_eq!(entries[0].fiat_currency.as_deref(), Some("EUR"));
+    assert_eq!(entries[1].fiat_value, Some(BigDecimal::from(92)));
+
+    // Only USD/EUR is stored, so EUR -> USD goes through the inverse
+    fx::convert_entries(&table, &mut entries, "USD").await.unwrap();
+    assert_eq!(entries[0].fiat_value, Some(BigDecimal::from(100)));
+    assert_eq!(entries[1].fiat_value, Some(BigDecimal::from(100)));
+}
+
+#[tokio::test]
+async fn test_missing_rate_is_an_error() {
+    let table = FxTable::default();
+    let mut entries = vec![valued_entry(1_704_153_600, 1, "100", "USD")];
+    assert!(fx::convert_entries(&table, &mut entries, "JPY").await.is_err());
+}
+
+#[test]
+fn test_cost_basis_rejects_mixed_currencies() {
+    let entries = vec![
+        valued_entry(1_704_153_600, 1, "100", "USD"),
+        valued_entry(1_704_326_400, -1, "90", "GBP"),
+    ];
+    assert!(lots::match_fifo(&entries).is_err());
+}
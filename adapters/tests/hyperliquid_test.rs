@@ -0,0 +1,122 @@
+use spectraplex_adapters::hyperliquid::HyperliquidAdapter;
+use spectraplex_adapters::hyperliquid_parser;
+use spectraplex_core::models::{ChainIngestor, EntryType, LedgerEntry};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::str::FromStr;
+
+const WALLET: &str = "0x8c967e73e7b15087c42a10d344cff4c96d877f1d";
+
+fn info_response(request: &Value) -> Value {
+    match request["type"].as_str().unwrap_or_default() {
+        "userFills" => json!([
+            {
+                "coin": "ETH", "px": "2500.0", "sz": "1.0", "side": "A", "time": 1704153600000u64, "startPosition": "1.0",
+                "dir": "Close Long", "closedPnl": "100.0", "hash": "0xfill", "oid": 1, "crossed": true, "fee": "1.25",
+                "tid": 11, "feeToken": "USDC"
+            },
+            {
+                "coin": "BTC", "px": "40000.0", "sz": "0.1", "side": "A", "time": 1704240000000u64, "startPosition": "0.1",
+                "dir": "Close Long", "closedPnl": "-250.0", "hash": "0xliq", "oid": 2, "crossed": true, "fee": "0",
+                "tid": 12, "feeToken": "USDC",
+                "liquidation": { "liquidatedUser": WALLET, "markPx": "40000.0", "method": "market" }
+            },
+            {
+                "coin": "PURR/USDC", "px": "0.2", "sz": "100.0", "side": "B", "time": 1704067300000u64, "startPosition": "0.0",
+                "dir": "Buy", "closedPnl": "0.0", "hash": "0xspot", "oid": 3, "crossed": false, "fee": "-0.01",
+                "tid": 13, "feeToken": "USDC"
+            }
+        ]),
+        "userFunding" => json!([
+            { "time": 1704110400000u64, "hash": "0x0", "delta": { "type": "funding", "coin": "ETH", "usdc": "-0.5", "szi": "1.0", "fundingRate": "0.0001" } }
+        ]),
+        "userNonFundingLedgerUpdates" => json!([
+            { "time": 1704067200000u64, "hash": "0xdep", "delta": { "type": "deposit", "usdc": "1000.0" } },
+            { "time": 1704326400000u64, "hash": "0xwd", "delta": { "type": "withdraw", "usdc": "500.0", "nonce": 1, "fee": "1.0" } },
+            { "time": 1704240000000u64, "hash": "0xliqevent", "delta": { "type": "liquidation", "accountValue": "10.0", "leverageType": "Cross", "liquidatedPositions": [] } }
+        ]),
+        _ => Value::Null,
+    }
+}
+
+// Local stand-in for the /info endpoint, one request per connection
+fn spawn_info_api() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut content_length = 0;
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim_end().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.trim_end().split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            let _ = reader.read_exact(&mut body);
+            let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            let response = info_response(&request).to_string();
+            let _ = write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                response.len(),
+                response
+            );
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+#[tokio::test]
+async fn test_fetch_and_normalize_hyperliquid_history() {
+    let adapter = HyperliquidAdapter::new(&spawn_info_api());
+    let txs = adapter.fetch_history(WALLET, 100).await.unwrap();
+    assert_eq!(txs.len(), 7);
+    assert!(txs.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+    assert_eq!(txs[0].tx_hash, "0xdep");
+
+    let entries: Vec<LedgerEntry> = txs
+        .iter()
+        .flat_map(|tx| hyperliquid_parser::parse_hyperliquid_transaction(tx).unwrap())
+        .collect();
+    let total = |kind: fn(&EntryType) -> bool, asset: &str| {
+        entries
+            .iter()
+            .filter(|e| kind(&e.entry_type) && e.asset_symbol == asset)
+            .fold(BigDecimal::from(0), |acc, e| acc + &e.amount)
+    };
+
+    // Realized PnL from the close and the liquidation, plus the spot buy's USDC leg
+    assert_eq!(total(|t| matches!(t, EntryType::Trade), "USDC"), dec("-170"));
+    assert_eq!(total(|t| matches!(t, EntryType::Trade), "PURR/USDC"), dec("100"));
+    // Taker fee, maker rebate and the bridge withdrawal fee
+    assert_eq!(total(|t| matches!(t, EntryType::Fee), "USDC"), dec("-2.24"));
+    assert_eq!(total(|t| matches!(t, EntryType::Income), "USDC"), dec("-0.5"));
+    assert_eq!(total(|t| matches!(t, EntryType::Transfer), "USDC"), dec("501"));
+
+    let liquidation = entries.iter().find(|e| e.amount == dec("-250")).unwrap();
+    assert_eq!(liquidation.source_program.as_deref(), Some("perp:BTC"));
+}
+
+#[tokio::test]
+async fn test_fetch_history_keeps_most_recent_events() {
+    let adapter = HyperliquidAdapter::new(&spawn_info_api());
+    let txs = adapter.fetch_history(WALLET, 2).await.unwrap();
+    assert_eq!(txs.len(), 2);
+    assert_eq!(txs[1].tx_hash, "0xwd");
+}
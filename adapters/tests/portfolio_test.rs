@@ -0,0 +1,148 @@
+use spectraplex_adapters::price_import::{self, OhlcImport};
+use spectraplex_core::balances::{self, DailyChange, Granularity};
+use spectraplex_core::fx::{FxRate, FxTable};
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, LedgerEntry, Transaction};
+use spectraplex_core::portfolio;
+use spectraplex_core::pricing::PriceHistory;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde_json::json;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn sol_history() -> PriceHistory {
+    let import = OhlcImport {
+        asset_symbol: "SOL".to_string(),
+        fiat_currency: "USD".to_string(),
+        resolution_secs: 86_400,
+        source: "test-feed".to_string(),
+    };
+    PriceHistory::new(price_import::parse_ohlc_json(&json!([[1704067200, 150, 150, 150, 150]]), &import).unwrap())
+}
+
+fn usd_gbp() -> FxTable {
+    FxTable::new(vec![FxRate {
+        base_currency: "USD".to_string(),
+        quote_currency: "GBP".to_string(),
+        rate_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        rate: BigDecimal::from_str("0.8").unwrap(),
+        source: "test".to_string(),
+    }])
+}
+
+fn entry(timestamp: i64, asset: &str, amount: i64, value: Option<i64>) -> LedgerEntry {
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp,
+        tx_hash: "sig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    };
+    let mut entry = LedgerEntry::new(&tx, asset.to_string(), BigDecimal::from(amount), EntryType::Transfer);
+    entry.fiat_value = value.map(BigDecimal::from);
+    entry.fiat_currency = value.map(|_| "USD".to_string());
+    entry
+}
+
+#[tokio::test]
+async fn test_snapshot_values_holdings_against_remaining_basis() {
+    let history = sol_history();
+
+    let entries = vec![
+        entry(1_700_000_000, "SOL", 10, Some(1000)),
+        entry(1_701_000_000, "SOL", -4, Some(600)),
+        entry(1_702_000_000, "UnpricedMint", 50, None),
+        entry(1_800_000_000, "SOL", 100, Some(1)), // After the snapshot time
+    ];
+
+    let snapshot = portfolio::snapshot(&history, &FxTable::default(), &entries, 1_704_100_000, "USD").await.unwrap();
+    assert_eq!(snapshot.holdings.len(), 2);
+
+    let sol = &snapshot.holdings[0];
+    assert_eq!(sol.asset_symbol, "SOL");
+    assert_eq!(sol.quantity, BigDecimal::from(6));
+    assert_eq!(sol.market_value, Some(BigDecimal::from(900)));
+    assert_eq!(sol.cost_basis, Some(BigDecimal::from(600)));
+    assert_eq!(sol.unrealized_gain, Some(BigDecimal::from(300)));
+
+    let unpriced = &snapshot.holdings[1];
+    assert_eq!(unpriced.quantity, BigDecimal::from(50));
+    assert!(unpriced.market_value.is_none() && unpriced.cost_basis.is_none());
+
+    assert_eq!(snapshot.total_unrealized_gain, BigDecimal::from(300));
+}
+
+#[tokio::test]
+async fn test_snapshot_carries_basis_into_pool_shares() {
+    let mut entries = vec![
+        entry(1_700_000_000, "SOL", 10, Some(1000)),
+        entry(1_701_000_000, "SOL", -4, None),
+        entry(1_701_000_000, "LP", 1, None),
+        entry(1_702_000_000, "UnpricedMint", 50, None),
+    ];
+    entries[1].entry_type = EntryType::Liquidity;
+    entries[2].entry_type = EntryType::LpToken;
+    entries[2].transaction_id = entries[1].transaction_id;
+
+    let snapshot = portfolio::snapshot(&sol_history(), &FxTable::default(), &entries, 1_704_100_000, "USD").await.unwrap();
+    let basis = |asset: &str| snapshot.holdings.iter().find(|h| h.asset_symbol == asset).unwrap().cost_basis.clone();
+    assert_eq!(basis("LP"), Some(BigDecimal::from(400)));
+    assert_eq!(basis("SOL"), Some(BigDecimal::from(600)));
+    assert_eq!(basis("UnpricedMint"), None);
+}
+
+#[tokio::test]
+async fn test_snapshot_carries_basis_across_a_linked_bridge_transfer() {
+    let mut entries = vec![
+        entry(1_700_000_000, "SOL_USDC", 100, Some(100)),
+        entry(1_701_000_000, "SOL_USDC", -100, None),
+        entry(1_701_000_000, "ETH_USDC", 99, None),
+    ];
+    let link = |other| Some(BridgeTransfer {
+        protocol: "wormhole".to_string(),
+        destination_chain: Some("ethereum".to_string()),
+        destination_address: None,
+        transfer_id: None,
+        linked_entry_id: Some(other),
+    });
+    let (out, inflow) = (entries[1].id, entries[2].id);
+    entries[1].entry_type = EntryType::Bridge;
+    entries[1].bridge = link(inflow);
+    entries[2].entry_type = EntryType::Bridge;
+    entries[2].bridge = link(out);
+
+    let snapshot = portfolio::snapshot(&sol_history(), &FxTable::default(), &entries, 1_704_100_000, "USD").await.unwrap();
+    assert_eq!(snapshot.holdings.len(), 1);
+    assert_eq!(snapshot.holdings[0].asset_symbol, "ETH_USDC");
+    assert_eq!(snapshot.holdings[0].cost_basis, Some(BigDecimal::from(100)));
+}
+
+#[tokio::test]
+async fn test_snapshot_converts_usd_prices_into_the_reporting_currency() {
+    let history = sol_history();
+    let mut entries = vec![entry(1_700_000_000, "SOL", 10, Some(800))];
+    entries[0].fiat_currency = Some("GBP".to_string());
+
+    let snapshot = portfolio::snapshot(&history, &usd_gbp(), &entries, 1_704_100_000, "GBP").await.unwrap();
+    let sol = &snapshot.holdings[0];
+    assert_eq!(sol.price, Some(BigDecimal::from(120)));
+    assert_eq!(sol.market_value, Some(BigDecimal::from(1200)));
+    assert_eq!(sol.unrealized_gain, Some(BigDecimal::from(400)));
+
+    // No rate to convert with: unpriced rather than valued in the wrong currency
+    let snapshot = portfolio::snapshot(&history, &FxTable::default(), &entries, 1_704_100_000, "GBP").await.unwrap();
+    assert!(snapshot.holdings[0].price.is_none());
+}
+
+#[tokio::test]
+async fn test_value_series_converts_usd_prices_into_the_reporting_currency() {
+    let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let changes = vec![DailyChange { asset_symbol: "SOL".to_string(), day, net_change: BigDecimal::from(2) }];
+    let mut points = balances::time_series(&changes, day, day, Granularity::Day);
+
+    balances::value_series(&sol_history(), &usd_gbp(), &mut points, "GBP").await.unwrap();
+    assert_eq!(points[0].balances[0].fiat_value, Some(BigDecimal::from(240)));
+    assert_eq!(points[0].total_fiat_value, BigDecimal::from(240));
+}
@@ -0,0 +1,131 @@
+use spectraplex_adapters::price_import::{self, OhlcImport};
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use spectraplex_core::pricing::{self, PriceHistory, PriceSource};
+use bigdecimal::BigDecimal;
+use serde_json::json;
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn import(resolution_secs: i64) -> OhlcImport {
+    OhlcImport {
+        asset_symbol: "SOL".to_string(),
+        fiat_currency: "usd".to_string(),
+        resolution_secs,
+        source: "test-feed".to_string(),
+    }
+}
+
+#[test]
+fn test_parse_ohlc_csv_with_mixed_timestamps() {
+    let csv = "Date,Open,High,Low,Close,Volume\n\
+               2024-01-01,100,110,95,105,1000\n\
+               1704153600000,105,120,100,118.5,2000\n";
+
+    let candles = price_import::parse_ohlc_csv(csv.as_bytes(), &import(86_400)).expect("CSV parse failed");
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].open_time, 1_704_067_200);
+    assert_eq!(candles[1].open_time, 1_704_153_600); // Milliseconds normalised
+    assert_eq!(candles[1].close, BigDecimal::from_str("118.5").unwrap());
+    assert_eq!(candles[0].fiat_currency, "USD");
+}
+
+#[test]
+fn test_parse_ohlc_json_objects_and_rows() {
+    let value = json!([
+        { "timestamp": 1704067200, "open": "100", "high": 110, "low": 95, "close": 105 },
+        [1704070800, "105", "106", "104", "105.5", "123"]
+    ]);
+
+    let candles = price_import::parse_ohlc_json(&value, &import(3_600)).expect("JSON parse failed");
+
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].high, BigDecimal::from(110));
+    assert_eq!(candles[1].close, BigDecimal::from_str("105.5").unwrap());
+}
+
+#[test]
+fn test_parse_resolution() {
+    assert_eq!(pricing::parse_resolution("4h").unwrap(), 14_400);
+    assert_eq!(pricing::parse_resolution(" 1d ").unwrap(), 86_400);
+    assert_eq!(pricing::format_resolution(pricing::parse_resolution("90m").unwrap()), "90m");
+    // Units that aren't ASCII are rejected, not split mid-character
+    assert!(pricing::parse_resolution("5µ").is_err());
+    assert!(pricing::parse_resolution("µ").is_err());
+    assert!(pricing::parse_resolution("").is_err());
+    assert!(pricing::parse_resolution("0d").is_err());
+    assert!(pricing::parse_resolution("9999999999999999w").is_err());
+}
+
+#[tokio::test]
+async fn test_price_pass_prefers_finest_candle() {
+    let mut history = PriceHistory::default();
+    history.extend(price_import::parse_ohlc_json(&json!([[1704067200, 100, 110, 90, 100]]), &import(86_400)).unwrap());
+    history.extend(price_import::parse_ohlc_json(&json!([[1704070800, 101, 103, 101, 102]]), &import(3_600)).unwrap());
+
+    let point = history.price_at("SOL", 1_704_072_000, "USD").await.unwrap().expect("no price");
+    assert_eq!(point.resolution, "1h");
+    assert_eq!(point.price, BigDecimal::from(102));
+
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp: 1_704_100_000,
+        tx_hash: "sig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    };
+    let mut entries = vec![
+        LedgerEntry::new(&tx, "SOL".to_string(), BigDecimal::from_str("-2.5").unwrap(), EntryType::Transfer),
+        LedgerEntry::new(&tx, "UnknownMint".to_string(), BigDecimal::from(1), EntryType::Transfer),
+    ];
+
+    let summary = pricing::price_entries(&history, &mut entries, "USD", None).await.unwrap();
+    assert_eq!(summary.priced, 1);
+    assert_eq!(summary.unpriced, 1);
+    assert_eq!(entries[0].fiat_value, Some(BigDecimal::from(250)));
+    assert_eq!(entries[0].price_source.as_deref(), Some("test-feed"));
+    assert_eq!(entries[0].price_resolution.as_deref(), Some("1d"));
+    assert!(entries[1].fiat_value.is_none());
+}
+
+#[tokio::test]
+async fn test_dex_implied_price_propagates_within_window() {
+    let mut history = PriceHistory::default();
+    history.extend(price_import::parse_ohlc_json(&json!([[1704067200, 100, 100, 100, 100]]), &import(86_400)).unwrap());
+
+    let swap = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp: 1_704_070_000,
+        tx_hash: "swap".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    };
+    let later = Transaction { id: Uuid::new_v4(), timestamp: 1_704_073_600, ..swap.clone() };
+    let much_later = Transaction { id: Uuid::new_v4(), timestamp: 1_704_200_000, ..swap.clone() };
+
+    // 2 SOL bought 400 MEME, so MEME was worth 0.5 USD
+    let mut entries = vec![
+        LedgerEntry::new(&swap, "SOL".to_string(), BigDecimal::from(-2), EntryType::Trade),
+        LedgerEntry::new(&swap, "MemeMint".to_string(), BigDecimal::from(400), EntryType::Trade),
+        LedgerEntry::new(&later, "MemeMint".to_string(), BigDecimal::from(-100), EntryType::Transfer),
+        LedgerEntry::new(&much_later, "MemeMint".to_string(), BigDecimal::from(-100), EntryType::Transfer),
+    ];
+
+    let observations = pricing::implied_prices(&history, &entries, "USD", &["SOL".to_string()]).await.unwrap();
+    assert_eq!(observations.len(), 1);
+    assert_eq!(observations[0].asset_symbol, "MemeMint");
+    assert_eq!(observations[0].close, BigDecimal::from_str("0.5").unwrap());
+    history.extend(observations);
+
+    let summary = pricing::price_entries(&history, &mut entries, "USD", Some(7_200)).await.unwrap();
+    assert_eq!(summary.priced, 3);
+    assert_eq!(entries[1].fiat_value, Some(BigDecimal::from(200)));
+    assert_eq!(entries[1].price_source.as_deref(), Some(pricing::DEX_IMPLIED_SOURCE));
+    assert_eq!(entries[2].fiat_value, Some(BigDecimal::from(50)));
+    assert_eq!(entries[2].price_resolution.as_deref(), Some("~1h"));
+    assert!(entries[3].fiat_value.is_none());
+}
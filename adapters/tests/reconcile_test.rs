@@ -0,0 +1,153 @@
+use spectraplex_adapters::solana::SolanaAdapter;
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use spectraplex_core::reconcile::{self, BalanceSource};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "5M5NQf9mi3cQ5sbqn4ddwU5nx4GRvMHmsbLFiW66SVMd";
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const BONK_MINT: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+fn token_account(pubkey: &str, mint: &str, amount: &str) -> Value {
+    json!({
+        "pubkey": pubkey,
+        "account": {
+            "lamports": 2039280,
+            "owner": TOKEN_PROGRAM_ID,
+            "executable": false,
+            "rentEpoch": 0,
+            "space": 165,
+            "data": {
+                "program": "spl-token",
+                "parsed": {
+                    "type": "account",
+                    "info": {
+                        "mint": mint,
+                        "owner": WALLET,
+                        "state": "initialized",
+                        "isNative": false,
+                        "tokenAmount": { "amount": "0", "decimals": 6, "uiAmount": null, "uiAmountString": amount }
+                    }
+                },
+                "space": 165
+            }
+        }
+    })
+}
+
+fn rpc_result(request: &Value) -> Value {
+    let context = json!({ "slot": 1 });
+    match request["method"].as_str().unwrap_or_default() {
+        "getBalance" => json!({ "context": context, "value": 2_500_000_000u64 }),
+        "getTokenAccountsByOwner" => {
+            let program = request["params"][1]["programId"].as_str().unwrap_or_default();
+            let accounts = if program == TOKEN_PROGRAM_ID {
+                vec![
+                    token_account("7UX2i7SucgLMQcfZ75s3VXmZZY4YRUyJN9X1RgfMoDUi", BONK_MINT, "1000"),
+                    token_account("9wFFyRfZBsuAha4YcuxcXLKwMxJR43S7fPfQLusDBzvT", BONK_MINT, "500"),
+                ]
+            } else {
+                vec![]
+            };
+            json!({ "context": context, "value": accounts })
+        }
+        "getVersion" => json!({ "solana-core": "2.0.0", "feature-set": 0 }),
+        _ => Value::Null,
+    }
+}
+
+// Minimal HTTP/1.1 JSON-RPC stand-in for a validator. Keeps connections alive like the real client expects.
+fn spawn_mock_rpc() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut writer = stream;
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let header = line.trim_end();
+                        if header.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap_or(0);
+                            }
+                        }
+                    }
+
+                    let mut body = vec![0; content_length];
+                    if reader.read_exact(&mut body).is_err() {
+                        return;
+                    }
+                    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                    let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": rpc_result(&request) }).to_string();
+                    let http = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        response.len(),
+                        response
+                    );
+                    if writer.write_all(http.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn entry(asset: &str, amount: &str) -> LedgerEntry {
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp: 1_700_000_000,
+        tx_hash: "sig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    };
+    LedgerEntry::new(&tx, asset.to_string(), BigDecimal::from_str(amount).unwrap(), EntryType::Transfer)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_reconcile_against_mock_rpc() {
+    let adapter = SolanaAdapter::new(&spawn_mock_rpc());
+    let onchain = adapter.current_balances(WALLET).await.unwrap();
+
+    let entries = vec![
+        entry("SOL", "3"),
+        entry("SOL", "-0.5"),
+        entry(BONK_MINT, "1200"), // 300 short of what the two token accounts hold
+        entry("ClosedMint", "10"),
+        entry("ClosedMint", "-10"),
+    ];
+    let report = reconcile::reconcile(WALLET, &entries, &onchain, &reconcile::default_tolerance());
+
+    assert_eq!(report.assets.len(), 2);
+    assert_eq!(report.discrepancies, 1);
+
+    let bonk = &report.assets[0];
+    assert_eq!(bonk.asset_symbol, BONK_MINT);
+    assert_eq!(bonk.onchain_balance, BigDecimal::from(1500));
+    assert_eq!(bonk.difference, BigDecimal::from(300));
+    assert!(!bonk.matches);
+
+    let sol = &report.assets[1];
+    assert_eq!(sol.asset_symbol, "SOL");
+    assert_eq!(sol.onchain_balance, BigDecimal::from_str("2.5").unwrap());
+    assert!(sol.matches);
+}
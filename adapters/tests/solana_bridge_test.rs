@@ -0,0 +1,146 @@
+use spectraplex_adapters::solana_bridge::{DLN_SOURCE_PROGRAM_ID, WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID};
+use spectraplex_adapters::solana_parser::{self, USDC_MINT};
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use solana_sdk::hash::hashv;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "BridgeWa11et111111111111111111111111111111";
+const USDC_ACCOUNT: &str = "UsdcAccount11111111111111111111111111111111";
+const EVM_RECIPIENT: [u8; 20] = [0xab; 20];
+
+fn ix(program: &str, accounts: &[&str], data: Vec<u8>) -> Value {
+    json!({ "programId": program, "accounts": accounts, "data": bs58::encode(data).into_string(), "stackHeight": null })
+}
+
+fn usdc_balance(ui_amount: f64) -> Value {
+    json!([{
+        "accountIndex": 1,
+        "mint": USDC_MINT,
+        "owner": WALLET,
+        "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+        "uiTokenAmount": {
+            "amount": ((ui_amount * 1e6) as u64).to_string(),
+            "decimals": 6,
+            "uiAmount": ui_amount,
+            "uiAmountString": ui_amount.to_string()
+        }
+    }])
+}
+
+// The wallet signs and pays; its USDC account is the second key
+fn bridge_tx(timestamp: i64, instruction: Value, logs: &[&str], usdc: (f64, f64)) -> Transaction {
+    let raw = json!({
+        "slot": 400000,
+        "transaction": {
+            "signatures": [format!("bridge{}", timestamp)],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": WALLET, "signer": true, "writable": true },
+                    { "pubkey": USDC_ACCOUNT, "signer": false, "writable": true }
+                ],
+                "instructions": [instruction],
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [1_000_000_000, 2_039_280],
+            "postBalances": [999_995_000, 2_039_280],
+            "innerInstructions": [],
+            "logMessages": logs,
+            "preTokenBalances": usdc_balance(usdc.0),
+            "postTokenBalances": usdc_balance(usdc.1),
+            "rewards": []
+        },
+        "blockTime": timestamp
+    });
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp,
+        tx_hash: format!("bridge{}", timestamp),
+        chain: Chain::Solana,
+        raw_metadata: raw,
+    }
+}
+
+fn bridged(entries: &[LedgerEntry]) -> Vec<&LedgerEntry> {
+    entries.iter().filter(|e| matches!(e.entry_type, EntryType::Bridge)).collect()
+}
+
+#[test]
+fn test_wormhole_transfer_records_destination_and_sequence() {
+    // transfer_native: nonce, amount, relayer fee, recipient left-padded to 32 bytes, Wormhole chain 2 (Ethereum)
+    let mut data = vec![5];
+    data.extend(7u32.to_le_bytes());
+    data.extend(100_000_000u64.to_le_bytes());
+    data.extend(0u64.to_le_bytes());
+    data.extend([0u8; 12]);
+    data.extend(EVM_RECIPIENT);
+    data.extend(2u16.to_le_bytes());
+    let tx = bridge_tx(
+        1_707_523_200,
+        ix(WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, &[WALLET, USDC_ACCOUNT, "Mint", "Custody"], data),
+        &["Program worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth invoke [2]", "Program log: Sequence: 4242"],
+        (150.0, 50.0),
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+    let sent = bridged(&entries);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].asset_symbol, USDC_MINT);
+    assert_eq!(sent[0].amount, BigDecimal::from_str("-100").unwrap());
+    assert_eq!(sent[0].source_program.as_deref(), Some(WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID));
+
+    let transfer = sent[0].bridge.as_ref().unwrap();
+    assert_eq!(transfer.protocol, "wormhole");
+    assert_eq!(transfer.destination_chain.as_deref(), Some("ethereum"));
+    assert_eq!(transfer.destination_address.as_deref(), Some(format!("0x{}", "ab".repeat(20)).as_str()));
+    assert_eq!(transfer.transfer_id.as_deref(), Some("4242"));
+    // The network fee isn't part of what was bridged
+    assert!(entries.iter().any(|e| e.asset_symbol == "SOL" && matches!(e.entry_type, EntryType::Fee)));
+}
+
+#[test]
+fn test_debridge_order_records_destination_and_receiver() {
+    // create_order: give amount, take chain id (u256, big-endian), take token, take amount, receiver
+    let mut data = hashv(&[b"global:create_order"]).to_bytes()[..8].to_vec();
+    data.extend(100_000_000u64.to_le_bytes());
+    let mut chain_id = [0u8; 32];
+    chain_id[24..].copy_from_slice(&42161u64.to_be_bytes());
+    data.extend(chain_id);
+    data.extend(20u32.to_le_bytes());
+    data.extend([0xaf; 20]);
+    data.extend([0u8; 32]);
+    data.extend(20u32.to_le_bytes());
+    data.extend(EVM_RECIPIENT);
+    let tx = bridge_tx(1_707_523_200, ix(DLN_SOURCE_PROGRAM_ID, &[WALLET, USDC_ACCOUNT], data), &[], (150.0, 50.0));
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+    let transfer = bridged(&entries)[0].bridge.clone().unwrap();
+    assert_eq!(transfer.protocol, "debridge");
+    assert_eq!(transfer.destination_chain.as_deref(), Some("arbitrum"));
+    assert_eq!(transfer.destination_address, Some(format!("0x{}", "ab".repeat(20))));
+    assert_eq!(transfer.transfer_id, None);
+}
+
+#[test]
+fn test_wormhole_redemption_is_a_bridge_inflow() {
+    // complete_wrapped, redeemed by the wallet itself; the VAA isn't decoded
+    let tx = bridge_tx(1_707_530_400, ix(WORMHOLE_TOKEN_BRIDGE_PROGRAM_ID, &[WALLET, "Vaa", USDC_ACCOUNT], vec![3]), &[], (0.0, 99.5));
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+    let received = bridged(&entries);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].amount, BigDecimal::from_str("99.5").unwrap());
+    let transfer = received[0].bridge.as_ref().unwrap();
+    assert_eq!(transfer.protocol, "wormhole");
+    assert_eq!(transfer.destination_chain, None);
+    assert_eq!(transfer.linked_entry_id, None);
+}
@@ -0,0 +1,198 @@
+use spectraplex_adapters::solana_lending::{KAMINO_LEND_PROGRAM_ID, MARGINFI_PROGRAM_ID, SOLEND_PROGRAM_ID};
+use spectraplex_adapters::solana_parser::{self, USDC_MINT};
+use spectraplex_core::lending;
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use solana_sdk::hash::hashv;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "LenderWa11et1111111111111111111111111111111";
+const USDC_ACCOUNT: &str = "UsdcAccount11111111111111111111111111111111";
+const MARGIN_ACCOUNT: &str = "MarginAccount111111111111111111111111111111";
+const OBLIGATION: &str = "Ob1igation111111111111111111111111111111111";
+
+fn anchor_data(name: &str, amount: u64) -> String {
+    let mut data = hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8].to_vec();
+    data.extend(amount.to_le_bytes());
+    bs58::encode(data).into_string()
+}
+
+fn tag_data(tag: u8, amount: u64) -> String {
+    let mut data = vec![tag];
+    data.extend(amount.to_le_bytes());
+    bs58::encode(data).into_string()
+}
+
+fn ix(program: &str, accounts: &[&str], data: String) -> Value {
+    json!({ "programId": program, "accounts": accounts, "data": data, "stackHeight": null })
+}
+
+fn usdc_balance(ui_amount: f64) -> Value {
+    json!([{
+        "accountIndex": 1,
+        "mint": USDC_MINT,
+        "owner": WALLET,
+        "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+        "uiTokenAmount": {
+            "amount": ((ui_amount * 1e6) as u64).to_string(),
+            "decimals": 6,
+            "uiAmount": ui_amount,
+            "uiAmountString": ui_amount.to_string()
+        }
+    }])
+}
+
+// The wallet signs and pays; its USDC account is the second key
+fn lending_tx(timestamp: i64, instructions: Value, lamports: (u64, u64), usdc: (f64, f64)) -> Transaction {
+    let raw = json!({
+        "slot": 200000,
+        "transaction": {
+            "signatures": [format!("lend{}", timestamp)],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": WALLET, "signer": true, "writable": true },
+                    { "pubkey": USDC_ACCOUNT, "signer": false, "writable": true }
+                ],
+                "instructions": instructions,
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [lamports.0, 2_039_280],
+            "postBalances": [lamports.1, 2_039_280],
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": usdc_balance(usdc.0),
+            "postTokenBalances": usdc_balance(usdc.1),
+            "rewards": []
+        },
+        "blockTime": timestamp
+    });
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp,
+        tx_hash: format!("lend{}", timestamp),
+        chain: Chain::Solana,
+        raw_metadata: raw,
+    }
+}
+
+fn of_type<'a>(entries: &'a [LedgerEntry], asset: &str, entry_type: EntryType) -> Vec<&'a LedgerEntry> {
+    entries
+        .iter()
+        .filter(|e| e.asset_symbol == asset && std::mem::discriminant(&e.entry_type) == std::mem::discriminant(&entry_type))
+        .collect()
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+#[test]
+fn test_marginfi_withdrawal_splits_interest_from_collateral() {
+    // group, marginfi_account, signer, bank, signer_token_account, ...
+    let accounts = ["Group111", MARGIN_ACCOUNT, WALLET, "Bank111", USDC_ACCOUNT];
+    let deposit = lending_tx(
+        1_700_000_000,
+        json!([ix(MARGINFI_PROGRAM_ID, &accounts, anchor_data("lending_account_deposit", 100_000_000))]),
+        (1_000_000_000, 999_995_000),
+        (250.0, 150.0),
+    );
+    let withdraw = lending_tx(
+        1_710_000_000,
+        json!([ix(MARGINFI_PROGRAM_ID, &accounts, anchor_data("lending_account_withdraw", 101_500_000))]),
+        (999_995_000, 999_990_000),
+        (150.0, 251.5),
+    );
+
+    let mut entries = solana_parser::parse_solana_transaction(&deposit).unwrap();
+    entries.extend(solana_parser::parse_solana_transaction(&withdraw).unwrap());
+
+    // Neither leg is a transfer, and the fee-only SOL change stays a fee
+    let supplied = of_type(&entries, USDC_MINT, EntryType::Collateral);
+    assert_eq!(supplied.len(), 2);
+    assert!(supplied.iter().all(|e| e.position_account.as_deref() == Some(MARGIN_ACCOUNT)));
+    assert!(supplied.iter().all(|e| e.source_program.as_deref() == Some(MARGINFI_PROGRAM_ID)));
+    assert_eq!(of_type(&entries, "SOL", EntryType::Fee).len(), 2);
+
+    let open = lending::track_positions(&mut entries);
+    assert!(open.is_empty());
+
+    let amounts: Vec<BigDecimal> = of_type(&entries, USDC_MINT, EntryType::Collateral).iter().map(|e| e.amount.clone()).collect();
+    assert_eq!(amounts, vec![dec("-100"), dec("100")]);
+    let interest = of_type(&entries, USDC_MINT, EntryType::Income);
+    assert_eq!(interest.len(), 1);
+    assert_eq!(interest[0].amount, dec("1.5"));
+    assert_eq!(interest[0].transaction_id, withdraw.id);
+}
+
+#[test]
+fn test_collateral_and_borrow_in_one_transaction_then_repay_with_interest() {
+    // Solend: deposit SOL as collateral and borrow USDC against it, obligation at index 8 and 4
+    let deposit_accounts = [WALLET, "Col", "Reserve", "Supply", "CMint", "Market", "Auth", "Dest", OBLIGATION, WALLET];
+    let borrow_accounts = ["Supply", USDC_ACCOUNT, "Reserve", "FeeRx", OBLIGATION, "Market", "Auth", WALLET];
+    let open = lending_tx(
+        1_700_000_000,
+        json!([
+            ix(SOLEND_PROGRAM_ID, &deposit_accounts, tag_data(14, 2_000_000_000)),
+            ix(SOLEND_PROGRAM_ID, &borrow_accounts, tag_data(10, 50_000_000)),
+        ]),
+        (5_000_000_000, 2_999_995_000),
+        (0.0, 50.0),
+    );
+    let repay_accounts = [USDC_ACCOUNT, "Supply", "Reserve", OBLIGATION, "Market", WALLET];
+    let repay = lending_tx(
+        1_705_000_000,
+        json!([ix(SOLEND_PROGRAM_ID, &repay_accounts, tag_data(11, 50_250_000))]),
+        (2_999_995_000, 2_999_990_000),
+        (60.0, 9.75),
+    );
+
+    let mut entries = solana_parser::parse_solana_transaction(&open).unwrap();
+    // The network fee is split off the SOL deposit rather than counted as collateral
+    let sol = of_type(&entries, "SOL", EntryType::Collateral);
+    assert_eq!(sol.len(), 1);
+    assert_eq!(sol[0].amount, dec("-2"));
+    assert_eq!(of_type(&entries, "SOL", EntryType::Fee)[0].amount, dec("-0.000005"));
+    let borrowed = of_type(&entries, USDC_MINT, EntryType::Loan);
+    assert_eq!(borrowed[0].amount, dec("50"));
+    assert_eq!(borrowed[0].position_account.as_deref(), Some(OBLIGATION));
+
+    entries.extend(solana_parser::parse_solana_transaction(&repay).unwrap());
+    let open_positions = lending::track_positions(&mut entries);
+
+    // Repaying 50.25 of a 50 loan is 0.25 of interest paid
+    let interest = of_type(&entries, USDC_MINT, EntryType::Fee);
+    assert_eq!(interest.len(), 1);
+    assert_eq!(interest[0].amount, dec("-0.25"));
+    assert_eq!(of_type(&entries, USDC_MINT, EntryType::Loan).iter().map(|e| e.amount.clone()).sum::<BigDecimal>(), dec("0"));
+
+    assert_eq!(open_positions.len(), 1);
+    assert_eq!(open_positions[0].asset_symbol, "SOL");
+    assert_eq!(open_positions[0].account, OBLIGATION);
+    assert_eq!(open_positions[0].supplied, dec("2"));
+    assert_eq!(lending::positions(&entries).len(), 1);
+}
+
+#[test]
+fn test_kamino_withdrawal_without_ingested_deposit_is_all_principal() {
+    let accounts = [WALLET, OBLIGATION, "Market", "Auth", "Reserve"];
+    let withdraw = lending_tx(
+        1_710_000_000,
+        json!([ix(KAMINO_LEND_PROGRAM_ID, &accounts, anchor_data("withdraw_obligation_collateral_and_redeem_reserve_collateral", 1))]),
+        (1_000_000_000, 999_995_000),
+        (0.0, 75.0),
+    );
+
+    let mut entries = solana_parser::parse_solana_transaction(&withdraw).unwrap();
+    lending::track_positions(&mut entries);
+    assert_eq!(of_type(&entries, USDC_MINT, EntryType::Collateral)[0].amount, dec("75"));
+    assert!(of_type(&entries, USDC_MINT, EntryType::Income).is_empty());
+}
@@ -0,0 +1,234 @@
+use spectraplex_adapters::solana_liquidity::{ORCA_WHIRLPOOL_PROGRAM_ID, RAYDIUM_AMM_PROGRAM_ID};
+use spectraplex_adapters::solana_parser::{self, USDC_MINT};
+use spectraplex_core::lots;
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use spectraplex_core::pricing::{self, PriceCandle, PriceHistory, LIQUIDITY_POOL_SOURCE};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use solana_sdk::hash::hashv;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "PoolWa11et11111111111111111111111111111111";
+const LP_MINT: &str = "RayLpMint1111111111111111111111111111111111";
+const POSITION_MINT: &str = "WhirlPosition1111111111111111111111111111111";
+const DAY: i64 = 86_400;
+
+fn ix(program: &str, accounts: &[&str], data: Vec<u8>) -> Value {
+    json!({ "programId": program, "accounts": accounts, "data": bs58::encode(data).into_string(), "stackHeight": null })
+}
+
+fn anchor_data(name: &str) -> Vec<u8> {
+    hashv(&[format!("global:{}", name).as_bytes()]).to_bytes()[..8].to_vec()
+}
+
+// (account index, mint, decimals, balance)
+fn token_balances(balances: &[(usize, &str, u8, f64)]) -> Value {
+    balances
+        .iter()
+        .map(|(index, mint, decimals, ui_amount)| {
+            json!({
+                "accountIndex": index,
+                "mint": mint,
+                "owner": WALLET,
+                "programId": "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+                "uiTokenAmount": {
+                    "amount": ((ui_amount * 10f64.powi(*decimals as i32)) as u64).to_string(),
+                    "decimals": decimals,
+                    "uiAmount": ui_amount,
+                    "uiAmountString": ui_amount.to_string()
+                }
+            })
+        })
+        .collect()
+}
+
+// The wallet signs and pays; its USDC and pool share accounts are the second and third keys
+fn pool_tx(
+    timestamp: i64,
+    instruction: Value,
+    lamports: (u64, u64),
+    pre_tokens: &[(usize, &str, u8, f64)],
+    post_tokens: &[(usize, &str, u8, f64)],
+) -> Transaction {
+    let raw = json!({
+        "slot": 300000,
+        "transaction": {
+            "signatures": [format!("pool{}", timestamp)],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": WALLET, "signer": true, "writable": true },
+                    { "pubkey": "UsdcAccount11111111111111111111111111111111", "signer": false, "writable": true },
+                    { "pubkey": "ShareAccount1111111111111111111111111111111", "signer": false, "writable": true }
+                ],
+                "instructions": [instruction],
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [lamports.0, 2_039_280, 2_039_280],
+            "postBalances": [lamports.1, 2_039_280, 2_039_280],
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": token_balances(pre_tokens),
+            "postTokenBalances": token_balances(post_tokens),
+            "rewards": []
+        },
+        "blockTime": timestamp
+    });
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp,
+        tx_hash: format!("pool{}", timestamp),
+        chain: Chain::Solana,
+        raw_metadata: raw,
+    }
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+fn candle(asset: &str, open_time: i64, price: &str) -> PriceCandle {
+    PriceCandle {
+        asset_symbol: asset.to_string(),
+        fiat_currency: "USD".to_string(),
+        open_time,
+        resolution_secs: DAY,
+        open: dec(price),
+        high: dec(price),
+        low: dec(price),
+        close: dec(price),
+        source: "test".to_string(),
+    }
+}
+
+fn of_type<'a>(entries: &'a [LedgerEntry], asset: &str, entry_type: EntryType) -> Vec<&'a LedgerEntry> {
+    entries
+        .iter()
+        .filter(|e| e.asset_symbol == asset && std::mem::discriminant(&e.entry_type) == std::mem::discriminant(&entry_type))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_raydium_add_carries_basis_and_removal_realizes_against_returned_assets() {
+    // token_program, amm, authority, open_orders, target_orders, lp_mint, coin_vault, pc_vault, market,
+    // user_coin, user_pc, user_lp, user_owner
+    let accounts = ["Tok", "Amm", "Auth", "Oo", "To", LP_MINT, "Cv", "Pv", "Mkt", "Uc", "Up", "Ul", WALLET];
+    let add_at = 1_700_000_000 - 1_700_000_000 % DAY;
+    let remove_at = add_at + 30 * DAY;
+    let add = pool_tx(
+        add_at,
+        ix(RAYDIUM_AMM_PROGRAM_ID, &accounts, vec![3]),
+        (3_000_000_000, 1_999_995_000),
+        &[(1, USDC_MINT, 6, 150.0)],
+        &[(1, USDC_MINT, 6, 50.0), (2, LP_MINT, 9, 10.0)],
+    );
+    let remove = pool_tx(
+        remove_at,
+        ix(RAYDIUM_AMM_PROGRAM_ID, &accounts, vec![4]),
+        (1_999_995_000, 3_249_990_000),
+        &[(1, USDC_MINT, 6, 50.0), (2, LP_MINT, 9, 10.0)],
+        &[(1, USDC_MINT, 6, 140.0), (2, LP_MINT, 9, 0.0)],
+    );
+
+    let mut entries = solana_parser::parse_solana_transaction(&add).unwrap();
+    assert_eq!(of_type(&entries, "SOL", EntryType::Liquidity)[0].amount, dec("-1"));
+    assert_eq!(of_type(&entries, "SOL", EntryType::Fee)[0].amount, dec("-0.000005"));
+    assert_eq!(of_type(&entries, USDC_MINT, EntryType::Liquidity)[0].amount, dec("-100"));
+    assert_eq!(of_type(&entries, LP_MINT, EntryType::LpToken)[0].amount, dec("10"));
+    assert!(entries.iter().all(|e| !matches!(e.entry_type, EntryType::Trade | EntryType::Transfer)));
+
+    entries.extend(solana_parser::parse_solana_transaction(&remove).unwrap());
+    assert_eq!(of_type(&entries, "SOL", EntryType::Liquidity)[1].amount, dec("1.25"));
+    assert_eq!(of_type(&entries, USDC_MINT, EntryType::Liquidity)[1].amount, dec("90"));
+    assert_eq!(of_type(&entries, LP_MINT, EntryType::LpToken)[1].amount, dec("-10"));
+
+    let prices = PriceHistory::new(vec![
+        candle("SOL", add_at, "100"),
+        candle("SOL", remove_at, "120"),
+        candle(USDC_MINT, add_at, "1"),
+        candle(USDC_MINT, remove_at, "1"),
+    ]);
+    let summary = pricing::price_entries(&prices, &mut entries, "USD", None).await.unwrap();
+    assert_eq!(summary.unpriced, 0);
+    // The share is worth what came out of the pool for it: 1.25 SOL at 120 plus 90 USDC
+    let redeemed = of_type(&entries, LP_MINT, EntryType::LpToken)[1];
+    assert_eq!(redeemed.fiat_value, Some(dec("240")));
+    assert_eq!(redeemed.price_source.as_deref(), Some(LIQUIDITY_POOL_SOURCE));
+
+    // The SOL and USDC that went in were bought earlier for 50 a SOL and at par
+    let mut bought = LedgerEntry::new(&add, "SOL".to_string(), dec("2"), EntryType::Trade);
+    bought.id = Uuid::new_v4();
+    bought.transaction_id = Uuid::new_v4();
+    bought.timestamp = add_at - DAY;
+    bought.fiat_value = Some(dec("100"));
+    bought.fiat_currency = Some("USD".to_string());
+    let mut usdc = bought.clone();
+    usdc.id = Uuid::new_v4();
+    usdc.asset_symbol = USDC_MINT.to_string();
+    usdc.fiat_value = Some(dec("150"));
+    usdc.amount = dec("150");
+    entries.extend([bought, usdc]);
+
+    let report = lots::match_fifo(&entries).unwrap();
+    // Only the pool share's redemption is a disposal, against the 150 carried into it
+    let pool_gains: Vec<_> = report.realized.iter().filter(|g| g.asset_symbol != "SOL" || g.quantity != dec("0.000005")).collect();
+    assert_eq!(pool_gains.len(), 1);
+    assert_eq!(pool_gains[0].asset_symbol, LP_MINT);
+    assert_eq!(pool_gains[0].cost_basis, dec("150"));
+    assert_eq!(pool_gains[0].proceeds, dec("240"));
+    assert_eq!(pool_gains[0].gain, dec("90"));
+    assert_eq!(pool_gains[0].acquired_at, Some(add_at));
+
+    // The returned assets start fresh lots at their market value
+    let returned: Vec<_> = report.open_lots.iter().filter(|l| l.acquired_at == remove_at).collect();
+    assert_eq!(returned.len(), 2);
+    assert!(returned.iter().any(|l| l.asset_symbol == "SOL" && l.cost_basis == dec("150")));
+    assert!(returned.iter().any(|l| l.asset_symbol == USDC_MINT && l.cost_basis == dec("90")));
+    assert!(report.open_lots.iter().all(|l| l.asset_symbol != LP_MINT));
+}
+
+#[test]
+fn test_whirlpool_position_nft_is_the_pool_share_not_a_purchase() {
+    // Opening a position mints its NFT to the wallet while SOL and USDC go into the pool
+    let accounts = [WALLET, WALLET, "Position", POSITION_MINT, "ShareAccount1111111111111111111111111111111", "Whirlpool"];
+    let open = pool_tx(
+        1_700_000_000,
+        ix(ORCA_WHIRLPOOL_PROGRAM_ID, &accounts, anchor_data("open_position_with_metadata")),
+        (3_000_000_000, 2_499_995_000),
+        &[(1, USDC_MINT, 6, 150.0)],
+        &[(1, USDC_MINT, 6, 100.0), (2, POSITION_MINT, 0, 1.0)],
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&open).unwrap();
+    let share = of_type(&entries, POSITION_MINT, EntryType::LpToken);
+    assert_eq!(share.len(), 1);
+    assert_eq!(share[0].amount, dec("1"));
+    assert_eq!(share[0].source_program.as_deref(), Some(ORCA_WHIRLPOOL_PROGRAM_ID));
+    assert_eq!(of_type(&entries, "SOL", EntryType::Liquidity)[0].amount, dec("-0.5"));
+    assert_eq!(of_type(&entries, USDC_MINT, EntryType::Liquidity)[0].amount, dec("-50"));
+    assert!(of_type(&entries, POSITION_MINT, EntryType::Trade).is_empty());
+}
+
+#[test]
+fn test_swap_through_pool_program_is_not_liquidity() {
+    // Raydium AMM swap_base_in (tag 9) is left to swap classification
+    let accounts = ["Tok", "Amm", WALLET];
+    let swap = pool_tx(
+        1_700_000_000,
+        ix(RAYDIUM_AMM_PROGRAM_ID, &accounts, vec![9]),
+        (3_000_000_000, 1_999_995_000),
+        &[(1, USDC_MINT, 6, 0.0)],
+        &[(1, USDC_MINT, 6, 100.0)],
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&swap).unwrap();
+    assert!(entries.iter().all(|e| !matches!(e.entry_type, EntryType::Liquidity | EntryType::LpToken)));
+    assert_eq!(of_type(&entries, USDC_MINT, EntryType::Trade).len(), 1);
+}
@@ -0,0 +1,154 @@
+use spectraplex_adapters::{solana_nft, solana_parser};
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry, Transaction};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "WalletAddress111111111111111111111111111111";
+const OTHER: &str = "OtherWallet11111111111111111111111111111111";
+const CREATOR: &str = "Creator111111111111111111111111111111111111";
+const TREASURY: &str = "Treasury11111111111111111111111111111111111";
+const NFT: &str = "NftMint111111111111111111111111111111111111";
+const MAGIC_EDEN: &str = "M2mx93ekt1fmXSVkTrUL9xVFHkmME8HTUi5Cyc5aF7K";
+const RENT: u64 = 2_039_280;
+
+fn token_balance(index: u8, owner: &str, amount: u64) -> Value {
+    json!({ "accountIndex": index, "mint": NFT, "owner": owner,
+        "uiTokenAmount": { "uiAmount": amount as f64, "decimals": 0, "amount": amount.to_string(), "uiAmountString": amount.to_string() } })
+}
+
+// Magic Eden sale of one NFT: the buyer signs and pays 2 SOL to the seller, a 5% royalty and a 2% marketplace fee
+fn sale(buyer: &str, seller: &str, pre: [u64; 6], post: [u64; 6], post_tokens: Value) -> Transaction {
+    let raw = json!({
+        "slot": 123458,
+        "transaction": {
+            "signatures": ["nftsig"],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": buyer, "signer": true, "writable": true },
+                    { "pubkey": seller, "signer": false, "writable": true },
+                    { "pubkey": CREATOR, "signer": false, "writable": true },
+                    { "pubkey": TREASURY, "signer": false, "writable": true },
+                    { "pubkey": "BuyerAta11111111111111111111111111111111111", "signer": false, "writable": true },
+                    { "pubkey": "SellerAta1111111111111111111111111111111111", "signer": false, "writable": true }
+                ],
+                "instructions": [
+                    { "programId": "ComputeBudget111111111111111111111111111111", "accounts": [], "data": "3DTZbgwsozUF", "stackHeight": null },
+                    { "programId": MAGIC_EDEN, "accounts": [buyer, seller], "data": "", "stackHeight": null }
+                ],
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": pre,
+            "postBalances": post,
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": [token_balance(5, seller, 1)],
+            "postTokenBalances": post_tokens,
+            "rewards": []
+        },
+        "blockTime": 1672531400,
+        "nft_metadata": {
+            NFT: { "name": "Mad Lad #1", "symbol": "MAD", "seller_fee_basis_points": 500, "creators": [CREATOR], "supply": 1 }
+        }
+    });
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        wallet_address: WALLET.to_string(),
+        timestamp: 1672531400,
+        tx_hash: "nftsig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: raw,
+    }
+}
+
+fn amounts(entries: &[LedgerEntry], kind: fn(&EntryType) -> bool) -> Vec<(String, BigDecimal)> {
+    entries.iter().filter(|e| kind(&e.entry_type)).map(|e| (e.asset_symbol.clone(), e.amount.clone())).collect()
+}
+
+fn sol(s: &str) -> (String, BigDecimal) {
+    ("SOL".to_string(), BigDecimal::from_str(s).unwrap())
+}
+
+#[test]
+fn test_marketplace_purchase_splits_price_royalty_and_fees() {
+    let paid = 2_000_000_000 + 100_000_000 + 40_000_000 + RENT + 5000;
+    let tx = sale(
+        WALLET,
+        OTHER,
+        [10_000_000_000, 1_000_000_000, 5_000_000_000, 50_000_000_000, 0, RENT],
+        [10_000_000_000 - paid, 3_000_000_000, 5_100_000_000, 50_040_000_000, RENT, RENT],
+        json!([token_balance(4, WALLET, 1), token_balance(5, OTHER, 0)]),
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+
+    // The NFT's cost is the price plus the rent for its new token account
+    assert_eq!(
+        amounts(&entries, |t| matches!(t, EntryType::Trade)),
+        vec![(NFT.to_string(), BigDecimal::from(1)), sol("-2.00203928")]
+    );
+    assert_eq!(amounts(&entries, |t| matches!(t, EntryType::Fee)), vec![sol("-0.000005"), sol("-0.1"), sol("-0.04")]);
+    assert!(entries.iter().all(|e| e.source_program.as_deref() == Some(MAGIC_EDEN)));
+}
+
+#[test]
+fn test_filled_listing_is_a_sale_at_the_net_price() {
+    // The wallet's token account is closed and its rent returned with the proceeds
+    let paid = 2_000_000_000 + 100_000_000 + 40_000_000 + RENT + 5000;
+    let tx = sale(
+        OTHER,
+        WALLET,
+        [10_000_000_000, 1_000_000_000, 5_000_000_000, 50_000_000_000, 0, RENT],
+        [10_000_000_000 - paid, 3_000_000_000 + RENT, 5_100_000_000, 50_040_000_000, RENT, 0],
+        json!([token_balance(4, OTHER, 1)]),
+    );
+
+    let entries = solana_parser::parse_solana_transaction(&tx).unwrap();
+
+    // The buyer took the listing, so the royalty and marketplace fee are theirs
+    assert_eq!(
+        amounts(&entries, |t| matches!(t, EntryType::Trade)),
+        vec![(NFT.to_string(), BigDecimal::from(-1)), sol("2.00203928")]
+    );
+    assert!(amounts(&entries, |t| matches!(t, EntryType::Fee)).is_empty());
+}
+
+#[test]
+fn test_decode_metaplex_metadata() {
+    let creator = Pubkey::new_from_array([7; 32]);
+    let padded = |s: &str, len: usize| {
+        let mut bytes = (len as u32).to_le_bytes().to_vec();
+        bytes.extend(s.as_bytes());
+        bytes.resize(4 + len, 0);
+        bytes
+    };
+
+    let mut data = vec![4u8]; // MetadataV1
+    data.extend([1; 32]); // update authority
+    data.extend([2; 32]); // mint
+    data.extend(padded("Mad Lad #1", 32));
+    data.extend(padded("MAD", 10));
+    data.extend(padded("https://example.com/1.json", 200));
+    data.extend(500u16.to_le_bytes());
+    data.push(1); // Some(creators)
+    data.extend(1u32.to_le_bytes());
+    data.extend(creator.to_bytes());
+    data.extend([1, 100]); // verified, share
+    data.extend([1, 1]); // primary sale happened, mutable
+
+    let metadata = solana_nft::decode_metadata(&data).unwrap();
+    assert_eq!(metadata.name, "Mad Lad #1");
+    assert_eq!(metadata.symbol, "MAD");
+    assert_eq!(metadata.seller_fee_basis_points, 500);
+    assert_eq!(metadata.creators, vec![creator.to_string()]);
+
+    assert!(solana_nft::decode_metadata(&data[..80]).is_err());
+}
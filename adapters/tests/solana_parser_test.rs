@@ -1,8 +1,10 @@
 use spectraplex_adapters::solana_parser;
-use spectraplex_core::models::{Chain, Transaction};
+use spectraplex_core::models::{Chain, EntryType, Transaction};
+use spectraplex_core::pricing::{self, PriceCandle, PriceHistory};
 use serde_json::json;
 use uuid::Uuid;
 use bigdecimal::{BigDecimal, FromPrimitive};
+use std::str::FromStr;
 
 #[test]
 fn test_parse_solana_native_transfer() {
@@ -57,3 +59,142 @@ fn test_parse_solana_native_transfer() {
     let expected_amount = BigDecimal::from_f64(-0.5).unwrap();
     assert_eq!(entry.amount, expected_amount);
 }
+
+#[test]
+fn test_parse_solana_swap_classifies_trade_legs_and_fee() {
+    let wallet = "WalletAddress111111111111111111111111111111";
+    let usdc = solana_parser::USDC_MINT;
+    let bonk = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    let full_tx_json = json!({
+        "slot": 123457,
+        "transaction": {
+            "signatures": ["swapsig"],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": wallet, "signer": true, "writable": true },
+                    { "pubkey": "Pool111111111111111111111111111111111111111", "signer": false, "writable": true }
+                ],
+                "instructions": [
+                    { "programId": "ComputeBudget111111111111111111111111111111", "accounts": [], "data": "3DTZbgwsozUF", "stackHeight": null },
+                    { "programId": "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "accounts": [wallet], "data": "", "stackHeight": null }
+                ],
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [10_000_000_000u64, 0],
+            "postBalances": [9_999_995_000u64, 0],
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": [
+                { "accountIndex": 2, "mint": usdc, "owner": wallet, "uiTokenAmount": { "uiAmount": 100.0, "decimals": 6, "amount": "100000000", "uiAmountString": "100" } },
+                { "accountIndex": 3, "mint": bonk, "owner": wallet, "uiTokenAmount": { "uiAmount": 0.0, "decimals": 5, "amount": "0", "uiAmountString": "0" } }
+            ],
+            "postTokenBalances": [
+                { "accountIndex": 2, "mint": usdc, "owner": wallet, "uiTokenAmount": { "uiAmount": 0.0, "decimals": 6, "amount": "0", "uiAmountString": "0" } },
+                { "accountIndex": 3, "mint": bonk, "owner": wallet, "uiTokenAmount": { "uiAmount": 5.0, "decimals": 5, "amount": "500000", "uiAmountString": "5" } }
+            ],
+            "rewards": []
+        },
+        "blockTime": 1672531300
+    });
+
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        wallet_address: wallet.to_string(),
+        timestamp: 1672531300,
+        tx_hash: "swapsig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: full_tx_json,
+    };
+
+    let entries = solana_parser::parse_solana_transaction(&tx).expect("Parser failed");
+    assert_eq!(entries.len(), 3);
+
+    let kind = |asset: &str| entries.iter().find(|e| e.asset_symbol == asset).map(|e| e.entry_type.clone());
+    assert!(matches!(kind("SOL"), Some(EntryType::Fee)));
+    assert!(matches!(kind(usdc), Some(EntryType::Trade)));
+    assert!(matches!(kind(bonk), Some(EntryType::Trade)));
+
+    // Compute budget setup is skipped when picking the program the swap went through
+    assert!(entries.iter().all(|e| e.source_program.as_deref() == Some("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4")));
+}
+
+#[tokio::test]
+async fn test_sol_swap_leg_excludes_network_fee() {
+    let wallet = "WalletAddress111111111111111111111111111111";
+    let bonk = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    // 1 SOL for 5 BONK, plus a 0.000005 SOL fee out of the same balance
+    let full_tx_json = json!({
+        "slot": 123458,
+        "transaction": {
+            "signatures": ["solswapsig"],
+            "message": {
+                "accountKeys": [
+                    { "pubkey": wallet, "signer": true, "writable": true },
+                    { "pubkey": "Pool111111111111111111111111111111111111111", "signer": false, "writable": true }
+                ],
+                "instructions": [
+                    { "programId": "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "accounts": [wallet], "data": "", "stackHeight": null }
+                ],
+                "recentBlockhash": "11111111111111111111111111111111"
+            }
+        },
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [10_000_000_000u64, 0],
+            "postBalances": [8_999_995_000u64, 0],
+            "innerInstructions": [],
+            "logMessages": [],
+            "preTokenBalances": [
+                { "accountIndex": 2, "mint": bonk, "owner": wallet, "uiTokenAmount": { "uiAmount": 0.0, "decimals": 5, "amount": "0", "uiAmountString": "0" } }
+            ],
+            "postTokenBalances": [
+                { "accountIndex": 2, "mint": bonk, "owner": wallet, "uiTokenAmount": { "uiAmount": 5.0, "decimals": 5, "amount": "500000", "uiAmountString": "5" } }
+            ],
+            "rewards": []
+        },
+        "blockTime": 1704070000
+    });
+
+    let tx = Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::new_v4(),
+        wallet_address: wallet.to_string(),
+        timestamp: 1704070000,
+        tx_hash: "solswapsig".to_string(),
+        chain: Chain::Solana,
+        raw_metadata: full_tx_json,
+    };
+
+    let entries = solana_parser::parse_solana_transaction(&tx).expect("Parser failed");
+    assert_eq!(entries.len(), 3);
+    let sol: Vec<_> = entries.iter().filter(|e| e.asset_symbol == "SOL").collect();
+    assert!(sol.iter().any(|e| matches!(e.entry_type, EntryType::Trade) && e.amount == BigDecimal::from(-1)));
+    assert!(sol.iter().any(|e| matches!(e.entry_type, EntryType::Fee) && e.amount == BigDecimal::from_str("-0.000005").unwrap()));
+
+    // At 100 a SOL, BONK was worth exactly 20
+    let history = PriceHistory::new(vec![PriceCandle {
+        asset_symbol: "SOL".to_string(),
+        fiat_currency: "USD".to_string(),
+        open_time: 1_704_067_200,
+        resolution_secs: 86_400,
+        open: BigDecimal::from(100),
+        high: BigDecimal::from(100),
+        low: BigDecimal::from(100),
+        close: BigDecimal::from(100),
+        source: "test-feed".to_string(),
+    }]);
+    let observations = pricing::implied_prices(&history, &entries, "USD", &["SOL".to_string()]).await.unwrap();
+    assert_eq!(observations.len(), 1);
+    assert_eq!(observations[0].asset_symbol, bonk);
+    assert_eq!(observations[0].close, BigDecimal::from(20));
+}
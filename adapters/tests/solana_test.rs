@@ -0,0 +1,109 @@
+use spectraplex_adapters::{solana::SolanaAdapter, solana_parser};
+use spectraplex_core::models::{ChainIngestor, EntryType};
+use bigdecimal::BigDecimal;
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::str::FromStr;
+
+const WALLET: &str = "7HpwvzfJZ49TyGxRtzURgYJGk3KC856pgH2rFEv9sf21";
+const SIGNATURE: &str = "26kkabed3SSZcAFYC9jGSBWhfPwm7DwLS6DhPvhMTFKmoKQG7gAKVffmcyztJCCpvvdkbXWyGnrrNrBNpnXrGwZs";
+
+// getTransaction as a mainnet node answers it for a v0 transaction: 0.25 SOL and 10 USDC sent
+const GET_TRANSACTION: &str = include_str!("data/solana_get_transaction_json_parsed.json");
+
+fn rpc_response(request: &Value) -> Value {
+    let params = &request["params"];
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "getSignaturesForAddress" => json!([{
+            "blockTime": 1717243200, "confirmationStatus": "finalized", "err": null, "memo": null,
+            "signature": SIGNATURE, "slot": 268172430
+        }]),
+        // Like a real node, refuse versioned transactions to clients that don't say they can read them
+        "getTransaction" if params[1]["maxSupportedTransactionVersion"] != json!(0) => {
+            return json!({ "jsonrpc": "2.0", "id": request["id"], "error": {
+                "code": -32015,
+                "message": "Transaction version (0) is not supported by the requesting client. Please try the request again with the following configuration parameter: \"maxSupportedTransactionVersion\": 0"
+            }});
+        }
+        "getTransaction" if params[1]["encoding"] == json!("jsonParsed") => {
+            let mut response: Value = serde_json::from_str(GET_TRANSACTION).unwrap();
+            response["id"] = request["id"].clone();
+            return response;
+        }
+        "getVersion" => json!({ "solana-core": "2.0.0", "feature-set": 0 }),
+        _ => Value::Null,
+    };
+    json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+}
+
+// Keep-alive JSON-RPC stand-in for a validator, as in reconcile_test
+fn spawn_mock_rpc() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut writer = stream;
+                loop {
+                    let mut content_length = 0;
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let header = line.trim_end();
+                        if header.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':') {
+                            if name.eq_ignore_ascii_case("content-length") {
+                                content_length = value.trim().parse().unwrap_or(0);
+                            }
+                        }
+                    }
+
+                    let mut body = vec![0; content_length];
+                    if reader.read_exact(&mut body).is_err() {
+                        return;
+                    }
+                    let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                    let response = rpc_response(&request).to_string();
+                    let http = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                        response.len(),
+                        response
+                    );
+                    if writer.write_all(http.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_fetched_v0_transaction_parses() {
+    let adapter = SolanaAdapter::new(&spawn_mock_rpc());
+    let txs = adapter.fetch_history(WALLET, 10).await.unwrap();
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].tx_hash, SIGNATURE);
+    assert_eq!(txs[0].timestamp, 1717243200);
+
+    let entries = solana_parser::parse_solana_transaction(&txs[0]).unwrap();
+    let moves: Vec<(&str, BigDecimal)> = entries
+        .iter()
+        .filter(|e| matches!(e.entry_type, EntryType::Transfer))
+        // The parser goes through f64, so compare to the lamport
+        .map(|e| (e.asset_symbol.as_str(), e.amount.round(9)))
+        .collect();
+    assert_eq!(
+        moves,
+        vec![("SOL", BigDecimal::from_str("-0.250015").unwrap()), (solana_parser::USDC_MINT, BigDecimal::from(-10))]
+    );
+}
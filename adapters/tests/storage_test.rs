@@ -0,0 +1,378 @@
+use spectraplex_adapters::normalize;
+use spectraplex_adapters::sqlite::SqliteStore;
+use spectraplex_core::adjustments::{Change, ManualEntry, MANUAL_PRICE_SOURCE};
+use spectraplex_core::fx::{self, FxRate};
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, LedgerEntry, ParserVersion, Transaction, UserSettings};
+use spectraplex_core::pricing::{self, PriceCandle};
+use spectraplex_core::spam::SpamOverride;
+use spectraplex_core::storage::{Cursor, MemoryStore, QueryFilter, Storage, SyncState};
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde_json::json;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const WALLET: &str = "StoreWa11et1111111111111111111111111111111";
+const JAN_5_2024: i64 = 1_704_412_800;
+
+fn tx(timestamp: i64, hash: &str) -> Transaction {
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp,
+        tx_hash: hash.to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({ "slot": 250000000, "signatures": [hash] }),
+    }
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+fn candle(close: &str) -> PriceCandle {
+    PriceCandle {
+        asset_symbol: "SOL".to_string(),
+        fiat_currency: "USD".to_string(),
+        open_time: JAN_5_2024,
+        resolution_secs: 86_400,
+        open: dec(close),
+        high: dec(close),
+        low: dec(close),
+        close: dec(close),
+        source: "test".to_string(),
+    }
+}
+
+// Everything the pipeline does with storage, from ingestion through pricing and bridge linking
+async fn round_trip(store: &dyn Storage) {
+    let (buy, send) = (tx(JAN_5_2024 + 60, "buy"), tx(JAN_5_2024 + 3_600, "send"));
+    let mut bought = LedgerEntry::new(&buy, "SOL".to_string(), dec("10.123456789"), EntryType::Trade);
+    bought.parser = Some(ParserVersion { name: "solana".to_string(), version: 3 });
+    let mut sent = LedgerEntry::new(&send, "SOL".to_string(), dec("-4"), EntryType::Transfer);
+    sent.bridge = Some(BridgeTransfer {
+        protocol: "wormhole".to_string(),
+        destination_chain: Some("ethereum".to_string()),
+        destination_address: None,
+        transfer_id: Some("4242".to_string()),
+        linked_entry_id: None,
+    });
+    let txs = vec![send.clone(), buy.clone()];
+    let entries = vec![sent.clone(), bought.clone()];
+
+    // Saving again leaves what's stored alone
+    store.save_transactions_with_entries(&txs, &entries).await.unwrap();
+    store.save_transactions_with_entries(&txs, &entries).await.unwrap();
+
+    let stored = store.get_transactions_by_wallet(WALLET).await.unwrap();
+    assert_eq!(stored.iter().map(|t| t.tx_hash.as_str()).collect::<Vec<_>>(), ["buy", "send"]);
+    assert_eq!(stored[0].raw_metadata, buy.raw_metadata);
+    assert!(matches!(stored[1].chain, Chain::Solana));
+
+    let loaded = store.get_ledger_entries_by_wallet(WALLET).await.unwrap();
+    assert_eq!(loaded.len(), 2);
+    assert_eq!(loaded[0].id, bought.id);
+    assert_eq!(loaded[0].amount, dec("10.123456789"));
+    assert!(matches!(loaded[0].entry_type, EntryType::Trade));
+    assert_eq!(loaded[0].parser, bought.parser);
+    assert!(loaded[1].parser.is_none());
+    assert_eq!(loaded[1].bridge, sent.bridge);
+    assert_eq!(store.get_ledger_entries_by_user(Uuid::nil()).await.unwrap().len(), 2);
+    assert!(store.get_ledger_entries_by_user(Uuid::new_v4()).await.unwrap().is_empty());
+
+    let ids = [buy.id, send.id];
+    assert_eq!(store.get_tx_hashes(&ids).await.unwrap().get(&send.id).map(String::as_str), Some("send"));
+    assert!(matches!(store.get_transaction_chains(&ids).await.unwrap().get(&buy.id), Some(Chain::Solana)));
+
+    // A re-imported bar replaces the first one
+    store.save_price_candles(&[candle("90")]).await.unwrap();
+    store.save_price_candles(&[candle("100")]).await.unwrap();
+    let mut loaded = loaded;
+    let summary = pricing::price_entries(store, &mut loaded, "usd", None).await.unwrap();
+    assert_eq!(summary.priced, 2);
+    store.update_ledger_valuations(&loaded).await.unwrap();
+
+    loaded[1].is_spam = true;
+    store.update_spam_flags(&loaded).await.unwrap();
+    loaded[1].entry_type = EntryType::Bridge;
+    loaded[1].bridge.as_mut().unwrap().linked_entry_id = Some(bought.id);
+    store.update_bridge_links(&loaded).await.unwrap();
+
+    let reloaded = store.get_ledger_entries_by_wallet(WALLET).await.unwrap();
+    assert_eq!(reloaded[0].fiat_value, Some(dec("1012.3456789")));
+    assert_eq!(reloaded[0].fiat_currency.as_deref(), Some("USD"));
+    assert_eq!(reloaded[0].price_resolution.as_deref(), Some("1d"));
+    assert!(!reloaded[0].is_spam && reloaded[1].is_spam);
+    assert!(matches!(reloaded[1].entry_type, EntryType::Bridge));
+    assert_eq!(reloaded[1].bridge.as_ref().unwrap().linked_entry_id, Some(bought.id));
+
+    // Sync state goes in with the transactions it covers
+    assert!(store.get_sync_state(WALLET, &Chain::Solana).await.unwrap().is_none());
+    let later = vec![tx(JAN_5_2024 + 7_200, "later")];
+    let mut state = SyncState::new(WALLET, Chain::Solana);
+    state.advance(&later);
+    store.save_ingested(&later, &state).await.unwrap();
+
+    let synced = store.get_sync_state(WALLET, &Chain::Solana).await.unwrap().unwrap();
+    assert_eq!(synced.last_timestamp, Some(JAN_5_2024 + 7_200));
+    assert_eq!(synced.last_tx_hashes, ["later"]);
+    assert!(store.get_sync_state(WALLET, &Chain::Ethereum).await.unwrap().is_none());
+    assert_eq!(store.get_transactions_by_wallet(WALLET).await.unwrap().len(), 3);
+
+    // Replacing a transaction's entries leaves the others alone
+    let resent = LedgerEntry::new(&send, "SOL".to_string(), dec("-4.5"), EntryType::Transfer);
+    store.replace_ledger_entries(&[send.id], std::slice::from_ref(&resent)).await.unwrap();
+    let replaced = store.get_ledger_entries_by_wallet(WALLET).await.unwrap();
+    assert_eq!(replaced.iter().map(|e| e.id).collect::<Vec<_>>(), [bought.id, resent.id]);
+    assert_eq!(replaced[1].amount, dec("-4.5"));
+}
+
+#[tokio::test]
+async fn test_memory_store_round_trip() {
+    round_trip(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_store_round_trip() {
+    round_trip(&SqliteStore::connect("sqlite::memory:").await.unwrap()).await;
+}
+
+// Walks a wallet page by page and checks each filter narrows the same way on every backend
+async fn paging(store: &dyn Storage) {
+    // Three transactions share a second, so ordering within it falls back to id
+    let mut txs: Vec<Transaction> = (0..5).map(|i| tx(JAN_5_2024 + (i / 3) * 60, &format!("tx{}", i))).collect();
+    txs[4].chain = Chain::Ethereum;
+    let mut entries = Vec::new();
+    for (i, t) in txs.iter().enumerate() {
+        let asset = if i % 2 == 0 { "SOL" } else { "USDC" };
+        entries.push(LedgerEntry::new(t, asset.to_string(), dec("1"), EntryType::Trade));
+        entries.push(LedgerEntry::new(t, "SOL".to_string(), dec("-0.001"), EntryType::Fee));
+    }
+    store.save_transactions_with_entries(&txs, &entries).await.unwrap();
+
+    let everything = QueryFilter::default();
+    let mut seen = Vec::new();
+    let mut after = None;
+    loop {
+        let page = store.page_ledger_entries(WALLET, &everything, after, 3).await.unwrap();
+        assert!(page.items.len() <= 3);
+        seen.extend(page.items.iter().map(|e| Cursor { timestamp: e.timestamp, id: e.id }));
+        match page.next_cursor {
+            Some(next) => after = Some(Cursor::decode(&next).unwrap()),
+            None => break,
+        }
+    }
+    let mut expected: Vec<Cursor> = entries.iter().map(|e| Cursor { timestamp: e.timestamp, id: e.id }).collect();
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    // A page that ends exactly at the last row has no next cursor
+    let all = store.page_transactions(WALLET, &everything, None, 5).await.unwrap();
+    assert_eq!(all.items.len(), 5);
+    assert!(all.next_cursor.is_none());
+    let first = store.page_transactions(WALLET, &everything, None, 2).await.unwrap();
+    let rest = store.page_transactions(WALLET, &everything, Some(Cursor::decode(first.next_cursor.as_ref().unwrap()).unwrap()), 10).await.unwrap();
+    let hashes = |page: &[Transaction]| page.iter().map(|t| t.tx_hash.clone()).collect::<Vec<_>>();
+    assert_eq!([hashes(&first.items), hashes(&rest.items)].concat(), hashes(&all.items));
+
+    let later = QueryFilter { from: Some(JAN_5_2024 + 60), ..Default::default() };
+    let mut late = hashes(&store.page_transactions(WALLET, &later, None, 10).await.unwrap().items);
+    late.sort();
+    assert_eq!(late, ["tx3", "tx4"]);
+    let earlier = QueryFilter { to: Some(JAN_5_2024 + 60), ..Default::default() };
+    assert_eq!(store.page_ledger_entries(WALLET, &earlier, None, 10).await.unwrap().items.len(), 6);
+
+    // Entry filters pick the transactions they belong to, transaction filters the entries
+    let usdc = QueryFilter { asset: Some("USDC".to_string()), ..Default::default() };
+    assert_eq!(hashes(&store.page_transactions(WALLET, &usdc, None, 10).await.unwrap().items), ["tx1", "tx3"]);
+    let fees = QueryFilter { entry_type: Some(EntryType::Fee), ..Default::default() };
+    assert_eq!(store.page_transactions(WALLET, &fees, None, 10).await.unwrap().items.len(), 5);
+    assert!(store.page_ledger_entries(WALLET, &fees, None, 10).await.unwrap().items.iter().all(|e| matches!(e.entry_type, EntryType::Fee)));
+    let ethereum = QueryFilter { chain: Some(Chain::Ethereum), ..Default::default() };
+    let on_ethereum = store.page_ledger_entries(WALLET, &ethereum, None, 10).await.unwrap().items;
+    assert_eq!(on_ethereum.len(), 2);
+    assert!(on_ethereum.iter().all(|e| e.transaction_id == txs[4].id));
+    let by_hash = QueryFilter { tx_hash: Some("tx2".to_string()), asset: Some("SOL".to_string()), ..Default::default() };
+    assert_eq!(store.page_ledger_entries(WALLET, &by_hash, None, 10).await.unwrap().items.len(), 2);
+    assert!(store.page_ledger_entries("SomeoneElse", &everything, None, 10).await.unwrap().items.is_empty());
+}
+
+#[tokio::test]
+async fn test_memory_store_paging() {
+    paging(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_store_paging() {
+    paging(&SqliteStore::connect("sqlite::memory:").await.unwrap()).await;
+}
+
+// FX rates, spam decisions and settings, which reports need on every backend
+async fn preferences(store: &dyn Storage) {
+    let user = Uuid::new_v4();
+    let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+    let rate = |d: u32, r: &str| FxRate {
+        base_currency: "USD".to_string(),
+        quote_currency: "GBP".to_string(),
+        rate_date: day(d),
+        rate: dec(r),
+        source: "test".to_string(),
+    };
+
+    store.save_fx_rates(&[rate(2, "0.80"), rate(4, "0.79")]).await.unwrap();
+    store.save_fx_rates(&[rate(4, "0.78")]).await.unwrap();
+    assert!(store.rate_on("USD", "GBP", day(1)).await.unwrap().is_none());
+    assert_eq!(store.rate_on("USD", "GBP", day(3)).await.unwrap(), Some(dec("0.80")));
+    assert_eq!(store.rate_on("USD", "GBP", day(9)).await.unwrap(), Some(dec("0.78")));
+    let back = fx::convert(store, &dec("78"), "GBP", "USD", day(5)).await.unwrap();
+    assert_eq!(back, Some(dec("100")));
+
+    assert_eq!(store.reporting_currency(Some(user)).await.unwrap(), "USD");
+    store.save_user_settings(&UserSettings { user_id: user, reporting_currency: "GBP".to_string() }).await.unwrap();
+    store.save_user_settings(&UserSettings { user_id: user, reporting_currency: "EUR".to_string() }).await.unwrap();
+    assert_eq!(store.reporting_currency(Some(user)).await.unwrap(), "EUR");
+    assert!(store.get_user_settings(Uuid::new_v4()).await.unwrap().is_none());
+
+    for (asset, is_spam) in [("ScamMint", true), ("ScamMint", false), ("OtherMint", true)] {
+        store.save_spam_override(&SpamOverride { user_id: user, asset_symbol: asset.to_string(), is_spam }).await.unwrap();
+    }
+    let overrides = store.get_spam_overrides(user).await.unwrap();
+    assert_eq!(overrides.len(), 2);
+    assert_eq!(overrides.get("ScamMint"), Some(&false));
+    assert!(store.delete_spam_override(user, "OtherMint").await.unwrap());
+    assert!(!store.delete_spam_override(user, "OtherMint").await.unwrap());
+    assert!(store.get_spam_overrides(Uuid::new_v4()).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_memory_store_preferences() {
+    preferences(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_store_preferences() {
+    preferences(&SqliteStore::connect("sqlite::memory:").await.unwrap()).await;
+}
+
+// Adjustments write through to the entry and, like manual entries, leave an audit trail
+async fn adjustments(store: &dyn Storage) {
+    let user = Uuid::nil();
+    let buy = tx(JAN_5_2024, "buy");
+    let mut bought = LedgerEntry::new(&buy, "SOL".to_string(), dec("2"), EntryType::Transfer);
+    bought.fiat_value = Some(dec("180"));
+    bought.fiat_currency = Some("USD".to_string());
+    store.save_transactions_with_entries(&[buy], std::slice::from_ref(&bought)).await.unwrap();
+
+    let changes = [
+        Change::Reclassify { entry_type: EntryType::Income },
+        Change::SetFiatValue { fiat_value: dec("210.5"), fiat_currency: "EUR".to_string() },
+        Change::Annotate { note: Some("Birthday gift".to_string()), tags: Some(vec!["gift".to_string()]) },
+    ];
+    for change in &changes {
+        assert!(store.adjust_entry(user, bought.id, change).await.unwrap().is_some());
+    }
+    assert!(store.adjust_entry(Uuid::new_v4(), bought.id, &changes[0]).await.unwrap().is_none());
+
+    let stored = store.get_ledger_entries_by_wallet(WALLET).await.unwrap();
+    assert_eq!(stored[0].entry_type.name(), "income");
+    assert_eq!(stored[0].fiat_value, Some(dec("210.5")));
+    assert_eq!(stored[0].fiat_currency.as_deref(), Some("EUR"));
+    assert_eq!(stored[0].price_source.as_deref(), Some(MANUAL_PRICE_SOURCE));
+
+    let adjusted = store.get_adjustments_by_wallet(WALLET).await.unwrap();
+    let adjustment = &adjusted[&bought.id];
+    assert_eq!(adjustment.entry_type.as_ref().map(|t| t.name()), Some("income"));
+    assert_eq!(adjustment.note.as_deref(), Some("Birthday gift"));
+    assert_eq!(adjustment.tags, ["gift"]);
+
+    let manual = ManualEntry {
+        wallet_address: WALLET.to_string(),
+        timestamp: JAN_5_2024 + 60,
+        asset_symbol: "SOL".to_string(),
+        amount: dec("-1"),
+        entry_type: EntryType::Trade,
+        fiat_value: Some(dec("100")),
+        fiat_currency: Some("USD".to_string()),
+    };
+    let created = normalize::create_manual_entry(store, user, &manual).await.unwrap();
+    assert_eq!(created.parser.as_ref().map(|p| p.name.as_str()), Some("manual"));
+    assert_eq!(store.get_ledger_entries_by_wallet(WALLET).await.unwrap().len(), 2);
+
+    let trail = store.get_audit_trail(user, None).await.unwrap();
+    assert_eq!(trail.len(), 4);
+    assert!(trail.iter().any(|r| r.entry_id == created.id && r.previous.is_null()));
+    let trail = store.get_audit_trail(user, Some(bought.id)).await.unwrap();
+    assert_eq!(trail.len(), 3);
+    assert!(trail.iter().any(|r| r.previous == json!({ "fiat_value": "180", "fiat_currency": "USD" })));
+}
+
+#[tokio::test]
+async fn test_memory_store_adjustments() {
+    adjustments(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_store_adjustments() {
+    adjustments(&SqliteStore::connect("sqlite::memory:").await.unwrap()).await;
+}
+
+// What the balance series is built from: the owner for the reporting currency and non-spam movements per UTC day
+async fn balance_changes(store: &dyn Storage) {
+    let user = Uuid::new_v4();
+    assert!(store.get_wallet_owner(WALLET).await.unwrap().is_none());
+
+    let mut buy = tx(JAN_5_2024 + 60, "buy");
+    buy.user_id = user;
+    let mut sell = tx(JAN_5_2024 + 3_600, "sell");
+    sell.user_id = user;
+    let mut later = tx(JAN_5_2024 + 2 * 86_400, "later");
+    later.user_id = user;
+    let entries = vec![
+        LedgerEntry::new(&buy, "SOL".to_string(), dec("10"), EntryType::Trade),
+        LedgerEntry::new(&sell, "SOL".to_string(), dec("-4"), EntryType::Trade),
+        LedgerEntry::new(&sell, "USDC".to_string(), dec("400"), EntryType::Trade),
+        LedgerEntry { is_spam: true, ..LedgerEntry::new(&sell, "ScamMint".to_string(), dec("1000"), EntryType::Transfer) },
+        LedgerEntry::new(&later, "SOL".to_string(), dec("-1"), EntryType::Fee),
+    ];
+    store.save_transactions_with_entries(&[buy, sell, later], &entries).await.unwrap();
+    assert_eq!(store.get_wallet_owner(WALLET).await.unwrap(), Some(user));
+
+    let jan_5 = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap();
+    let mut changes: Vec<(NaiveDate, String, BigDecimal)> = store
+        .get_daily_balance_changes(WALLET, jan_5)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.day, c.asset_symbol, c.net_change))
+        .collect();
+    changes.sort();
+    assert_eq!(changes, vec![(jan_5, "SOL".to_string(), dec("6")), (jan_5, "USDC".to_string(), dec("400"))]);
+
+    let jan_7 = NaiveDate::from_ymd_opt(2024, 1, 7).unwrap();
+    let changes = store.get_daily_balance_changes(WALLET, jan_7).await.unwrap();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes.last().map(|c| (c.day, c.net_change.clone())), Some((jan_7, dec("-1"))));
+}
+
+#[tokio::test]
+async fn test_memory_store_balance_changes() {
+    balance_changes(&MemoryStore::new()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_store_balance_changes() {
+    balance_changes(&SqliteStore::connect("sqlite::memory:").await.unwrap()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_save_is_all_or_nothing() {
+    let store = SqliteStore::connect("sqlite::memory:").await.unwrap();
+    let saved = tx(JAN_5_2024, "saved");
+    let orphan = LedgerEntry::new(&tx(JAN_5_2024, "never stored"), "SOL".to_string(), dec("1"), EntryType::Trade);
+
+    // The entry references a transaction that isn't being saved, so neither goes in
+    assert!(store.save_transactions_with_entries(&[saved], &[orphan]).await.is_err());
+    assert!(store.get_transactions_by_wallet(WALLET).await.unwrap().is_empty());
+    assert!(store.get_ledger_entries_by_wallet(WALLET).await.unwrap().is_empty());
+}
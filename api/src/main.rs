@@ -1,19 +1,40 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    routing::{get, post},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
     Json, Router,
 };
 use serde::Deserialize;
-use spectraplex_adapters::{repo::Repository, solana::SolanaAdapter, solana_parser};
-use spectraplex_core::models::{ChainIngestor, LedgerEntry, Transaction};
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use spectraplex_adapters::{ethereum::{self, EthereumAdapter}, evm::{self, EvmNetwork}, hyperliquid::HyperliquidAdapter, normalize, repo::Repository, solana::SolanaAdapter, solana_parser, sqlite::SqliteStore};
+use spectraplex_adapters::export::{self, ExportFormat};
+use spectraplex_core::models::{Chain, ChainIngestor, EntryType, LedgerEntry, ParserVersion, Transaction, UserSettings};
+use spectraplex_core::balances::{self, BalanceSeries, Granularity};
+use spectraplex_core::reconcile::{self, BalanceSource, ReconciliationReport};
+use spectraplex_core::{form_8949::{self, Form8949Report}, fx, income, portfolio::{self, PortfolioSnapshot}, pricing::{self, PricingSummary}};
+use spectraplex_core::spam::{self, SpamLists, SpamOverride};
+use spectraplex_core::uk_pooling::{self, Sa108Report};
+use spectraplex_core::lending::{self, LendingPosition};
+use spectraplex_core::bridge::{self, LinkSummary};
+use spectraplex_core::renormalize::{self, Renormalization};
+use spectraplex_core::adjustments::{Adjustment, AuditRecord, Change, ManualEntry};
+use spectraplex_core::storage::{self, Cursor, Page, QueryFilter, Storage, SyncState};
+use sqlx::postgres::PgPoolOptions;
+use chrono::NaiveDate;
+use bigdecimal::BigDecimal;
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use uuid::Uuid;
 
-// App State to share DB Pool
+// Hardcoded limit for API safety
+const INGEST_LIMIT: usize = 50;
+
+// App State to share the storage backend
 struct AppState {
-    pool: PgPool,
+    store: Arc<dyn Storage>,
+    networks: Vec<EvmNetwork>,
+    spam_lists: SpamLists,
 }
 
 #[tokio::main]
@@ -21,20 +42,54 @@ async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
     tracing_subscriber::fmt::init();
 
+    // A sqlite: URL runs the API on an embedded file instead of Postgres, like the CLI's --db-url
     let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&database_url)
-        .await?;
+    let store: Arc<dyn Storage> = if database_url.starts_with("sqlite:") {
+        Arc::new(SqliteStore::connect(&database_url).await?)
+    } else {
+        let pool = PgPoolOptions::new()
+            .max_connections(10)
+            .connect(&database_url)
+            .await?;
+        Arc::new(Repository::new(pool))
+    };
+
+    // Optional JSON file of extra EVM networks, same format as the CLI's --evm-networks
+    let networks_path = std::env::var("EVM_NETWORKS").ok().map(std::path::PathBuf::from);
+    let networks = evm::load_networks(networks_path.as_deref())?;
+
+    // Optional spam deny/allow list files, same format as the CLI's --spam-denylist/--spam-allowlist
+    let denylist = std::env::var("SPAM_DENYLIST").ok().map(std::path::PathBuf::from);
+    let allowlist = std::env::var("SPAM_ALLOWLIST").ok().map(std::path::PathBuf::from);
+    let spam_lists = SpamLists::load(denylist.as_deref(), allowlist.as_deref())?;
 
-    let shared_state = Arc::new(AppState { pool });
+    let shared_state = Arc::new(AppState { store, networks, spam_lists });
 
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/v1/networks", get(get_networks))
         .route("/v1/ingest", post(trigger_ingest))
         .route("/v1/normalize", post(trigger_normalize))
+        .route("/v1/renormalize", post(trigger_renormalize))
+        .route("/v1/price", post(trigger_price))
         .route("/v1/transactions/:wallet", get(get_transactions))
         .route("/v1/ledger/:wallet", get(get_ledger))
+        .route("/v1/reports/8949", get(get_form_8949))
+        .route("/v1/reports/sa108", get(get_sa108))
+        .route("/v1/reports/income", get(get_income_report))
+        .route("/v1/export/:format", get(get_export))
+        .route("/v1/portfolio/:wallet", get(get_portfolio))
+        .route("/v1/positions/:wallet", get(get_positions))
+        .route("/v1/balances/:wallet", get(get_balances))
+        .route("/v1/reconcile/:wallet", get(get_reconciliation))
+        .route("/v1/users/:user_id/settings", get(get_settings).put(update_settings))
+        .route("/v1/users/:user_id/spam", get(get_spam_overrides))
+        .route("/v1/users/:user_id/spam/:asset", put(set_spam_override).delete(delete_spam_override))
+        .route("/v1/users/:user_id/bridges/link", post(link_bridges))
+        .route("/v1/users/:user_id/entries", post(create_manual_entry))
+        .route("/v1/users/:user_id/entries/:entry_id/adjustments", post(adjust_entry))
+        .route("/v1/users/:user_id/audit", get(get_audit_trail))
+        .route("/v1/adjustments/:wallet", get(get_adjustments))
         .with_state(shared_state);
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
@@ -52,9 +107,15 @@ async fn health_check() -> &'static str {
 // Request Models
 #[derive(Deserialize)]
 struct IngestRequest {
-    _chain: String,
+    #[serde(alias = "_chain")]
+    chain: String,
     wallet: String,
     rpc_url: String,
+    // EVM block window; defaults to the latest DEFAULT_SCAN_BLOCKS blocks
+    #[serde(default)]
+    from_block: Option<u64>,
+    #[serde(default)]
+    scan_blocks: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -62,8 +123,130 @@ struct NormalizeRequest {
     wallet: String,
 }
 
+#[derive(Deserialize)]
+struct RenormalizeRequest {
+    wallet: String,
+    #[serde(default)]
+    dry_run: bool, // Return the diff without writing it
+}
+
+#[derive(Deserialize)]
+struct PriceRequest {
+    wallet: String,
+    #[serde(default = "default_fiat")]
+    fiat: String,
+    #[serde(default)]
+    dex_implied: bool,
+    propagate_window: Option<String>,
+}
+
+fn default_fiat() -> String {
+    "USD".to_string()
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    from: Option<i64>, // Unix timestamp, inclusive
+    to: Option<i64>,   // Unix timestamp, exclusive
+    asset: Option<String>,
+    entry_type: Option<String>, // e.g. "trade" or "lp_token"
+    chain: Option<String>,
+    tx_hash: Option<String>,
+    cursor: Option<String>, // `next_cursor` from the previous page
+    limit: Option<usize>,
+}
+
+impl PageQuery {
+    fn parse(self) -> Result<(QueryFilter, Option<Cursor>, usize), StatusCode> {
+        let entry_type = self.entry_type.map(|n| EntryType::parse(&n)).transpose().map_err(|_| StatusCode::BAD_REQUEST)?;
+        let after = self.cursor.map(|c| Cursor::decode(&c)).transpose().map_err(|_| StatusCode::BAD_REQUEST)?;
+        let filter = QueryFilter {
+            from: self.from,
+            to: self.to,
+            asset: self.asset,
+            entry_type,
+            chain: self.chain.map(|c| Chain::from_name(&c)),
+            tx_hash: self.tx_hash,
+        };
+        let limit = self.limit.unwrap_or(storage::DEFAULT_PAGE_SIZE).clamp(1, storage::MAX_PAGE_SIZE);
+        Ok((filter, after, limit))
+    }
+}
+
+#[derive(Deserialize)]
+struct ReportQuery {
+    wallet: Option<String>,
+    user_id: Option<Uuid>,
+    tax_year: i32,
+    currency: Option<String>, // Defaults to the user's reporting currency
+    #[serde(default)]
+    include_spam: bool,
+}
+
+#[derive(Deserialize)]
+struct IncomeQuery {
+    wallet: Option<String>,
+    user_id: Option<Uuid>,
+    tax_year: i32,
+    currency: Option<String>,
+    format: Option<String>, // json (default) or csv
+    #[serde(default)]
+    include_spam: bool,
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    wallet: Option<String>,
+    user_id: Option<Uuid>,
+    #[serde(default)]
+    include_spam: bool,
+}
+
+#[derive(Deserialize)]
+struct PortfolioQuery {
+    at: Option<i64>, // Unix timestamp, defaults to now
+    currency: Option<String>,
+    #[serde(default)]
+    include_spam: bool,
+}
+
+#[derive(Deserialize)]
+struct BalancesQuery {
+    from: Option<NaiveDate>, // Defaults to 30 days before `to`
+    to: Option<NaiveDate>,   // Defaults to today (UTC)
+    granularity: Option<String>,
+    currency: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ReconcileQuery {
+    rpc_url: String,
+    tolerance: Option<BigDecimal>,
+}
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    entry_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+struct SettingsRequest {
+    reporting_currency: String,
+}
+
+#[derive(Deserialize)]
+struct SpamOverrideRequest {
+    is_spam: bool,
+}
+
 // Handlers
 
+async fn get_networks(State(state): State<Arc<AppState>>) -> Json<Vec<EvmNetwork>> {
+    // Configured RPC URLs often embed provider API keys
+    let networks = state.networks.iter().cloned().map(|mut n| { n.rpc_url = None; n }).collect();
+    Json(networks)
+}
+
 async fn trigger_ingest(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<IngestRequest>,
@@ -71,61 +254,512 @@ async fn trigger_ingest(
     // In a real system, this should spawn a background task or push to a queue (e.g. Redis/bullmq)
     // For prototype, we'll just run it inline (blocking the request until done - not ideal for prod but ok for demo)
     
-    let adapter = SolanaAdapter::new(&payload.rpc_url);
-    // Hardcoded limit for API safety
-    let events = adapter.fetch_history(&payload.wallet, 50).await.map_err(|e| {
+    let scan_blocks = payload.scan_blocks.unwrap_or(ethereum::DEFAULT_SCAN_BLOCKS);
+    if scan_blocks == 0 || scan_blocks > ethereum::MAX_SCAN_BLOCKS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let events = match payload.chain.as_str() {
+        "hyperliquid" => HyperliquidAdapter::new(&payload.rpc_url).fetch_history(&payload.wallet, INGEST_LIMIT).await,
+        "solana" => SolanaAdapter::new(&payload.rpc_url).fetch_history(&payload.wallet, INGEST_LIMIT).await,
+        name => match evm::find_network(&state.networks, name) {
+            Some(network) => EthereumAdapter::for_network(network.clone(), &payload.rpc_url, payload.from_block)
+                .with_scan_blocks(scan_blocks)
+                .fetch_history(&payload.wallet, INGEST_LIMIT).await,
+            // Neither built in nor a configured EVM network
+            None => return Err(StatusCode::BAD_REQUEST),
+        },
+    }
+    .map_err(|e| {
         eprintln!("Ingest Error: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let repo = Repository::new(state.pool.clone());
-    repo.save_transactions(&events).await.map_err(|e| {
+    // Only what's past the last ingest is stored, as in the CLI: refetched history gets fresh ids every time
+    let store = state.store.as_ref();
+    let chain = Chain::from_name(&payload.chain);
+    let mut sync = store.get_sync_state(&payload.wallet, &chain).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .unwrap_or_else(|| SyncState::new(&payload.wallet, chain));
+    let fetched = events.len();
+    let events: Vec<Transaction> = events.into_iter().filter(|t| sync.is_new(t)).collect();
+    // A full page of nothing but new transactions may not reach back to the last ingest
+    if sync.last_timestamp.is_some() && fetched == INGEST_LIMIT && events.len() == fetched {
+        eprintln!("Ingest Error: more than {} transactions for {} since the last sync", INGEST_LIMIT, payload.wallet);
+        return Err(StatusCode::CONFLICT);
+    }
+    sync.advance(&events);
+    store.save_ingested(&events, &sync).await.map_err(|e| {
         eprintln!("DB Error: {}", e);
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    Ok(Json(format!("Ingested {} transactions", events.len())))
+    Ok(Json(format!("Ingested {} new transactions ({} already stored)", events.len(), fetched - events.len())))
 }
 
 async fn trigger_normalize(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<NormalizeRequest>,
 ) -> Result<Json<String>, StatusCode> {
-    let repo = Repository::new(state.pool.clone());
+    let store = state.store.as_ref();
     
-    let txs = repo.get_transactions_by_wallet(&payload.wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let txs = store.get_transactions_by_wallet(&payload.wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
     let mut all_entries = Vec::new();
 
     for tx in txs {
-        let entries = match tx.chain {
-            spectraplex_core::models::Chain::Solana => {
-                solana_parser::parse_solana_transaction(&tx).unwrap_or_default()
-            },
-            _ => vec![]
-        };
-        all_entries.extend(entries);
+        all_entries.extend(normalize::parse_transaction(&tx).unwrap_or_default());
     }
+    lending::track_positions(&mut all_entries);
 
-    repo.save_ledger_entries(&all_entries).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Some(user_id) = all_entries.first().map(|e| e.user_id) {
+        let overrides = store.get_spam_overrides(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        spam::classify(&mut all_entries, &state.spam_lists, &overrides);
+    }
+    store.save_ledger_entries(&all_entries).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(format!("Normalized {} ledger entries", all_entries.len())))
 }
 
+// Unlike normalize, a transaction that fails to parse fails the whole request, rather than wiping its entries
+async fn trigger_renormalize(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RenormalizeRequest>,
+) -> Result<Json<Renormalization>, StatusCode> {
+    let store = state.store.as_ref();
+    let txs = store.get_transactions_by_wallet(&payload.wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut fresh = Vec::new();
+    for tx in &txs {
+        let entries = normalize::parse_transaction(tx).map_err(|e| {
+            eprintln!("Parse Error: {}: {}", tx.tx_hash, e);
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+        fresh.extend(entries);
+    }
+    lending::track_positions(&mut fresh);
+    if let Some(user_id) = fresh.first().map(|e| e.user_id) {
+        let overrides = store.get_spam_overrides(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        spam::classify(&mut fresh, &state.spam_lists, &overrides);
+    }
+
+    let current: HashMap<Uuid, ParserVersion> = txs.iter().map(|t| (t.id, normalize::parser_for(&t.chain))).collect();
+    let stored = store.get_ledger_entries_by_wallet(&payload.wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let adjusted = store.get_adjustments_by_wallet(&payload.wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let plan = renormalize::plan(&stored, fresh, &current, &adjusted);
+
+    if !payload.dry_run && !plan.transaction_ids.is_empty() {
+        store.replace_ledger_entries(&plan.transaction_ids, &plan.entries).await.map_err(|e| {
+            eprintln!("DB Error: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    }
+    Ok(Json(plan))
+}
+
+async fn trigger_price(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<PriceRequest>,
+) -> Result<Json<PricingSummary>, StatusCode> {
+    let store = state.store.as_ref();
+    let mut entries = store.get_ledger_entries_by_wallet(&payload.wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if payload.dex_implied {
+        let quote_assets = vec!["SOL".to_string(), solana_parser::USDC_MINT.to_string()];
+        let observations = pricing::implied_prices(store, &entries, &payload.fiat, &quote_assets).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        store.save_price_candles(&observations).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let window = payload
+        .propagate_window
+        .as_deref()
+        .map(pricing::parse_resolution)
+        .transpose()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let summary = pricing::price_entries(store, &mut entries, &payload.fiat, window).await.map_err(|e| {
+        eprintln!("Pricing Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    store.update_ledger_valuations(&entries).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Newly priced tokens are no longer unvalued airdrops
+    if let Some(user_id) = entries.first().map(|e| e.user_id) {
+        let overrides = store.get_spam_overrides(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        spam::classify(&mut entries, &state.spam_lists, &overrides);
+        store.update_spam_flags(&entries).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    Ok(Json(summary))
+}
+
 async fn get_transactions(
     State(state): State<Arc<AppState>>,
     Path(wallet): Path<String>,
-) -> Result<Json<Vec<Transaction>>, StatusCode> {
-    let repo = Repository::new(state.pool.clone());
-    let txs = repo.get_transactions_by_wallet(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(txs))
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<Transaction>>, StatusCode> {
+    let (filter, after, limit) = query.parse()?;
+    let store = state.store.as_ref();
+    let page = store.page_transactions(&wallet, &filter, after, limit).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(page))
 }
 
 async fn get_ledger(
     State(state): State<Arc<AppState>>,
     Path(wallet): Path<String>,
-) -> Result<Json<Vec<LedgerEntry>>, StatusCode> {
-    let repo = Repository::new(state.pool.clone());
-    let entries = repo.get_ledger_entries_by_wallet(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(entries)) 
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page<LedgerEntry>>, StatusCode> {
+    let (filter, after, limit) = query.parse()?;
+    let store = state.store.as_ref();
+    let page = store.page_ledger_entries(&wallet, &filter, after, limit).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(page))
+}
+
+async fn get_form_8949(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<Form8949Report>, StatusCode> {
+    let store = state.store.as_ref();
+    let (entries, currency) = report_entries(store, query.wallet.as_deref(), query.user_id, query.currency.as_deref(), query.include_spam).await?;
+
+    // Assets with unvalued entries come back in unvalued_assets rather than failing the report
+    let report = form_8949::from_entries(&entries, query.tax_year, &currency).map_err(|e| {
+        eprintln!("Report Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(report))
+}
+
+// UK return for the tax year starting in tax_year (2024 for 2024-25), always in GBP
+async fn get_sa108(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ReportQuery>,
+) -> Result<Json<Sa108Report>, StatusCode> {
+    let store = state.store.as_ref();
+    let currency = query.currency.as_deref().unwrap_or("GBP");
+    let (entries, currency) = report_entries(store, query.wallet.as_deref(), query.user_id, Some(currency), query.include_spam).await?;
+    if currency != "GBP" {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let report = uk_pooling::sa108_report(&entries, query.tax_year).map_err(|e| {
+        eprintln!("Report Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(report))
+}
+
+async fn get_income_report(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<IncomeQuery>,
+) -> Result<Response, StatusCode> {
+    let store = state.store.as_ref();
+    let (entries, currency) = report_entries(store, query.wallet.as_deref(), query.user_id, query.currency.as_deref(), query.include_spam).await?;
+
+    let report = income::build(&entries, query.tax_year, &currency).map_err(|e| {
+        eprintln!("Report Error: {}", e);
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(report).into_response()),
+        "csv" => {
+            let mut body = Vec::new();
+            income::write_csv(&report, &mut body).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let disposition = format!("attachment; filename=\"income_{}.csv\"", query.tax_year);
+            Ok(([(header::CONTENT_TYPE, "text/csv".to_string()), (header::CONTENT_DISPOSITION, disposition)], body).into_response())
+        }
+        _ => Err(StatusCode::BAD_REQUEST),
+    }
+}
+
+async fn get_export(
+    State(state): State<Arc<AppState>>,
+    Path(format): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> Result<Response, StatusCode> {
+    let format: ExportFormat = format.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+    let store = state.store.as_ref();
+    let entries = match (&query.wallet, query.user_id) {
+        (Some(wallet), _) => store.get_ledger_entries_by_wallet(wallet).await,
+        (None, Some(user_id)) => store.get_ledger_entries_by_user(user_id).await,
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let entries = spam::filter(entries, query.include_spam);
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.transaction_id).collect();
+    let tx_hashes = store.get_tx_hashes(&ids).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let rows = export::build_rows(&entries, &tx_hashes);
+    let mut body = Vec::new();
+    export::write_csv(format, &rows, &mut body).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disposition = format!("attachment; filename=\"{}\"", format.file_name());
+    Ok(([(header::CONTENT_TYPE, "text/csv".to_string()), (header::CONTENT_DISPOSITION, disposition)], body).into_response())
+}
+
+// Entries for a wallet or user, converted into the requested (or the user's) reporting currency
+async fn report_entries(
+    store: &dyn Storage,
+    wallet: Option<&str>,
+    user_id: Option<Uuid>,
+    currency: Option<&str>,
+    include_spam: bool,
+) -> Result<(Vec<LedgerEntry>, String), StatusCode> {
+    let entries = match (wallet, user_id) {
+        (Some(wallet), _) => store.get_ledger_entries_by_wallet(wallet).await,
+        (None, Some(user_id)) => store.get_ledger_entries_by_user(user_id).await,
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let currency = match currency {
+        Some(c) => fx::normalize_currency(c).map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => store
+            .reporting_currency(user_id.or_else(|| entries.first().map(|e| e.user_id)))
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    };
+    // Spam goes first so a junk token without an FX rate can't fail the report
+    let mut entries = spam::filter(entries, include_spam);
+    fx::convert_entries(store, &mut entries, &currency).await.map_err(|e| {
+        eprintln!("FX Error: {}", e);
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+
+    Ok((entries, currency))
+}
+
+async fn get_settings(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserSettings>, StatusCode> {
+    let store = state.store.as_ref();
+    let reporting_currency = store.reporting_currency(Some(user_id)).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(UserSettings { user_id, reporting_currency }))
+}
+
+async fn update_settings(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<SettingsRequest>,
+) -> Result<Json<UserSettings>, StatusCode> {
+    let settings = UserSettings {
+        user_id,
+        reporting_currency: fx::normalize_currency(&payload.reporting_currency).map_err(|_| StatusCode::BAD_REQUEST)?,
+    };
+    let store = state.store.as_ref();
+    store.save_user_settings(&settings).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(settings))
+}
+
+async fn get_spam_overrides(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<Vec<SpamOverride>>, StatusCode> {
+    let store = state.store.as_ref();
+    let overrides = store.get_spam_overrides(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut overrides: Vec<SpamOverride> = overrides
+        .into_iter()
+        .map(|(asset_symbol, is_spam)| SpamOverride { user_id, asset_symbol, is_spam })
+        .collect();
+    overrides.sort_by(|a, b| a.asset_symbol.cmp(&b.asset_symbol));
+    Ok(Json(overrides))
+}
+
+async fn set_spam_override(
+    State(state): State<Arc<AppState>>,
+    Path((user_id, asset)): Path<(Uuid, String)>,
+    Json(payload): Json<SpamOverrideRequest>,
+) -> Result<Json<SpamOverride>, StatusCode> {
+    let o = SpamOverride { user_id, asset_symbol: asset, is_spam: payload.is_spam };
+    let store = state.store.as_ref();
+    store.save_spam_override(&o).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    reclassify_spam(store, &state.spam_lists, user_id).await?;
+    Ok(Json(o))
+}
+
+async fn delete_spam_override(
+    State(state): State<Arc<AppState>>,
+    Path((user_id, asset)): Path<(Uuid, String)>,
+) -> Result<StatusCode, StatusCode> {
+    let store = state.store.as_ref();
+    if !store.delete_spam_override(user_id, &asset).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)? {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    reclassify_spam(store, &state.spam_lists, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Re-runs spam classification over all of a user's entries after their overrides change
+async fn reclassify_spam(store: &dyn Storage, lists: &SpamLists, user_id: Uuid) -> Result<(), StatusCode> {
+    let mut entries = store.get_ledger_entries_by_user(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let overrides = store.get_spam_overrides(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    spam::classify(&mut entries, lists, &overrides);
+    store.update_spam_flags(&entries).await.map_err(|e| {
+        eprintln!("DB Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn get_portfolio(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<PortfolioQuery>,
+) -> Result<Json<PortfolioSnapshot>, StatusCode> {
+    let store = state.store.as_ref();
+    let entries = store.get_ledger_entries_by_wallet(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let currency = match &query.currency {
+        Some(c) => fx::normalize_currency(c).map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => store
+            .reporting_currency(entries.first().map(|e| e.user_id))
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    };
+    let mut entries = spam::filter(entries, query.include_spam);
+    fx::convert_entries(store, &mut entries, &currency).await.map_err(|e| {
+        eprintln!("FX Error: {}", e);
+        StatusCode::UNPROCESSABLE_ENTITY
+    })?;
+
+    let at = query.at.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let snapshot = portfolio::snapshot(store, store, &entries, at, &currency).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(snapshot))
+}
+
+async fn get_positions(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+) -> Result<Json<Vec<LendingPosition>>, StatusCode> {
+    let store = state.store.as_ref();
+    let entries = store.get_ledger_entries_by_wallet(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(lending::positions(&entries)))
+}
+
+// Pairs bridge transfers across all of the user's wallets, so needs everything they've normalized
+async fn link_bridges(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<LinkSummary>, StatusCode> {
+    let store = state.store.as_ref();
+    let mut entries = store.get_ledger_entries_by_user(user_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ids: Vec<Uuid> = entries.iter().map(|e| e.transaction_id).collect();
+    let chains = store.get_transaction_chains(&ids).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let summary = bridge::link_transfers(&mut entries, &chains);
+    store.update_bridge_links(&entries).await.map_err(|e| {
+        eprintln!("DB Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(summary))
+}
+
+async fn create_manual_entry(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<ManualEntry>,
+) -> Result<Json<LedgerEntry>, StatusCode> {
+    let manual = payload.validated().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let store = state.store.as_ref();
+    let entry = normalize::create_manual_entry(store, user_id, &manual).await.map_err(|e| {
+        eprintln!("DB Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(entry))
+}
+
+// Reclassifies, revalues or annotates one of the user's entries
+async fn adjust_entry(
+    State(state): State<Arc<AppState>>,
+    Path((user_id, entry_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<Change>,
+) -> Result<Json<Adjustment>, StatusCode> {
+    if matches!(payload, Change::CreateEntry(_)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let change = payload.validated().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let store = state.store.as_ref();
+    let adjustment = store.adjust_entry(user_id, entry_id, &change).await.map_err(|e| {
+        eprintln!("DB Error: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    adjustment.map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_audit_trail(
+    State(state): State<Arc<AppState>>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditRecord>>, StatusCode> {
+    let store = state.store.as_ref();
+    let records = store.get_audit_trail(user_id, query.entry_id).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(records))
+}
+
+async fn get_adjustments(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+) -> Result<Json<Vec<Adjustment>>, StatusCode> {
+    let store = state.store.as_ref();
+    let adjustments = store.get_adjustments_by_wallet(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut adjustments: Vec<Adjustment> = adjustments.into_values().collect();
+    adjustments.sort_by_key(|a| a.entry_id);
+    Ok(Json(adjustments))
+}
+
+async fn get_balances(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<BalancesQuery>,
+) -> Result<Json<BalanceSeries>, StatusCode> {
+    let granularity: Granularity = query
+        .granularity
+        .as_deref()
+        .unwrap_or("day")
+        .parse()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    let to = query.to.unwrap_or_else(|| chrono::Utc::now().date_naive());
+    let from = query.from.unwrap_or(to - chrono::Duration::days(30));
+    if from > to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let store = state.store.as_ref();
+    let currency = match &query.currency {
+        Some(c) => fx::normalize_currency(c).map_err(|_| StatusCode::BAD_REQUEST)?,
+        None => {
+            let owner = store.get_wallet_owner(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            store.reporting_currency(owner).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        }
+    };
+
+    let changes = store.get_daily_balance_changes(&wallet, to).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut points = balances::time_series(&changes, from, to, granularity);
+    balances::value_series(store, store, &mut points, &currency).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(BalanceSeries {
+        wallet_address: wallet,
+        currency,
+        granularity,
+        points,
+    }))
+}
+
+async fn get_reconciliation(
+    State(state): State<Arc<AppState>>,
+    Path(wallet): Path<String>,
+    Query(query): Query<ReconcileQuery>,
+) -> Result<Json<ReconciliationReport>, StatusCode> {
+    let store = state.store.as_ref();
+    let entries = store.get_ledger_entries_by_wallet(&wallet).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let adapter = SolanaAdapter::new(&query.rpc_url);
+    let onchain = adapter.current_balances(&wallet).await.map_err(|e| {
+        eprintln!("RPC Error: {}", e);
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let tolerance = query.tolerance.unwrap_or_else(reconcile::default_tolerance);
+    Ok(Json(reconcile::reconcile(&wallet, &entries, &onchain, &tolerance)))
 }
@@ -1,17 +1,42 @@
 use clap::{Parser, Subcommand};
-use spectraplex_adapters::{solana::SolanaAdapter, solana_grpc::SolanaGrpcAdapter, solana_parser, repo::Repository};
-use spectraplex_core::models::{ChainIngestor, Transaction};
-use std::path::PathBuf;
+use spectraplex_adapters::{normalize, solana::SolanaAdapter, solana_grpc::SolanaGrpcAdapter, solana_parser, repo::Repository, sqlite::SqliteStore};
+use spectraplex_adapters::cex_import::{CexStatement, Exchange};
+use spectraplex_adapters::hyperliquid::{HyperliquidAdapter, HYPERLIQUID_API_URL};
+use spectraplex_adapters::{ethereum::{self, EthereumAdapter}, evm};
+use spectraplex_adapters::price_import::{self, OhlcImport};
+use spectraplex_adapters::export::{self, ExportFormat};
+use spectraplex_core::models::{Chain, ChainIngestor, EntryType, LedgerEntry, ParserVersion, Transaction};
+use spectraplex_core::adjustments::{Change, ManualEntry};
+use spectraplex_core::models::UserSettings;
+use spectraplex_core::storage::{Storage, SyncState};
+use spectraplex_core::{bridge, form_8949, fx, income, lending, portfolio, pricing, reconcile, renormalize, spam, uk_pooling};
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Write, BufReader, BufRead};
-use sqlx::postgres::PgPoolOptions;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use uuid::Uuid;
+use bigdecimal::BigDecimal;
 
 #[derive(Parser)]
 #[command(about = "Spectraplex CLI", long_about = None)]
 struct Cli {
+    /// Postgres URL, or sqlite://<file> for an embedded database
     #[arg(global = true, long, env = "DATABASE_URL")]
     db_url: Option<String>,
 
+    /// JSON file of extra EVM networks, or overrides for the built-in ones
+    #[arg(global = true, long, env = "EVM_NETWORKS")]
+    evm_networks: Option<PathBuf>,
+
+    /// Assets to always treat as spam, one per line; `prefix*` and `*suffix` match patterns
+    #[arg(global = true, long, env = "SPAM_DENYLIST")]
+    spam_denylist: Option<PathBuf>,
+
+    /// Assets never to treat as spam, one per line
+    #[arg(global = true, long, env = "SPAM_ALLOWLIST")]
+    spam_allowlist: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -40,9 +65,26 @@ enum Commands {
 
         #[arg(long)]
         x_token: Option<String>,
+
+        /// Exchange statement CSV, for --chain coinbase, kraken or binance
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// User the imported statement belongs to
+        #[arg(short, long)]
+        user: Option<Uuid>,
+
+        /// First block to scan on EVM chains. Defaults to the last --scan-blocks blocks.
+        #[arg(long)]
+        from_block: Option<u64>,
+
+        /// Blocks to scan on EVM chains, at most 10000. Ingest longer histories in windows with --from-block.
+        #[arg(long, default_value_t = ethereum::DEFAULT_SCAN_BLOCKS)]
+        scan_blocks: u64,
         
-        #[arg(long, default_value_t = 10)]
-        limit: usize,
+        /// Defaults to 10 for RPC sources and every row for statements
+        #[arg(long)]
+        limit: Option<usize>,
     },
     /// Normalize Bronze data to Silver layer (Ledger Entries)
     Normalize {
@@ -51,7 +93,280 @@ enum Commands {
 
         #[arg(short, long, default_value = "silver_ledger.jsonl")]
         output: PathBuf,
-    }
+    },
+    /// Generate IRS Form 8949 rows (CSV) and Schedule D totals, or UK disposals and SA108 totals, from realized gains
+    Report {
+        #[arg(short, long)]
+        wallet: Option<String>,
+
+        #[arg(short, long)]
+        user: Option<Uuid>,
+
+        /// For uk, the year the tax year starts in (2024 for 2024-25)
+        #[arg(long)]
+        tax_year: i32,
+
+        /// us (FIFO, Form 8949) or uk (HMRC share pooling, SA108)
+        #[arg(long, default_value = "us")]
+        jurisdiction: String,
+
+        /// Currency to report in. Defaults to the user's reporting currency, then USD; uk is always GBP.
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Silver JSONL to read when no database is configured
+        #[arg(short, long, default_value = "silver_ledger.jsonl")]
+        input: PathBuf,
+
+        /// Defaults to form_8949.csv, or uk_disposals.csv for uk
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Keep entries classified as spam
+        #[arg(long)]
+        include_spam: bool,
+    },
+    /// Income schedule (staking rewards, airdrops, interest) valued at receipt
+    Income {
+        #[arg(short, long)]
+        wallet: Option<String>,
+
+        #[arg(short, long)]
+        user: Option<Uuid>,
+
+        #[arg(long)]
+        tax_year: i32,
+
+        /// Currency to report in. Defaults to the user's reporting currency, then USD.
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Silver JSONL to read when no database is configured
+        #[arg(short, long, default_value = "silver_ledger.jsonl")]
+        input: PathBuf,
+
+        #[arg(short, long, default_value = "income_report.csv")]
+        output: PathBuf,
+
+        /// csv or json
+        #[arg(long, default_value = "csv")]
+        format: String,
+
+        /// Keep entries classified as spam
+        #[arg(long)]
+        include_spam: bool,
+    },
+    /// Export ledger entries as a Koinly, CoinTracker or CoinLedger CSV
+    Export {
+        #[arg(short, long)]
+        wallet: Option<String>,
+
+        #[arg(short, long)]
+        user: Option<Uuid>,
+
+        /// koinly, cointracker or coinledger
+        #[arg(short, long)]
+        format: ExportFormat,
+
+        /// Silver JSONL to read when no database is configured
+        #[arg(short, long, default_value = "silver_ledger.jsonl")]
+        input: PathBuf,
+
+        /// Defaults to a file named after the format
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Keep entries classified as spam
+        #[arg(long)]
+        include_spam: bool,
+    },
+    /// Import an OHLC price history file (.csv or .json) into the price history table
+    ImportPrices {
+        #[arg(short, long)]
+        file: PathBuf,
+
+        /// Asset symbol as it appears in the ledger (SOL or the token mint)
+        #[arg(short, long)]
+        asset: String,
+
+        #[arg(long, default_value = "USD")]
+        fiat: String,
+
+        /// Candle width, e.g. 1m, 1h, 1d
+        #[arg(short, long, default_value = "1d")]
+        resolution: String,
+
+        /// Where the file came from, recorded on every entry priced with it
+        #[arg(short, long)]
+        source: String,
+    },
+    /// Fill fiat values on a wallet's ledger entries from the price history table
+    Price {
+        #[arg(short, long)]
+        wallet: String,
+
+        #[arg(long, default_value = "USD")]
+        fiat: String,
+
+        /// First record prices implied by the wallet's swaps for tokens with no price history
+        #[arg(long)]
+        dex_implied: bool,
+
+        /// Assets a swap must be against to imply a price
+        #[arg(long, default_values_t = ["SOL".to_string(), solana_parser::USDC_MINT.to_string()])]
+        quote_asset: Vec<String>,
+
+        /// Let unpriced entries borrow the nearest implied price within this window, e.g. 6h
+        #[arg(long)]
+        propagate_window: Option<String>,
+    },
+    /// Import daily FX rates from a CSV with date and rate columns (1 base = rate quote)
+    ImportFx {
+        #[arg(short, long)]
+        file: PathBuf,
+
+        #[arg(long)]
+        base: String,
+
+        #[arg(long)]
+        quote: String,
+
+        #[arg(short, long)]
+        source: String,
+    },
+    /// Value current holdings and unrealized gains for a wallet or user
+    Portfolio {
+        #[arg(short, long)]
+        wallet: Option<String>,
+
+        #[arg(short, long)]
+        user: Option<Uuid>,
+
+        /// Unix timestamp to value at. Defaults to now.
+        #[arg(long)]
+        at: Option<i64>,
+
+        /// Defaults to the user's reporting currency, then USD
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Keep assets classified as spam
+        #[arg(long)]
+        include_spam: bool,
+    },
+    /// Set the currency a user's reports are produced in
+    SetCurrency {
+        #[arg(short, long)]
+        user: Uuid,
+
+        #[arg(short, long)]
+        currency: String,
+    },
+    /// Compare ledger balances per asset with current on-chain balances
+    Reconcile {
+        #[arg(short, long)]
+        wallet: String,
+
+        #[arg(long)]
+        rpc: String,
+
+        #[arg(short, long, default_value = "silver_ledger.jsonl")]
+        input: PathBuf,
+
+        /// Largest difference still treated as a match
+        #[arg(long)]
+        tolerance: Option<BigDecimal>,
+    },
+    /// List the EVM networks that can be ingested
+    Networks,
+    /// Show open lending positions (collateral supplied, principal owed) per protocol account
+    Positions {
+        #[arg(short, long)]
+        wallet: String,
+
+        #[arg(short, long, default_value = "silver_ledger.jsonl")]
+        input: PathBuf,
+    },
+    /// Pair bridge transfers across a user's wallets on different chains, so basis follows the asset
+    LinkBridges {
+        #[arg(short, long)]
+        user: Uuid,
+    },
+    /// Rebuild a wallet's ledger entries that came from an older parser version
+    Renormalize {
+        #[arg(short, long)]
+        wallet: String,
+
+        /// Print what would change without writing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Correct or annotate a ledger entry: each option given is its own change in the audit trail
+    Adjust {
+        #[arg(short, long)]
+        user: Uuid,
+
+        #[arg(short, long)]
+        entry: Uuid,
+
+        /// Reclassify the entry, e.g. "income" or "transfer"
+        #[arg(long)]
+        entry_type: Option<String>,
+
+        /// Override the entry's fiat value
+        #[arg(long)]
+        fiat_value: Option<BigDecimal>,
+
+        /// Currency of --fiat-value. Defaults to the user's reporting currency.
+        #[arg(long)]
+        currency: Option<String>,
+
+        /// Replaces the entry's note; an empty one clears it
+        #[arg(long)]
+        note: Option<String>,
+
+        /// Replaces the entry's tags; repeat for several
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// Add an off-chain movement no ingestor sees, such as a gift or one side of an OTC trade
+    AddEntry {
+        #[arg(short, long)]
+        user: Uuid,
+
+        #[arg(short, long)]
+        wallet: String,
+
+        /// Unix timestamp of the movement
+        #[arg(long)]
+        timestamp: i64,
+
+        #[arg(long)]
+        asset: String,
+
+        /// Negative for a disposal
+        #[arg(long, allow_hyphen_values = true)]
+        amount: BigDecimal,
+
+        #[arg(long, default_value = "transfer")]
+        entry_type: String,
+
+        #[arg(long)]
+        fiat_value: Option<BigDecimal>,
+
+        /// Currency of --fiat-value. Defaults to the user's reporting currency.
+        #[arg(long)]
+        currency: Option<String>,
+    },
+    /// Show the adjustments and manual entries a user has made, oldest first
+    Audit {
+        #[arg(short, long)]
+        user: Uuid,
+
+        /// Only changes to this entry
+        #[arg(short, long)]
+        entry: Option<Uuid>,
+    },
 }
 
 #[tokio::main]
@@ -59,12 +374,17 @@ async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
     let cli = Cli::parse();
 
-    // Setup DB Pool if URL provided
-    let pool = if let Some(url) = &cli.db_url {
-        Some(PgPoolOptions::new().max_connections(5).connect(url).await?)
-    } else {
-        None
+    // Setup storage if URL provided. Migrations need the Postgres pool itself.
+    let (pool, store): (Option<PgPool>, Option<Box<dyn Storage>>) = match &cli.db_url {
+        Some(url) if url.starts_with("sqlite:") => (None, Some(Box::new(SqliteStore::connect(url).await?))),
+        Some(url) => {
+            let pool = PgPoolOptions::new().max_connections(5).connect(url).await?;
+            (Some(pool.clone()), Some(Box::new(Repository::new(pool))))
+        }
+        None => (None, None),
     };
+    let networks = evm::load_networks(cli.evm_networks.as_deref())?;
+    let spam_lists = spam::SpamLists::load(cli.spam_denylist.as_deref(), cli.spam_allowlist.as_deref())?;
 
     match cli.command {
         Commands::InitDb => {
@@ -72,14 +392,34 @@ async fn main() -> anyhow::Result<()> {
                 println!("Running migrations...");
                 sqlx::migrate!("../migrations").run(&p).await?;
                 println!("Database initialized successfully.");
+            } else if store.is_some() {
+                // The SQLite schema is created on connect
+                println!("Database initialized successfully.");
             } else {
                 println!("Error: --db-url is required for InitDb");
             }
         }
-        Commands::Ingest { chain, wallet, output, rpc, grpc_url, x_token, limit } => {
+        Commands::Ingest { chain, wallet, output, rpc, grpc_url, x_token, file, user, from_block, scan_blocks, limit } => {
             println!("Starting ingestion for {} on chain {}", wallet, chain);
+            let limit = limit.unwrap_or(match chain.as_str() {
+                "coinbase" | "kraken" | "binance" | "hyperliquid" => usize::MAX,
+                _ => 10,
+            });
 
             let events = match chain.as_str() {
+                "coinbase" | "kraken" | "binance" => {
+                    let Some(path) = file else {
+                        anyhow::bail!("--file is required for {} statements", chain);
+                    };
+                    let exchange: Exchange = chain.parse()?;
+                    let statement = CexStatement::new(path, exchange, user.unwrap_or(Uuid::nil()));
+                    statement.fetch_history(&wallet, limit).await?
+                }
+                "hyperliquid" => {
+                    // --rpc overrides the info API base URL
+                    let adapter = HyperliquidAdapter::new(rpc.as_deref().unwrap_or(HYPERLIQUID_API_URL));
+                    adapter.fetch_history(&wallet, limit).await?
+                }
                 "solana" => {
                     if let Some(endpoint) = grpc_url {
                         let adapter = SolanaGrpcAdapter::new(&endpoint, x_token);
@@ -91,17 +431,38 @@ async fn main() -> anyhow::Result<()> {
                         anyhow::bail!("Either --grpc-url or --rpc must be provided for Solana");
                     }
                 }
-                _ => {
-                    println!("Unsupported chain: {}", chain);
-                    return Ok(());
+                name => {
+                    let Some(network) = evm::find_network(&networks, name) else {
+                        anyhow::bail!("Unsupported chain: {} (see `networks` for the EVM networks that can be ingested)", chain);
+                    };
+                    // --rpc overrides the network's configured RPC
+                    let Some(rpc_url) = rpc.or_else(|| network.rpc_url.clone()) else {
+                        anyhow::bail!("--rpc is required for {}", name);
+                    };
+                    let adapter = EthereumAdapter::for_network(network.clone(), &rpc_url, from_block).with_scan_blocks(scan_blocks);
+                    let txs = adapter.fetch_history(&wallet, limit).await?;
+                    if let Some(url) = txs.last().and_then(|t| network.tx_url(&t.tx_hash)) {
+                        println!("Latest transaction: {}", url);
+                    }
+                    txs
                 }
             };
 
             // Strategy: DB first, fallback to File
-            if let Some(p) = pool {
-                let repo = Repository::new(p);
-                repo.save_transactions(&events).await?;
-                println!("Saved {} transactions to Database.", events.len());
+            if let Some(store) = &store {
+                // Only what's past the last run is stored, so re-ingesting doesn't duplicate transactions
+                let chain = Chain::from_name(&chain);
+                let mut state = store.get_sync_state(&wallet, &chain).await?.unwrap_or_else(|| SyncState::new(&wallet, chain));
+                let fetched = events.len();
+                let events: Vec<Transaction> = events.into_iter().filter(|t| state.is_new(t)).collect();
+                // A full page of nothing but new transactions may not reach back to the last run, and moving
+                // the state past it would skip whatever is in between for good
+                if state.last_timestamp.is_some() && fetched == limit && events.len() == fetched {
+                    anyhow::bail!("More than {} transactions since the last sync; re-run with a higher --limit", limit);
+                }
+                state.advance(&events);
+                store.save_ingested(&events, &state).await?;
+                println!("Saved {} new transactions to Database ({} already stored).", events.len(), fetched - events.len());
             } else {
                 // Write to JSONL
                 let mut file = File::create(&output)?;
@@ -113,14 +474,13 @@ async fn main() -> anyhow::Result<()> {
             }
         }
         Commands::Normalize { input, output } => {
-            let transactions = if let Some(p) = pool.clone() {
+            let transactions = if let Some(store) = &store {
                 
                 let input_str = input.to_string_lossy();
                 if input_str.starts_with("db:") {
                     let wallet = input_str.strip_prefix("db:").unwrap();
                     println!("Fetching transactions for wallet {} from DB...", wallet);
-                    let repo = Repository::new(p);
-                    repo.get_transactions_by_wallet(wallet).await?
+                    store.get_transactions_by_wallet(wallet).await?
                 } else {
                     println!("Reading raw data from {:?}...", input);
                     let file = File::open(&input)?;
@@ -148,26 +508,25 @@ async fn main() -> anyhow::Result<()> {
 
             let mut all_entries = Vec::new();
 
-            for tx in transactions {
-                // Use the parser to extract actual ledger entries
-                let entries = match tx.chain {
-                    spectraplex_core::models::Chain::Solana => {
-                        solana_parser::parse_solana_transaction(&tx)?
-                    },
-                    _ => {
-                        println!("Skipping unsupported chain for normalization: {:?}", tx.chain);
-                        vec![]
-                    }
-                };
-                all_entries.extend(entries);
+            for tx in &transactions {
+                all_entries.extend(normalize::parse_transaction(tx)?);
             }
 
-            if let Some(p) = pool {
-                println!("Saving {} ledger entries to Database...", all_entries.len());
-                let repo = Repository::new(p);
-                repo.save_ledger_entries(&all_entries).await?;
+            // Splits interest out of lending withdrawals and repayments, so needs the whole history at once
+            let positions = lending::track_positions(&mut all_entries);
+            if !positions.is_empty() {
+                println!("{} open lending positions", positions.len());
+            }
+
+            if let Some(store) = &store {
+                let overrides = spam_overrides(&**store, &all_entries).await?;
+                spam::classify(&mut all_entries, &spam_lists, &overrides);
+                // Raw transactions read from a file go in with their entries, which reference them
+                println!("Saving {} transactions and {} ledger entries to Database...", transactions.len(), all_entries.len());
+                store.save_transactions_with_entries(&transactions, &all_entries).await?;
                 println!("Done.");
             } else {
+                spam::classify(&mut all_entries, &spam_lists, &HashMap::new());
                 let mut out_file = File::create(&output)?;
                 for entry in all_entries {
                     serde_json::to_writer(&out_file, &entry)?;
@@ -176,7 +535,452 @@ async fn main() -> anyhow::Result<()> {
                 println!("Normalization complete. Output written to {:?}", output);
             }
         }
+        Commands::Report { wallet, user, tax_year, jurisdiction, currency, input, output, include_spam } => {
+            match jurisdiction.as_str() {
+                "us" => {
+                    let (entries, currency) = load_report_entries(store.as_deref(), wallet.as_deref(), user, currency, &input, include_spam).await?;
+
+                    let report = form_8949::from_entries(&entries, tax_year, &currency)?;
+
+                    let output = output.unwrap_or_else(|| PathBuf::from("form_8949.csv"));
+                    let mut writer = csv::Writer::from_path(&output)?;
+                    writer.write_record(["part", "description", "date_acquired", "date_sold", "proceeds", "cost_basis", "gain_or_loss"])?;
+                    let parts = [("I", &report.short_term), ("II", &report.long_term)];
+                    for (part, rows) in parts {
+                        for row in rows {
+                            let date_acquired = row
+                                .date_acquired
+                                .map(|d| d.format("%m/%d/%Y").to_string())
+                                .unwrap_or_else(|| "VARIOUS".to_string());
+                            writer.write_record([
+                                part.to_string(),
+                                row.description.clone(),
+                                date_acquired,
+                                row.date_sold.format("%m/%d/%Y").to_string(),
+                                row.proceeds.to_string(),
+                                row.cost_basis.to_string(),
+                                row.gain_or_loss.to_string(),
+                            ])?;
+                        }
+                    }
+                    writer.flush()?;
+
+                    let d = &report.schedule_d;
+                    println!("Form 8949 rows written to {:?}", output);
+                    println!("Schedule D ({}, {})", tax_year, report.currency);
+                    println!("  Short-term: proceeds {} cost {} gain/loss {}", d.short_term.proceeds, d.short_term.cost_basis, d.short_term.gain_or_loss);
+                    println!("  Long-term:  proceeds {} cost {} gain/loss {}", d.long_term.proceeds, d.long_term.cost_basis, d.long_term.gain_or_loss);
+                    println!("  Net:        {}", d.net_gain_or_loss);
+                    if !report.unvalued_assets.is_empty() {
+                        println!("Left off, some entries have no fiat value (see `price`): {}", report.unvalued_assets.join(", "));
+                    }
+                }
+                "uk" => {
+                    // HMRC wants sterling, so there's no reporting currency to fall back on
+                    let currency = currency.unwrap_or_else(|| "GBP".to_string());
+                    let (entries, currency) = load_report_entries(store.as_deref(), wallet.as_deref(), user, Some(currency), &input, include_spam).await?;
+                    if currency != "GBP" {
+                        anyhow::bail!("UK returns are in GBP, not {}", currency);
+                    }
+                    let report = uk_pooling::sa108_report(&entries, tax_year)?;
+
+                    let output = output.unwrap_or_else(|| PathBuf::from("uk_disposals.csv"));
+                    let mut writer = csv::Writer::from_path(&output)?;
+                    writer.write_record(["date", "asset", "quantity", "proceeds", "allowable_cost", "gain_or_loss", "rules"])?;
+                    for d in &report.disposals {
+                        let rules: Vec<String> = d.matches.iter().map(|m| format!("{:?}", m.rule)).collect();
+                        writer.write_record([
+                            d.date.format("%d/%m/%Y").to_string(),
+                            d.asset_symbol.clone(),
+                            d.quantity.normalized().to_string(),
+                            d.proceeds.round(2).to_string(),
+                            d.allowable_cost.round(2).to_string(),
+                            d.gain.round(2).to_string(),
+                            rules.join(" "),
+                        ])?;
+                    }
+                    writer.flush()?;
+
+                    let summary = &report.summary;
+                    println!("Disposals written to {:?}", output);
+                    println!("SA108 cryptoassets ({}, GBP)", summary.tax_year);
+                    println!("  Number of disposals:  {}", summary.number_of_disposals);
+                    println!("  Disposal proceeds:    {}", summary.disposal_proceeds.round(2));
+                    println!("  Allowable costs:      {}", summary.allowable_costs.round(2));
+                    println!("  Gains before losses:  {}", summary.gains_before_losses.round(2));
+                    println!("  Losses:               {}", summary.losses.round(2));
+                    if !report.unvalued_assets.is_empty() {
+                        println!("Left off, some entries have no fiat value (see `price`): {}", report.unvalued_assets.join(", "));
+                    }
+                }
+                _ => anyhow::bail!("Unknown jurisdiction: {} (expected us or uk)", jurisdiction),
+            }
+        }
+        Commands::Income { wallet, user, tax_year, currency, input, output, format, include_spam } => {
+            let (entries, currency) = load_report_entries(store.as_deref(), wallet.as_deref(), user, currency, &input, include_spam).await?;
+            let report = income::build(&entries, tax_year, &currency)?;
+
+            let file = File::create(&output)?;
+            match format.as_str() {
+                "csv" => income::write_csv(&report, file)?,
+                "json" => serde_json::to_writer_pretty(file, &report)?,
+                _ => anyhow::bail!("Unknown format: {} (expected csv or json)", format),
+            }
+
+            println!("{} income lines written to {:?}", report.lines.len(), output);
+            println!("Total income ({}, {}): {}", tax_year, report.currency, report.total_fair_market_value);
+        }
+        Commands::Export { wallet, user, format, input, output, include_spam } => {
+            let (entries, tx_hashes) = if let Some(store) = &store {
+                let entries = match (&wallet, user) {
+                    (Some(w), _) => store.get_ledger_entries_by_wallet(w).await?,
+                    (None, Some(u)) => store.get_ledger_entries_by_user(u).await?,
+                    (None, None) => anyhow::bail!("Either --wallet or --user must be provided"),
+                };
+                let ids: Vec<Uuid> = entries.iter().map(|e| e.transaction_id).collect();
+                let tx_hashes = store.get_tx_hashes(&ids).await?;
+                (entries, tx_hashes)
+            } else {
+                // Silver files don't carry transaction hashes
+                (read_silver(&input, wallet.as_deref(), user)?, HashMap::new())
+            };
+            let entries = spam::filter(entries, include_spam);
+
+            let rows = export::build_rows(&entries, &tx_hashes);
+            let output = output.unwrap_or_else(|| PathBuf::from(format.file_name()));
+            export::write_csv(format, &rows, File::create(&output)?)?;
+            println!("Exported {} rows from {} entries to {:?}", rows.len(), entries.len(), output);
+        }
+        Commands::ImportPrices { file, asset, fiat, resolution, source } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for ImportPrices");
+            };
+            let import = OhlcImport {
+                asset_symbol: asset,
+                fiat_currency: fiat,
+                resolution_secs: pricing::parse_resolution(&resolution)?,
+                source,
+            };
+            let candles = price_import::load_ohlc_file(&file, &import)?;
+            store.save_price_candles(&candles).await?;
+            println!("Imported {} candles for {} from {:?}", candles.len(), import.asset_symbol, file);
+        }
+        Commands::Price { wallet, fiat, dex_implied, quote_asset, propagate_window } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for Price");
+            };
+            let mut entries = store.get_ledger_entries_by_wallet(&wallet).await?;
+
+            if dex_implied {
+                let observations = pricing::implied_prices(&*store, &entries, &fiat, &quote_asset).await?;
+                store.save_price_candles(&observations).await?;
+                println!("Recorded {} DEX-implied prices", observations.len());
+            }
+
+            let window = propagate_window.as_deref().map(pricing::parse_resolution).transpose()?;
+            let summary = pricing::price_entries(&*store, &mut entries, &fiat, window).await?;
+            store.update_ledger_valuations(&entries).await?;
+
+            // A token that turned out to have a market is no longer an unvalued airdrop
+            let overrides = spam_overrides(&*store, &entries).await?;
+            spam::classify(&mut entries, &spam_lists, &overrides);
+            store.update_spam_flags(&entries).await?;
+            println!("Priced {} entries in {} ({} without a price)", summary.priced, fiat, summary.unpriced);
+        }
+        Commands::ImportFx { file, base, quote, source } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for ImportFx");
+            };
+            let rates = price_import::parse_fx_csv(File::open(&file)?, &base, &quote, &source)?;
+            store.save_fx_rates(&rates).await?;
+            println!("Imported {} {}/{} rates from {:?}", rates.len(), base.to_uppercase(), quote.to_uppercase(), file);
+        }
+        Commands::SetCurrency { user, currency } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for SetCurrency");
+            };
+            let settings = UserSettings {
+                user_id: user,
+                reporting_currency: fx::normalize_currency(&currency)?,
+            };
+            store.save_user_settings(&settings).await?;
+            println!("Reporting currency for {} set to {}", user, settings.reporting_currency);
+        }
+        Commands::Portfolio { wallet, user, at, currency, include_spam } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for Portfolio");
+            };
+            let entries = match (&wallet, user) {
+                (Some(w), _) => store.get_ledger_entries_by_wallet(w).await?,
+                (None, Some(u)) => store.get_ledger_entries_by_user(u).await?,
+                (None, None) => anyhow::bail!("Either --wallet or --user must be provided"),
+            };
+            let mut entries = spam::filter(entries, include_spam);
+            let currency = report_currency(Some(&*store), &mut entries, user, currency, "the database").await?;
+
+            let at = at.unwrap_or_else(|| chrono::Utc::now().timestamp());
+            let snapshot = portfolio::snapshot(&*store, &*store, &entries, at, &currency).await?;
+
+            let show = |v: &Option<BigDecimal>| v.as_ref().map(|d| d.round(2).to_string()).unwrap_or_else(|| "-".to_string());
+            println!("{:<46} {:>20} {:>14} {:>14} {:>14}", "asset", "quantity", "value", "basis", "unrealized");
+            for h in &snapshot.holdings {
+                println!(
+                    "{:<46} {:>20} {:>14} {:>14} {:>14}",
+                    h.asset_symbol, h.quantity.normalized(), show(&h.market_value), show(&h.cost_basis), show(&h.unrealized_gain)
+                );
+            }
+            println!(
+                "Total ({}): value {} basis {} unrealized {}",
+                snapshot.currency,
+                snapshot.total_market_value.round(2),
+                snapshot.total_cost_basis.round(2),
+                snapshot.total_unrealized_gain.round(2)
+            );
+        }
+        Commands::Reconcile { wallet, rpc, input, tolerance } => {
+            let entries = if let Some(store) = &store {
+                store.get_ledger_entries_by_wallet(&wallet).await?
+            } else {
+                read_silver(&input, Some(&wallet), None)?
+            };
+
+            let adapter = SolanaAdapter::new(&rpc);
+            let onchain = reconcile::BalanceSource::current_balances(&adapter, &wallet).await?;
+            let tolerance = tolerance.unwrap_or_else(reconcile::default_tolerance);
+            let report = reconcile::reconcile(&wallet, &entries, &onchain, &tolerance);
+
+            println!("{:<46} {:>20} {:>20} {:>20}", "asset", "ledger", "onchain", "difference");
+            for a in &report.assets {
+                println!(
+                    "{:<46} {:>20} {:>20} {:>20}{}",
+                    a.asset_symbol,
+                    a.ledger_balance.normalized(),
+                    a.onchain_balance.normalized(),
+                    a.difference.normalized(),
+                    if a.matches { "" } else { "  MISMATCH" }
+                );
+            }
+            println!("{} of {} assets have discrepancies", report.discrepancies, report.assets.len());
+        }
+        Commands::Positions { wallet, input } => {
+            let entries = if let Some(store) = &store {
+                store.get_ledger_entries_by_wallet(&wallet).await?
+            } else {
+                read_silver(&input, Some(&wallet), None)?
+            };
+
+            println!("{:<46} {:<46} {:<46} {:>16} {:>16}", "protocol", "account", "asset", "supplied", "borrowed");
+            for p in lending::positions(&entries) {
+                println!(
+                    "{:<46} {:<46} {:<46} {:>16} {:>16}",
+                    p.protocol, p.account, p.asset_symbol, p.supplied.normalized(), p.borrowed.normalized()
+                );
+            }
+        }
+        Commands::LinkBridges { user } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for LinkBridges");
+            };
+            let mut entries = store.get_ledger_entries_by_user(user).await?;
+            let ids: Vec<Uuid> = entries.iter().map(|e| e.transaction_id).collect();
+            let chains = store.get_transaction_chains(&ids).await?;
+
+            let summary = bridge::link_transfers(&mut entries, &chains);
+            store.update_bridge_links(&entries).await?;
+            println!("Linked {} bridge transfers, {} bridge entries still unpaired", summary.linked, summary.unpaired);
+        }
+        Commands::Renormalize { wallet, dry_run } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for Renormalize");
+            };
+            let txs = store.get_transactions_by_wallet(&wallet).await?;
+            let mut fresh = Vec::new();
+            for tx in &txs {
+                fresh.extend(normalize::parse_transaction(tx)?);
+            }
+            lending::track_positions(&mut fresh);
+            let adjusted = store.get_adjustments_by_wallet(&wallet).await?;
+            let overrides = spam_overrides(&*store, &fresh).await?;
+            spam::classify(&mut fresh, &spam_lists, &overrides);
+
+            let current: HashMap<Uuid, ParserVersion> = txs.iter().map(|t| (t.id, normalize::parser_for(&t.chain))).collect();
+            let stored = store.get_ledger_entries_by_wallet(&wallet).await?;
+            let plan = renormalize::plan(&stored, fresh, &current, &adjusted);
+
+            let hashes: HashMap<Uuid, &str> = txs.iter().map(|t| (t.id, t.tx_hash.as_str())).collect();
+            let mut diff: Vec<(&LedgerEntry, &str)> = plan.removed.iter().map(|e| (e, "-")).chain(plan.added.iter().map(|e| (e, "+"))).collect();
+            diff.sort_by_key(|(e, _)| e.timestamp);
+            for (e, sign) in diff {
+                println!("{} {} {:<10} {:>24} {}", sign, hashes[&e.transaction_id], e.entry_type.name(), e.amount.normalized(), e.asset_symbol);
+            }
+            println!(
+                "{} transactions to rebuild: {} entries added, {} removed, {} unchanged",
+                plan.transactions, plan.added.len(), plan.removed.len(), plan.unchanged
+            );
+            if !plan.orphaned_adjustments.is_empty() {
+                println!("{} removed entries had manual adjustments, which will no longer apply", plan.orphaned_adjustments.len());
+            }
+
+            if dry_run {
+                println!("Dry run; nothing was written.");
+            } else if !plan.transaction_ids.is_empty() {
+                store.replace_ledger_entries(&plan.transaction_ids, &plan.entries).await?;
+                println!("Done.");
+            }
+        }
+        Commands::Adjust { user, entry, entry_type, fiat_value, currency, note, tags } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for Adjust");
+            };
+            let mut changes = Vec::new();
+            if let Some(name) = entry_type {
+                changes.push(Change::Reclassify { entry_type: EntryType::parse(&name)? });
+            }
+            if let Some(fiat_value) = fiat_value {
+                let fiat_currency = match currency {
+                    Some(c) => c,
+                    None => store.reporting_currency(Some(user)).await?,
+                };
+                changes.push(Change::SetFiatValue { fiat_value, fiat_currency });
+            }
+            if note.is_some() || !tags.is_empty() {
+                changes.push(Change::Annotate { note, tags: Some(tags).filter(|t| !t.is_empty()) });
+            }
+            if changes.is_empty() {
+                anyhow::bail!("Nothing to change: give --entry-type, --fiat-value, --note or --tag");
+            }
+
+            for change in changes {
+                let change = change.validated()?;
+                if store.adjust_entry(user, entry, &change).await?.is_none() {
+                    anyhow::bail!("User {} has no ledger entry {}", user, entry);
+                }
+            }
+            println!("Adjusted entry {}", entry);
+        }
+        Commands::AddEntry { user, wallet, timestamp, asset, amount, entry_type, fiat_value, currency } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for AddEntry");
+            };
+            let fiat_currency = match (&fiat_value, currency) {
+                (Some(_), None) => Some(store.reporting_currency(Some(user)).await?),
+                (_, currency) => currency,
+            };
+            let manual = ManualEntry {
+                wallet_address: wallet,
+                timestamp,
+                asset_symbol: asset,
+                amount,
+                entry_type: EntryType::parse(&entry_type)?,
+                fiat_value,
+                fiat_currency,
+            }
+            .validated()?;
+            let entry = normalize::create_manual_entry(&*store, user, &manual).await?;
+            println!("Added entry {}", entry.id);
+        }
+        Commands::Audit { user, entry } => {
+            let Some(store) = store else {
+                anyhow::bail!("--db-url is required for Audit");
+            };
+            for record in store.get_audit_trail(user, entry).await? {
+                let at = chrono::DateTime::from_timestamp(record.recorded_at, 0).map(|t| t.to_rfc3339()).unwrap_or_default();
+                println!("{} {} {} (was {})", at, record.entry_id, serde_json::to_string(&record.change)?, record.previous);
+            }
+        }
+        Commands::Networks => {
+            println!("{:<12} {:>10} {:<8} {:<40} RPC", "NETWORK", "CHAIN ID", "NATIVE", "EXPLORER");
+            for n in &networks {
+                println!(
+                    "{:<12} {:>10} {:<8} {:<40} {}",
+                    n.name,
+                    n.chain_id,
+                    n.native_asset,
+                    n.explorer_url.as_deref().unwrap_or("-"),
+                    n.rpc_url.as_deref().unwrap_or("-")
+                );
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Ledger entries for a report, valued in one currency: from the database when configured, otherwise from a
+/// silver JSONL file. Spam is dropped unless asked for.
+async fn load_report_entries(
+    store: Option<&dyn Storage>,
+    wallet: Option<&str>,
+    user: Option<Uuid>,
+    currency: Option<String>,
+    input: &Path,
+    include_spam: bool,
+) -> anyhow::Result<(Vec<LedgerEntry>, String)> {
+    let entries = if let Some(store) = store {
+        match (wallet, user) {
+            (Some(w), _) => store.get_ledger_entries_by_wallet(w).await?,
+            (None, Some(u)) => store.get_ledger_entries_by_user(u).await?,
+            (None, None) => anyhow::bail!("Either --wallet or --user must be provided"),
+        }
+    } else {
+        read_silver(input, wallet, user)?
+    };
+
+    // Spam goes first so a junk token without an FX rate can't fail the report
+    let mut entries = spam::filter(entries, include_spam);
+    let source = if store.is_some() { "the database".to_string() } else { input.display().to_string() };
+    let currency = report_currency(store, &mut entries, user, currency, &source).await?;
+    Ok((entries, currency))
+}
+
+/// Ledger entries from a Silver JSONL file, narrowed to a wallet and/or user when given.
+fn read_silver(path: &Path, wallet: Option<&str>, user: Option<Uuid>) -> anyhow::Result<Vec<LedgerEntry>> {
+    println!("Reading ledger entries from {:?}...", path);
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let entry: LedgerEntry = serde_json::from_str(&line?)?;
+        let wallet_match = wallet.is_none_or(|w| entry.wallet_address == w);
+        let user_match = user.is_none_or(|u| entry.user_id == u);
+        if wallet_match && user_match {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// The currency to report in. With a database that's the one asked for or the user's reporting currency, converted
+/// to with stored FX rates. Without one there's no FX table, so entries have to be in one currency already.
+async fn report_currency(
+    store: Option<&dyn Storage>,
+    entries: &mut [LedgerEntry],
+    user: Option<Uuid>,
+    currency: Option<String>,
+    source: &str,
+) -> anyhow::Result<String> {
+    if let Some(store) = store {
+        let currency = match currency {
+            Some(c) => fx::normalize_currency(&c)?,
+            None => store.reporting_currency(user.or_else(|| entries.first().map(|e| e.user_id))).await?,
+        };
+        fx::convert_entries(store, entries, &currency).await?;
+        return Ok(currency);
+    }
+
+    let found = fx::ensure_single_currency(entries)?.unwrap_or_else(|| fx::DEFAULT_CURRENCY.to_string());
+    if let Some(c) = currency {
+        let c = fx::normalize_currency(&c)?;
+        if c != found {
+            anyhow::bail!("{} is valued in {}; converting to {} needs a --db-url with FX rates", source, found, c);
+        }
+    }
+    Ok(found)
+}
+
+/// The user's spam decisions, which are kept in the database.
+async fn spam_overrides(store: &dyn Storage, entries: &[LedgerEntry]) -> anyhow::Result<HashMap<String, bool>> {
+    match entries.first() {
+        Some(e) => store.get_spam_overrides(e.user_id).await,
+        None => Ok(HashMap::new()),
+    }
+}
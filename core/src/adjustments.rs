@@ -0,0 +1,183 @@
+use crate::fx::normalize_currency;
+use crate::models::{Chain, EntryType, LedgerEntry, Transaction};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+// Price source recorded on entries whose fiat value a user entered
+pub const MANUAL_PRICE_SOURCE: &str = "manual";
+
+/// A change a user makes to the ledger by hand.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Change {
+    Reclassify { entry_type: EntryType },
+    SetFiatValue { fiat_value: BigDecimal, fiat_currency: String },
+    // Unset fields are left as they are; an empty note clears it
+    Annotate { note: Option<String>, tags: Option<Vec<String>> },
+    CreateEntry(ManualEntry),
+}
+
+impl Change {
+    /// Checks a change as submitted, normalizing currency codes.
+    pub fn validated(self) -> anyhow::Result<Self> {
+        Ok(match self {
+            Change::SetFiatValue { fiat_value, fiat_currency } => {
+                Change::SetFiatValue { fiat_value, fiat_currency: normalize_currency(&fiat_currency)? }
+            }
+            Change::CreateEntry(manual) => Change::CreateEntry(manual.validated()?),
+            other => other,
+        })
+    }
+}
+
+/// Where a user's adjustments to one entry stand. Unset fields leave what the parser produced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Adjustment {
+    pub entry_id: Uuid,
+    pub user_id: Uuid,
+    pub wallet_address: String,
+    pub entry_type: Option<EntryType>,
+    pub fiat_value: Option<BigDecimal>,
+    pub fiat_currency: Option<String>,
+    pub note: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Adjustment {
+    pub fn new(entry: &LedgerEntry) -> Self {
+        Self {
+            entry_id: entry.id,
+            user_id: entry.user_id,
+            wallet_address: entry.wallet_address.clone(),
+            entry_type: None,
+            fiat_value: None,
+            fiat_currency: None,
+            note: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Takes in a change to the entry. New entries aren't adjustments, so `CreateEntry` is an error.
+    pub fn record(&mut self, change: &Change) -> anyhow::Result<()> {
+        match change {
+            Change::Reclassify { entry_type } => self.entry_type = Some(entry_type.clone()),
+            Change::SetFiatValue { fiat_value, fiat_currency } => {
+                self.fiat_value = Some(fiat_value.clone());
+                self.fiat_currency = Some(fiat_currency.clone());
+            }
+            Change::Annotate { note, tags } => {
+                if let Some(note) = note {
+                    self.note = Some(note.clone()).filter(|n| !n.is_empty());
+                }
+                if let Some(tags) = tags {
+                    self.tags = tags.clone();
+                }
+            }
+            Change::CreateEntry(_) => anyhow::bail!("A new entry can't be recorded as an adjustment"),
+        }
+        Ok(())
+    }
+
+    /// Overrides the entry's type and valuation. Notes and tags stay on the adjustment.
+    pub fn apply(&self, entry: &mut LedgerEntry) {
+        if let Some(entry_type) = &self.entry_type {
+            entry.entry_type = entry_type.clone();
+        }
+        if let Some(value) = &self.fiat_value {
+            entry.fiat_value = Some(value.clone());
+            entry.fiat_currency = self.fiat_currency.clone();
+            entry.price_source = Some(MANUAL_PRICE_SOURCE.to_string());
+            entry.price_resolution = None;
+        }
+    }
+}
+
+/// One change in the audit trail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub entry_id: Uuid,
+    pub change: Change,
+    pub previous: serde_json::Value, // The fields it changed, as they were; null for a new entry
+    pub recorded_at: i64,
+}
+
+impl AuditRecord {
+    /// Records `change` to `entry`, whose adjustment stood at `before`.
+    pub fn new(entry: &LedgerEntry, before: &Adjustment, change: &Change, recorded_at: i64) -> Self {
+        let previous = match change {
+            Change::Reclassify { .. } => json!({ "entry_type": entry.entry_type }),
+            Change::SetFiatValue { .. } => json!({ "fiat_value": entry.fiat_value, "fiat_currency": entry.fiat_currency }),
+            Change::Annotate { .. } => json!({ "note": before.note, "tags": before.tags }),
+            Change::CreateEntry(_) => serde_json::Value::Null,
+        };
+        Self { id: Uuid::new_v4(), user_id: entry.user_id, entry_id: entry.id, change: change.clone(), previous, recorded_at }
+    }
+}
+
+/// Makes `change` to `entry`, whose adjustments stood at `before`, and applies the result to it.
+/// Returns where the adjustments stand afterwards, with the change's audit record.
+pub fn adjust(entry: &mut LedgerEntry, before: &Adjustment, change: &Change, recorded_at: i64) -> anyhow::Result<(Adjustment, AuditRecord)> {
+    let mut adjustment = before.clone();
+    adjustment.record(change)?;
+    let record = AuditRecord::new(entry, before, change, recorded_at);
+    adjustment.apply(entry);
+    Ok((adjustment, record))
+}
+
+/// An off-chain movement entered by hand, e.g. a gift received or one side of an OTC trade. It's kept as a raw
+/// transaction on the manual chain, so it normalizes, re-normalizes and reports like anything ingested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManualEntry {
+    pub wallet_address: String,
+    pub timestamp: i64,
+    pub asset_symbol: String,
+    pub amount: BigDecimal, // Negative for a disposal
+    pub entry_type: EntryType,
+    pub fiat_value: Option<BigDecimal>,
+    pub fiat_currency: Option<String>,
+}
+
+impl ManualEntry {
+    /// Checks an entry as submitted, normalizing its currency code.
+    pub fn validated(self) -> anyhow::Result<Self> {
+        if self.amount.is_zero() {
+            anyhow::bail!("A manual entry needs a non-zero amount");
+        }
+        let fiat_currency = match (&self.fiat_value, &self.fiat_currency) {
+            (Some(_), Some(c)) => Some(normalize_currency(c)?),
+            (None, None) => None,
+            _ => anyhow::bail!("A manual entry's fiat value and currency go together"),
+        };
+        Ok(Self { fiat_currency, ..self })
+    }
+
+    pub fn to_transaction(&self, user_id: Uuid) -> anyhow::Result<Transaction> {
+        let id = Uuid::new_v4();
+        Ok(Transaction {
+            id,
+            user_id,
+            wallet_address: self.wallet_address.clone(),
+            timestamp: self.timestamp,
+            tx_hash: format!("manual:{}", id.simple()),
+            chain: Chain::Manual,
+            raw_metadata: serde_json::to_value(self)?,
+        })
+    }
+}
+
+/// The entry a manual transaction was created with.
+pub fn parse_manual_transaction(tx: &Transaction) -> anyhow::Result<Vec<LedgerEntry>> {
+    let manual: ManualEntry = serde_json::from_value(tx.raw_metadata.clone())?;
+    let mut entry = LedgerEntry::new(tx, manual.asset_symbol, manual.amount, manual.entry_type);
+    if let Some(value) = manual.fiat_value {
+        entry.fiat_value = Some(value);
+        entry.fiat_currency = manual.fiat_currency;
+        entry.price_source = Some(MANUAL_PRICE_SOURCE.to_string());
+    }
+    Ok(vec![entry])
+}
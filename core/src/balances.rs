@@ -0,0 +1,156 @@
+use crate::fx::{self, FxSource};
+use crate::models::LedgerEntry;
+use crate::pricing::PriceSource;
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Day,
+    Week, // ISO weeks, starting Monday
+    Month,
+}
+
+impl FromStr for Granularity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" => Ok(Granularity::Day),
+            "week" => Ok(Granularity::Week),
+            "month" => Ok(Granularity::Month),
+            _ => Err(anyhow::anyhow!("Unknown granularity: {}", s)),
+        }
+    }
+}
+
+impl Granularity {
+    fn period_start(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => day,
+            Granularity::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+            Granularity::Month => day.with_day(1).expect("first of month"),
+        }
+    }
+
+    fn next_period(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Granularity::Day => start + Duration::days(1),
+            Granularity::Week => start + Duration::days(7),
+            Granularity::Month => start.checked_add_months(Months::new(1)).expect("date in range"),
+        }
+    }
+}
+
+// Net movement of an asset on a UTC day, as kept in the materialized daily_balances table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyChange {
+    pub asset_symbol: String,
+    pub day: NaiveDate,
+    pub net_change: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetBalance {
+    pub asset_symbol: String,
+    pub balance: BigDecimal,
+    pub fiat_value: Option<BigDecimal>,
+}
+
+// Closing balances at the end of a period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalancePoint {
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub balances: Vec<AssetBalance>,
+    pub total_fiat_value: BigDecimal, // Sum over balances that could be priced
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceSeries {
+    pub wallet_address: String,
+    pub currency: String,
+    pub granularity: Granularity,
+    pub points: Vec<BalancePoint>,
+}
+
+/// Net movement per asset and UTC day of the non-spam entries up to and including `to`, oldest day first:
+/// what the materialized daily_balances table holds, worked out from the entries themselves.
+pub fn daily_changes(entries: &[LedgerEntry], to: NaiveDate) -> Vec<DailyChange> {
+    let mut days: BTreeMap<(NaiveDate, String), BigDecimal> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| !e.is_spam) {
+        let Some(day) = DateTime::from_timestamp(entry.timestamp, 0).map(|dt| dt.date_naive()) else { continue };
+        if day <= to {
+            *days.entry((day, entry.asset_symbol.clone())).or_insert_with(BigDecimal::zero) += &entry.amount;
+        }
+    }
+    days.into_iter()
+        .map(|((day, asset_symbol), net_change)| DailyChange { asset_symbol, day, net_change })
+        .collect()
+}
+
+/// Closing balance per asset for every period between `from` and `to`, including quiet periods.
+/// `changes` must cover everything up to `to`, since balances are cumulative from the first entry.
+pub fn time_series(changes: &[DailyChange], from: NaiveDate, to: NaiveDate, granularity: Granularity) -> Vec<BalancePoint> {
+    let mut sorted: Vec<&DailyChange> = changes.iter().filter(|c| c.day <= to).collect();
+    sorted.sort_by_key(|c| c.day);
+
+    let mut running: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    let mut pending = sorted.into_iter().peekable();
+    let mut points = Vec::new();
+
+    let mut start = granularity.period_start(from);
+    while start <= to {
+        let period_end = (granularity.next_period(start) - Duration::days(1)).min(to);
+
+        while let Some(change) = pending.next_if(|c| c.day <= period_end) {
+            *running.entry(change.asset_symbol.clone()).or_insert_with(BigDecimal::zero) += &change.net_change;
+        }
+
+        points.push(BalancePoint {
+            period_start: start,
+            period_end,
+            balances: running
+                .iter()
+                .filter(|(_, balance)| !balance.is_zero())
+                .map(|(asset, balance)| AssetBalance {
+                    asset_symbol: asset.clone(),
+                    balance: balance.clone(),
+                    fiat_value: None,
+                })
+                .collect(),
+            total_fiat_value: BigDecimal::zero(),
+        });
+
+        start = granularity.next_period(start);
+    }
+
+    points
+}
+
+/// Prices each closing balance at the end of its period (23:59:59 UTC), converting
+/// USD prices through `fx` when there's no history in `currency`.
+pub async fn value_series<P: PriceSource + ?Sized, F: FxSource + ?Sized>(
+    source: &P,
+    fx: &F,
+    points: &mut [BalancePoint],
+    currency: &str,
+) -> anyhow::Result<()> {
+    for point in points.iter_mut() {
+        let at = point.period_end.and_hms_opt(23, 59, 59).expect("valid time").and_utc().timestamp();
+        let mut total = BigDecimal::zero();
+        for balance in point.balances.iter_mut() {
+            if let Some(price) = fx::price_in(source, fx, &balance.asset_symbol, at, currency).await? {
+                let value = &balance.balance * price.price;
+                total += &value;
+                balance.fiat_value = Some(value);
+            }
+        }
+        point.total_fiat_value = total;
+    }
+    Ok(())
+}
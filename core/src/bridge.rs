@@ -0,0 +1,160 @@
+use crate::models::{BridgeTransfer, Chain, EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use uuid::Uuid;
+
+// How long after being sent a bridged transfer can arrive and still be paired with it.
+// Wormhole transfers can sit unredeemed for a while, so this is generous.
+pub const LINK_WINDOW_SECS: i64 = 3 * 86_400;
+
+// The same asset under its symbol on each chain, so a bridge can't pair one asset with another.
+// EVM contracts are stored lower-cased.
+const CANONICAL_ASSETS: &[(&str, &str)] = &[
+    ("SOL", "SOL"),
+    ("So11111111111111111111111111111111111111112", "SOL"), // Wrapped SOL (Solana)
+    ("ETH", "ETH"),
+    ("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "ETH"), // WETH (Ethereum)
+    ("USDC", "USDC"),
+    ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"), // Solana
+    ("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "USDC"), // Ethereum
+    ("0xaf88d065e77c8cc2239327c5edb3a432268e5831", "USDC"), // Arbitrum
+    ("0x833589fcd6edb6e08f4c7c32d4f71b54bda02913", "USDC"), // Base
+    ("0x0b2c639c533813f4aa9d7837caf62653d097ff85", "USDC"), // Optimism
+    ("0x3c499c542cef5e3811e1192ce70d8cc03d5c3359", "USDC"), // Polygon
+    ("USDT", "USDT"),
+    ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "USDT"), // Solana
+    ("0xdac17f958d2ee523a2206206994597c13d831ec7", "USDT"), // Ethereum
+];
+
+/// The asset `symbol` is on any chain, if it's a well-known one (e.g. "USDC" for the USDC mint on Solana).
+pub fn canonical_asset(symbol: &str) -> Option<&'static str> {
+    CANONICAL_ASSETS.iter().find(|(s, _)| *s == symbol).map(|(_, canonical)| *canonical)
+}
+
+/// Largest share of the amount sent that the bridge may keep as fees for the two sides to still pair up.
+pub fn max_fee_share() -> BigDecimal {
+    BigDecimal::from_str("0.05").unwrap()
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LinkSummary {
+    pub linked: usize,   // Pairs made in this pass
+    pub unpaired: usize, // Bridge entries still without their other side
+}
+
+/// Pairs bridge outflows with the inflows they became on another chain, so basis can follow the asset
+/// (`lots::match_fifo`). `chains` maps transaction ids to the chain they were ingested from.
+///
+/// A pair is an outflow and a later inflow of the same user, on different chains, within `LINK_WINDOW_SECS`,
+/// where the inflow is the amount sent less at most `max_fee_share` of it and at least one side was decoded as
+/// a bridge transfer. A decoded destination chain, address or transfer id must agree with the other side.
+/// Candidates are taken closest in time first, with matching transfer ids ahead of everything else.
+/// Both sides become `Bridge` entries pointing at each other.
+///
+/// Transfers that swap assets on the way (e.g. a deBridge order giving USDC and taking ETH) aren't paired
+/// when `canonical_asset` knows both sides. Other tokens, such as a wrapped mint, pair on the amount alone.
+pub fn link_transfers(entries: &mut [LedgerEntry], chains: &HashMap<Uuid, Chain>) -> LinkSummary {
+    let chain_of = |e: &LedgerEntry| chains.get(&e.transaction_id).map(|c| c.name().to_string());
+    let unlinked = |e: &LedgerEntry| {
+        matches!(e.entry_type, EntryType::Bridge | EntryType::Transfer)
+            && e.bridge.as_ref().is_none_or(|b| b.linked_entry_id.is_none())
+    };
+
+    let outflows: Vec<usize> = (0..entries.len()).filter(|i| unlinked(&entries[*i]) && entries[*i].amount < BigDecimal::zero()).collect();
+    let inflows: Vec<usize> = (0..entries.len()).filter(|i| unlinked(&entries[*i]) && entries[*i].amount > BigDecimal::zero()).collect();
+
+    // (transfer ids differ or are unknown, seconds in transit, outflow, inflow)
+    let mut candidates = Vec::new();
+    for &o in &outflows {
+        for &i in &inflows {
+            let (out, inc) = (&entries[o], &entries[i]);
+            if out.bridge.is_none() && inc.bridge.is_none() {
+                continue;
+            }
+            let (Some(from), Some(to)) = (chain_of(out), chain_of(inc)) else { continue };
+            let transit = inc.timestamp - out.timestamp;
+            if from == to || out.user_id != inc.user_id || !(0..=LINK_WINDOW_SECS).contains(&transit) {
+                continue;
+            }
+
+            if let (Some(a), Some(b)) = (canonical_asset(&out.asset_symbol), canonical_asset(&inc.asset_symbol)) {
+                if a != b {
+                    continue;
+                }
+            }
+
+            let sent = out.amount.abs();
+            if inc.amount > sent || inc.amount < &sent - &sent * max_fee_share() {
+                continue;
+            }
+
+            if let Some(b) = &out.bridge {
+                if b.destination_chain.as_ref().is_some_and(|c| *c != to)
+                    || b.destination_address.as_ref().is_some_and(|a| !a.eq_ignore_ascii_case(&inc.wallet_address))
+                {
+                    continue;
+                }
+            }
+            let ids = (
+                out.bridge.as_ref().and_then(|b| b.transfer_id.as_ref()),
+                inc.bridge.as_ref().and_then(|b| b.transfer_id.as_ref()),
+            );
+            if let (Some(a), Some(b)) = ids {
+                if a != b {
+                    continue;
+                }
+            }
+            let same_id = matches!(ids, (Some(_), Some(_)));
+            candidates.push((!same_id, transit, o, i));
+        }
+    }
+    candidates.sort();
+
+    let mut taken = HashSet::new();
+    let mut summary = LinkSummary::default();
+    for (_, _, o, i) in candidates {
+        if taken.contains(&o) || taken.contains(&i) {
+            continue;
+        }
+        taken.insert(o);
+        taken.insert(i);
+
+        let to = chain_of(&entries[i]);
+        let known = entries[o].bridge.clone().or_else(|| entries[i].bridge.clone()).unwrap();
+        let out_id = entries[o].id;
+        let in_id = entries[i].id;
+        let in_wallet = entries[i].wallet_address.clone();
+
+        let out = &mut entries[o];
+        let mut sent = out.bridge.take().unwrap_or_else(|| BridgeTransfer { transfer_id: None, ..known.clone() });
+        sent.destination_chain = sent.destination_chain.or(to);
+        sent.destination_address = sent.destination_address.or(Some(in_wallet));
+        out.entry_type = EntryType::Bridge;
+
+        let inc = &mut entries[i];
+        let mut received = inc.bridge.take().unwrap_or_else(|| BridgeTransfer { transfer_id: None, ..known.clone() });
+        received.destination_chain = sent.destination_chain.clone();
+        received.destination_address = sent.destination_address.clone();
+        received.transfer_id = received.transfer_id.or(sent.transfer_id.clone());
+        received.linked_entry_id = Some(out_id);
+        sent.transfer_id = sent.transfer_id.or(received.transfer_id.clone());
+        sent.linked_entry_id = Some(in_id);
+        inc.bridge = Some(received);
+        inc.entry_type = EntryType::Bridge;
+        entries[o].bridge = Some(sent);
+
+        summary.linked += 1;
+    }
+    summary.unpaired = entries.iter().filter(|e| matches!(e.entry_type, EntryType::Bridge) && linked_entry(e).is_none()).count();
+    summary
+}
+
+/// The entry on the other chain this one was paired with, if any.
+pub fn linked_entry(entry: &LedgerEntry) -> Option<Uuid> {
+    match entry.entry_type {
+        EntryType::Bridge => entry.bridge.as_ref().and_then(|b| b.linked_entry_id),
+        _ => None,
+    }
+}
@@ -0,0 +1,157 @@
+use crate::fx::ensure_single_currency;
+use crate::lots::{self, RealizedGain};
+use crate::models::LedgerEntry;
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use chrono::{DateTime, Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Term {
+    ShortTerm, // Part I
+    LongTerm,  // Part II
+}
+
+// One line of Form 8949, columns (a) through (e) and (h)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Form8949Row {
+    pub description: String,
+    pub date_acquired: Option<NaiveDate>, // None is reported as "VARIOUS"
+    pub date_sold: NaiveDate,
+    pub proceeds: BigDecimal,
+    pub cost_basis: BigDecimal,
+    pub gain_or_loss: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleDLine {
+    pub proceeds: BigDecimal,
+    pub cost_basis: BigDecimal,
+    pub gain_or_loss: BigDecimal,
+}
+
+// Schedule D totals carried from the 8949 parts (lines 3 and 10 for digital assets without a 1099-B)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleD {
+    pub short_term: ScheduleDLine,
+    pub long_term: ScheduleDLine,
+    pub net_gain_or_loss: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Form8949Report {
+    pub tax_year: i32,
+    pub currency: String,
+    pub short_term: Vec<Form8949Row>,
+    pub long_term: Vec<Form8949Row>,
+    pub schedule_d: ScheduleD,
+    // Assets left off because an entry carrying basis into them has no fiat value
+    pub unvalued_assets: Vec<String>,
+}
+
+/// Matches lots for each `lots::carry_groups` group separately and builds the report from the
+/// gains. A group with an unvalued entry is left off and its assets listed in `unvalued_assets`,
+/// rather than one unpriced token failing the whole report.
+pub fn from_entries(entries: &[LedgerEntry], tax_year: i32, currency: &str) -> anyhow::Result<Form8949Report> {
+    // Checked up front so mixed currencies fail the report instead of passing for unvalued assets
+    ensure_single_currency(entries)?;
+    let mut gains = Vec::new();
+    let mut unvalued_assets = Vec::new();
+    for group in lots::carry_groups(entries) {
+        match lots::match_fifo(&group) {
+            Ok(report) => gains.extend(report.realized),
+            Err(_) => {
+                let mut assets: Vec<String> = group.into_iter().map(|e| e.asset_symbol).collect();
+                assets.dedup();
+                unvalued_assets.extend(assets);
+            }
+        }
+    }
+    unvalued_assets.sort();
+    unvalued_assets.dedup();
+    gains.sort_by_key(|g| g.disposed_at);
+
+    let mut report = build(&gains, tax_year, currency)?;
+    report.unvalued_assets = unvalued_assets;
+    Ok(report)
+}
+
+/// Builds the report from realized gains, keeping disposals dated within `tax_year` (UTC).
+/// `currency` labels the amounts; the gains must already be expressed in it.
+pub fn build(gains: &[RealizedGain], tax_year: i32, currency: &str) -> anyhow::Result<Form8949Report> {
+    let mut short_term = Vec::new();
+    let mut long_term = Vec::new();
+
+    for gain in gains {
+        let date_sold = to_date(gain.disposed_at)?;
+        if date_sold.year() != tax_year {
+            continue;
+        }
+        let date_acquired = gain.acquired_at.map(to_date).transpose()?;
+
+        // Column (h) is (d) minus (e) as printed, not the unrounded gain rounded on its own
+        let proceeds = cents(&gain.proceeds);
+        let cost_basis = cents(&gain.cost_basis);
+        let row = Form8949Row {
+            description: format!("{} {}", gain.quantity.normalized(), gain.asset_symbol),
+            date_acquired,
+            date_sold,
+            gain_or_loss: &proceeds - &cost_basis,
+            proceeds,
+            cost_basis,
+        };
+
+        match term(date_acquired, date_sold) {
+            Term::ShortTerm => short_term.push(row),
+            Term::LongTerm => long_term.push(row),
+        }
+    }
+
+    let short_line = totals(&short_term);
+    let long_line = totals(&long_term);
+    let net_gain_or_loss = &short_line.gain_or_loss + &long_line.gain_or_loss;
+
+    Ok(Form8949Report {
+        tax_year,
+        currency: currency.to_string(),
+        short_term,
+        long_term,
+        schedule_d: ScheduleD {
+            short_term: short_line,
+            long_term: long_line,
+            net_gain_or_loss,
+        },
+        unvalued_assets: Vec::new(),
+    })
+}
+
+/// Long-term means held for more than one year. Unknown acquisitions are treated as short-term.
+pub fn term(date_acquired: Option<NaiveDate>, date_sold: NaiveDate) -> Term {
+    match date_acquired.and_then(|d| d.checked_add_months(Months::new(12))) {
+        Some(anniversary) if date_sold > anniversary => Term::LongTerm,
+        _ => Term::ShortTerm,
+    }
+}
+
+fn totals(rows: &[Form8949Row]) -> ScheduleDLine {
+    let mut line = ScheduleDLine {
+        proceeds: BigDecimal::zero(),
+        cost_basis: BigDecimal::zero(),
+        gain_or_loss: BigDecimal::zero(),
+    };
+    for row in rows {
+        line.proceeds += &row.proceeds;
+        line.cost_basis += &row.cost_basis;
+        line.gain_or_loss += &row.gain_or_loss;
+    }
+    line
+}
+
+fn to_date(timestamp: i64) -> anyhow::Result<NaiveDate> {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.date_naive())
+        .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {}", timestamp))
+}
+
+fn cents(value: &BigDecimal) -> BigDecimal {
+    value.with_scale_round(2, RoundingMode::HalfEven)
+}
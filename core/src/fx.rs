@@ -0,0 +1,158 @@
+use crate::models::LedgerEntry;
+use crate::pricing::{PricePoint, PriceSource};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+// Valuations priced before currencies were tracked were all USD
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+// Daily reference rate: 1 unit of base = rate units of quote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxRate {
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub rate_date: NaiveDate,
+    pub rate: BigDecimal,
+    pub source: String,
+}
+
+#[async_trait::async_trait]
+pub trait FxSource: Send + Sync {
+    /// Latest rate for the pair published on or before `date`.
+    async fn rate_on(&self, base: &str, quote: &str, date: NaiveDate) -> anyhow::Result<Option<BigDecimal>>;
+}
+
+/// Upper-cased ISO 4217 style code, e.g. "eur" -> "EUR".
+pub fn normalize_currency(code: &str) -> anyhow::Result<String> {
+    let code = code.trim().to_uppercase();
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(anyhow::anyhow!("Invalid currency code: {}", code));
+    }
+    Ok(code)
+}
+
+/// Cost basis math is only meaningful in one currency; returns it (None if nothing is valued).
+pub fn ensure_single_currency(entries: &[LedgerEntry]) -> anyhow::Result<Option<String>> {
+    let mut currency: Option<String> = None;
+    for entry in entries.iter().filter(|e| e.fiat_value.is_some()) {
+        let code = entry.fiat_currency.as_deref().unwrap_or(DEFAULT_CURRENCY);
+        match &currency {
+            Some(c) if c != code => {
+                return Err(anyhow::anyhow!("Ledger mixes {} and {} valuations; convert to one currency first", c, code))
+            }
+            Some(_) => {}
+            None => currency = Some(code.to_string()),
+        }
+    }
+    Ok(currency)
+}
+
+/// Converts `value` from one currency to another at the rate for `date`,
+/// dividing by the inverse pair when only that one is stored.
+pub async fn convert<F: FxSource + ?Sized>(
+    fx: &F,
+    value: &BigDecimal,
+    from: &str,
+    to: &str,
+    date: NaiveDate,
+) -> anyhow::Result<Option<BigDecimal>> {
+    if from == to {
+        return Ok(Some(value.clone()));
+    }
+    if let Some(rate) = fx.rate_on(from, to, date).await? {
+        return Ok(Some(value * rate));
+    }
+    match fx.rate_on(to, from, date).await? {
+        Some(inverse) if !inverse.is_zero() => Ok(Some(value / inverse)),
+        _ => Ok(None),
+    }
+}
+
+/// Prices `asset` in `currency`, falling back to the USD candles converted at the rate
+/// for the day (UTC) when none are stored in that currency. None if either is missing.
+pub async fn price_in<P: PriceSource + ?Sized, F: FxSource + ?Sized>(
+    source: &P,
+    fx: &F,
+    asset: &str,
+    timestamp: i64,
+    currency: &str,
+) -> anyhow::Result<Option<PricePoint>> {
+    if let Some(point) = source.price_at(asset, timestamp, currency).await? {
+        return Ok(Some(point));
+    }
+    if currency == DEFAULT_CURRENCY {
+        return Ok(None);
+    }
+    let Some(point) = source.price_at(asset, timestamp, DEFAULT_CURRENCY).await? else { return Ok(None) };
+
+    let date = DateTime::from_timestamp(timestamp, 0)
+        .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {}", timestamp))?
+        .date_naive();
+    let price = convert(fx, &point.price, DEFAULT_CURRENCY, currency, date).await?;
+    Ok(price.map(|price| PricePoint { price, ..point }))
+}
+
+/// Re-expresses every valued entry in `target`, using the rate for the entry's day (UTC).
+/// Fails if a needed rate is missing rather than mixing currencies in a report.
+pub async fn convert_entries<F: FxSource + ?Sized>(fx: &F, entries: &mut [LedgerEntry], target: &str) -> anyhow::Result<()> {
+    let target = normalize_currency(target)?;
+
+    for entry in entries.iter_mut() {
+        let Some(value) = &entry.fiat_value else { continue };
+        let from = entry.fiat_currency.clone().unwrap_or_else(|| DEFAULT_CURRENCY.to_string());
+        if from == target {
+            entry.fiat_currency = Some(target.clone());
+            continue;
+        }
+
+        let date = DateTime::from_timestamp(entry.timestamp, 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {} on entry {}", entry.timestamp, entry.id))?
+            .date_naive();
+        let converted = convert(fx, value, &from, &target, date)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No {}/{} FX rate on or before {}", from, target, date))?;
+
+        entry.fiat_value = Some(converted);
+        entry.fiat_currency = Some(target.clone());
+    }
+    Ok(())
+}
+
+/// In-memory rate table, for tests and small imports.
+#[derive(Debug, Default, Clone)]
+pub struct FxTable {
+    rates: Vec<FxRate>,
+}
+
+impl FxTable {
+    pub fn new(rates: Vec<FxRate>) -> Self {
+        Self { rates }
+    }
+
+    /// Adds rates, replacing any already held for the same pair and date.
+    pub fn upsert(&mut self, rates: impl IntoIterator<Item = FxRate>) {
+        for rate in rates {
+            self.rates.retain(|r| {
+                (&r.base_currency, &r.quote_currency, r.rate_date) != (&rate.base_currency, &rate.quote_currency, rate.rate_date)
+            });
+            self.rates.push(rate);
+        }
+    }
+
+    // Synchronous, like `PriceHistory`'s lookups, for stores holding the table behind a lock
+    pub(crate) fn rate(&self, base: &str, quote: &str, date: NaiveDate) -> Option<BigDecimal> {
+        self.rates
+            .iter()
+            .filter(|r| r.base_currency == base && r.quote_currency == quote && r.rate_date <= date)
+            .max_by_key(|r| r.rate_date)
+            .map(|r| r.rate.clone())
+    }
+}
+
+#[async_trait::async_trait]
+impl FxSource for FxTable {
+    async fn rate_on(&self, base: &str, quote: &str, date: NaiveDate) -> anyhow::Result<Option<BigDecimal>> {
+        Ok(self.rate(base, quote, date))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::fx;
+use crate::models::{EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, RoundingMode, Zero};
+use chrono::{DateTime, Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Income received from one source in one asset during one calendar month
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncomeLine {
+    pub month: String, // YYYY-MM (UTC)
+    pub asset_symbol: String,
+    pub source_program: Option<String>,
+    pub entries: usize,
+    pub quantity: BigDecimal,
+    pub fair_market_value: BigDecimal, // Sum of fiat_value at receipt
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncomeReport {
+    pub tax_year: i32,
+    pub currency: String,
+    pub lines: Vec<IncomeLine>,
+    pub total_fair_market_value: BigDecimal, // Schedule 1 "other income" figure
+}
+
+/// Groups staking rewards, airdrops and other income received during `tax_year` (UTC)
+/// by month, asset and source program. Entries must already be valued in `currency`.
+pub fn build(entries: &[LedgerEntry], tax_year: i32, currency: &str) -> anyhow::Result<IncomeReport> {
+    let mut received = Vec::new();
+    for entry in entries {
+        if !matches!(entry.entry_type, EntryType::Income | EntryType::Staking) || entry.amount <= BigDecimal::zero() {
+            continue;
+        }
+        if to_date(entry.timestamp)?.year() == tax_year {
+            received.push(entry.clone());
+        }
+    }
+
+    if let Some(found) = fx::ensure_single_currency(&received)? {
+        if found != currency {
+            anyhow::bail!("Income is valued in {} but the report was requested in {}", found, currency);
+        }
+    }
+
+    let mut groups: BTreeMap<(String, String, Option<String>), IncomeLine> = BTreeMap::new();
+    for entry in &received {
+        // Income is taxed at its value when received, so an unvalued entry can't be reported
+        let value = entry
+            .fiat_value
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Income entry {} has no fiat value at receipt", entry.id))?;
+
+        let month = to_date(entry.timestamp)?.format("%Y-%m").to_string();
+        let key = (month.clone(), entry.asset_symbol.clone(), entry.source_program.clone());
+        let line = groups.entry(key).or_insert_with(|| IncomeLine {
+            month,
+            asset_symbol: entry.asset_symbol.clone(),
+            source_program: entry.source_program.clone(),
+            entries: 0,
+            quantity: BigDecimal::zero(),
+            fair_market_value: BigDecimal::zero(),
+        });
+        line.entries += 1;
+        line.quantity += &entry.amount;
+        line.fair_market_value += value;
+    }
+
+    let mut lines: Vec<IncomeLine> = groups.into_values().collect();
+    let mut total = BigDecimal::zero();
+    for line in lines.iter_mut() {
+        line.fair_market_value = line.fair_market_value.with_scale_round(2, RoundingMode::HalfEven);
+        total += &line.fair_market_value;
+    }
+
+    Ok(IncomeReport {
+        tax_year,
+        currency: currency.to_string(),
+        lines,
+        total_fair_market_value: total,
+    })
+}
+
+/// Writes the report lines as CSV, one row per month/asset/source.
+pub fn write_csv<W: std::io::Write>(report: &IncomeReport, writer: W) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["month", "asset", "source_program", "entries", "quantity", "fair_market_value", "currency"])?;
+    for line in &report.lines {
+        writer.write_record([
+            line.month.clone(),
+            line.asset_symbol.clone(),
+            line.source_program.clone().unwrap_or_default(),
+            line.entries.to_string(),
+            line.quantity.normalized().to_string(),
+            line.fair_market_value.to_string(),
+            report.currency.clone(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn to_date(timestamp: i64) -> anyhow::Result<NaiveDate> {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.date_naive())
+        .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {}", timestamp))
+}
@@ -0,0 +1,105 @@
+use crate::models::{EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// What one protocol account (obligation, margin account) holds of one asset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LendingPosition {
+    pub protocol: String, // Program the position lives in
+    pub account: String,
+    pub asset_symbol: String,
+    pub supplied: BigDecimal, // Collateral principal still deposited
+    pub borrowed: BigDecimal, // Loan principal still owed
+}
+
+#[derive(Default)]
+struct Principal {
+    supplied: BigDecimal,
+    borrowed: BigDecimal,
+}
+
+/// Walks collateral and loan entries in time order, tracking principal per protocol account and asset.
+/// Withdrawals beyond what was supplied are interest earned and split off as `Income`; repayments beyond
+/// what was borrowed are interest paid and split off as `Fee`. Returns the positions still open.
+///
+/// A withdrawal or repayment for a position with no earlier deposit or borrow is all principal,
+/// as the history that opened it wasn't ingested.
+pub fn track_positions(entries: &mut Vec<LedgerEntry>) -> Vec<LendingPosition> {
+    let mut order: Vec<usize> = (0..entries.len())
+        .filter(|i| matches!(entries[*i].entry_type, EntryType::Collateral | EntryType::Loan))
+        .collect();
+    order.sort_by_key(|i| entries[*i].timestamp);
+
+    let mut positions: BTreeMap<(String, String, String), Principal> = BTreeMap::new();
+    let mut interest = Vec::new();
+
+    for i in order {
+        let entry = &mut entries[i];
+        let key = (
+            entry.source_program.clone().unwrap_or_default(),
+            entry.position_account.clone().unwrap_or_default(),
+            entry.asset_symbol.clone(),
+        );
+        let seen = positions.contains_key(&key);
+        let position = positions.entry(key).or_default();
+
+        let (principal, inflow) = match entry.entry_type {
+            EntryType::Collateral => (&mut position.supplied, false),
+            _ => (&mut position.borrowed, true),
+        };
+        let quantity = entry.amount.abs();
+        // Deposits and borrows open principal; withdrawals and repayments close it
+        if (entry.amount > BigDecimal::zero()) == inflow {
+            *principal += &quantity;
+            continue;
+        }
+        if !seen {
+            continue;
+        }
+
+        let closed = if quantity > *principal { principal.clone() } else { quantity.clone() };
+        *principal -= &closed;
+        let excess = &quantity - &closed;
+        if excess.is_zero() {
+            continue;
+        }
+
+        let (entry_type, sign) = match entry.entry_type {
+            EntryType::Collateral => (EntryType::Income, BigDecimal::from(1)),
+            _ => (EntryType::Fee, BigDecimal::from(-1)),
+        };
+        let mut split = entry.clone();
+        split.id = Uuid::new_v4();
+        split.entry_type = entry_type;
+        split.amount = &excess * &sign;
+        // Whatever the movement was valued at is shared out by quantity
+        if let Some(value) = &entry.fiat_value {
+            split.fiat_value = Some(value * &excess / &quantity);
+            entry.fiat_value = Some(value * &closed / &quantity);
+        }
+        entry.amount = &closed * &sign;
+        interest.push(split);
+    }
+
+    entries.extend(interest.into_iter().filter(|e| !e.amount.is_zero()));
+    entries.retain(|e| !(matches!(e.entry_type, EntryType::Collateral | EntryType::Loan) && e.amount.is_zero()));
+
+    positions
+        .into_iter()
+        .filter(|(_, p)| !p.supplied.is_zero() || !p.borrowed.is_zero())
+        .map(|((protocol, account, asset_symbol), p)| LendingPosition {
+            protocol,
+            account,
+            asset_symbol,
+            supplied: p.supplied,
+            borrowed: p.borrowed,
+        })
+        .collect()
+}
+
+/// Open positions implied by already-tracked entries.
+pub fn positions(entries: &[LedgerEntry]) -> Vec<LendingPosition> {
+    track_positions(&mut entries.to_vec())
+}
@@ -1 +1,16 @@
 pub mod models;
+pub mod uk_pooling;
+pub mod lots;
+pub mod form_8949;
+pub mod pricing;
+pub mod fx;
+pub mod portfolio;
+pub mod balances;
+pub mod reconcile;
+pub mod income;
+pub mod spam;
+pub mod lending;
+pub mod bridge;
+pub mod storage;
+pub mod renormalize;
+pub mod adjustments;
\ No newline at end of file
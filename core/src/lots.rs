@@ -0,0 +1,246 @@
+use crate::bridge;
+use crate::fx::ensure_single_currency;
+use crate::models::{EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use uuid::Uuid;
+
+// An open acquisition that later disposals draw down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lot {
+    pub asset_symbol: String,
+    pub entry_id: Uuid,
+    pub acquired_at: i64,
+    pub quantity: BigDecimal,
+    pub cost_basis: BigDecimal,
+}
+
+// One disposal matched against one lot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealizedGain {
+    pub asset_symbol: String,
+    pub disposal_entry_id: Uuid,
+    pub acquired_at: Option<i64>, // None when no lot was left to match (history incomplete, zero basis)
+    pub disposed_at: i64,
+    pub quantity: BigDecimal,
+    pub proceeds: BigDecimal,
+    pub cost_basis: BigDecimal,
+    pub gain: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotReport {
+    pub realized: Vec<RealizedGain>,
+    pub open_lots: Vec<Lot>,
+}
+
+/// Matches disposals (negative amounts) against the oldest open lots of the same asset.
+/// Every non-zero entry must carry a `fiat_value`. Collateral moves are skipped; the lots stay open.
+///
+/// Adding liquidity isn't a disposal: the lots of the assets added are drawn down and their basis
+/// carried to the pool share received, which needs no `fiat_value`. Redeeming the share disposes of it.
+///
+/// Neither is a linked bridge transfer: the lots sent are carried, acquisition dates and all, to the
+/// inflow on the other chain, spread over what arrived. Bridge fees kept out of the amount add to its basis.
+pub fn match_fifo(entries: &[LedgerEntry]) -> anyhow::Result<LotReport> {
+    ensure_single_currency(entries)?;
+
+    let mut sorted: Vec<&LedgerEntry> = entries
+        .iter()
+        .filter(|e| !e.amount.is_zero() && !e.entry_type.keeps_ownership())
+        .collect();
+    // Pool shares sort after the assets that paid for them, and bridged inflows after what was sent
+    let sent_at: HashMap<Uuid, i64> = entries.iter().filter(|e| bridge::linked_entry(e).is_some()).map(|e| (e.id, e.timestamp)).collect();
+    sorted.sort_by_key(|e| {
+        let sent = bridge::linked_entry(e).filter(|_| e.amount > BigDecimal::zero()).and_then(|id| sent_at.get(&id));
+        (e.timestamp.max(sent.copied().unwrap_or(i64::MIN)), is_share_received(e))
+    });
+
+    let liquidity_adds: HashSet<Uuid> = entries.iter().filter(|e| is_share_received(e)).map(|e| e.transaction_id).collect();
+    let mut carried: HashMap<Uuid, BigDecimal> = HashMap::new();
+    // Per bridge outflow: quantity sent and what it took from each lot
+    let mut bridged: HashMap<Uuid, (BigDecimal, Vec<Drawn>)> = HashMap::new();
+
+    let mut open: BTreeMap<String, VecDeque<Lot>> = BTreeMap::new();
+    let mut realized = Vec::new();
+
+    for entry in sorted {
+        let lots = open.entry(entry.asset_symbol.clone()).or_default();
+
+        if matches!(entry.entry_type, EntryType::Liquidity) && entry.amount < BigDecimal::zero() && liquidity_adds.contains(&entry.transaction_id) {
+            // Whatever has no lot left to draw from carries no basis
+            let basis = draw(lots, &entry.amount.abs())
+                .0
+                .iter()
+                .fold(BigDecimal::zero(), |acc, (_, _, cost_basis)| acc + cost_basis);
+            *carried.entry(entry.transaction_id).or_default() += basis;
+            continue;
+        }
+
+        if bridge::linked_entry(entry).is_some() && entry.amount < BigDecimal::zero() {
+            let sent = entry.amount.abs();
+            let (mut matched, remaining) = draw(lots, &sent);
+            if remaining > BigDecimal::zero() {
+                matched.push((entry.timestamp, remaining, BigDecimal::zero()));
+            }
+            bridged.insert(entry.id, (sent, matched));
+            continue;
+        }
+
+        // An inflow whose outflow isn't among the entries is valued like any other acquisition
+        let carried_over = match bridge::linked_entry(entry) {
+            Some(id) if entry.amount > BigDecimal::zero() => bridged.remove(&id),
+            _ => None,
+        };
+        if let Some((sent, matched)) = carried_over {
+            for (acquired_at, quantity, cost_basis) in matched {
+                lots.push_back(Lot {
+                    asset_symbol: entry.asset_symbol.clone(),
+                    entry_id: entry.id,
+                    acquired_at,
+                    quantity: &quantity * &entry.amount / &sent,
+                    cost_basis,
+                });
+            }
+            continue;
+        }
+
+        let value = if is_share_received(entry) {
+            carried.remove(&entry.transaction_id).unwrap_or_default()
+        } else {
+            entry
+                .fiat_value
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Ledger entry {} has no fiat_value", entry.id))?
+                .abs()
+        };
+
+        if entry.amount > BigDecimal::zero() {
+            lots.push_back(Lot {
+                asset_symbol: entry.asset_symbol.clone(),
+                entry_id: entry.id,
+                acquired_at: entry.timestamp,
+                quantity: entry.amount.clone(),
+                cost_basis: value,
+            });
+            continue;
+        }
+
+        let disposed = entry.amount.abs();
+        let (matched, remaining) = draw(lots, &disposed);
+
+        for (acquired_at, quantity, cost_basis) in matched {
+            let proceeds = &value * &quantity / &disposed;
+            realized.push(RealizedGain {
+                asset_symbol: entry.asset_symbol.clone(),
+                disposal_entry_id: entry.id,
+                acquired_at: Some(acquired_at),
+                disposed_at: entry.timestamp,
+                gain: &proceeds - &cost_basis,
+                quantity,
+                proceeds,
+                cost_basis,
+            });
+        }
+
+        if remaining > BigDecimal::zero() {
+            let proceeds = &value * &remaining / &disposed;
+            realized.push(RealizedGain {
+                asset_symbol: entry.asset_symbol.clone(),
+                disposal_entry_id: entry.id,
+                acquired_at: None,
+                disposed_at: entry.timestamp,
+                quantity: remaining,
+                gain: proceeds.clone(),
+                proceeds,
+                cost_basis: BigDecimal::zero(),
+            });
+        }
+    }
+
+    Ok(LotReport {
+        realized,
+        open_lots: open.into_values().flatten().collect(),
+    })
+}
+
+/// Splits entries into the groups `match_fifo` has to see together: assets whose basis is carried
+/// into one another (what's paid into a pool and the share received for it, or the two sides of a
+/// linked bridge transfer, which may go by different symbols). Matching each group
+/// on its own keeps one unvalued entry from blanking the basis of unrelated assets.
+pub fn carry_groups(entries: &[LedgerEntry]) -> Vec<Vec<LedgerEntry>> {
+    let mut assets: Vec<&str> = entries.iter().map(|e| e.asset_symbol.as_str()).collect();
+    assets.sort();
+    assets.dedup();
+    let index = |asset: &str| assets.binary_search(&asset).expect("collected above");
+    let mut parent: Vec<usize> = (0..assets.len()).collect();
+
+    let shares: HashMap<Uuid, &str> = entries.iter().filter(|e| is_share_received(e)).map(|e| (e.transaction_id, e.asset_symbol.as_str())).collect();
+    for entry in entries.iter().filter(|e| matches!(e.entry_type, EntryType::Liquidity) && e.amount < BigDecimal::zero()) {
+        if let Some(share) = shares.get(&entry.transaction_id) {
+            union(&mut parent, index(&entry.asset_symbol), index(share));
+        }
+    }
+    let sent: HashMap<Uuid, &str> = entries
+        .iter()
+        .filter(|e| bridge::linked_entry(e).is_some() && e.amount < BigDecimal::zero())
+        .map(|e| (e.id, e.asset_symbol.as_str()))
+        .collect();
+    for entry in entries.iter().filter(|e| e.amount > BigDecimal::zero()) {
+        if let Some(asset) = bridge::linked_entry(entry).and_then(|id| sent.get(&id)) {
+            union(&mut parent, index(&entry.asset_symbol), index(asset));
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<LedgerEntry>> = BTreeMap::new();
+    for entry in entries {
+        let root = find(&mut parent, index(&entry.asset_symbol));
+        groups.entry(root).or_default().push(entry.clone());
+    }
+    groups.into_values().collect()
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(parent, a), find(parent, b));
+    parent[a] = b;
+}
+
+pub(crate) fn is_share_received(entry: &LedgerEntry) -> bool {
+    matches!(entry.entry_type, EntryType::LpToken) && entry.amount > BigDecimal::zero()
+}
+
+// (acquired_at, quantity, cost basis) taken from one lot
+type Drawn = (i64, BigDecimal, BigDecimal);
+
+// Takes `quantity` from the oldest lots, one `Drawn` per lot touched,
+// and how much was left over once the lots ran out
+fn draw(lots: &mut VecDeque<Lot>, quantity: &BigDecimal) -> (Vec<Drawn>, BigDecimal) {
+    let mut remaining = quantity.clone();
+    let mut matched = Vec::new();
+
+    while remaining > BigDecimal::zero() {
+        let Some(lot) = lots.front_mut() else { break };
+
+        let quantity = if lot.quantity < remaining { lot.quantity.clone() } else { remaining.clone() };
+        let cost_basis = &lot.cost_basis * &quantity / &lot.quantity;
+
+        lot.cost_basis -= &cost_basis;
+        lot.quantity -= &quantity;
+        remaining -= &quantity;
+        matched.push((lot.acquired_at, quantity, cost_basis));
+
+        if lot.quantity.is_zero() {
+            lots.pop_front();
+        }
+    }
+    (matched, remaining)
+}
@@ -7,6 +7,49 @@ pub enum Chain {
     Solana,
     Hyperliquid,
     Ethereum,
+    // Centralized exchange statements, imported from CSV
+    Coinbase,
+    Kraken,
+    Binance,
+    // Off-chain movements entered by hand (gifts, OTC trades)
+    Manual,
+    // Any other EVM network, by its configured name (e.g. "arbitrum")
+    Evm(String),
+}
+
+impl Chain {
+    /// Name stored in the `transactions.chain` column.
+    pub fn name(&self) -> &str {
+        match self {
+            Chain::Solana => "solana",
+            Chain::Hyperliquid => "hyperliquid",
+            Chain::Ethereum => "ethereum",
+            Chain::Coinbase => "coinbase",
+            Chain::Kraken => "kraken",
+            Chain::Binance => "binance",
+            Chain::Manual => "manual",
+            Chain::Evm(name) => name,
+        }
+    }
+
+    /// Inverse of `name`. Names that aren't built in are EVM networks, the only open-ended family,
+    /// so this is for names read back from storage: check user input against the configured networks.
+    pub fn from_name(name: &str) -> Chain {
+        match name {
+            "solana" => Chain::Solana,
+            "hyperliquid" => Chain::Hyperliquid,
+            "ethereum" => Chain::Ethereum,
+            "coinbase" => Chain::Coinbase,
+            "kraken" => Chain::Kraken,
+            "binance" => Chain::Binance,
+            "manual" => Chain::Manual,
+            other => Chain::Evm(other.to_string()),
+        }
+    }
+
+    pub fn is_evm(&self) -> bool {
+        matches!(self, Chain::Ethereum | Chain::Evm(_))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +59,60 @@ pub enum EntryType {
     Transfer,
     Staking,
     Income,
+    Collateral, // Supplied to or withdrawn from a lending protocol
+    Loan,       // Principal borrowed from or repaid to a lending protocol
+    Liquidity,  // Asset added to or removed from a liquidity pool
+    LpToken,    // Pool share (LP token or CLMM position NFT) received for liquidity or returned to redeem it
+    Bridge,     // Sent into or received out of a cross-chain bridge
+}
+
+impl EntryType {
+    /// Name stored in the `ledger_entries.entry_type` column.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryType::Trade => "trade",
+            EntryType::Fee => "fee",
+            EntryType::Transfer => "transfer",
+            EntryType::Staking => "staking",
+            EntryType::Income => "income",
+            EntryType::Collateral => "collateral",
+            EntryType::Loan => "loan",
+            EntryType::Liquidity => "liquidity",
+            EntryType::LpToken => "lp_token",
+            EntryType::Bridge => "bridge",
+        }
+    }
+
+    /// Inverse of `name`. Unknown names read as plain transfers.
+    pub fn from_name(name: &str) -> EntryType {
+        match name {
+            "trade" => EntryType::Trade,
+            "fee" => EntryType::Fee,
+            "staking" => EntryType::Staking,
+            "income" => EntryType::Income,
+            "collateral" => EntryType::Collateral,
+            "loan" => EntryType::Loan,
+            "liquidity" => EntryType::Liquidity,
+            "lp_token" => EntryType::LpToken,
+            "bridge" => EntryType::Bridge,
+            _ => EntryType::Transfer,
+        }
+    }
+
+    /// Like `from_name`, but unknown names are an error rather than a transfer.
+    pub fn parse(name: &str) -> anyhow::Result<EntryType> {
+        let entry_type = EntryType::from_name(name);
+        if entry_type.name() != name {
+            anyhow::bail!("Unknown entry type: {}", name);
+        }
+        Ok(entry_type)
+    }
+
+    /// Collateral stays the depositor's while a protocol holds it, so moving it in and out
+    /// is neither an acquisition nor a disposal.
+    pub fn keeps_ownership(&self) -> bool {
+        matches!(self, EntryType::Collateral)
+    }
 }
 
 // Bronze Layer: Raw Immutable Data
@@ -37,10 +134,74 @@ pub struct LedgerEntry {
     pub transaction_id: Uuid,
     pub user_id: Uuid,
     pub wallet_address: String,
+    pub timestamp: i64, // Copied from the parent transaction so tax logic can order entries
     pub asset_symbol: String,
     pub amount: BigDecimal, 
     pub entry_type: EntryType,
     pub fiat_value: Option<BigDecimal>,
+    pub fiat_currency: Option<String>,    // ISO code fiat_value is expressed in
+    pub price_source: Option<String>,     // Which price source valued fiat_value
+    pub price_resolution: Option<String>, // Candle width the price came from, e.g. "1h"
+    pub source_program: Option<String>,   // Program/contract the transaction invoked, e.g. a staking or airdrop program
+    #[serde(default)]
+    pub is_spam: bool,                    // Left out of balances and reports unless asked for
+    #[serde(default)]
+    pub position_account: Option<String>, // Protocol account holding the position, e.g. a lending obligation
+    #[serde(default)]
+    pub bridge: Option<BridgeTransfer>,   // Set on Bridge entries
+    #[serde(default)]
+    pub parser: Option<ParserVersion>,    // Unset on entries normalized before parsers were versioned
+}
+
+/// One side of a cross-chain bridge transfer. The destination is known on the sending side when the
+/// bridge instruction could be decoded; the link is filled in once the other side has been matched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeTransfer {
+    pub protocol: String,                    // e.g. "wormhole"
+    pub destination_chain: Option<String>,   // Chain name, as in `Chain::name`
+    pub destination_address: Option<String>,
+    pub transfer_id: Option<String>,         // The protocol's own id, e.g. a Wormhole sequence
+    pub linked_entry_id: Option<Uuid>,       // Entry on the other chain this one was paired with
+}
+
+/// Parser that produced a ledger entry. A parser's version goes up whenever its output changes, so
+/// entries from an older one can be found and rebuilt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParserVersion {
+    pub name: String, // e.g. "solana"
+    pub version: u32,
+}
+
+impl LedgerEntry {
+    /// Unpriced entry for a movement observed in `tx`.
+    pub fn new(tx: &Transaction, asset_symbol: String, amount: BigDecimal, entry_type: EntryType) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            transaction_id: tx.id,
+            user_id: tx.user_id,
+            wallet_address: tx.wallet_address.clone(),
+            timestamp: tx.timestamp,
+            asset_symbol,
+            amount,
+            entry_type,
+            fiat_value: None,
+            fiat_currency: None,
+            price_source: None,
+            price_resolution: None,
+            source_program: None,
+            is_spam: false,
+            position_account: None,
+            bridge: None,
+            parser: None,
+        }
+    }
+}
+
+// Per-user preferences that shape reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserSettings {
+    pub user_id: Uuid,
+    pub reporting_currency: String,
 }
 
 #[async_trait::async_trait]
@@ -0,0 +1,110 @@
+use crate::fx::{self, FxSource};
+use crate::lots;
+use crate::models::LedgerEntry;
+use crate::pricing::PriceSource;
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holding {
+    pub asset_symbol: String,
+    pub quantity: BigDecimal,
+    pub price: Option<BigDecimal>,        // None when the price store has nothing for the asset at that time
+    pub market_value: Option<BigDecimal>,
+    pub cost_basis: Option<BigDecimal>,   // Remaining FIFO lot basis. None if some entries are unvalued.
+    pub unrealized_gain: Option<BigDecimal>,
+    pub price_source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub at: i64,
+    pub currency: String,
+    pub holdings: Vec<Holding>,
+    // Totals only cover holdings that have both a price and a basis
+    pub total_market_value: BigDecimal,
+    pub total_cost_basis: BigDecimal,
+    pub total_unrealized_gain: BigDecimal,
+}
+
+/// Values the holdings implied by `entries` up to and including `at`.
+/// Entries must already be valued in `currency` (see `fx::convert_entries`); prices are
+/// converted from USD through `fx` when there's no history in `currency` itself.
+pub async fn snapshot<P: PriceSource + ?Sized, F: FxSource + ?Sized>(
+    source: &P,
+    fx: &F,
+    entries: &[LedgerEntry],
+    at: i64,
+    currency: &str,
+) -> anyhow::Result<PortfolioSnapshot> {
+    let entries: Vec<LedgerEntry> = entries.iter().filter(|e| e.timestamp <= at).cloned().collect();
+    let mut by_asset: BTreeMap<&str, Vec<&LedgerEntry>> = BTreeMap::new();
+    for entry in &entries {
+        by_asset.entry(entry.asset_symbol.as_str()).or_default().push(entry);
+    }
+
+    // Per group of assets carrying basis into each other, so one unpriced token doesn't blank out
+    // the basis of everything else, while a pool share still gets the basis of what paid for it
+    let mut basis: HashMap<String, Option<BigDecimal>> = HashMap::new();
+    for group in lots::carry_groups(&entries) {
+        let report = lots::match_fifo(&group).ok();
+        for entry in &group {
+            basis.entry(entry.asset_symbol.clone()).or_insert_with(|| {
+                report.as_ref().map(|r| {
+                    r.open_lots
+                        .iter()
+                        .filter(|lot| lot.asset_symbol == entry.asset_symbol)
+                        .fold(BigDecimal::zero(), |acc, lot| acc + &lot.cost_basis)
+                })
+            });
+        }
+    }
+
+    let mut snapshot = PortfolioSnapshot {
+        at,
+        currency: currency.to_string(),
+        holdings: Vec::new(),
+        total_market_value: BigDecimal::zero(),
+        total_cost_basis: BigDecimal::zero(),
+        total_unrealized_gain: BigDecimal::zero(),
+    };
+
+    for (asset, asset_entries) in by_asset {
+        // Collateral in a lending protocol is still held
+        let quantity = asset_entries
+            .iter()
+            .filter(|e| !e.entry_type.keeps_ownership())
+            .fold(BigDecimal::zero(), |acc, e| acc + &e.amount);
+        if quantity.is_zero() {
+            continue;
+        }
+
+        let cost_basis = basis.remove(asset).flatten();
+
+        let point = fx::price_in(source, fx, asset, at, currency).await?;
+        let market_value = point.as_ref().map(|p| &quantity * &p.price);
+        let unrealized_gain = match (&market_value, &cost_basis) {
+            (Some(value), Some(basis)) => Some(value - basis),
+            _ => None,
+        };
+
+        if let (Some(value), Some(basis), Some(gain)) = (&market_value, &cost_basis, &unrealized_gain) {
+            snapshot.total_market_value += value;
+            snapshot.total_cost_basis += basis;
+            snapshot.total_unrealized_gain += gain;
+        }
+
+        snapshot.holdings.push(Holding {
+            asset_symbol: asset.to_string(),
+            quantity,
+            price: point.as_ref().map(|p| p.price.clone()),
+            market_value,
+            cost_basis,
+            unrealized_gain,
+            price_source: point.map(|p| p.source),
+        });
+    }
+
+    Ok(snapshot)
+}
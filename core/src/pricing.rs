@@ -0,0 +1,291 @@
+use crate::fx::normalize_currency;
+use crate::models::{EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+// One OHLC bar of price history for an asset quoted in a fiat currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceCandle {
+    pub asset_symbol: String,
+    pub fiat_currency: String,
+    pub open_time: i64,
+    pub resolution_secs: i64,
+    pub open: BigDecimal,
+    pub high: BigDecimal,
+    pub low: BigDecimal,
+    pub close: BigDecimal,
+    pub source: String,
+}
+
+// The price a source resolved for a point in time, with enough context to audit it later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub price: BigDecimal,
+    pub source: String,
+    pub resolution: String, // e.g. "1h", the width of the candle the price came from
+    pub timestamp: i64,     // Open time of that candle
+}
+
+// Point observations (e.g. a price implied by a single swap) are stored as one-second candles
+pub const OBSERVATION_RESOLUTION_SECS: i64 = 1;
+pub const DEX_IMPLIED_SOURCE: &str = "dex-implied";
+// Pool shares valued at the assets that went into or came out of the pool with them
+pub const LIQUIDITY_POOL_SOURCE: &str = "liquidity-pool";
+
+#[async_trait::async_trait]
+pub trait PriceSource: Send + Sync {
+    async fn price_at(&self, asset: &str, timestamp: i64, fiat: &str) -> anyhow::Result<Option<PricePoint>>;
+
+    /// Closest point observation within `window_secs` of the timestamp. Sources without observations return None.
+    async fn nearest_observation(
+        &self,
+        _asset: &str,
+        _timestamp: i64,
+        _fiat: &str,
+        _window_secs: i64,
+    ) -> anyhow::Result<Option<PricePoint>> {
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PricingSummary {
+    pub priced: usize,
+    pub unpriced: usize,
+}
+
+/// Fills `fiat_value` (absolute amount times price) on entries that don't have one yet,
+/// recording which source and candle resolution produced it. With `propagate_window_secs`,
+/// entries with no candle fall back to the nearest point observation of the same asset in that window.
+/// Pool shares have no market of their own and are valued at the pool assets moved in the same transaction.
+pub async fn price_entries<P: PriceSource + ?Sized>(
+    source: &P,
+    entries: &mut [LedgerEntry],
+    fiat: &str,
+    propagate_window_secs: Option<i64>,
+) -> anyhow::Result<PricingSummary> {
+    let mut summary = PricingSummary::default();
+    let fiat = normalize_currency(fiat)?;
+    let fiat = fiat.as_str();
+
+    for entry in entries.iter_mut().filter(|e| e.fiat_value.is_none() && !matches!(e.entry_type, EntryType::LpToken)) {
+        let mut point = source.price_at(&entry.asset_symbol, entry.timestamp, fiat).await?;
+        if let (None, Some(window)) = (&point, propagate_window_secs) {
+            point = source
+                .nearest_observation(&entry.asset_symbol, entry.timestamp, fiat, window)
+                .await?
+                .map(|p| PricePoint {
+                    // Record how far the borrowed observation was, e.g. "~2h"
+                    resolution: format!("~{}", format_resolution((p.timestamp - entry.timestamp).abs().max(1))),
+                    ..p
+                });
+        }
+
+        match point {
+            Some(point) => {
+                entry.fiat_value = Some(entry.amount.abs() * &point.price);
+                entry.fiat_currency = Some(fiat.to_string());
+                entry.price_source = Some(point.source);
+                entry.price_resolution = Some(point.resolution);
+                summary.priced += 1;
+            }
+            None => summary.unpriced += 1,
+        }
+    }
+
+    // Total value of each transaction's pool legs, None if any of them is unpriced
+    let mut pool_legs: BTreeMap<Uuid, Option<BigDecimal>> = BTreeMap::new();
+    let mut shares: BTreeMap<Uuid, usize> = BTreeMap::new();
+    for entry in entries.iter() {
+        match entry.entry_type {
+            EntryType::Liquidity => {
+                let total = pool_legs.entry(entry.transaction_id).or_insert_with(|| Some(BigDecimal::zero()));
+                *total = match (total.take(), &entry.fiat_value) {
+                    (Some(total), Some(value)) => Some(total + value.abs()),
+                    _ => None,
+                };
+            }
+            EntryType::LpToken => *shares.entry(entry.transaction_id).or_default() += 1,
+            _ => {}
+        }
+    }
+    for entry in entries.iter_mut().filter(|e| e.fiat_value.is_none() && matches!(e.entry_type, EntryType::LpToken)) {
+        // With several shares in one transaction there's no telling how the value splits
+        match pool_legs.get(&entry.transaction_id) {
+            Some(Some(total)) if shares[&entry.transaction_id] == 1 => {
+                entry.fiat_value = Some(total.clone());
+                entry.fiat_currency = Some(fiat.to_string());
+                entry.price_source = Some(LIQUIDITY_POOL_SOURCE.to_string());
+                summary.priced += 1;
+            }
+            _ => summary.unpriced += 1,
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Derives per-unit prices for assets with no price history from swaps against a priced quote asset
+/// (e.g. SOL or USDC). Expects swap legs to have been classified as `Trade` entries.
+pub async fn implied_prices<P: PriceSource + ?Sized>(
+    source: &P,
+    entries: &[LedgerEntry],
+    fiat: &str,
+    quote_assets: &[String],
+) -> anyhow::Result<Vec<PriceCandle>> {
+    let mut swaps: BTreeMap<Uuid, Vec<&LedgerEntry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| matches!(e.entry_type, EntryType::Trade)) {
+        swaps.entry(entry.transaction_id).or_default().push(entry);
+    }
+
+    let mut candles = Vec::new();
+    for legs in swaps.values() {
+        let [a, b] = legs.as_slice() else { continue };
+        if a.amount.is_zero() || b.amount.is_zero() {
+            continue;
+        }
+        let (quote, base) = match (quote_assets.contains(&a.asset_symbol), quote_assets.contains(&b.asset_symbol)) {
+            (true, false) => (a, b),
+            (false, true) => (b, a),
+            _ => continue,
+        };
+
+        // Only fill gaps, never second-guess real price history
+        if source.price_at(&base.asset_symbol, base.timestamp, fiat).await?.is_some() {
+            continue;
+        }
+        let Some(quote_price) = source.price_at(&quote.asset_symbol, quote.timestamp, fiat).await? else {
+            continue;
+        };
+
+        let price = quote.amount.abs() * quote_price.price / base.amount.abs();
+        candles.push(PriceCandle {
+            asset_symbol: base.asset_symbol.clone(),
+            fiat_currency: fiat.to_uppercase(),
+            open_time: base.timestamp,
+            resolution_secs: OBSERVATION_RESOLUTION_SECS,
+            open: price.clone(),
+            high: price.clone(),
+            low: price.clone(),
+            close: price,
+            source: DEX_IMPLIED_SOURCE.to_string(),
+        });
+    }
+
+    Ok(candles)
+}
+
+/// In-memory price history, for tests and for pricing without a database.
+#[derive(Debug, Default, Clone)]
+pub struct PriceHistory {
+    candles: Vec<PriceCandle>,
+}
+
+impl PriceHistory {
+    pub fn new(candles: Vec<PriceCandle>) -> Self {
+        Self { candles }
+    }
+
+    pub fn extend(&mut self, candles: impl IntoIterator<Item = PriceCandle>) {
+        self.candles.extend(candles);
+    }
+
+    /// Adds candles, replacing any already held for the same asset, currency, width and open time.
+    pub fn upsert(&mut self, candles: impl IntoIterator<Item = PriceCandle>) {
+        for candle in candles {
+            self.candles.retain(|c| {
+                (&c.asset_symbol, &c.fiat_currency, c.resolution_secs, c.open_time)
+                    != (&candle.asset_symbol, &candle.fiat_currency, candle.resolution_secs, candle.open_time)
+            });
+            self.candles.push(candle);
+        }
+    }
+
+    // The lookups are synchronous so stores holding a history behind a lock can answer without awaiting
+    pub(crate) fn point_at(&self, asset: &str, timestamp: i64, fiat: &str) -> Option<PricePoint> {
+        // Finest candle that contains the timestamp wins
+        let candle = self
+            .candles
+            .iter()
+            .filter(|c| c.asset_symbol == asset && c.fiat_currency.eq_ignore_ascii_case(fiat))
+            .filter(|c| c.open_time <= timestamp && timestamp < c.open_time + c.resolution_secs)
+            .min_by_key(|c| c.resolution_secs);
+
+        candle.map(|c| PricePoint {
+            price: c.close.clone(),
+            source: c.source.clone(),
+            resolution: format_resolution(c.resolution_secs),
+            timestamp: c.open_time,
+        })
+    }
+
+    pub(crate) fn observation_near(&self, asset: &str, timestamp: i64, fiat: &str, window_secs: i64) -> Option<PricePoint> {
+        let candle = self
+            .candles
+            .iter()
+            .filter(|c| c.asset_symbol == asset && c.fiat_currency.eq_ignore_ascii_case(fiat))
+            .filter(|c| c.resolution_secs == OBSERVATION_RESOLUTION_SECS)
+            .filter(|c| (c.open_time - timestamp).abs() <= window_secs)
+            .min_by_key(|c| (c.open_time - timestamp).abs());
+
+        candle.map(|c| PricePoint {
+            price: c.close.clone(),
+            source: c.source.clone(),
+            resolution: format_resolution(c.resolution_secs),
+            timestamp: c.open_time,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PriceHistory {
+    async fn price_at(&self, asset: &str, timestamp: i64, fiat: &str) -> anyhow::Result<Option<PricePoint>> {
+        Ok(self.point_at(asset, timestamp, fiat))
+    }
+
+    async fn nearest_observation(
+        &self,
+        asset: &str,
+        timestamp: i64,
+        fiat: &str,
+        window_secs: i64,
+    ) -> anyhow::Result<Option<PricePoint>> {
+        Ok(self.observation_near(asset, timestamp, fiat, window_secs))
+    }
+}
+
+/// Parses a candle width such as "1m", "4h" or "1d" into seconds.
+pub fn parse_resolution(value: &str) -> anyhow::Result<i64> {
+    let value = value.trim();
+    // The unit may be any character the user typed, so split on a char boundary
+    let (digits, unit) = match value.char_indices().last() {
+        Some((at, _)) => value.split_at(at),
+        None => return Err(anyhow::anyhow!("Invalid resolution: {}", value)),
+    };
+    let count: i64 = digits
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid resolution: {}", value))?;
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(anyhow::anyhow!("Invalid resolution: {}", value)),
+    };
+    if count <= 0 {
+        return Err(anyhow::anyhow!("Invalid resolution: {}", value));
+    }
+    count.checked_mul(unit_secs).ok_or_else(|| anyhow::anyhow!("Invalid resolution: {}", value))
+}
+
+pub fn format_resolution(secs: i64) -> String {
+    for (unit_secs, unit) in [(604_800, "w"), (86_400, "d"), (3_600, "h"), (60, "m")] {
+        if secs % unit_secs == 0 {
+            return format!("{}{}", secs / unit_secs, unit);
+        }
+    }
+    format!("{}s", secs)
+}
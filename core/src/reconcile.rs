@@ -0,0 +1,88 @@
+use crate::models::LedgerEntry;
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+// Same dust threshold the parser uses when deciding whether a balance moved
+pub const DEFAULT_TOLERANCE: &str = "0.000001";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnchainBalance {
+    pub asset_symbol: String,
+    pub balance: BigDecimal,
+}
+
+// Reads what the chain says a wallet holds right now
+#[async_trait::async_trait]
+pub trait BalanceSource: Send + Sync {
+    async fn current_balances(&self, wallet: &str) -> anyhow::Result<Vec<OnchainBalance>>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetReconciliation {
+    pub asset_symbol: String,
+    pub ledger_balance: BigDecimal,
+    pub onchain_balance: BigDecimal,
+    pub difference: BigDecimal, // onchain - ledger; positive means the ledger is missing inflows
+    pub matches: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub wallet_address: String,
+    pub assets: Vec<AssetReconciliation>,
+    pub discrepancies: usize,
+}
+
+pub fn default_tolerance() -> BigDecimal {
+    BigDecimal::from_str(DEFAULT_TOLERANCE).expect("valid decimal")
+}
+
+/// Compares the sum of a wallet's ledger entries per asset with its on-chain balances.
+/// Assets that are zero on both sides are left out.
+pub fn reconcile(wallet: &str, entries: &[LedgerEntry], onchain: &[OnchainBalance], tolerance: &BigDecimal) -> ReconciliationReport {
+    let mut ledger: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    for entry in entries.iter().filter(|e| e.wallet_address == wallet) {
+        *ledger.entry(entry.asset_symbol.clone()).or_insert_with(BigDecimal::zero) += &entry.amount;
+    }
+
+    let mut chain: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    for balance in onchain {
+        // A wallet can hold several token accounts for the same mint
+        *chain.entry(balance.asset_symbol.clone()).or_insert_with(BigDecimal::zero) += &balance.balance;
+    }
+
+    let mut assets: Vec<String> = ledger.keys().chain(chain.keys()).cloned().collect();
+    assets.sort();
+    assets.dedup();
+
+    let mut report = ReconciliationReport {
+        wallet_address: wallet.to_string(),
+        assets: Vec::new(),
+        discrepancies: 0,
+    };
+
+    for asset in assets {
+        let ledger_balance = ledger.get(&asset).cloned().unwrap_or_else(BigDecimal::zero);
+        let onchain_balance = chain.get(&asset).cloned().unwrap_or_else(BigDecimal::zero);
+        if ledger_balance.is_zero() && onchain_balance.is_zero() {
+            continue;
+        }
+
+        let difference = &onchain_balance - &ledger_balance;
+        let matches = difference.abs() <= *tolerance;
+        if !matches {
+            report.discrepancies += 1;
+        }
+        report.assets.push(AssetReconciliation {
+            asset_symbol: asset,
+            ledger_balance,
+            onchain_balance,
+            difference,
+            matches,
+        });
+    }
+
+    report
+}
@@ -0,0 +1,111 @@
+use crate::adjustments::Adjustment;
+use crate::bridge;
+use crate::models::{EntryType, LedgerEntry, ParserVersion};
+use serde::Serialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// What re-normalizing a wallet does to its ledger. Serializes as the diff; the replacement itself is skipped.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Renormalization {
+    pub transactions: usize,             // Transactions whose entries are rebuilt
+    pub unchanged: usize,                // Entries the current parser reproduces
+    pub added: Vec<LedgerEntry>,         // Entries only the current parser produces
+    pub removed: Vec<LedgerEntry>,       // Stored entries it no longer produces
+    pub orphaned_adjustments: Vec<Uuid>, // Removed entries a user had adjusted; the adjustments no longer apply
+    #[serde(skip)]
+    pub transaction_ids: Vec<Uuid>,
+    #[serde(skip)]
+    pub entries: Vec<LedgerEntry>, // Everything those transactions' entries are replaced with
+}
+
+impl Renormalization {
+    pub fn changes_anything(&self) -> bool {
+        !self.added.is_empty() || !self.removed.is_empty()
+    }
+}
+
+/// Works out how to rebuild a wallet's ledger from `fresh`, the entries its transactions parse into today.
+/// `current` maps each transaction id to the parser version that handles it now, and `adjusted` holds the
+/// wallet's manual adjustments by entry id.
+///
+/// A transaction is rebuilt unless it already has entries and every one came from its current parser;
+/// that also covers entries normalized before parsers were versioned, and duplicates from normalizing twice.
+/// Fresh entries matching a stored one keep the stored entry, with its id, valuation, spam flag and bridge link,
+/// so re-running after a change that only touched other transactions leaves them alone, and adjustments to
+/// them still apply. A reclassified entry matches whatever type it's parsed as.
+pub fn plan(
+    stored: &[LedgerEntry],
+    fresh: Vec<LedgerEntry>,
+    current: &HashMap<Uuid, ParserVersion>,
+    adjusted: &HashMap<Uuid, Adjustment>,
+) -> Renormalization {
+    let mut stored_by_tx: HashMap<Uuid, Vec<&LedgerEntry>> = HashMap::new();
+    for e in stored {
+        stored_by_tx.entry(e.transaction_id).or_default().push(e);
+    }
+    let mut fresh_by_tx: HashMap<Uuid, Vec<LedgerEntry>> = HashMap::new();
+    for e in fresh {
+        fresh_by_tx.entry(e.transaction_id).or_default().push(e);
+    }
+
+    let mut ids: Vec<&Uuid> = current.keys().collect();
+    ids.sort();
+
+    let mut plan = Renormalization::default();
+    for id in ids {
+        let old = stored_by_tx.remove(id).unwrap_or_default();
+        let new = fresh_by_tx.remove(id).unwrap_or_default();
+        let up_to_date = !old.is_empty() && old.iter().all(|e| e.parser.as_ref() == current.get(id));
+        if up_to_date || (old.is_empty() && new.is_empty()) {
+            continue;
+        }
+
+        let mut kept = vec![false; old.len()];
+        for entry in new {
+            let reclassified = |e: &LedgerEntry| adjusted.get(&e.id).is_some_and(|a| a.entry_type.is_some());
+            match (0..old.len()).find(|&i| !kept[i] && same_movement(old[i], &entry, reclassified(old[i]))) {
+                Some(i) => {
+                    kept[i] = true;
+                    plan.unchanged += 1;
+                    plan.entries.push(LedgerEntry { parser: entry.parser, ..old[i].clone() });
+                }
+                None => {
+                    plan.added.push(entry.clone());
+                    plan.entries.push(entry);
+                }
+            }
+        }
+        for (e, _) in old.iter().zip(&kept).filter(|(_, k)| !**k) {
+            if adjusted.contains_key(&e.id) {
+                plan.orphaned_adjustments.push(e.id);
+            }
+            plan.removed.push((*e).clone());
+        }
+        plan.transactions += 1;
+        plan.transaction_ids.push(*id);
+    }
+    plan
+}
+
+// Linking turns parsed transfers into bridge entries afterwards, so a linked entry still matches the
+// transfer it was parsed as. Bridge details the parser no longer produces, on an entry that was never
+// linked, are stale and make it a different movement.
+fn same_movement(stored: &LedgerEntry, fresh: &LedgerEntry, reclassified: bool) -> bool {
+    let linked = bridge::linked_entry(stored).is_some();
+    let same_type = reclassified
+        || stored.entry_type.name() == fresh.entry_type.name()
+        || (linked && matches!(fresh.entry_type, EntryType::Transfer | EntryType::Bridge));
+    let same_bridge = match (&stored.bridge, &fresh.bridge) {
+        (Some(s), Some(f)) => s.protocol == f.protocol,
+        (Some(_), None) => linked,
+        (None, Some(_)) => false,
+        (None, None) => true,
+    };
+    same_type
+        && same_bridge
+        && stored.asset_symbol == fresh.asset_symbol
+        && stored.amount == fresh.amount
+        && stored.source_program == fresh.source_program
+        && stored.position_account == fresh.position_account
+}
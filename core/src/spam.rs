@@ -0,0 +1,140 @@
+use crate::models::{EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use uuid::Uuid;
+
+// Assets that are never spam and that lookalike mints imitate
+const KNOWN_ASSETS: &[&str] = &[
+    "SOL",
+    "ETH",
+    "USDC",
+    "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", // USDC (Solana)
+    "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", // USDT (Solana)
+];
+
+// Mint and contract addresses are at least this long; tickers (SOL, BTC on an exchange) never are
+const MIN_ADDRESS_LEN: usize = 32;
+
+// Characters a lookalike shares with the address it imitates at each end, as wallets truncate them
+const LOOKALIKE_AFFIX: usize = 4;
+
+/// A user's decision about one asset, overriding the lists and heuristics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpamOverride {
+    pub user_id: Uuid,
+    pub asset_symbol: String,
+    pub is_spam: bool,
+}
+
+/// Local deny and allow lists. Denylist lines may end or start with `*` to match a prefix or suffix.
+#[derive(Debug, Clone, Default)]
+pub struct SpamLists {
+    pub deny: Vec<String>,
+    pub allow: HashSet<String>,
+}
+
+impl SpamLists {
+    pub fn new(deny: Vec<String>, allow: HashSet<String>) -> Self {
+        Self { deny, allow }
+    }
+
+    /// Reads one asset per line; blank lines and `#` comments are skipped.
+    pub fn load(deny: Option<&Path>, allow: Option<&Path>) -> anyhow::Result<Self> {
+        let deny = match deny {
+            Some(path) => parse_list(std::fs::File::open(path)?)?,
+            None => Vec::new(),
+        };
+        let allow = match allow {
+            Some(path) => parse_list(std::fs::File::open(path)?)?.into_iter().collect(),
+            None => HashSet::new(),
+        };
+        Ok(Self::new(deny, allow))
+    }
+
+    fn denies(&self, asset: &str) -> bool {
+        self.deny.iter().any(|pattern| match (pattern.strip_suffix('*'), pattern.strip_prefix('*')) {
+            (Some(prefix), _) => asset.starts_with(prefix),
+            (_, Some(suffix)) => asset.ends_with(suffix),
+            _ => asset == pattern,
+        })
+    }
+}
+
+pub fn parse_list<R: Read>(reader: R) -> anyhow::Result<Vec<String>> {
+    let mut assets = Vec::new();
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let asset = line.split('#').next().unwrap_or_default().trim();
+        if !asset.is_empty() {
+            assets.push(asset.to_string());
+        }
+    }
+    Ok(assets)
+}
+
+/// Sets `is_spam` on every entry. Per asset, in order of precedence: the user's override,
+/// the allowlist, the denylist, then the heuristics.
+pub fn classify(entries: &mut [LedgerEntry], lists: &SpamLists, overrides: &HashMap<String, bool>) {
+    let mut by_tx: BTreeMap<Uuid, Vec<&LedgerEntry>> = BTreeMap::new();
+    for entry in entries.iter() {
+        by_tx.entry(entry.transaction_id).or_default().push(entry);
+    }
+
+    // An asset the wallet ever sent, traded or paid a fee around was solicited
+    let mut solicited: HashSet<String> = HashSet::new();
+    let mut valued: HashSet<String> = HashSet::new();
+    for legs in by_tx.values() {
+        let unsolicited = legs.iter().all(|e| matches!(e.entry_type, EntryType::Transfer) && e.amount > BigDecimal::zero());
+        for e in legs {
+            if !unsolicited {
+                solicited.insert(e.asset_symbol.clone());
+            }
+            if e.fiat_value.as_ref().is_some_and(|v| !v.is_zero()) {
+                valued.insert(e.asset_symbol.clone());
+            }
+        }
+    }
+
+    let mut decisions: HashMap<String, bool> = HashMap::new();
+    for entry in entries.iter_mut() {
+        let asset = entry.asset_symbol.clone();
+        let is_spam = *decisions.entry(asset.clone()).or_insert_with(|| {
+            if let Some(decision) = overrides.get(&asset) {
+                return *decision;
+            }
+            if lists.allow.contains(&asset) || KNOWN_ASSETS.contains(&asset.as_str()) {
+                return false;
+            }
+            if lists.denies(&asset) || is_lookalike(&asset, lists) {
+                return true;
+            }
+            // Unsolicited token airdrops with no market value
+            asset.len() >= MIN_ADDRESS_LEN && !solicited.contains(&asset) && !valued.contains(&asset)
+        });
+        entry.is_spam = is_spam;
+    }
+}
+
+/// Whether `asset` imitates a known or allowlisted address: same first and last characters, different in between.
+pub fn is_lookalike(asset: &str, lists: &SpamLists) -> bool {
+    let asset = asset.as_bytes();
+    if asset.len() < MIN_ADDRESS_LEN {
+        return false;
+    }
+    let known = KNOWN_ASSETS.iter().copied().chain(lists.allow.iter().map(String::as_str)).map(str::as_bytes);
+    known.filter(|k| k.len() >= MIN_ADDRESS_LEN && *k != asset).any(|k| {
+        asset[..LOOKALIKE_AFFIX] == k[..LOOKALIKE_AFFIX] && asset[asset.len() - LOOKALIKE_AFFIX..] == k[k.len() - LOOKALIKE_AFFIX..]
+    })
+}
+
+/// Entries to report on: everything, or everything but spam.
+pub fn filter(entries: Vec<LedgerEntry>, include_spam: bool) -> Vec<LedgerEntry> {
+    if include_spam {
+        entries
+    } else {
+        entries.into_iter().filter(|e| !e.is_spam).collect()
+    }
+}
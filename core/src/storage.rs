@@ -0,0 +1,508 @@
+use crate::adjustments::{self, Adjustment, AuditRecord, Change};
+use crate::balances::{self, DailyChange};
+use crate::fx::{FxRate, FxSource, FxTable, DEFAULT_CURRENCY};
+use crate::models::{Chain, EntryType, LedgerEntry, Transaction, UserSettings};
+use crate::pricing::{PriceCandle, PriceHistory, PricePoint, PriceSource};
+use crate::spam::SpamOverride;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// How far a wallet has been ingested on a chain, so the next run only stores what's new.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncState {
+    pub wallet_address: String,
+    pub chain: Chain,
+    pub last_timestamp: Option<i64>, // Newest transaction stored, None until one has been
+    pub last_tx_hashes: Vec<String>, // Every transaction stored at that timestamp, as several can share a second
+}
+
+impl SyncState {
+    pub fn new(wallet_address: &str, chain: Chain) -> Self {
+        Self {
+            wallet_address: wallet_address.to_string(),
+            chain,
+            last_timestamp: None,
+            last_tx_hashes: Vec::new(),
+        }
+    }
+
+    /// Whether the transaction is past what's already been stored.
+    pub fn is_new(&self, tx: &Transaction) -> bool {
+        match self.last_timestamp {
+            Some(last) => tx.timestamp > last || (tx.timestamp == last && !self.last_tx_hashes.contains(&tx.tx_hash)),
+            None => true,
+        }
+    }
+
+    /// Moves the state past newly stored transactions.
+    pub fn advance(&mut self, txs: &[Transaction]) {
+        let Some(newest) = txs.iter().map(|t| t.timestamp).max() else { return };
+        match self.last_timestamp {
+            Some(last) if last > newest => return,
+            Some(last) if last == newest => {}
+            _ => self.last_tx_hashes.clear(),
+        }
+        self.last_timestamp = Some(newest);
+        for tx in txs.iter().filter(|t| t.timestamp == newest) {
+            if !self.last_tx_hashes.contains(&tx.tx_hash) {
+                self.last_tx_hashes.push(tx.tx_hash.clone());
+            }
+        }
+    }
+}
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Narrows a paged query; unset fields match everything. Asset and entry type pick transactions with a
+/// matching ledger entry, and chain and tx hash pick ledger entries of a matching transaction.
+#[derive(Debug, Default, Clone)]
+pub struct QueryFilter {
+    pub from: Option<i64>, // Inclusive
+    pub to: Option<i64>,   // Exclusive
+    pub asset: Option<String>,
+    pub entry_type: Option<EntryType>,
+    pub chain: Option<Chain>,
+    pub tx_hash: Option<String>,
+}
+
+/// Keyset position in a wallet's history: pages are ordered by timestamp, then id, and the next one
+/// starts after the last item of the previous. Handed to clients as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub timestamp: i64,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        format!("{}.{}", self.timestamp, self.id.simple())
+    }
+
+    pub fn decode(value: &str) -> anyhow::Result<Self> {
+        let (timestamp, id) = value.split_once('.').ok_or_else(|| anyhow::anyhow!("Invalid cursor: {}", value))?;
+        Ok(Self {
+            timestamp: timestamp.parse().map_err(|_| anyhow::anyhow!("Invalid cursor: {}", value))?,
+            id: Uuid::from_str(id).map_err(|_| anyhow::anyhow!("Invalid cursor: {}", value))?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>, // None on the last page
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` rows in cursor order; the extra row only says there's another page.
+    pub fn from_rows(mut rows: Vec<T>, limit: usize, key: impl Fn(&T) -> Cursor) -> Self {
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(|r| key(r).encode())
+        } else {
+            None
+        };
+        Self { items: rows, next_cursor }
+    }
+}
+
+/// Where raw transactions, ledger entries, sync state, price history, FX rates, user preferences and
+/// manual adjustments are kept.
+/// Postgres, an embedded SQLite file or `MemoryStore` all run the same pipeline, so the CLI and the API work without a database server too.
+///
+/// Saves skip records whose id is already stored, and reads return entries oldest first.
+#[async_trait::async_trait]
+pub trait Storage: PriceSource + FxSource {
+    /// Saves raw transactions atomically. Ones already stored are left as they are.
+    async fn save_transactions(&self, txs: &[Transaction]) -> anyhow::Result<()>;
+
+    /// Saves ledger entries atomically. Entries already stored are left as they are.
+    async fn save_ledger_entries(&self, entries: &[LedgerEntry]) -> anyhow::Result<()>;
+
+    /// Saves transactions together with the ledger entries parsed from them: either everything is written or nothing is.
+    async fn save_transactions_with_entries(&self, txs: &[Transaction], entries: &[LedgerEntry]) -> anyhow::Result<()>;
+
+    /// Deletes every ledger entry of the given transactions and saves `entries` in their place, atomically.
+    async fn replace_ledger_entries(&self, transaction_ids: &[Uuid], entries: &[LedgerEntry]) -> anyhow::Result<()>;
+
+    /// Saves newly ingested transactions together with the sync state moved past them.
+    async fn save_ingested(&self, txs: &[Transaction], state: &SyncState) -> anyhow::Result<()>;
+
+    async fn get_sync_state(&self, wallet: &str, chain: &Chain) -> anyhow::Result<Option<SyncState>>;
+
+    async fn get_transactions_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<Transaction>>;
+
+    async fn get_ledger_entries_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<LedgerEntry>>;
+
+    async fn get_ledger_entries_by_user(&self, user_id: Uuid) -> anyhow::Result<Vec<LedgerEntry>>;
+
+    /// The user a wallet's transactions were ingested for, if any.
+    async fn get_wallet_owner(&self, wallet: &str) -> anyhow::Result<Option<Uuid>>;
+
+    /// Per-day net movements for a wallet up to and including `to`, for building balance time series.
+    /// Summed from the wallet's entries here; Postgres reads its materialized daily_balances table instead.
+    async fn get_daily_balance_changes(&self, wallet: &str, to: NaiveDate) -> anyhow::Result<Vec<DailyChange>> {
+        let entries = self.get_ledger_entries_by_wallet(wallet).await?;
+        Ok(balances::daily_changes(&entries, to))
+    }
+
+    /// Up to `limit` of the wallet's transactions matching the filter, after the cursor.
+    async fn page_transactions(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<Transaction>>;
+
+    /// Up to `limit` of the wallet's ledger entries matching the filter, after the cursor.
+    async fn page_ledger_entries(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<LedgerEntry>>;
+
+    /// On-chain hashes for the given transaction ids, for exports that reference the original transaction.
+    async fn get_tx_hashes(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, String>>;
+
+    /// Chain each of the given transactions was ingested from.
+    async fn get_transaction_chains(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, Chain>>;
+
+    /// Writes back the valuation fields set by the price pass.
+    async fn update_ledger_valuations(&self, entries: &[LedgerEntry]) -> anyhow::Result<()>;
+
+    /// Writes back `is_spam` after classification.
+    async fn update_spam_flags(&self, entries: &[LedgerEntry]) -> anyhow::Result<()>;
+
+    /// Writes back the entry type and bridge fields set by the bridge linker.
+    async fn update_bridge_links(&self, entries: &[LedgerEntry]) -> anyhow::Result<()>;
+
+    /// Re-importing a bar replaces it.
+    async fn save_price_candles(&self, candles: &[PriceCandle]) -> anyhow::Result<()>;
+
+    /// Re-importing a pair's rate for a day replaces it.
+    async fn save_fx_rates(&self, rates: &[FxRate]) -> anyhow::Result<()>;
+
+    /// The user's spam decisions, by asset.
+    async fn get_spam_overrides(&self, user_id: Uuid) -> anyhow::Result<HashMap<String, bool>>;
+
+    async fn save_spam_override(&self, o: &SpamOverride) -> anyhow::Result<()>;
+
+    /// Returns whether there was an override to remove.
+    async fn delete_spam_override(&self, user_id: Uuid, asset: &str) -> anyhow::Result<bool>;
+
+    async fn get_user_settings(&self, user_id: Uuid) -> anyhow::Result<Option<UserSettings>>;
+
+    async fn save_user_settings(&self, settings: &UserSettings) -> anyhow::Result<()>;
+
+    /// The user's adjustments to entries in the wallet, by entry id.
+    async fn get_adjustments_by_wallet(&self, wallet: &str) -> anyhow::Result<HashMap<Uuid, Adjustment>>;
+
+    /// Makes a change to one of the user's entries and records it in the audit trail, all at once.
+    /// Returns where the entry's adjustments stand afterwards, or None if the user has no such entry.
+    async fn adjust_entry(&self, user_id: Uuid, entry_id: Uuid, change: &Change) -> anyhow::Result<Option<Adjustment>>;
+
+    /// Saves a hand-entered transaction with its entries and the audit record of its creation, atomically.
+    async fn save_manual_entry(&self, tx: &Transaction, entries: &[LedgerEntry], record: &AuditRecord) -> anyhow::Result<()>;
+
+    /// The user's recorded changes, oldest first, optionally only those to one entry.
+    async fn get_audit_trail(&self, user_id: Uuid, entry_id: Option<Uuid>) -> anyhow::Result<Vec<AuditRecord>>;
+
+    /// The user's configured reporting currency, falling back to USD.
+    async fn reporting_currency(&self, user_id: Option<Uuid>) -> anyhow::Result<String> {
+        let settings = match user_id {
+            Some(u) => self.get_user_settings(u).await?,
+            None => None,
+        };
+        Ok(settings.map(|s| s.reporting_currency).unwrap_or_else(|| DEFAULT_CURRENCY.to_string()))
+    }
+}
+
+/// Storage held in memory, for tests and one-off runs.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+#[derive(Debug, Default)]
+struct Tables {
+    transactions: Vec<Transaction>,
+    entries: Vec<LedgerEntry>,
+    sync: HashMap<(String, String), SyncState>,
+    prices: PriceHistory,
+    fx: FxTable,
+    spam: HashMap<(Uuid, String), bool>,
+    settings: HashMap<Uuid, UserSettings>,
+    adjustments: HashMap<Uuid, Adjustment>,
+    audit: Vec<AuditRecord>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Tables {
+    fn insert_transactions(&mut self, txs: &[Transaction]) {
+        for tx in txs {
+            if !self.transactions.iter().any(|t| t.id == tx.id) {
+                self.transactions.push(tx.clone());
+            }
+        }
+    }
+
+    fn insert_entries(&mut self, entries: &[LedgerEntry]) {
+        for entry in entries {
+            if !self.entries.iter().any(|e| e.id == entry.id) {
+                self.entries.push(entry.clone());
+            }
+        }
+    }
+
+    // Stable sort, so entries at the same time keep the order they were saved in, like `ORDER BY timestamp, created_at`
+    fn entries_where(&self, keep: impl Fn(&LedgerEntry) -> bool) -> Vec<LedgerEntry> {
+        let mut entries: Vec<LedgerEntry> = self.entries.iter().filter(|e| keep(e)).cloned().collect();
+        entries.sort_by_key(|e| e.timestamp);
+        entries
+    }
+
+    fn update(&mut self, entries: &[LedgerEntry], apply: impl Fn(&mut LedgerEntry, &LedgerEntry)) {
+        let by_id: HashMap<Uuid, &LedgerEntry> = entries.iter().map(|e| (e.id, e)).collect();
+        for stored in self.entries.iter_mut() {
+            if let Some(updated) = by_id.get(&stored.id) {
+                apply(stored, updated);
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Storage for MemoryStore {
+    async fn save_transactions(&self, txs: &[Transaction]) -> anyhow::Result<()> {
+        self.tables().insert_transactions(txs);
+        Ok(())
+    }
+
+    async fn save_ledger_entries(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        self.tables().insert_entries(entries);
+        Ok(())
+    }
+
+    async fn save_transactions_with_entries(&self, txs: &[Transaction], entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        tables.insert_transactions(txs);
+        tables.insert_entries(entries);
+        Ok(())
+    }
+
+    async fn replace_ledger_entries(&self, transaction_ids: &[Uuid], entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        tables.entries.retain(|e| !transaction_ids.contains(&e.transaction_id));
+        tables.insert_entries(entries);
+        Ok(())
+    }
+
+    async fn save_ingested(&self, txs: &[Transaction], state: &SyncState) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        tables.insert_transactions(txs);
+        tables.sync.insert((state.wallet_address.clone(), state.chain.name().to_string()), state.clone());
+        Ok(())
+    }
+
+    async fn get_sync_state(&self, wallet: &str, chain: &Chain) -> anyhow::Result<Option<SyncState>> {
+        Ok(self.tables().sync.get(&(wallet.to_string(), chain.name().to_string())).cloned())
+    }
+
+    async fn get_transactions_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<Transaction>> {
+        let mut txs: Vec<Transaction> = self.tables().transactions.iter().filter(|t| t.wallet_address == wallet).cloned().collect();
+        txs.sort_by_key(|t| t.timestamp);
+        Ok(txs)
+    }
+
+    async fn get_ledger_entries_by_wallet(&self, wallet: &str) -> anyhow::Result<Vec<LedgerEntry>> {
+        Ok(self.tables().entries_where(|e| e.wallet_address == wallet))
+    }
+
+    async fn get_ledger_entries_by_user(&self, user_id: Uuid) -> anyhow::Result<Vec<LedgerEntry>> {
+        Ok(self.tables().entries_where(|e| e.user_id == user_id))
+    }
+
+    async fn get_wallet_owner(&self, wallet: &str) -> anyhow::Result<Option<Uuid>> {
+        Ok(self.tables().transactions.iter().find(|t| t.wallet_address == wallet).map(|t| t.user_id))
+    }
+
+    async fn page_transactions(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<Transaction>> {
+        let tables = self.tables();
+        let by_entry = filter.asset.is_some() || filter.entry_type.is_some();
+        let mut txs: Vec<&Transaction> = tables
+            .transactions
+            .iter()
+            .filter(|t| t.wallet_address == wallet && in_range(filter, t.timestamp) && tx_fields_match(filter, t))
+            .filter(|t| !by_entry || tables.entries.iter().any(|e| e.transaction_id == t.id && entry_fields_match(filter, e)))
+            .filter(|t| after.is_none_or(|a| tx_cursor(t) > a))
+            .collect();
+        txs.sort_by_key(|t| tx_cursor(t));
+        let rows = txs.into_iter().take(limit + 1).cloned().collect();
+        Ok(Page::from_rows(rows, limit, tx_cursor))
+    }
+
+    async fn page_ledger_entries(&self, wallet: &str, filter: &QueryFilter, after: Option<Cursor>, limit: usize) -> anyhow::Result<Page<LedgerEntry>> {
+        let tables = self.tables();
+        let by_tx = filter.chain.is_some() || filter.tx_hash.is_some();
+        let mut entries: Vec<&LedgerEntry> = tables
+            .entries
+            .iter()
+            .filter(|e| e.wallet_address == wallet && in_range(filter, e.timestamp) && entry_fields_match(filter, e))
+            .filter(|e| !by_tx || tables.transactions.iter().any(|t| t.id == e.transaction_id && tx_fields_match(filter, t)))
+            .filter(|e| after.is_none_or(|a| entry_cursor(e) > a))
+            .collect();
+        entries.sort_by_key(|e| entry_cursor(e));
+        let rows = entries.into_iter().take(limit + 1).cloned().collect();
+        Ok(Page::from_rows(rows, limit, entry_cursor))
+    }
+
+    async fn get_tx_hashes(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, String>> {
+        Ok(self.tables().transactions.iter().filter(|t| ids.contains(&t.id)).map(|t| (t.id, t.tx_hash.clone())).collect())
+    }
+
+    async fn get_transaction_chains(&self, ids: &[Uuid]) -> anyhow::Result<HashMap<Uuid, Chain>> {
+        Ok(self.tables().transactions.iter().filter(|t| ids.contains(&t.id)).map(|t| (t.id, t.chain.clone())).collect())
+    }
+
+    async fn update_ledger_valuations(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        self.tables().update(entries, |stored, e| {
+            stored.fiat_value = e.fiat_value.clone();
+            stored.fiat_currency = e.fiat_currency.clone();
+            stored.price_source = e.price_source.clone();
+            stored.price_resolution = e.price_resolution.clone();
+        });
+        Ok(())
+    }
+
+    async fn update_spam_flags(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        self.tables().update(entries, |stored, e| stored.is_spam = e.is_spam);
+        Ok(())
+    }
+
+    async fn update_bridge_links(&self, entries: &[LedgerEntry]) -> anyhow::Result<()> {
+        let bridged: Vec<LedgerEntry> = entries.iter().filter(|e| e.bridge.is_some()).cloned().collect();
+        self.tables().update(&bridged, |stored, e| {
+            stored.entry_type = e.entry_type.clone();
+            stored.bridge = e.bridge.clone();
+        });
+        Ok(())
+    }
+
+    async fn save_price_candles(&self, candles: &[PriceCandle]) -> anyhow::Result<()> {
+        self.tables().prices.upsert(candles.iter().cloned());
+        Ok(())
+    }
+
+    async fn save_fx_rates(&self, rates: &[FxRate]) -> anyhow::Result<()> {
+        self.tables().fx.upsert(rates.iter().cloned());
+        Ok(())
+    }
+
+    async fn get_spam_overrides(&self, user_id: Uuid) -> anyhow::Result<HashMap<String, bool>> {
+        Ok(self.tables().spam.iter().filter(|((u, _), _)| *u == user_id).map(|((_, asset), is_spam)| (asset.clone(), *is_spam)).collect())
+    }
+
+    async fn save_spam_override(&self, o: &SpamOverride) -> anyhow::Result<()> {
+        self.tables().spam.insert((o.user_id, o.asset_symbol.clone()), o.is_spam);
+        Ok(())
+    }
+
+    async fn delete_spam_override(&self, user_id: Uuid, asset: &str) -> anyhow::Result<bool> {
+        Ok(self.tables().spam.remove(&(user_id, asset.to_string())).is_some())
+    }
+
+    async fn get_user_settings(&self, user_id: Uuid) -> anyhow::Result<Option<UserSettings>> {
+        Ok(self.tables().settings.get(&user_id).cloned())
+    }
+
+    async fn save_user_settings(&self, settings: &UserSettings) -> anyhow::Result<()> {
+        self.tables().settings.insert(settings.user_id, settings.clone());
+        Ok(())
+    }
+
+    async fn get_adjustments_by_wallet(&self, wallet: &str) -> anyhow::Result<HashMap<Uuid, Adjustment>> {
+        Ok(self.tables().adjustments.values().filter(|a| a.wallet_address == wallet).map(|a| (a.entry_id, a.clone())).collect())
+    }
+
+    async fn adjust_entry(&self, user_id: Uuid, entry_id: Uuid, change: &Change) -> anyhow::Result<Option<Adjustment>> {
+        let mut tables = self.tables();
+        let tables = &mut *tables;
+        let Some(entry) = tables.entries.iter_mut().find(|e| e.id == entry_id && e.user_id == user_id) else { return Ok(None) };
+        let before = tables.adjustments.get(&entry_id).cloned().unwrap_or_else(|| Adjustment::new(entry));
+        let (adjustment, record) = adjustments::adjust(entry, &before, change, chrono::Utc::now().timestamp())?;
+        tables.adjustments.insert(entry_id, adjustment.clone());
+        tables.audit.push(record);
+        Ok(Some(adjustment))
+    }
+
+    async fn save_manual_entry(&self, tx: &Transaction, entries: &[LedgerEntry], record: &AuditRecord) -> anyhow::Result<()> {
+        let mut tables = self.tables();
+        tables.insert_transactions(std::slice::from_ref(tx));
+        tables.insert_entries(entries);
+        tables.audit.push(record.clone());
+        Ok(())
+    }
+
+    async fn get_audit_trail(&self, user_id: Uuid, entry_id: Option<Uuid>) -> anyhow::Result<Vec<AuditRecord>> {
+        let mut records: Vec<AuditRecord> = self
+            .tables()
+            .audit
+            .iter()
+            .filter(|r| r.user_id == user_id && entry_id.is_none_or(|id| r.entry_id == id))
+            .cloned()
+            .collect();
+        records.sort_by_key(|r| (r.recorded_at, r.id));
+        Ok(records)
+    }
+}
+
+#[async_trait::async_trait]
+impl FxSource for MemoryStore {
+    async fn rate_on(&self, base: &str, quote: &str, date: NaiveDate) -> anyhow::Result<Option<BigDecimal>> {
+        Ok(self.tables().fx.rate(base, quote, date))
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for MemoryStore {
+    async fn price_at(&self, asset: &str, timestamp: i64, fiat: &str) -> anyhow::Result<Option<PricePoint>> {
+        Ok(self.tables().prices.point_at(asset, timestamp, fiat))
+    }
+
+    async fn nearest_observation(
+        &self,
+        asset: &str,
+        timestamp: i64,
+        fiat: &str,
+        window_secs: i64,
+    ) -> anyhow::Result<Option<PricePoint>> {
+        Ok(self.tables().prices.observation_near(asset, timestamp, fiat, window_secs))
+    }
+}
+
+fn tx_cursor(tx: &Transaction) -> Cursor {
+    Cursor { timestamp: tx.timestamp, id: tx.id }
+}
+
+fn entry_cursor(entry: &LedgerEntry) -> Cursor {
+    Cursor { timestamp: entry.timestamp, id: entry.id }
+}
+
+fn in_range(filter: &QueryFilter, timestamp: i64) -> bool {
+    filter.from.is_none_or(|f| timestamp >= f) && filter.to.is_none_or(|t| timestamp < t)
+}
+
+fn tx_fields_match(filter: &QueryFilter, tx: &Transaction) -> bool {
+    filter.chain.as_ref().is_none_or(|c| c.name() == tx.chain.name()) && filter.tx_hash.as_ref().is_none_or(|h| *h == tx.tx_hash)
+}
+
+fn entry_fields_match(filter: &QueryFilter, entry: &LedgerEntry) -> bool {
+    filter.asset.as_ref().is_none_or(|a| *a == entry.asset_symbol)
+        && filter.entry_type.as_ref().is_none_or(|t| t.name() == entry.entry_type.name())
+}
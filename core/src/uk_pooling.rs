@@ -0,0 +1,415 @@
+use crate::bridge;
+use crate::fx::ensure_single_currency;
+use crate::lots;
+use crate::models::{EntryType, LedgerEntry};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+// HMRC share identification rules (TCGA 1992 s105, s106A, s104) as applied to cryptoassets (CRYPTO22200).
+// A disposal is matched, in this order, against:
+// 1. Acquisitions on the same day
+// 2. Acquisitions in the 30 days after the disposal ("bed and breakfast")
+// 3. The Section 104 pool at its average cost
+// All acquisitions (and disposals) of an asset on the same day are treated as a single transaction.
+//
+// As in `lots::match_fifo`, adding liquidity isn't a disposal. What's paid in leaves its Section 104
+// pool at the pool's average cost, and the share received joins its own pool at that cost. A linked
+// bridge transfer is carried the same way, joining the pool on the other chain once it has arrived.
+// These carries never take part in same-day or 30-day matching: the holding moves, it isn't re-acquired.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MatchRule {
+    SameDay,
+    BedAndBreakfast,
+    Section104,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisposalMatch {
+    pub rule: MatchRule,
+    pub acquisition_date: Option<NaiveDate>, // None when matched against the pool
+    pub quantity: BigDecimal,
+    pub allowable_cost: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Disposal {
+    pub asset_symbol: String,
+    pub date: NaiveDate,
+    pub quantity: BigDecimal,
+    pub proceeds: BigDecimal,
+    pub allowable_cost: BigDecimal,
+    pub gain: BigDecimal,
+    pub matches: Vec<DisposalMatch>,
+    // Quantity with no acquisition to match against (history incomplete). Costed at zero.
+    pub unmatched_quantity: BigDecimal,
+    pub entry_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Section104Pool {
+    pub asset_symbol: String,
+    pub quantity: BigDecimal,
+    pub allowable_cost: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UkPoolingReport {
+    pub disposals: Vec<Disposal>,
+    pub pools: Vec<Section104Pool>, // Pool state after the last entry
+    // Assets left off because an entry carrying cost into them has no fiat value
+    pub unvalued_assets: Vec<String>,
+}
+
+// Totals for the capital gains summary pages (SA108 "Cryptoassets" boxes)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sa108Summary {
+    pub tax_year: String,
+    pub number_of_disposals: usize,
+    pub disposal_proceeds: BigDecimal,
+    pub allowable_costs: BigDecimal,
+    pub gains_before_losses: BigDecimal,
+    pub losses: BigDecimal,
+}
+
+// One UK tax year's return: the SA108 totals and the disposals behind them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sa108Report {
+    pub summary: Sa108Summary,
+    pub disposals: Vec<Disposal>, // Only those in the tax year
+    pub unvalued_assets: Vec<String>,
+}
+
+#[derive(Default)]
+struct Day {
+    acquired: BigDecimal,
+    cost: BigDecimal,
+    disposed: BigDecimal,
+    proceeds: BigDecimal,
+    disposal_entry_ids: Vec<Uuid>,
+    carried_in: Vec<(Uuid, BigDecimal)>,  // (carry, quantity) joining the pool at the carried cost
+    carried_out: Vec<(Uuid, BigDecimal)>, // (carry, quantity) leaving the pool, its cost carried on
+}
+
+struct PendingDisposal {
+    date: NaiveDate,
+    remaining: BigDecimal,
+    matches: Vec<DisposalMatch>,
+}
+
+/// Runs the HMRC matching rules over ledger entries. Positive amounts are acquisitions,
+/// negative amounts are disposals. Every entry must carry a `fiat_value` (the GBP value of the movement),
+/// except the assets added to a liquidity pool and the share received for them, and linked bridge transfers.
+pub fn calculate(entries: &[LedgerEntry]) -> anyhow::Result<UkPoolingReport> {
+    ensure_single_currency(entries)?;
+    let mut assets: BTreeMap<String, BTreeMap<NaiveDate, Day>> = BTreeMap::new();
+
+    // Carries are keyed by the liquidity add's transaction, or the bridge outflow's entry
+    let liquidity_adds: HashSet<Uuid> = entries.iter().filter(|e| lots::is_share_received(e)).map(|e| e.transaction_id).collect();
+    let mut shares_received: HashSet<Uuid> = HashSet::new();
+    let sent_at: HashMap<Uuid, i64> = entries
+        .iter()
+        .filter(|e| bridge::linked_entry(e).is_some() && e.amount < BigDecimal::zero())
+        .map(|e| (e.id, e.timestamp))
+        .collect();
+
+    for entry in entries {
+        if entry.amount.is_zero() || entry.entry_type.keeps_ownership() {
+            continue;
+        }
+        // An inflow whose outflow isn't among the entries is valued like any other acquisition
+        let bridged_from = bridge::linked_entry(entry).filter(|id| entry.amount > BigDecimal::zero() && sent_at.contains_key(id));
+        // Not before it was sent, so it can't join a pool ahead of leaving the other one
+        let timestamp = bridged_from.map_or(entry.timestamp, |id| entry.timestamp.max(sent_at[&id]));
+        let date = DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| anyhow::anyhow!("Invalid timestamp {} on entry {}", timestamp, entry.id))?
+            .date_naive();
+
+        let day = assets
+            .entry(entry.asset_symbol.clone())
+            .or_default()
+            .entry(date)
+            .or_default();
+
+        if matches!(entry.entry_type, EntryType::Liquidity) && entry.amount < BigDecimal::zero() && liquidity_adds.contains(&entry.transaction_id) {
+            day.carried_out.push((entry.transaction_id, entry.amount.abs()));
+            continue;
+        }
+        if sent_at.contains_key(&entry.id) {
+            day.carried_out.push((entry.id, entry.amount.abs()));
+            continue;
+        }
+        if let Some(sent) = bridged_from {
+            day.carried_in.push((sent, entry.amount.clone()));
+            continue;
+        }
+        if lots::is_share_received(entry) {
+            // Like `match_fifo`, only the first share of a transaction takes the basis
+            let carry = if shares_received.insert(entry.transaction_id) { entry.transaction_id } else { Uuid::nil() };
+            day.carried_in.push((carry, entry.amount.clone()));
+            continue;
+        }
+
+        let value = entry
+            .fiat_value
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Ledger entry {} has no fiat_value", entry.id))?
+            .abs();
+
+        if entry.amount > BigDecimal::zero() {
+            day.acquired += &entry.amount;
+            day.cost += value;
+        } else {
+            day.disposed += entry.amount.abs();
+            day.proceeds += value;
+            day.disposal_entry_ids.push(entry.id);
+        }
+    }
+
+    // What a carry takes out depends on its pool, which can itself hold cost carried in from another
+    // asset, so the assets are matched again until the carried costs settle (once per carry at most)
+    let carries: usize = assets.values().flat_map(|days| days.values()).map(|d| d.carried_out.len()).sum();
+    let mut carried: HashMap<Uuid, BigDecimal> = HashMap::new();
+    let mut pass = 0;
+    loop {
+        let mut disposals = Vec::new();
+        let mut pools = Vec::new();
+        let mut carried_next = HashMap::new();
+
+        for (asset, days) in &assets {
+            let (asset_disposals, pool) = match_asset(asset, days, &carried, &mut carried_next);
+            disposals.extend(asset_disposals);
+            pools.push(pool);
+        }
+
+        pass += 1;
+        if carried_next == carried || pass > carries {
+            disposals.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.asset_symbol.cmp(&b.asset_symbol)));
+            return Ok(UkPoolingReport { disposals, pools, unvalued_assets: Vec::new() });
+        }
+        carried = carried_next;
+    }
+}
+
+// `carried` is the cost of each carry into this asset's pool, and what this asset's carries take out is added to `carried_out`
+fn match_asset(
+    asset: &str,
+    days: &BTreeMap<NaiveDate, Day>,
+    carried: &HashMap<Uuid, BigDecimal>,
+    carried_out: &mut HashMap<Uuid, BigDecimal>,
+) -> (Vec<Disposal>, Section104Pool) {
+    // Acquisition quantity still available after each matching pass
+    let mut available: BTreeMap<NaiveDate, BigDecimal> = days
+        .iter()
+        .filter(|(_, d)| d.acquired > BigDecimal::zero())
+        .map(|(date, d)| (*date, d.acquired.clone()))
+        .collect();
+
+    let mut pending: Vec<PendingDisposal> = days
+        .iter()
+        .filter(|(_, d)| d.disposed > BigDecimal::zero())
+        .map(|(date, d)| PendingDisposal { date: *date, remaining: d.disposed.clone(), matches: Vec::new() })
+        .collect();
+
+    // 1. Same day. Runs for every day before any 30-day matching, as it takes priority.
+    for disposal in pending.iter_mut() {
+        if let Some(left) = available.get_mut(&disposal.date) {
+            let quantity = min(&disposal.remaining, left);
+            if quantity > BigDecimal::zero() {
+                *left -= &quantity;
+                disposal.remaining -= &quantity;
+                disposal.matches.push(DisposalMatch {
+                    rule: MatchRule::SameDay,
+                    acquisition_date: Some(disposal.date),
+                    allowable_cost: cost_of(&days[&disposal.date], &quantity),
+                    quantity,
+                });
+            }
+        }
+    }
+
+    // 2. Bed and breakfast: earliest disposal first, earliest acquisition in the window first
+    for disposal in pending.iter_mut() {
+        let window_end = disposal.date + chrono::Duration::days(30);
+        for (acq_date, left) in available.range_mut(disposal.date.succ_opt().unwrap_or(disposal.date)..=window_end) {
+            if disposal.remaining.is_zero() {
+                break;
+            }
+            let quantity = min(&disposal.remaining, left);
+            if quantity > BigDecimal::zero() {
+                *left -= &quantity;
+                disposal.remaining -= &quantity;
+                disposal.matches.push(DisposalMatch {
+                    rule: MatchRule::BedAndBreakfast,
+                    acquisition_date: Some(*acq_date),
+                    allowable_cost: cost_of(&days[acq_date], &quantity),
+                    quantity,
+                });
+            }
+        }
+    }
+
+    // 3. Section 104 pool, walked chronologically with whatever the first two rules left over
+    let mut pool_quantity = BigDecimal::zero();
+    let mut pool_cost = BigDecimal::zero();
+    let mut pending = pending.into_iter().peekable();
+    let mut disposals = Vec::new();
+
+    for (date, day) in days {
+        if let Some(left) = available.get(date) {
+            if *left > BigDecimal::zero() {
+                pool_cost += cost_of(day, left);
+                pool_quantity += left;
+            }
+        }
+        for (carry, quantity) in &day.carried_in {
+            pool_cost += carried.get(carry).cloned().unwrap_or_default();
+            pool_quantity += quantity;
+        }
+
+        if pending.peek().map(|p| p.date) == Some(*date) {
+            let disposal = pending.next().expect("peeked");
+            disposals.push(match_pool(asset, *date, day, disposal, &mut pool_quantity, &mut pool_cost));
+        }
+
+        // Whatever the pool doesn't cover carries no cost
+        for (carry, quantity) in &day.carried_out {
+            let quantity = min(quantity, &pool_quantity);
+            let cost = if quantity > BigDecimal::zero() { &pool_cost * &quantity / &pool_quantity } else { BigDecimal::zero() };
+            pool_quantity -= &quantity;
+            pool_cost -= &cost;
+            *carried_out.entry(*carry).or_default() += cost;
+        }
+    }
+
+    let pool = Section104Pool {
+        asset_symbol: asset.to_string(),
+        quantity: pool_quantity,
+        allowable_cost: pool_cost,
+    };
+
+    (disposals, pool)
+}
+
+// Matches what the first two rules left of a disposal against the pool as it stands
+fn match_pool(
+    asset: &str,
+    date: NaiveDate,
+    day: &Day,
+    mut disposal: PendingDisposal,
+    pool_quantity: &mut BigDecimal,
+    pool_cost: &mut BigDecimal,
+) -> Disposal {
+
+    let quantity = min(&disposal.remaining, pool_quantity);
+    if quantity > BigDecimal::zero() {
+        let cost = &*pool_cost * &quantity / &*pool_quantity;
+        *pool_quantity -= &quantity;
+        *pool_cost -= &cost;
+        disposal.remaining -= &quantity;
+        disposal.matches.push(DisposalMatch {
+            rule: MatchRule::Section104,
+            acquisition_date: None,
+            quantity,
+            allowable_cost: cost,
+        });
+    }
+
+    let allowable_cost = disposal
+        .matches
+        .iter()
+        .fold(BigDecimal::zero(), |acc, m| acc + &m.allowable_cost);
+
+    Disposal {
+        asset_symbol: asset.to_string(),
+        date,
+        quantity: day.disposed.clone(),
+        proceeds: day.proceeds.clone(),
+        gain: &day.proceeds - &allowable_cost,
+        allowable_cost,
+        matches: disposal.matches,
+        unmatched_quantity: disposal.remaining,
+        entry_ids: day.disposal_entry_ids.clone(),
+    }
+}
+
+/// Runs `calculate` for each `lots::carry_groups` group separately. A group with an unvalued entry is
+/// left off and its assets listed in `unvalued_assets`, rather than one unpriced token failing the return.
+pub fn from_entries(entries: &[LedgerEntry]) -> anyhow::Result<UkPoolingReport> {
+    // Checked up front so mixed currencies fail the report instead of passing for unvalued assets
+    ensure_single_currency(entries)?;
+    let mut report = UkPoolingReport { disposals: Vec::new(), pools: Vec::new(), unvalued_assets: Vec::new() };
+    for group in lots::carry_groups(entries) {
+        match calculate(&group) {
+            Ok(r) => {
+                report.disposals.extend(r.disposals);
+                report.pools.extend(r.pools);
+            }
+            Err(_) => report.unvalued_assets.extend(group.into_iter().map(|e| e.asset_symbol)),
+        }
+    }
+    report.disposals.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.asset_symbol.cmp(&b.asset_symbol)));
+    report.pools.sort_by(|a, b| a.asset_symbol.cmp(&b.asset_symbol));
+    report.unvalued_assets.sort();
+    report.unvalued_assets.dedup();
+    Ok(report)
+}
+
+/// Aggregates the disposals falling in the UK tax year starting 6 April of `start_year`.
+pub fn sa108_summary(report: &UkPoolingReport, start_year: i32) -> Sa108Summary {
+    let (from, to) = tax_year_bounds(start_year);
+
+    let mut summary = Sa108Summary {
+        tax_year: format!("{}-{:02}", start_year, (start_year + 1) % 100),
+        number_of_disposals: 0,
+        disposal_proceeds: BigDecimal::zero(),
+        allowable_costs: BigDecimal::zero(),
+        gains_before_losses: BigDecimal::zero(),
+        losses: BigDecimal::zero(),
+    };
+
+    for disposal in report.disposals.iter().filter(|d| d.date >= from && d.date <= to) {
+        summary.number_of_disposals += 1;
+        summary.disposal_proceeds += &disposal.proceeds;
+        summary.allowable_costs += &disposal.allowable_cost;
+        if disposal.gain > BigDecimal::zero() {
+            summary.gains_before_losses += &disposal.gain;
+        } else {
+            summary.losses += disposal.gain.abs();
+        }
+    }
+
+    summary
+}
+
+/// Pools `entries` with `from_entries` and keeps what the return for the tax year starting in `start_year` needs.
+pub fn sa108_report(entries: &[LedgerEntry], start_year: i32) -> anyhow::Result<Sa108Report> {
+    let report = from_entries(entries)?;
+    let summary = sa108_summary(&report, start_year);
+    let (from, to) = tax_year_bounds(start_year);
+    Ok(Sa108Report {
+        summary,
+        disposals: report.disposals.into_iter().filter(|d| d.date >= from && d.date <= to).collect(),
+        unvalued_assets: report.unvalued_assets,
+    })
+}
+
+/// First and last day of the UK tax year starting in `start_year` (6 April to 5 April).
+pub fn tax_year_bounds(start_year: i32) -> (NaiveDate, NaiveDate) {
+    (
+        NaiveDate::from_ymd_opt(start_year, 4, 6).expect("valid date"),
+        NaiveDate::from_ymd_opt(start_year + 1, 4, 5).expect("valid date"),
+    )
+}
+
+// Share of the day's acquisition cost attributable to `quantity`
+fn cost_of(day: &Day, quantity: &BigDecimal) -> BigDecimal {
+    &day.cost * quantity / &day.acquired
+}
+
+fn min(a: &BigDecimal, b: &BigDecimal) -> BigDecimal {
+    if a < b { a.clone() } else { b.clone() }
+}
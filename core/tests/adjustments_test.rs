@@ -0,0 +1,87 @@
+use spectraplex_core::adjustments::{self, Adjustment, AuditRecord, Change, ManualEntry, MANUAL_PRICE_SOURCE};
+use spectraplex_core::models::{Chain, EntryType, LedgerEntry};
+use bigdecimal::BigDecimal;
+use serde_json::json;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const JAN_5_2024: i64 = 1_704_412_800;
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+fn gift(fiat_value: Option<&str>, fiat_currency: Option<&str>) -> ManualEntry {
+    ManualEntry {
+        wallet_address: "Wallet".to_string(),
+        timestamp: JAN_5_2024,
+        asset_symbol: "SOL".to_string(),
+        amount: dec("2"),
+        entry_type: EntryType::Income,
+        fiat_value: fiat_value.map(dec),
+        fiat_currency: fiat_currency.map(|c| c.to_string()),
+    }
+}
+
+#[test]
+fn test_manual_entry_round_trips_through_its_transaction() {
+    let manual = gift(Some("200"), Some("usd")).validated().unwrap();
+    assert_eq!(manual.fiat_currency.as_deref(), Some("USD"));
+
+    let tx = manual.to_transaction(Uuid::nil()).unwrap();
+    assert!(matches!(tx.chain, Chain::Manual));
+    assert!(tx.tx_hash.starts_with("manual:"));
+
+    let entries = adjustments::parse_manual_transaction(&tx).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].transaction_id, tx.id);
+    assert_eq!(entries[0].amount, dec("2"));
+    assert!(matches!(entries[0].entry_type, EntryType::Income));
+    assert_eq!(entries[0].fiat_value, Some(dec("200")));
+    assert_eq!(entries[0].price_source.as_deref(), Some(MANUAL_PRICE_SOURCE));
+
+    assert!(gift(Some("200"), None).validated().is_err());
+    assert!(gift(None, Some("USD")).validated().is_err());
+    assert!(ManualEntry { amount: dec("0"), ..gift(None, None) }.validated().is_err());
+}
+
+#[test]
+fn test_adjustments_override_the_entry_and_are_audited() {
+    let tx = gift(None, None).to_transaction(Uuid::nil()).unwrap();
+    let mut entry = LedgerEntry::new(&tx, "SOL".to_string(), dec("2"), EntryType::Transfer);
+    entry.fiat_value = Some(dec("180"));
+    entry.fiat_currency = Some("USD".to_string());
+    entry.price_source = Some("coingecko".to_string());
+    entry.price_resolution = Some("1d".to_string());
+
+    let mut adjustment = Adjustment::new(&entry);
+    let reclassify = Change::Reclassify { entry_type: EntryType::Income };
+    let record = AuditRecord::new(&entry, &adjustment, &reclassify, JAN_5_2024);
+    assert_eq!(record.previous, json!({ "entry_type": "Transfer" }));
+    adjustment.record(&reclassify).unwrap();
+
+    let revalue = Change::SetFiatValue { fiat_value: dec("210"), fiat_currency: "eur".to_string() }.validated().unwrap();
+    let record = AuditRecord::new(&entry, &adjustment, &revalue, JAN_5_2024);
+    assert_eq!(record.previous, json!({ "fiat_value": "180", "fiat_currency": "USD" }));
+    adjustment.record(&revalue).unwrap();
+
+    adjustment.apply(&mut entry);
+    assert!(matches!(entry.entry_type, EntryType::Income));
+    assert_eq!(entry.fiat_value, Some(dec("210")));
+    assert_eq!(entry.fiat_currency.as_deref(), Some("EUR"));
+    assert_eq!(entry.price_source.as_deref(), Some(MANUAL_PRICE_SOURCE));
+    assert!(entry.price_resolution.is_none());
+
+    // Annotations only touch what they give; an empty note clears it
+    adjustment.record(&Change::Annotate { note: Some("Birthday gift".to_string()), tags: Some(vec!["gift".to_string()]) }).unwrap();
+    adjustment.record(&Change::Annotate { note: None, tags: Some(vec!["gift".to_string(), "family".to_string()]) }).unwrap();
+    assert_eq!(adjustment.note.as_deref(), Some("Birthday gift"));
+    assert_eq!(adjustment.tags, ["gift", "family"]);
+    let before = adjustment.clone();
+    let clear = Change::Annotate { note: Some(String::new()), tags: None };
+    assert_eq!(AuditRecord::new(&entry, &before, &clear, JAN_5_2024).previous, json!({ "note": "Birthday gift", "tags": ["gift", "family"] }));
+    adjustment.record(&clear).unwrap();
+    assert!(adjustment.note.is_none());
+
+    assert!(adjustment.record(&Change::CreateEntry(gift(None, None))).is_err());
+}
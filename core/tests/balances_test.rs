@@ -0,0 +1,49 @@
+use spectraplex_core::balances::{self, DailyChange, Granularity};
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+
+fn change(asset: &str, day: &str, amount: i64) -> DailyChange {
+    DailyChange {
+        asset_symbol: asset.to_string(),
+        day: NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap(),
+        net_change: BigDecimal::from(amount),
+    }
+}
+
+fn date(s: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+}
+
+#[test]
+fn test_daily_series_carries_balances_through_quiet_days() {
+    let changes = vec![
+        change("SOL", "2023-12-15", 10), // Before the range, still counts toward the opening balance
+        change("SOL", "2024-01-02", -3),
+        change("USDC", "2024-01-02", 50),
+    ];
+
+    let points = balances::time_series(&changes, date("2024-01-01"), date("2024-01-03"), Granularity::Day);
+    assert_eq!(points.len(), 3);
+
+    assert_eq!(points[0].balances.len(), 1);
+    assert_eq!(points[0].balances[0].balance, BigDecimal::from(10));
+    assert_eq!(points[1].balances[0].balance, BigDecimal::from(7));
+    assert_eq!(points[1].balances[1].balance, BigDecimal::from(50));
+    assert_eq!(points[2].balances[0].balance, BigDecimal::from(7));
+}
+
+#[test]
+fn test_weekly_and_monthly_buckets() {
+    let changes = vec![change("SOL", "2024-01-03", 5), change("SOL", "2024-02-10", -5)];
+
+    // 2024-01-01 is a Monday
+    let weeks = balances::time_series(&changes, date("2024-01-03"), date("2024-01-20"), Granularity::Week);
+    assert_eq!(weeks.len(), 3);
+    assert_eq!(weeks[0].period_start, date("2024-01-01"));
+    assert_eq!(weeks[2].period_end, date("2024-01-20")); // Clipped to the range
+
+    let months = balances::time_series(&changes, date("2024-01-01"), date("2024-03-31"), Granularity::Month);
+    assert_eq!(months.len(), 3);
+    assert_eq!(months[0].balances[0].balance, BigDecimal::from(5));
+    assert!(months[1].balances.is_empty()); // Fully spent in February
+}
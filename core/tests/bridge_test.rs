@@ -0,0 +1,142 @@
+use spectraplex_core::bridge;
+use spectraplex_core::lots;
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, LedgerEntry};
+use bigdecimal::BigDecimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const JAN_5_2024: i64 = 1_704_412_800;
+const FEB_10_2024: i64 = 1_707_523_200;
+const HOUR: i64 = 3_600;
+
+const SOLANA_WALLET: &str = "BridgeWa11et111111111111111111111111111111";
+const EVM_WALLET: &str = "0xAbCdEf0000000000000000000000000000000001";
+const SOL_USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const ETH_USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+
+fn entry(wallet: &str, asset: &str, timestamp: i64, amount: &str, entry_type: EntryType, fiat: Option<&str>) -> LedgerEntry {
+    LedgerEntry {
+        id: Uuid::new_v4(),
+        transaction_id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: wallet.to_string(),
+        timestamp,
+        asset_symbol: asset.to_string(),
+        amount: BigDecimal::from_str(amount).unwrap(),
+        entry_type,
+        fiat_value: fiat.map(|f| BigDecimal::from_str(f).unwrap()),
+        fiat_currency: fiat.map(|_| "USD".to_string()),
+        price_source: None,
+        price_resolution: None,
+        source_program: None,
+        is_spam: false,
+        position_account: None,
+        bridge: None,
+        parser: None,
+    }
+}
+
+fn sent(destination_chain: Option<&str>, destination_address: Option<&str>) -> Option<BridgeTransfer> {
+    Some(BridgeTransfer {
+        protocol: "wormhole".to_string(),
+        destination_chain: destination_chain.map(str::to_string),
+        destination_address: destination_address.map(str::to_string),
+        transfer_id: Some("4242".to_string()),
+        linked_entry_id: None,
+    })
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+#[test]
+fn test_outflow_pairs_with_inflow_on_destination_chain() {
+    let mut out = entry(SOLANA_WALLET, SOL_USDC, FEB_10_2024, "-100", EntryType::Bridge, None);
+    out.bridge = sent(Some("ethereum"), Some(&EVM_WALLET.to_lowercase()));
+    let entries = vec![
+        out,
+        // Same amount but on the chain it left from
+        entry(SOLANA_WALLET, SOL_USDC, FEB_10_2024 + HOUR, "100", EntryType::Transfer, None),
+        // Right chain, but more than the bridge could have kept
+        entry(EVM_WALLET, ETH_USDC, FEB_10_2024 + HOUR, "90", EntryType::Transfer, None),
+        entry(EVM_WALLET, ETH_USDC, FEB_10_2024 + 2 * HOUR, "99.9", EntryType::Transfer, None),
+        // A second match, later: the closer one wins
+        entry(EVM_WALLET, ETH_USDC, FEB_10_2024 + 5 * HOUR, "99.95", EntryType::Transfer, None),
+    ];
+    let chains: HashMap<Uuid, Chain> = entries
+        .iter()
+        .map(|e| (e.transaction_id, if e.wallet_address == SOLANA_WALLET { Chain::Solana } else { Chain::Ethereum }))
+        .collect();
+
+    let mut linked = entries.clone();
+    let summary = bridge::link_transfers(&mut linked, &chains);
+    assert_eq!(summary.linked, 1);
+    assert_eq!(summary.unpaired, 0);
+
+    let inflow = &linked[3];
+    assert!(matches!(inflow.entry_type, EntryType::Bridge));
+    let received = inflow.bridge.as_ref().unwrap();
+    assert_eq!(received.linked_entry_id, Some(linked[0].id));
+    assert_eq!(received.protocol, "wormhole");
+    assert_eq!(received.transfer_id.as_deref(), Some("4242"));
+    assert_eq!(bridge::linked_entry(&linked[0]), Some(inflow.id));
+    assert!(linked[1..].iter().enumerate().all(|(i, e)| i == 2 || matches!(e.entry_type, EntryType::Transfer)));
+
+    // A destination elsewhere leaves it unpaired
+    let mut elsewhere = entries;
+    elsewhere[0].bridge = sent(Some("arbitrum"), None);
+    let summary = bridge::link_transfers(&mut elsewhere, &chains);
+    assert_eq!(summary.linked, 0);
+    assert_eq!(summary.unpaired, 1);
+}
+
+#[test]
+fn test_different_known_assets_are_not_paired() {
+    let mut out = entry(SOLANA_WALLET, "SOL", FEB_10_2024, "-1", EntryType::Bridge, None);
+    out.bridge = sent(Some("ethereum"), None);
+    let entries = vec![out, entry(EVM_WALLET, "ETH", FEB_10_2024 + HOUR, "0.98", EntryType::Transfer, None)];
+    let chains: HashMap<Uuid, Chain> = entries
+        .iter()
+        .map(|e| (e.transaction_id, if e.wallet_address == SOLANA_WALLET { Chain::Solana } else { Chain::Ethereum }))
+        .collect();
+
+    let mut linked = entries.clone();
+    let summary = bridge::link_transfers(&mut linked, &chains);
+    assert_eq!(summary.linked, 0);
+    assert_eq!(summary.unpaired, 1);
+
+    // A token it doesn't know still pairs on the amount
+    let mut wrapped = entries;
+    wrapped[1].asset_symbol = "0x000000000000000000000000000000000000beef".to_string();
+    assert_eq!(bridge::link_transfers(&mut wrapped, &chains).linked, 1);
+}
+
+#[test]
+fn test_basis_and_holding_period_follow_linked_transfer() {
+    let mut out = entry(SOLANA_WALLET, SOL_USDC, FEB_10_2024, "-100", EntryType::Bridge, Some("100"));
+    let mut inflow = entry(EVM_WALLET, ETH_USDC, FEB_10_2024 + HOUR, "99.9", EntryType::Bridge, None);
+    out.bridge = sent(Some("ethereum"), Some(EVM_WALLET));
+    inflow.bridge = Some(BridgeTransfer { linked_entry_id: Some(out.id), ..out.bridge.clone().unwrap() });
+    out.bridge.as_mut().unwrap().linked_entry_id = Some(inflow.id);
+    // Bridge arrivals can carry an earlier clock than the send; it still follows it
+    inflow.timestamp = FEB_10_2024 - 60;
+
+    let entries = vec![
+        entry(SOLANA_WALLET, SOL_USDC, JAN_5_2024, "100", EntryType::Trade, Some("100")),
+        out,
+        inflow,
+        entry(EVM_WALLET, ETH_USDC, FEB_10_2024 + 2 * HOUR, "-99.9", EntryType::Trade, Some("120")),
+    ];
+
+    let report = lots::match_fifo(&entries).unwrap();
+    assert_eq!(report.realized.len(), 1);
+    let gain = &report.realized[0];
+    assert_eq!(gain.asset_symbol, ETH_USDC);
+    assert_eq!(gain.acquired_at, Some(JAN_5_2024));
+    // The 0.1 kept by the bridge stays in the basis
+    assert_eq!(gain.cost_basis, dec("100"));
+    assert_eq!(gain.gain, dec("20"));
+    assert!(report.open_lots.is_empty());
+}
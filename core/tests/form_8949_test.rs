@@ -0,0 +1,135 @@
+use spectraplex_core::models::{EntryType, LedgerEntry};
+use spectraplex_core::{form_8949, lots};
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use spectraplex_core::lots::RealizedGain;
+use uuid::Uuid;
+
+const JAN_1_2023: i64 = 1_672_531_200;
+const JUN_1_2024: i64 = 1_717_200_000;
+const MAR_1_2024: i64 = 1_709_251_200;
+
+fn entry(timestamp: i64, amount: &str, fiat: &str) -> LedgerEntry {
+    LedgerEntry {
+        id: Uuid::new_v4(),
+        transaction_id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp,
+        asset_symbol: "SOL".to_string(),
+        amount: BigDecimal::from_str(amount).unwrap(),
+        entry_type: EntryType::Trade,
+        fiat_value: Some(BigDecimal::from_str(fiat).unwrap()),
+        fiat_currency: Some("USD".to_string()),
+        price_source: None,
+        price_resolution: None,
+        source_program: None,
+        is_spam: false,
+        position_account: None,
+        bridge: None,
+        parser: None,
+    }
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+#[test]
+fn test_fifo_splits_disposal_across_lots() {
+    let entries = vec![
+        entry(JAN_1_2023, "10", "100"),
+        entry(MAR_1_2024, "10", "500"),
+        entry(JUN_1_2024, "-15", "1500"),
+    ];
+
+    let report = lots::match_fifo(&entries).expect("FIFO failed");
+    assert_eq!(report.realized.len(), 2);
+
+    assert_eq!(report.realized[0].acquired_at, Some(JAN_1_2023));
+    assert_eq!(report.realized[0].proceeds, dec("1000"));
+    assert_eq!(report.realized[0].gain, dec("900"));
+
+    assert_eq!(report.realized[1].acquired_at, Some(MAR_1_2024));
+    assert_eq!(report.realized[1].cost_basis, dec("250"));
+    assert_eq!(report.realized[1].gain, dec("250"));
+
+    assert_eq!(report.open_lots.len(), 1);
+    assert_eq!(report.open_lots[0].quantity, dec("5"));
+    assert_eq!(report.open_lots[0].cost_basis, dec("250"));
+}
+
+#[test]
+fn test_form_8949_splits_short_and_long_term() {
+    let entries = vec![
+        entry(JAN_1_2023, "10", "100"),
+        entry(MAR_1_2024, "10", "500"),
+        entry(JUN_1_2024, "-15", "1500"),
+    ];
+
+    let gains = lots::match_fifo(&entries).unwrap().realized;
+    let report = form_8949::build(&gains, 2024, "USD").unwrap();
+
+    assert_eq!(report.long_term.len(), 1);
+    assert_eq!(report.short_term.len(), 1);
+    assert_eq!(report.long_term[0].description, "10 SOL");
+    assert_eq!(report.schedule_d.long_term.gain_or_loss, dec("900"));
+    assert_eq!(report.schedule_d.short_term.proceeds, dec("500"));
+    assert_eq!(report.schedule_d.net_gain_or_loss, dec("1150"));
+
+    // Nothing was sold in 2023
+    let empty = form_8949::build(&gains, 2023, "USD").unwrap();
+    assert!(empty.short_term.is_empty() && empty.long_term.is_empty());
+}
+
+#[test]
+fn test_disposal_without_lots_has_zero_basis() {
+    let gains = lots::match_fifo(&[entry(JUN_1_2024, "-1", "150")]).unwrap().realized;
+    assert_eq!(gains.len(), 1);
+    assert_eq!(gains[0].acquired_at, None);
+    assert_eq!(gains[0].gain, dec("150"));
+}
+
+#[test]
+fn test_gain_column_is_proceeds_minus_cost_as_rounded() {
+    // 6.67 rounded on its own, but 10.00 - 3.34 on the form
+    let gain = RealizedGain {
+        asset_symbol: "SOL".to_string(),
+        disposal_entry_id: Uuid::new_v4(),
+        acquired_at: Some(MAR_1_2024),
+        disposed_at: JUN_1_2024,
+        quantity: dec("0.1"),
+        proceeds: dec("10.005"),
+        cost_basis: dec("3.335"),
+        gain: dec("6.67"),
+    };
+    let report = form_8949::build(&[gain.clone(), gain], 2024, "USD").unwrap();
+
+    let row = &report.short_term[0];
+    assert_eq!((&row.proceeds, &row.cost_basis, &row.gain_or_loss), (&dec("10.00"), &dec("3.34"), &dec("6.66")));
+    let line = &report.schedule_d.short_term;
+    assert_eq!(&line.proceeds - &line.cost_basis, line.gain_or_loss);
+    assert_eq!(report.schedule_d.net_gain_or_loss, dec("13.32"));
+}
+
+#[test]
+fn test_unvalued_asset_is_left_off_not_fatal() {
+    let mut junk = entry(MAR_1_2024, "1000", "0");
+    junk.asset_symbol = "JUNK".to_string();
+    junk.fiat_value = None;
+    let entries = vec![
+        entry(JAN_1_2023, "10", "100"),
+        junk,
+        entry(JUN_1_2024, "-10", "1500"),
+    ];
+    assert!(lots::match_fifo(&entries).is_err());
+
+    let report = form_8949::from_entries(&entries, 2024, "USD").unwrap();
+    assert_eq!(report.long_term.len(), 1);
+    assert_eq!(report.schedule_d.net_gain_or_loss, dec("1400"));
+    assert_eq!(report.unvalued_assets, vec!["JUNK".to_string()]);
+
+    let mut eur = entry(MAR_1_2024, "1", "90");
+    eur.fiat_currency = Some("EUR".to_string());
+    assert!(form_8949::from_entries(&[entries, vec![eur]].concat(), 2024, "USD").is_err());
+}
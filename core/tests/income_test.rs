@@ -0,0 +1,73 @@
+use spectraplex_core::income;
+use spectraplex_core::models::{EntryType, LedgerEntry};
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const JAN_5_2024: i64 = 1_704_412_800;
+const JAN_20_2024: i64 = 1_705_708_800;
+const FEB_10_2024: i64 = 1_707_523_200;
+const DEC_31_2023: i64 = 1_703_980_800;
+
+const STAKE_PROGRAM: &str = "Stake11111111111111111111111111111111111111";
+
+fn entry(timestamp: i64, entry_type: EntryType, amount: &str, fiat: Option<&str>, program: Option<&str>) -> LedgerEntry {
+    LedgerEntry {
+        id: Uuid::new_v4(),
+        transaction_id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp,
+        asset_symbol: "SOL".to_string(),
+        amount: BigDecimal::from_str(amount).unwrap(),
+        entry_type,
+        fiat_value: fiat.map(|f| BigDecimal::from_str(f).unwrap()),
+        fiat_currency: fiat.map(|_| "USD".to_string()),
+        price_source: None,
+        price_resolution: None,
+        source_program: program.map(|p| p.to_string()),
+        is_spam: false,
+        position_account: None,
+        bridge: None,
+        parser: None,
+    }
+}
+
+#[test]
+fn test_income_grouped_by_month_and_source() {
+    let entries = vec![
+        entry(JAN_5_2024, EntryType::Staking, "0.5", Some("50.005"), Some(STAKE_PROGRAM)),
+        entry(JAN_20_2024, EntryType::Staking, "0.25", Some("25"), Some(STAKE_PROGRAM)),
+        entry(JAN_20_2024, EntryType::Income, "1", Some("100"), Some("Airdrop111")),
+        entry(FEB_10_2024, EntryType::Staking, "0.5", Some("60"), Some(STAKE_PROGRAM)),
+        entry(DEC_31_2023, EntryType::Staking, "1", Some("90"), Some(STAKE_PROGRAM)), // Previous tax year
+        entry(JAN_5_2024, EntryType::Trade, "3", Some("300"), None),                    // Not income
+    ];
+
+    let report = income::build(&entries, 2024, "USD").unwrap();
+    assert_eq!(report.lines.len(), 3);
+
+    let jan_airdrop = &report.lines[0];
+    assert_eq!(jan_airdrop.month, "2024-01");
+    assert_eq!(jan_airdrop.source_program.as_deref(), Some("Airdrop111"));
+
+    let jan_stake = &report.lines[1];
+    assert_eq!(jan_stake.entries, 2);
+    assert_eq!(jan_stake.quantity, BigDecimal::from_str("0.75").unwrap());
+    assert_eq!(jan_stake.fair_market_value, BigDecimal::from_str("75.00").unwrap());
+
+    assert_eq!(report.lines[2].month, "2024-02");
+    assert_eq!(report.total_fair_market_value, BigDecimal::from_str("235.00").unwrap());
+
+    let mut csv = Vec::new();
+    income::write_csv(&report, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("month,asset,source_program,entries,quantity,fair_market_value,currency\n"));
+    assert!(csv.contains(&format!("2024-01,SOL,{},2,0.75,75.00,USD", STAKE_PROGRAM)));
+}
+
+#[test]
+fn test_income_requires_value_at_receipt() {
+    let entries = vec![entry(JAN_5_2024, EntryType::Income, "10", None, None)];
+    assert!(income::build(&entries, 2024, "USD").is_err());
+}
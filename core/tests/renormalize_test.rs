@@ -0,0 +1,150 @@
+use spectraplex_core::adjustments::Adjustment;
+use spectraplex_core::models::{BridgeTransfer, Chain, EntryType, LedgerEntry, ParserVersion, Transaction};
+use spectraplex_core::renormalize;
+use bigdecimal::BigDecimal;
+use serde_json::json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const JAN_5_2024: i64 = 1_704_412_800;
+
+fn tx(hash: &str) -> Transaction {
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp: JAN_5_2024,
+        tx_hash: hash.to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    }
+}
+
+fn solana(version: u32) -> Option<ParserVersion> {
+    Some(ParserVersion { name: "solana".to_string(), version })
+}
+
+fn entry(tx: &Transaction, asset: &str, amount: &str, entry_type: EntryType, parser: Option<ParserVersion>) -> LedgerEntry {
+    let mut e = LedgerEntry::new(tx, asset.to_string(), BigDecimal::from_str(amount).unwrap(), entry_type);
+    e.parser = parser;
+    e
+}
+
+fn current(txs: &[&Transaction]) -> HashMap<Uuid, ParserVersion> {
+    txs.iter().map(|t| (t.id, solana(2).unwrap())).collect()
+}
+
+#[test]
+fn test_only_entries_from_older_parsers_are_rebuilt() {
+    let (fresh_tx, stale_tx, unversioned_tx) = (tx("fresh"), tx("stale"), tx("unversioned"));
+
+    let mut priced = entry(&stale_tx, "SOL", "1", EntryType::Trade, solana(1));
+    priced.fiat_value = Some(BigDecimal::from(100));
+    let stored = vec![
+        entry(&fresh_tx, "SOL", "5", EntryType::Trade, solana(2)),
+        priced.clone(),
+        entry(&stale_tx, "SOL", "-0.000005", EntryType::Fee, solana(1)),
+        // Normalized twice before versioning
+        entry(&unversioned_tx, "USDC", "10", EntryType::Transfer, None),
+        entry(&unversioned_tx, "USDC", "10", EntryType::Transfer, None),
+    ];
+    let parsed = vec![
+        entry(&fresh_tx, "SOL", "5", EntryType::Trade, solana(2)),
+        entry(&stale_tx, "SOL", "1", EntryType::Trade, solana(2)),
+        // The new version gets the fee right
+        entry(&stale_tx, "SOL", "-0.00001", EntryType::Fee, solana(2)),
+        entry(&unversioned_tx, "USDC", "10", EntryType::Transfer, solana(2)),
+    ];
+
+    let plan = renormalize::plan(&stored, parsed, &current(&[&fresh_tx, &stale_tx, &unversioned_tx]), &HashMap::new());
+    assert_eq!(plan.transactions, 2);
+    assert!(!plan.transaction_ids.contains(&fresh_tx.id));
+    assert_eq!(plan.unchanged, 2);
+    assert_eq!(plan.added.len(), 1);
+    assert_eq!(plan.added[0].amount, BigDecimal::from_str("-0.00001").unwrap());
+    assert_eq!(plan.removed.len(), 2);
+    assert!(plan.changes_anything());
+
+    // The reproduced trade is the stored one, valuation and all, at the new version
+    let trade = plan.entries.iter().find(|e| e.transaction_id == stale_tx.id && matches!(e.entry_type, EntryType::Trade)).unwrap();
+    assert_eq!(trade.id, priced.id);
+    assert_eq!(trade.fiat_value, priced.fiat_value);
+    assert_eq!(trade.parser, solana(2));
+    assert_eq!(plan.entries.iter().filter(|e| e.transaction_id == unversioned_tx.id).count(), 1);
+
+    // Once applied, there's nothing left to do
+    let rebuilt: Vec<LedgerEntry> = stored.iter().filter(|e| e.transaction_id == fresh_tx.id).cloned().chain(plan.entries.clone()).collect();
+    let again = renormalize::plan(&rebuilt, rebuilt.clone(), &current(&[&fresh_tx, &stale_tx, &unversioned_tx]), &HashMap::new());
+    assert_eq!(again.transactions, 0);
+    assert!(!again.changes_anything());
+}
+
+#[test]
+fn test_linked_bridge_entry_matches_the_transfer_it_was_parsed_as() {
+    let sent = tx("sent");
+    let mut linked = entry(&sent, "USDC", "-100", EntryType::Bridge, solana(1));
+    linked.bridge = Some(BridgeTransfer {
+        protocol: "wormhole".to_string(),
+        destination_chain: Some("ethereum".to_string()),
+        destination_address: None,
+        transfer_id: Some("4242".to_string()),
+        linked_entry_id: Some(Uuid::new_v4()),
+    });
+    let mut parsed = entry(&sent, "USDC", "-100", EntryType::Bridge, solana(2));
+    parsed.bridge = Some(BridgeTransfer { linked_entry_id: None, destination_chain: None, ..linked.bridge.clone().unwrap() });
+
+    let plan = renormalize::plan(std::slice::from_ref(&linked), vec![parsed], &current(&[&sent]), &HashMap::new());
+    assert!(!plan.changes_anything());
+    assert_eq!(plan.entries[0].bridge, linked.bridge);
+    assert_eq!(plan.entries[0].parser, solana(2));
+}
+
+#[test]
+fn test_bridge_details_the_parser_dropped_are_not_kept() {
+    let sent = tx("sent");
+    // Parsed as a bridge by an older version, never linked
+    let mut stale = entry(&sent, "USDC", "-100", EntryType::Transfer, solana(1));
+    stale.bridge = Some(BridgeTransfer {
+        protocol: "wormhole".to_string(),
+        destination_chain: Some("ethereum".to_string()),
+        destination_address: None,
+        transfer_id: None,
+        linked_entry_id: None,
+    });
+    let parsed = entry(&sent, "USDC", "-100", EntryType::Transfer, solana(2));
+
+    let plan = renormalize::plan(std::slice::from_ref(&stale), vec![parsed], &current(&[&sent]), &HashMap::new());
+    assert_eq!((plan.added.len(), plan.removed.len()), (1, 1));
+    assert_eq!(plan.entries.len(), 1);
+    assert!(plan.entries[0].bridge.is_none());
+
+    // Once linked, the plain transfer it was parsed as still matches
+    let mut linked = stale.clone();
+    linked.entry_type = EntryType::Bridge;
+    linked.bridge.as_mut().unwrap().linked_entry_id = Some(Uuid::new_v4());
+    let parsed = entry(&sent, "USDC", "-100", EntryType::Transfer, solana(2));
+    let plan = renormalize::plan(std::slice::from_ref(&linked), vec![parsed], &current(&[&sent]), &HashMap::new());
+    assert!(!plan.changes_anything());
+    assert_eq!(plan.entries[0].bridge, linked.bridge);
+}
+
+#[test]
+fn test_adjusted_entries_survive_and_orphans_are_reported() {
+    let (staked, dropped) = (tx("staked"), tx("dropped"));
+    // The user reclassified a transfer as staking income and annotated another entry the new parser drops
+    let mut reward = entry(&staked, "SOL", "0.1", EntryType::Staking, solana(1));
+    reward.fiat_value = Some(BigDecimal::from(10));
+    let dust = entry(&dropped, "SCAM", "1", EntryType::Transfer, solana(1));
+    let mut adjusted = HashMap::new();
+    adjusted.insert(reward.id, Adjustment { entry_type: Some(EntryType::Staking), ..Adjustment::new(&reward) });
+    adjusted.insert(dust.id, Adjustment { note: Some("airdrop".to_string()), ..Adjustment::new(&dust) });
+
+    let parsed = vec![entry(&staked, "SOL", "0.1", EntryType::Transfer, solana(2))];
+    let plan = renormalize::plan(&[reward.clone(), dust.clone()], parsed, &current(&[&staked, &dropped]), &adjusted);
+    assert_eq!(plan.unchanged, 1);
+    assert_eq!(plan.entries[0].id, reward.id);
+    assert!(matches!(plan.entries[0].entry_type, EntryType::Staking));
+    assert_eq!(plan.removed.len(), 1);
+    assert_eq!(plan.orphaned_adjustments, [dust.id]);
+}
@@ -0,0 +1,101 @@
+use spectraplex_core::spam::{self, SpamLists};
+use spectraplex_core::models::{EntryType, LedgerEntry};
+use bigdecimal::BigDecimal;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use uuid::Uuid;
+
+const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const AIRDROP_MINT: &str = "AirDropSpam1111111111111111111111111111111";
+const BONK_MINT: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+fn entry(tx: Uuid, asset: &str, amount: &str, entry_type: EntryType, fiat: Option<&str>) -> LedgerEntry {
+    LedgerEntry {
+        id: Uuid::new_v4(),
+        transaction_id: tx,
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp: 1_704_412_800,
+        asset_symbol: asset.to_string(),
+        amount: BigDecimal::from_str(amount).unwrap(),
+        entry_type,
+        fiat_value: fiat.map(|f| BigDecimal::from_str(f).unwrap()),
+        fiat_currency: fiat.map(|_| "USD".to_string()),
+        price_source: None,
+        price_resolution: None,
+        source_program: None,
+        is_spam: false,
+        position_account: None,
+        bridge: None,
+        parser: None,
+    }
+}
+
+fn spam_assets(entries: &[LedgerEntry]) -> HashSet<String> {
+    entries.iter().filter(|e| e.is_spam).map(|e| e.asset_symbol.clone()).collect()
+}
+
+#[test]
+fn test_unsolicited_unvalued_airdrop_is_spam() {
+    let swap = Uuid::new_v4();
+    let mut entries = vec![
+        entry(Uuid::new_v4(), AIRDROP_MINT, "1000", EntryType::Transfer, None),
+        entry(Uuid::new_v4(), "SOL", "2", EntryType::Transfer, None), // Native, never spam
+        entry(swap, "SOL", "-1", EntryType::Trade, Some("100")),
+        entry(swap, BONK_MINT, "5000", EntryType::Trade, None), // Bought, so wanted
+    ];
+
+    spam::classify(&mut entries, &SpamLists::default(), &HashMap::new());
+    assert_eq!(spam_assets(&entries), HashSet::from([AIRDROP_MINT.to_string()]));
+}
+
+#[test]
+fn test_valued_airdrop_is_not_spam() {
+    let mut entries = vec![entry(Uuid::new_v4(), AIRDROP_MINT, "10", EntryType::Transfer, Some("25"))];
+    spam::classify(&mut entries, &SpamLists::default(), &HashMap::new());
+    assert!(!entries[0].is_spam);
+}
+
+#[test]
+fn test_lookalike_of_known_mint_is_spam() {
+    // Same first and last four characters as USDC, as a truncated wallet display would show it
+    let fake_usdc = "EPjFxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxDt1v";
+    let swap = Uuid::new_v4();
+    let mut entries = vec![
+        entry(swap, "SOL", "-1", EntryType::Trade, Some("100")),
+        entry(swap, fake_usdc, "100", EntryType::Trade, None),
+        entry(Uuid::new_v4(), USDC_MINT, "5", EntryType::Transfer, None),
+    ];
+
+    spam::classify(&mut entries, &SpamLists::default(), &HashMap::new());
+    assert_eq!(spam_assets(&entries), HashSet::from([fake_usdc.to_string()]));
+}
+
+#[test]
+fn test_lists_and_overrides_take_precedence() {
+    let deny = spam::parse_list("# scam launches\nScam*\n*pump  # fake pump.fun tokens\n\n".as_bytes()).unwrap();
+    assert_eq!(deny, vec!["Scam*".to_string(), "*pump".to_string()]);
+    let lists = SpamLists::new(deny, HashSet::from([AIRDROP_MINT.to_string()]));
+
+    let mut entries = vec![
+        entry(Uuid::new_v4(), AIRDROP_MINT, "1000", EntryType::Transfer, None), // Allowlisted
+        entry(Uuid::new_v4(), "ScamCoin", "1", EntryType::Transfer, None),
+        entry(Uuid::new_v4(), "FakeTokenpump", "1", EntryType::Transfer, None),
+        entry(Uuid::new_v4(), BONK_MINT, "1", EntryType::Transfer, None),
+    ];
+
+    spam::classify(&mut entries, &lists, &HashMap::new());
+    let expected = HashSet::from(["ScamCoin".to_string(), "FakeTokenpump".to_string(), BONK_MINT.to_string()]);
+    assert_eq!(spam_assets(&entries), expected);
+
+    // The user's own decision beats both the lists and the heuristics
+    let overrides = HashMap::from([("ScamCoin".to_string(), false), (AIRDROP_MINT.to_string(), true), (BONK_MINT.to_string(), false)]);
+    spam::classify(&mut entries, &lists, &overrides);
+    let expected = HashSet::from([AIRDROP_MINT.to_string(), "FakeTokenpump".to_string()]);
+    assert_eq!(spam_assets(&entries), expected);
+
+    let kept = spam::filter(entries.clone(), false);
+    assert_eq!(kept.len(), 2);
+    assert!(kept.iter().all(|e| !e.is_spam));
+    assert_eq!(spam::filter(entries, true).len(), 4);
+}
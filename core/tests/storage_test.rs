@@ -0,0 +1,53 @@
+use spectraplex_core::models::{Chain, Transaction};
+use spectraplex_core::storage::{Cursor, SyncState};
+use serde_json::json;
+use uuid::Uuid;
+
+const WALLET: &str = "SyncWa11et11111111111111111111111111111111";
+
+fn tx(timestamp: i64, hash: &str) -> Transaction {
+    Transaction {
+        id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: WALLET.to_string(),
+        timestamp,
+        tx_hash: hash.to_string(),
+        chain: Chain::Solana,
+        raw_metadata: json!({}),
+    }
+}
+
+fn new_hashes(state: &SyncState, fetched: &[Transaction]) -> Vec<String> {
+    fetched.iter().filter(|t| state.is_new(t)).map(|t| t.tx_hash.clone()).collect()
+}
+
+#[test]
+fn test_sync_state_skips_what_was_already_stored() {
+    let mut state = SyncState::new(WALLET, Chain::Solana);
+    let first = vec![tx(100, "a"), tx(200, "b"), tx(200, "c")];
+    assert_eq!(new_hashes(&state, &first), ["a", "b", "c"]);
+    state.advance(&first);
+    assert_eq!(state.last_timestamp, Some(200));
+    assert_eq!(state.last_tx_hashes, ["b", "c"]);
+
+    // The next fetch overlaps the first; a transaction landing in the same second as the last one still counts
+    let second = vec![tx(200, "b"), tx(200, "c"), tx(200, "d"), tx(300, "e")];
+    assert_eq!(new_hashes(&state, &second), ["d", "e"]);
+    state.advance(&[tx(200, "d")]);
+    assert_eq!(state.last_tx_hashes, ["b", "c", "d"]);
+    state.advance(&[tx(300, "e")]);
+    assert_eq!(state.last_timestamp, Some(300));
+    assert_eq!(state.last_tx_hashes, ["e"]);
+
+    // Nothing new leaves it where it was
+    state.advance(&[]);
+    assert_eq!(state.last_timestamp, Some(300));
+}
+
+#[test]
+fn test_cursor_round_trips() {
+    let cursor = Cursor { timestamp: 1_704_412_800, id: Uuid::new_v4() };
+    assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+    assert!(Cursor::decode("1704412800").is_err());
+    assert!(Cursor::decode("soon.not-a-uuid").is_err());
+}
@@ -0,0 +1,198 @@
+use spectraplex_core::models::{BridgeTransfer, EntryType, LedgerEntry};
+use spectraplex_core::uk_pooling::{self, MatchRule};
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const DAY: i64 = 86_400;
+const START: i64 = 1_680_739_200; // 2023-04-06 00:00:00 UTC
+
+fn entry(day: i64, amount: &str, fiat: &str) -> LedgerEntry {
+    LedgerEntry {
+        id: Uuid::new_v4(),
+        transaction_id: Uuid::new_v4(),
+        user_id: Uuid::nil(),
+        wallet_address: "Wallet".to_string(),
+        timestamp: START + day * DAY + 3600,
+        asset_symbol: "SOL".to_string(),
+        amount: BigDecimal::from_str(amount).unwrap(),
+        entry_type: EntryType::Trade,
+        fiat_value: Some(BigDecimal::from_str(fiat).unwrap()),
+        fiat_currency: Some("USD".to_string()),
+        price_source: None,
+        price_resolution: None,
+        source_program: None,
+        is_spam: false,
+        position_account: None,
+        bridge: None,
+        parser: None,
+    }
+}
+
+fn dec(s: &str) -> BigDecimal {
+    BigDecimal::from_str(s).unwrap()
+}
+
+#[test]
+fn test_same_day_then_bed_and_breakfast_then_pool() {
+    let entries = vec![
+        entry(0, "100", "1000"),
+        entry(9, "50", "1000"),
+        entry(19, "-60", "1200"),
+        entry(19, "10", "250"),
+        entry(29, "20", "300"),
+    ];
+
+    let report = uk_pooling::calculate(&entries).expect("calculation failed");
+    assert_eq!(report.disposals.len(), 1);
+
+    let disposal = &report.disposals[0];
+    let rules: Vec<MatchRule> = disposal.matches.iter().map(|m| m.rule.clone()).collect();
+    assert_eq!(rules, vec![MatchRule::SameDay, MatchRule::BedAndBreakfast, MatchRule::Section104]);
+
+    assert_eq!(disposal.matches[0].allowable_cost, dec("250"));
+    assert_eq!(disposal.matches[1].allowable_cost, dec("300"));
+    // 30 of the 150 pooled at an average of 2000 / 150
+    assert_eq!(disposal.matches[2].quantity, dec("30"));
+    assert_eq!(disposal.matches[2].allowable_cost, dec("400"));
+    assert_eq!(disposal.allowable_cost, dec("950"));
+    assert_eq!(disposal.gain, dec("250"));
+
+    // The bed and breakfast acquisition never enters the pool
+    assert_eq!(report.pools[0].quantity, dec("120"));
+    assert_eq!(report.pools[0].allowable_cost, dec("1600"));
+}
+
+#[test]
+fn test_acquisition_outside_thirty_days_goes_to_pool() {
+    let entries = vec![
+        entry(0, "10", "100"),
+        entry(1, "-5", "100"),
+        entry(32, "5", "200"),
+    ];
+
+    let report = uk_pooling::calculate(&entries).unwrap();
+    let disposal = &report.disposals[0];
+    assert_eq!(disposal.matches.len(), 1);
+    assert_eq!(disposal.matches[0].rule, MatchRule::Section104);
+    assert_eq!(disposal.gain, dec("50"));
+    assert_eq!(report.pools[0].quantity, dec("10"));
+    assert_eq!(report.pools[0].allowable_cost, dec("250"));
+}
+
+#[test]
+fn test_sa108_summary_splits_gains_and_losses_by_tax_year() {
+    let entries = vec![
+        entry(0, "10", "100"),
+        entry(10, "-2", "40"),
+        entry(20, "-2", "10"),
+        entry(400, "-1", "50"), // Falls in 2024-25
+    ];
+
+    let report = uk_pooling::calculate(&entries).unwrap();
+    let summary = uk_pooling::sa108_summary(&report, 2023);
+
+    assert_eq!(summary.tax_year, "2023-24");
+    assert_eq!(summary.number_of_disposals, 2);
+    assert_eq!(summary.disposal_proceeds, dec("50"));
+    assert_eq!(summary.allowable_costs, dec("40"));
+    assert_eq!(summary.gains_before_losses, dec("20"));
+    assert_eq!(summary.losses, dec("10"));
+}
+
+#[test]
+fn test_missing_fiat_value_is_an_error() {
+    let mut unpriced = entry(0, "1", "0");
+    unpriced.fiat_value = None;
+    assert!(uk_pooling::calculate(&[unpriced]).is_err());
+}
+
+#[test]
+fn test_unvalued_asset_is_left_off_the_return() {
+    let mut junk = entry(1, "1000", "0");
+    junk.asset_symbol = "JUNK".to_string();
+    junk.fiat_value = None;
+    let entries = vec![entry(0, "10", "100"), junk, entry(2, "-5", "80")];
+
+    let report = uk_pooling::from_entries(&entries).unwrap();
+    assert_eq!(report.disposals.len(), 1);
+    assert_eq!(report.disposals[0].gain, dec("30"));
+    assert_eq!(report.pools.len(), 1);
+    assert_eq!(report.unvalued_assets, vec!["JUNK".to_string()]);
+
+    let sa108 = uk_pooling::sa108_report(&entries, 2023).unwrap();
+    assert_eq!(sa108.summary.gains_before_losses, dec("30"));
+    assert_eq!(sa108.disposals.len(), 1);
+    assert_eq!(sa108.unvalued_assets, vec!["JUNK".to_string()]);
+    assert!(uk_pooling::sa108_report(&entries, 2024).unwrap().disposals.is_empty());
+}
+
+// An unvalued move of `asset` in transaction `tx`
+fn carry(tx: Uuid, day: i64, asset: &str, amount: &str, entry_type: EntryType) -> LedgerEntry {
+    let mut entry = entry(day, amount, "0");
+    entry.transaction_id = tx;
+    entry.asset_symbol = asset.to_string();
+    entry.entry_type = entry_type;
+    entry.fiat_value = None;
+    entry
+}
+
+#[test]
+fn test_liquidity_added_carries_pool_cost_to_the_share() {
+    let add = Uuid::new_v4();
+    let mut usdc = entry(1, "100", "100");
+    usdc.asset_symbol = "USDC".to_string();
+    let mut redeemed = entry(40, "-1", "700");
+    redeemed.asset_symbol = "LP".to_string();
+    redeemed.entry_type = EntryType::LpToken;
+
+    let entries = vec![
+        entry(0, "10", "1000"),
+        usdc,
+        carry(add, 5, "SOL", "-5", EntryType::Liquidity),
+        carry(add, 5, "USDC", "-50", EntryType::Liquidity),
+        carry(add, 5, "LP", "1", EntryType::LpToken),
+        redeemed,
+    ];
+
+    // Adding liquidity isn't a disposal; redeeming the share is, against what was paid in
+    let report = uk_pooling::calculate(&entries).unwrap();
+    assert_eq!(report.disposals.len(), 1);
+    let disposal = &report.disposals[0];
+    assert_eq!(disposal.asset_symbol, "LP");
+    assert_eq!(disposal.matches[0].rule, MatchRule::Section104);
+    assert_eq!(disposal.allowable_cost, dec("550"));
+    assert_eq!(disposal.gain, dec("150"));
+
+    let sol = report.pools.iter().find(|p| p.asset_symbol == "SOL").unwrap();
+    assert_eq!(sol.quantity, dec("5"));
+    assert_eq!(sol.allowable_cost, dec("500"));
+}
+
+#[test]
+fn test_bridged_pool_cost_follows_to_the_other_chain() {
+    let mut out = carry(Uuid::new_v4(), 5, "SOL_USDC", "-10", EntryType::Bridge);
+    let mut inflow = carry(Uuid::new_v4(), 5, "ETH_USDC", "9.9", EntryType::Bridge);
+    let link = |other: Uuid| Some(BridgeTransfer {
+        protocol: "wormhole".to_string(),
+        destination_chain: Some("ethereum".to_string()),
+        destination_address: None,
+        transfer_id: None,
+        linked_entry_id: Some(other),
+    });
+    out.bridge = link(inflow.id);
+    inflow.bridge = link(out.id);
+
+    let mut bought = entry(0, "10", "100");
+    bought.asset_symbol = "SOL_USDC".to_string();
+    let mut sold = entry(40, "-9.9", "120");
+    sold.asset_symbol = "ETH_USDC".to_string();
+
+    // Only the sale on the other chain is a disposal, against the cost of what was sent
+    let report = uk_pooling::calculate(&[bought, out, inflow, sold]).unwrap();
+    assert_eq!(report.disposals.len(), 1);
+    assert_eq!(report.disposals[0].asset_symbol, "ETH_USDC");
+    assert_eq!(report.disposals[0].allowable_cost, dec("100"));
+    assert_eq!(report.disposals[0].gain, dec("20"));
+    assert!(report.pools.iter().all(|p| p.quantity == dec("0")));
+}
@@ -0,0 +1,18 @@
+-- Add timestamp to ledger_entries so cost basis calculations can order entries without joining
+ALTER TABLE ledger_entries ADD COLUMN timestamp BIGINT;
+
+-- Backfill from the parent transaction
+UPDATE ledger_entries le
+SET timestamp = tx.timestamp
+FROM transactions tx
+WHERE le.transaction_id = tx.id;
+
+-- Entries without a transaction fall back to when they were recorded
+UPDATE ledger_entries
+SET timestamp = EXTRACT(EPOCH FROM created_at)::BIGINT
+WHERE timestamp IS NULL;
+
+ALTER TABLE ledger_entries ALTER COLUMN timestamp SET NOT NULL;
+
+-- Index for chronological scans per wallet
+CREATE INDEX idx_ledger_wallet_timestamp ON ledger_entries(wallet_address, timestamp);
@@ -0,0 +1,22 @@
+-- Historical OHLC prices imported from CSV/JSON files
+CREATE TABLE price_history (
+    asset_symbol VARCHAR(50) NOT NULL,
+    fiat_currency VARCHAR(10) NOT NULL,
+    resolution_secs BIGINT NOT NULL,
+    open_time BIGINT NOT NULL,
+    open NUMERIC NOT NULL,
+    high NUMERIC NOT NULL,
+    low NUMERIC NOT NULL,
+    close NUMERIC NOT NULL,
+    source VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (asset_symbol, fiat_currency, resolution_secs, open_time)
+);
+
+-- Index for point-in-time lookups
+CREATE INDEX idx_price_history_lookup ON price_history(asset_symbol, fiat_currency, open_time);
+
+-- Audit trail for how each ledger entry was valued
+ALTER TABLE ledger_entries ADD COLUMN price_source VARCHAR(100);
+-- Labels such as "~90061s" have no fixed width
+ALTER TABLE ledger_entries ADD COLUMN price_resolution TEXT;
@@ -0,0 +1,21 @@
+-- Currency of each valuation. Everything priced so far was USD.
+ALTER TABLE ledger_entries ADD COLUMN fiat_currency VARCHAR(3);
+UPDATE ledger_entries SET fiat_currency = 'USD' WHERE fiat_value IS NOT NULL;
+
+-- Daily FX reference rates: 1 base = rate quote
+CREATE TABLE fx_rates (
+    base_currency VARCHAR(3) NOT NULL,
+    quote_currency VARCHAR(3) NOT NULL,
+    rate_date DATE NOT NULL,
+    rate NUMERIC NOT NULL,
+    source VARCHAR(100) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (base_currency, quote_currency, rate_date)
+);
+
+-- Per-user preferences
+CREATE TABLE user_settings (
+    user_id UUID PRIMARY KEY,
+    reporting_currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
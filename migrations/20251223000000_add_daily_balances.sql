@@ -0,0 +1,14 @@
+-- Materialized per-day net movement per wallet and asset, maintained as ledger entries are saved
+CREATE TABLE daily_balances (
+    wallet_address VARCHAR(255) NOT NULL,
+    asset_symbol VARCHAR(50) NOT NULL,
+    day DATE NOT NULL,
+    net_change NUMERIC NOT NULL,
+    PRIMARY KEY (wallet_address, asset_symbol, day)
+);
+
+-- Backfill from existing entries
+INSERT INTO daily_balances (wallet_address, asset_symbol, day, net_change)
+SELECT wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date, SUM(amount)
+FROM ledger_entries
+GROUP BY wallet_address, asset_symbol, (to_timestamp(timestamp) AT TIME ZONE 'UTC')::date;
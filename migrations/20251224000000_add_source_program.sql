@@ -0,0 +1,2 @@
+-- Program/contract the entry's transaction invoked, used to group income by source
+ALTER TABLE ledger_entries ADD COLUMN source_program VARCHAR(64);
@@ -0,0 +1,4 @@
+-- Chains are no longer a closed set: exchange statements are ingested as their own sources,
+-- and EVM networks come from configuration
+ALTER TABLE transactions ALTER COLUMN chain TYPE VARCHAR(32) USING chain::text;
+DROP TYPE chain_enum;
@@ -0,0 +1,11 @@
+-- Spam classification. Spam entries stay in the ledger but out of daily balances and reports.
+ALTER TABLE ledger_entries ADD COLUMN is_spam BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Per-user decisions that override the deny/allow lists and heuristics
+CREATE TABLE spam_overrides (
+    user_id UUID NOT NULL,
+    asset_symbol VARCHAR(50) NOT NULL,
+    is_spam BOOLEAN NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, asset_symbol)
+);
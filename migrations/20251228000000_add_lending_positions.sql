@@ -0,0 +1,5 @@
+-- Lending protocol collateral and loan principal, tracked per protocol account
+ALTER TYPE entry_type_enum ADD VALUE 'collateral';
+ALTER TYPE entry_type_enum ADD VALUE 'loan';
+
+ALTER TABLE ledger_entries ADD COLUMN position_account VARCHAR(64);
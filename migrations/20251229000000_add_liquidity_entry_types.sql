@@ -0,0 +1,3 @@
+-- Liquidity pool deposits and withdrawals, and the pool shares they mint and burn
+ALTER TYPE entry_type_enum ADD VALUE 'liquidity';
+ALTER TYPE entry_type_enum ADD VALUE 'lp_token';
@@ -0,0 +1,8 @@
+-- Cross-chain bridge transfers: where each was sent, and the entry on the other chain it was paired with
+ALTER TYPE entry_type_enum ADD VALUE 'bridge';
+
+ALTER TABLE ledger_entries ADD COLUMN bridge_protocol VARCHAR(32);
+ALTER TABLE ledger_entries ADD COLUMN bridge_destination_chain VARCHAR(32);
+ALTER TABLE ledger_entries ADD COLUMN bridge_destination_address VARCHAR(255);
+ALTER TABLE ledger_entries ADD COLUMN bridge_transfer_id VARCHAR(128);
+ALTER TABLE ledger_entries ADD COLUMN linked_entry_id UUID;
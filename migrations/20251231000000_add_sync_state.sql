@@ -0,0 +1,9 @@
+-- How far each wallet has been ingested per chain, so re-running ingestion only stores new transactions
+CREATE TABLE sync_state (
+    wallet_address VARCHAR(255) NOT NULL,
+    chain VARCHAR(32) NOT NULL,
+    last_timestamp BIGINT,
+    last_tx_hashes TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (wallet_address, chain)
+);
@@ -0,0 +1,5 @@
+-- Paged wallet queries walk (timestamp, id); these replace the timestamp-only indexes
+CREATE INDEX idx_transactions_wallet_keyset ON transactions(wallet_address, timestamp, id);
+CREATE INDEX idx_ledger_wallet_keyset ON ledger_entries(wallet_address, timestamp, id);
+DROP INDEX IF EXISTS idx_transactions_wallet_time;
+DROP INDEX IF EXISTS idx_ledger_wallet_timestamp;
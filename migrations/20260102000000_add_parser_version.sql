@@ -0,0 +1,4 @@
+-- Parser (and its version) that produced each ledger entry, so entries from an older version can be rebuilt.
+-- Entries normalized before this stay NULL and count as older than any version.
+ALTER TABLE ledger_entries ADD COLUMN parser_name VARCHAR(32);
+ALTER TABLE ledger_entries ADD COLUMN parser_version INTEGER;
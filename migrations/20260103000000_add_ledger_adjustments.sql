@@ -0,0 +1,27 @@
+-- Users' corrections and annotations, one row per adjusted entry. Type and valuation overrides are also written
+-- to the entry itself; keeping them here lets re-normalization carry them over. No foreign key, since
+-- re-normalizing replaces entries and an adjustment can outlive the entry it was made to.
+CREATE TABLE ledger_adjustments (
+    entry_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    wallet_address VARCHAR(255) NOT NULL,
+    entry_type VARCHAR(32),
+    fiat_value NUMERIC,
+    fiat_currency VARCHAR(3),
+    note TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX idx_adjustments_wallet ON ledger_adjustments(wallet_address);
+
+-- Every adjustment and manual entry, as it was made
+CREATE TABLE adjustment_audit (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL,
+    entry_id UUID NOT NULL,
+    change JSONB NOT NULL,
+    previous JSONB NOT NULL,
+    recorded_at BIGINT NOT NULL
+);
+CREATE INDEX idx_adjustment_audit_user ON adjustment_audit(user_id, recorded_at);
+CREATE INDEX idx_adjustment_audit_entry ON adjustment_audit(entry_id, recorded_at);